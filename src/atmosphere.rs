@@ -0,0 +1,667 @@
+use std::ops::{AddAssign, Mul, SubAssign};
+
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::power::{setup_power_grid, LoadPriority, PowerConsumer, ShipGrid};
+use crate::sensors::{SensorKind, SensorModel, SpawnSensorExt};
+
+// The air in the ship. Each compartment has its own, and gas moves between them through
+// doors and vents, or out into space through leaks. Like the power grid, it's all worked
+// out every fixed tick.
+pub struct AtmospherePlugin;
+impl Plugin for AtmospherePlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<Compartment>()
+            .register_type::<Door>()
+            .register_type::<Leak>()
+            .register_type::<Vent>()
+            .register_type::<Scrubber>();
+        // Scrubbers and vents run off the main bus
+        app.add_systems(
+            Startup,
+            setup_compartments
+                .in_set(SpawningSet)
+                .after(setup_power_grid),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                breathe,
+                run_scrubbers,
+                run_vents,
+                flow_through_doors,
+                flow_through_leaks,
+                update_compartments,
+                update_atmosphere_sensors,
+            )
+                .chain(),
+        );
+        app.add_systems(Startup, fit_bridge_sensors.in_set(FittingSet));
+        app.add_sample_file("AIR.BAS", include_str!("../assets/programs/AIR.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// Where the bridge's sensors are, for README.TXT
+const README_PARAGRAPH: &str = "\
+AIR reads the bridge's air sensor, on the diagnostics
+computer's port 2. The bridge's thermometer and pressure
+gauge are on ports 4 and 5.
+";
+
+// The gas constant, in J/(mol K)
+const R: f32 = 8.314;
+
+// How many moles a second get through each square metre of opening, per kPa of pressure
+// difference. Roughly right for a hole into vacuum: about 0.85 mol/s through a square
+// centimetre at one atmosphere.
+const FLOW_COEFFICIENT: f32 = 84.0;
+
+// Even with no pressure difference, air swaps back and forth through an open door. This is
+// how much goes each way, in cubic metres a second per square metre of doorway.
+const DOOR_MIXING: f32 = 0.05;
+
+// Per person, in mol/s. About 0.84kg of oxygen used and 1kg of carbon dioxide breathed out
+// a day.
+const OXYGEN_USE: f32 = 3.0e-4;
+const CARBON_DIOXIDE_OUTPUT: f32 = 2.6e-4;
+
+// Earth sea level, which is what the ship is kept at
+const STANDARD_PRESSURE: f32 = 101.325;
+const STANDARD_TEMPERATURE: f32 = 293.15;
+
+// The ship's compartments, for anything that needs to go in one when it's spawned
+#[derive(Resource)]
+pub struct ShipCompartments {
+    pub bridge: Entity,
+}
+
+// An amount of air, in moles of each gas
+#[derive(Debug, Clone, Copy, Default, Reflect)]
+pub struct Gas {
+    pub oxygen: f32,
+    pub carbon_dioxide: f32,
+    pub nitrogen: f32,
+}
+
+impl Gas {
+    // Ordinary air, enough of it to fill `volume` cubic metres at standard pressure and
+    // temperature
+    pub fn air(volume: f32) -> Self {
+        let moles = STANDARD_PRESSURE * 1000.0 * volume / (R * STANDARD_TEMPERATURE);
+        Self {
+            oxygen: moles * 0.2095,
+            carbon_dioxide: moles * 0.0004,
+            nitrogen: moles * 0.7901,
+        }
+    }
+
+    pub fn total(&self) -> f32 {
+        self.oxygen + self.carbon_dioxide + self.nitrogen
+    }
+}
+
+impl Mul<f32> for Gas {
+    type Output = Gas;
+
+    fn mul(self, scale: f32) -> Gas {
+        Gas {
+            oxygen: self.oxygen * scale,
+            carbon_dioxide: self.carbon_dioxide * scale,
+            nitrogen: self.nitrogen * scale,
+        }
+    }
+}
+
+impl AddAssign for Gas {
+    fn add_assign(&mut self, other: Gas) {
+        self.oxygen += other.oxygen;
+        self.carbon_dioxide += other.carbon_dioxide;
+        self.nitrogen += other.nitrogen;
+    }
+}
+
+impl SubAssign for Gas {
+    fn sub_assign(&mut self, other: Gas) {
+        self.oxygen -= other.oxygen;
+        self.carbon_dioxide -= other.carbon_dioxide;
+        self.nitrogen -= other.nitrogen;
+    }
+}
+
+// A sealed space full of air
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Compartment {
+    // In cubic metres
+    pub volume: f32,
+    pub gas: Gas,
+    // In kelvin
+    pub temperature: f32,
+    // How many people are in here breathing
+    pub occupants: u32,
+    // Worked out from everything above at the end of each tick, so they're easy to read.
+    // Pressure's in kPa, and the fractions are of the total amount of gas.
+    pub pressure: f32,
+    pub oxygen_fraction: f32,
+    pub carbon_dioxide_fraction: f32,
+}
+
+impl Compartment {
+    // Full of ordinary air, at standard pressure and temperature
+    pub fn new(volume: f32) -> Self {
+        let mut compartment = Self {
+            volume,
+            gas: Gas::air(volume),
+            temperature: STANDARD_TEMPERATURE,
+            occupants: 0,
+            pressure: 0.0,
+            oxygen_fraction: 0.0,
+            carbon_dioxide_fraction: 0.0,
+        };
+        compartment.update_readings();
+        compartment
+    }
+
+    fn update_readings(&mut self) {
+        let total = self.gas.total();
+        self.pressure = self.current_pressure();
+        let (oxygen, carbon_dioxide) = if total > 0.0 {
+            (self.gas.oxygen / total, self.gas.carbon_dioxide / total)
+        } else {
+            (0.0, 0.0)
+        };
+        self.oxygen_fraction = oxygen;
+        self.carbon_dioxide_fraction = carbon_dioxide;
+    }
+
+    // Worked out from scratch, rather than waiting for the end of the tick, in kPa
+    fn current_pressure(&self) -> f32 {
+        self.pressure_of(self.gas.total())
+    }
+
+    // The pressure `moles` of gas would have in here, in kPa
+    fn pressure_of(&self, moles: f32) -> f32 {
+        moles * R * self.temperature / self.volume / 1000.0
+    }
+
+    // Takes `moles` of gas out, in whatever mix is in here. Gives back what was taken.
+    fn remove(&mut self, moles: f32) -> Gas {
+        let total = self.gas.total();
+        if total <= 0.0 {
+            return Gas::default();
+        }
+        let taken = self.gas * (moles / total).clamp(0.0, 1.0);
+        self.gas -= taken;
+        taken
+    }
+
+    // Lets gas in, mixing its temperature with what's already here
+    fn add(&mut self, gas: Gas, temperature: f32) {
+        let before = self.gas.total();
+        let added = gas.total();
+        if before + added <= 0.0 {
+            return;
+        }
+        // In f64, since this happens a lot and f32 rounding errors add up to a noticeable
+        // drift over a few minutes
+        let (before, added) = (before as f64, added as f64);
+        self.temperature = ((self.temperature as f64 * before + temperature as f64 * added)
+            / (before + added)) as f32;
+        self.gas += gas;
+    }
+
+    // Moves up to `moles` of gas into `other`
+    fn flow_into(&mut self, other: &mut Compartment, moles: f32) {
+        let gas = self.remove(moles);
+        other.add(gas, self.temperature);
+    }
+}
+
+// Connects two compartments. While it's open, air flows through from whichever side's at
+// the higher pressure.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Door {
+    pub compartments: (Entity, Entity),
+    pub open: bool,
+    // In square metres
+    pub area: f32,
+}
+
+// A hole from a compartment into space
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Leak {
+    pub compartment: Entity,
+    // In square metres. Patching a leak means making this smaller.
+    pub area: f32,
+}
+
+// A fan blowing air from one compartment into another. Needs a `PowerConsumer` to run.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Vent {
+    pub from: Entity,
+    pub to: Entity,
+    // How much it moves on full power, in cubic metres a second
+    pub flow: f32,
+}
+
+// Takes carbon dioxide out of a compartment's air and turns it back into oxygen. Needs a
+// `PowerConsumer` to run.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Scrubber {
+    pub compartment: Entity,
+    // How much air it goes through on full power, in cubic metres a second
+    pub flow: f32,
+}
+
+// Reports on a compartment's air through a computer port.
+// Registers:
+// - 0-1: the pressure, in tenths of a kPa (16-bit, low byte first)
+// - 2-3: oxygen, in tenths of a percent
+// - 4-5: carbon dioxide, in parts per million
+// - 6: the temperature, in degrees Celsius (signed)
+#[derive(Component)]
+pub struct AtmosphereSensor {
+    pub compartment: Entity,
+}
+
+// Plugs a sensor for `compartment` into one of a computer's ports.
+// Use `Commands::spawn_atmosphere_sensor` rather than adding this directly.
+pub struct SpawnAtmosphereSensor {
+    pub computer: Entity,
+    pub port: usize,
+    pub compartment: Entity,
+}
+
+pub trait SpawnAtmosphereSensorExt {
+    fn spawn_atmosphere_sensor(&mut self, computer: Entity, port: usize, compartment: Entity);
+}
+
+impl SpawnAtmosphereSensorExt for Commands<'_, '_> {
+    fn spawn_atmosphere_sensor(&mut self, computer: Entity, port: usize, compartment: Entity) {
+        self.add(SpawnAtmosphereSensor {
+            computer,
+            port,
+            compartment,
+        });
+    }
+}
+
+impl Command for SpawnAtmosphereSensor {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            AtmosphereSensor {
+                compartment: self.compartment,
+            },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+pub fn setup_compartments(mut commands: Commands, grid: Res<ShipGrid>) {
+    let mut bridge = Compartment::new(40.0);
+    bridge.occupants = 1;
+    let bridge = commands.spawn((Name::new("Bridge"), bridge)).id();
+    let cargo = commands
+        .spawn((Name::new("Cargo hold"), Compartment::new(60.0)))
+        .id();
+    let airlock = commands
+        .spawn((Name::new("Airlock"), Compartment::new(8.0)))
+        .id();
+
+    commands.spawn((
+        Name::new("Cargo door"),
+        Door {
+            compartments: (bridge, cargo),
+            open: true,
+            area: 2.0,
+        },
+    ));
+    commands.spawn((
+        Name::new("Airlock door"),
+        Door {
+            compartments: (bridge, airlock),
+            open: false,
+            area: 1.6,
+        },
+    ));
+    // Nobody's found it yet
+    commands.spawn((
+        Name::new("Hull breach"),
+        Leak {
+            compartment: cargo,
+            area: 5.0e-6,
+        },
+    ));
+
+    commands.spawn((
+        Name::new("CO2 scrubber"),
+        Scrubber {
+            compartment: bridge,
+            flow: 0.05,
+        },
+        PowerConsumer::new(grid.main_bus, 250.0, LoadPriority::Essential),
+    ));
+    commands.spawn((
+        Name::new("Ventilation fan"),
+        Vent {
+            from: bridge,
+            to: cargo,
+            flow: 0.5,
+        },
+        PowerConsumer::new(grid.main_bus, 150.0, LoadPriority::Essential),
+    ));
+
+    commands.insert_resource(ShipCompartments { bridge });
+}
+
+// The diagnostics computer keeps an eye on the bridge's air with the air sensor on port 2
+// ($E820), and the bridge's own thermometer and pressure gauge on ports 4 and 5 ($E840 and
+// $E850), which aren't as good as the air sensor but are there when it isn't
+fn fit_bridge_sensors(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    compartments: Res<ShipCompartments>,
+) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_atmosphere_sensor(computer, 2, compartments.bridge);
+    commands.spawn_sensor(
+        computer,
+        4,
+        "Bridge thermometer",
+        SensorKind::Thermometer {
+            entity: compartments.bridge,
+        },
+        SensorModel::THERMOMETER,
+    );
+    commands.spawn_sensor(
+        computer,
+        5,
+        "Bridge pressure gauge",
+        SensorKind::PressureGauge {
+            compartment: compartments.bridge,
+        },
+        SensorModel::PRESSURE_GAUGE,
+    );
+}
+
+fn breathe(time: Res<Time>, mut compartments: Query<&mut Compartment>) {
+    let delta = time.delta_seconds();
+    for mut compartment in compartments.iter_mut() {
+        let people = compartment.occupants as f32;
+        let gas = &mut compartment.gas;
+        // Nobody can breathe in oxygen that isn't there
+        let breathed = (OXYGEN_USE * people * delta).min(gas.oxygen);
+        gas.oxygen -= breathed;
+        gas.carbon_dioxide += breathed * CARBON_DIOXIDE_OUTPUT / OXYGEN_USE;
+    }
+}
+
+fn run_scrubbers(
+    time: Res<Time>,
+    scrubbers: Query<(&Scrubber, &PowerConsumer)>,
+    mut compartments: Query<&mut Compartment>,
+) {
+    let delta = time.delta_seconds();
+    for (scrubber, power) in scrubbers.iter() {
+        let Ok(mut compartment) = compartments.get_mut(scrubber.compartment) else {
+            continue;
+        };
+        // It cleans whatever fraction of the air it gets through
+        let share = (scrubber.flow * power.supply * delta / compartment.volume).min(1.0);
+        let scrubbed = compartment.gas.carbon_dioxide * share;
+        compartment.gas.carbon_dioxide -= scrubbed;
+        compartment.gas.oxygen += scrubbed;
+    }
+}
+
+fn run_vents(
+    time: Res<Time>,
+    vents: Query<(&Vent, &PowerConsumer)>,
+    mut compartments: Query<&mut Compartment>,
+) {
+    let delta = time.delta_seconds();
+    for (vent, power) in vents.iter() {
+        let Ok([mut from, mut to]) = compartments.get_many_mut([vent.from, vent.to]) else {
+            continue;
+        };
+        let volume = (vent.flow * power.supply * delta).min(from.volume);
+        let moles = from.gas.total() * volume / from.volume;
+        from.flow_into(&mut to, moles);
+    }
+}
+
+fn flow_through_doors(
+    time: Res<Time>,
+    doors: Query<&Door>,
+    mut compartments: Query<&mut Compartment>,
+) {
+    let delta = time.delta_seconds();
+    for door in doors.iter() {
+        if !door.open {
+            continue;
+        }
+        let (a, b) = door.compartments;
+        let Ok([a, b]) = compartments.get_many_mut([a, b]) else {
+            continue;
+        };
+        let (mut high, mut low) = if a.current_pressure() >= b.current_pressure() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        let volume = DOOR_MIXING * door.area * delta;
+        let (high_moles, low_moles) = (
+            high.gas.total() * volume / high.volume,
+            low.gas.total() * volume / low.volume,
+        );
+        let from_high = high.remove(high_moles);
+        let from_low = low.remove(low_moles);
+        let (high_temperature, low_temperature) = (high.temperature, low.temperature);
+        high.add(from_low, low_temperature);
+        low.add(from_high, high_temperature);
+
+        let difference = high.current_pressure() - low.current_pressure();
+        // Any more than this and the pressures would cross over, rather than evening out
+        let to_equalise =
+            difference / (high.pressure_of(1.0) + low.pressure_of(1.0)).max(f32::EPSILON);
+        let moles = (FLOW_COEFFICIENT * door.area * difference * delta).min(to_equalise);
+        high.flow_into(&mut low, moles);
+    }
+}
+
+fn flow_through_leaks(
+    time: Res<Time>,
+    leaks: Query<&Leak>,
+    mut compartments: Query<&mut Compartment>,
+) {
+    let delta = time.delta_seconds();
+    for leak in leaks.iter() {
+        let Ok(mut compartment) = compartments.get_mut(leak.compartment) else {
+            continue;
+        };
+        let moles = FLOW_COEFFICIENT * leak.area * compartment.current_pressure() * delta;
+        compartment.remove(moles);
+    }
+}
+
+fn update_compartments(mut compartments: Query<&mut Compartment>) {
+    for mut compartment in compartments.iter_mut() {
+        compartment.update_readings();
+    }
+}
+
+fn update_atmosphere_sensors(
+    sensors: Query<(&AtmosphereSensor, &BusDevice)>,
+    compartments: Query<&Compartment>,
+) {
+    for (sensor, device) in sensors.iter() {
+        let Ok(compartment) = compartments.get(sensor.compartment) else {
+            continue;
+        };
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        let readings = [
+            compartment.pressure * 10.0,
+            compartment.oxygen_fraction * 1000.0,
+            compartment.carbon_dioxide_fraction * 1_000_000.0,
+        ];
+        for (idx, reading) in readings.into_iter().enumerate() {
+            let [low, high] = (reading.round() as u16).to_le_bytes();
+            registers[idx * 2] = low;
+            registers[idx * 2 + 1] = high;
+        }
+        registers[6] = (compartment.temperature - 273.15).round() as i8 as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world
+    }
+
+    // Runs the air for a second
+    fn tick(world: &mut World) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(breathe);
+        world.run_system_once(run_scrubbers);
+        world.run_system_once(run_vents);
+        world.run_system_once(flow_through_doors);
+        world.run_system_once(flow_through_leaks);
+        world.run_system_once(update_compartments);
+        world.run_system_once(update_atmosphere_sensors);
+    }
+
+    fn compartment(world: &World, compartment: Entity) -> &Compartment {
+        world.get::<Compartment>(compartment).unwrap()
+    }
+
+    #[test]
+    fn air_through_a_door_evens_out_without_any_going_missing() {
+        let mut world = world();
+        let full = world.spawn(Compartment::new(20.0)).id();
+        let mut half = Compartment::new(10.0);
+        half.gas = half.gas * 0.5;
+        let half = world.spawn(half).id();
+        let door = world
+            .spawn(Door {
+                compartments: (full, half),
+                open: false,
+                area: 1.5,
+            })
+            .id();
+        let total = |world: &World| {
+            compartment(world, full).gas.total() + compartment(world, half).gas.total()
+        };
+        let before = total(&world);
+
+        // Nothing gets through a closed door
+        tick(&mut world);
+        assert!(compartment(&world, half).pressure < 51.0);
+
+        world.get_mut::<Door>(door).unwrap().open = true;
+        for _ in 0..60 {
+            tick(&mut world);
+            // The side that started out higher never ends up lower
+            assert!(compartment(&world, full).pressure >= compartment(&world, half).pressure);
+        }
+        let (full, half) = (compartment(&world, full), compartment(&world, half));
+        assert!((full.pressure - half.pressure).abs() < 0.01);
+        // Two thirds of the air in two thirds of the space, so two thirds of the pressure
+        // either side of the door
+        assert!((full.pressure - STANDARD_PRESSURE * 5.0 / 6.0).abs() < 0.1);
+        assert!((total(&world) - before).abs() / before < 1e-4);
+    }
+
+    #[test]
+    fn leaks_empty_a_compartment_into_space() {
+        let mut world = world();
+        let hull = world.spawn(Compartment::new(10.0)).id();
+        world.spawn(Leak {
+            compartment: hull,
+            area: 1e-4,
+        });
+
+        let mut last = STANDARD_PRESSURE;
+        for _ in 0..10 {
+            tick(&mut world);
+            let pressure = compartment(&world, hull).pressure;
+            assert!(pressure < last && pressure > 0.0);
+            last = pressure;
+        }
+        // The mix doesn't change on the way out
+        assert!((compartment(&world, hull).oxygen_fraction - 0.2095).abs() < 1e-4);
+    }
+
+    #[test]
+    fn people_turn_oxygen_into_carbon_dioxide() {
+        let mut world = world();
+        let mut cabin = Compartment::new(10.0);
+        cabin.occupants = 2;
+        let before = cabin.gas;
+        let cabin = world.spawn(cabin).id();
+
+        tick(&mut world);
+        let after = compartment(&world, cabin).gas;
+        assert!((before.oxygen - after.oxygen - OXYGEN_USE * 2.0).abs() < 1e-5);
+        assert!(
+            (after.carbon_dioxide - before.carbon_dioxide - CARBON_DIOXIDE_OUTPUT * 2.0).abs()
+                < 1e-5
+        );
+
+        // But there's only so much to breathe
+        world.get_mut::<Compartment>(cabin).unwrap().gas.oxygen = OXYGEN_USE;
+        tick(&mut world);
+        assert_eq!(compartment(&world, cabin).gas.oxygen, 0.0);
+    }
+
+    #[test]
+    fn the_sensor_reports_the_air_in_its_registers() {
+        let mut world = world();
+        let bridge = world.spawn(Compartment::new(40.0)).id();
+        let registers = Port::shared();
+        world.spawn((
+            AtmosphereSensor {
+                compartment: bridge,
+            },
+            BusDevice {
+                computer: Entity::PLACEHOLDER,
+                port: 2,
+                registers: registers.clone(),
+            },
+        ));
+
+        tick(&mut world);
+        let registers = registers.lock().unwrap().registers;
+        let word = |idx: usize| u16::from_le_bytes([registers[idx], registers[idx + 1]]);
+        assert_eq!(word(0), 1013);
+        assert_eq!(word(4), 400);
+        assert_eq!(registers[6], 20);
+    }
+}
@@ -0,0 +1,723 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::transform::TransformSystem;
+
+use crate::computer::bus::{connect_bus_devices, BusDevice, N_PORTS};
+use crate::computer::{AddSampleFilesExt, ShipOS};
+use crate::console::ControlMode;
+use crate::core::system_sets::FittingSet;
+use crate::interaction::{Interactable, Interactor, LookedAt};
+use crate::power::{PowerBus, PowerConsumer};
+
+// Cables, and the sockets they plug into. Anything with a socket is only connected to what
+// its cable says it is: a device with a data socket is on whichever computer port the other
+// end's plugged into (and on nothing if it isn't plugged in), and anything that runs on
+// power with a power socket is on whichever bus the other end's plugged into. Things
+// without sockets (the cards inside the computers, and everything when running headless)
+// stay plugged in wherever they were spawned.
+pub struct CablingPlugin;
+impl Plugin for CablingPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<Socket>().register_type::<Cable>();
+        app.init_resource::<CableAssets>();
+        app.init_resource::<CableInHand>();
+        // Everything needs to be spawned and plugged in before it can be cabled up
+        app.add_systems(Startup, setup_cabling.after(FittingSet));
+        app.add_systems(
+            Update,
+            (
+                route_cables.run_if(in_state(ControlMode::FreeLook)),
+                // After devices get plugged into wherever they were spawned, so the cables
+                // get the last word
+                update_connections.after(connect_bus_devices),
+            )
+                .chain(),
+        );
+        // Cables are drawn between where their sockets are, so wait until that's known
+        app.add_systems(
+            PostUpdate,
+            (draw_cables, draw_cable_in_hand).after(TransformSystem::TransformPropagate),
+        );
+        app.add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to plug things in, for README.TXT
+const README_PARAGRAPH: &str = "\
+The sockets under each screen are its power and its ports.
+A device with a socket is only on a port if it's cabled up
+to it. C picks a cable up, plugs it in, or clips it in place.
+";
+
+// Where a cable being carried about is held, in metres in front of the player's eyes
+const HAND_DISTANCE: f32 = 0.4;
+
+const CABLE_RADIUS: f32 = 0.004;
+
+// How far a cable hangs down between the points it's held at, as a fraction of the
+// distance between them
+const CABLE_SAG: f32 = 0.2;
+
+// The kinds of sockets there are. A cable only goes between two of the same kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PortKind {
+    Power,
+    Serial,
+    Parallel,
+    // For things that only give a voltage. The computer's end has a converter behind it.
+    Analog,
+}
+
+// The sockets along the bottom of a computer: port n's is PORT_KINDS[n]
+pub const PORT_KINDS: [PortKind; N_PORTS] = {
+    use PortKind::*;
+    [
+        Parallel, Parallel, Parallel, Parallel, Serial, Serial, Serial, Serial, Analog, Analog,
+        Analog, Analog, Parallel, Parallel, Parallel, Parallel,
+    ]
+};
+
+impl PortKind {
+    fn colour(self) -> Color {
+        match self {
+            PortKind::Power => Color::srgb(0.6, 0.05, 0.05),
+            PortKind::Serial => Color::srgb(0.1, 0.2, 0.6),
+            PortKind::Parallel => Color::srgb(0.15, 0.15, 0.15),
+            PortKind::Analog => Color::srgb(0.7, 0.6, 0.1),
+        }
+    }
+}
+
+// Somewhere to plug a cable in. Sockets are entities of their own, so they can be looked at
+// and pointed at, and sit wherever they are on the thing they belong to.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Socket {
+    // The thing the socket's on, which is what gets connected
+    pub owner: Entity,
+    pub name: String,
+    pub kind: PortKind,
+    // Which port it is, if it's on a computer
+    pub port: Option<usize>,
+}
+
+// A cable between two sockets. Only one cable can go in each socket.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Cable {
+    pub ends: [Entity; 2],
+    // Where it's clipped to things along the way, in world space, from the first end to the
+    // second. It hangs down in between.
+    pub route: Vec<Vec3>,
+}
+
+// What sockets and cables look like. Shared, so there aren't hundreds of the same mesh.
+#[derive(Resource)]
+struct CableAssets {
+    socket_mesh: Handle<Mesh>,
+    // One for each kind, in the same order as `PortKind`
+    materials: [Handle<StandardMaterial>; 4],
+}
+
+impl FromWorld for CableAssets {
+    fn from_world(world: &mut World) -> Self {
+        let socket_mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::new(0.01, 0.01, 0.006));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = [
+            PortKind::Power,
+            PortKind::Serial,
+            PortKind::Parallel,
+            PortKind::Analog,
+        ]
+        .map(|kind| {
+            materials.add(StandardMaterial {
+                base_color: kind.colour(),
+                perceptual_roughness: 0.6,
+                ..default()
+            })
+        });
+        Self {
+            socket_mesh,
+            materials,
+        }
+    }
+}
+
+impl CableAssets {
+    fn material(&self, kind: PortKind) -> Handle<StandardMaterial> {
+        self.materials[kind as usize].clone()
+    }
+}
+
+// Puts a socket on something. `parent` is what it's physically attached to, and
+// `transform` is relative to that, which might not be the same as what it's the socket
+// for (a computer's sockets are on its case, for instance).
+// Use `Commands::spawn_socket` rather than adding this directly, or apply it straight to the
+// world from inside another command.
+pub struct SpawnSocket {
+    pub owner: Entity,
+    pub parent: Entity,
+    pub name: String,
+    pub kind: PortKind,
+    pub port: Option<usize>,
+    pub transform: Transform,
+}
+
+pub trait SpawnSocketExt {
+    fn spawn_socket(
+        &mut self,
+        owner: Entity,
+        parent: Entity,
+        name: &str,
+        kind: PortKind,
+        transform: Transform,
+    );
+}
+
+impl SpawnSocketExt for Commands<'_, '_> {
+    fn spawn_socket(
+        &mut self,
+        owner: Entity,
+        parent: Entity,
+        name: &str,
+        kind: PortKind,
+        transform: Transform,
+    ) {
+        self.add(SpawnSocket {
+            owner,
+            parent,
+            name: name.to_owned(),
+            kind,
+            port: None,
+            transform,
+        });
+    }
+}
+
+impl Command for SpawnSocket {
+    fn apply(self, world: &mut World) {
+        world.init_resource::<CableAssets>();
+        let assets = world.resource::<CableAssets>();
+        let mesh = assets.socket_mesh.clone();
+        let material = assets.material(self.kind);
+
+        world
+            .spawn((
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: self.transform,
+                    ..default()
+                },
+                Socket {
+                    owner: self.owner,
+                    name: self.name,
+                    kind: self.kind,
+                    port: self.port,
+                },
+                Interactable,
+            ))
+            .set_parent(self.parent);
+    }
+}
+
+// Plugs a cable in between two sockets, as long as they're the same kind and neither of
+// them has a cable in already.
+// Use `Commands::spawn_cable` rather than adding this directly.
+pub struct SpawnCable {
+    pub from: Entity,
+    pub to: Entity,
+    pub route: Vec<Vec3>,
+}
+
+pub trait SpawnCableExt {
+    fn spawn_cable(&mut self, from: Entity, to: Entity, route: Vec<Vec3>);
+}
+
+impl SpawnCableExt for Commands<'_, '_> {
+    fn spawn_cable(&mut self, from: Entity, to: Entity, route: Vec<Vec3>) {
+        self.add(SpawnCable { from, to, route });
+    }
+}
+
+impl Command for SpawnCable {
+    fn apply(self, world: &mut World) {
+        let (Some(from), Some(to)) = (world.get::<Socket>(self.from), world.get::<Socket>(self.to))
+        else {
+            warn!("Tried to plug a cable into something that isn't a socket");
+            return;
+        };
+        if from.kind != to.kind {
+            warn!(
+                "Tried to cable a {:?} socket ({}) to a {:?} socket ({})",
+                from.kind, from.name, to.kind, to.name
+            );
+            return;
+        }
+        let name = Name::new(format!("{} cable", from.name));
+        let taken = world
+            .query::<&Cable>()
+            .iter(world)
+            .any(|cable| cable.ends.contains(&self.from) || cable.ends.contains(&self.to));
+        if taken || self.from == self.to {
+            warn!("Tried to plug a cable into a socket that's already got one");
+            return;
+        }
+
+        world.spawn((
+            name,
+            Cable {
+                ends: [self.from, self.to],
+                route: self.route,
+            },
+        ));
+    }
+}
+
+// Cables everything up to wherever it was spawned plugged in: devices to their computer's
+// port, and anything that runs on power to a free socket on its bus
+fn setup_cabling(
+    mut commands: Commands,
+    sockets: Query<(Entity, &Socket)>,
+    devices: Query<&BusDevice>,
+    consumers: Query<&PowerConsumer>,
+) {
+    let mut taken = HashSet::new();
+    for (socket, Socket { owner, kind, .. }) in sockets.iter() {
+        let wanted = |(other, other_socket): &(Entity, &Socket)| {
+            if taken.contains(other) || other_socket.kind != *kind {
+                return false;
+            }
+            if let (Ok(device), PortKind::Serial | PortKind::Parallel | PortKind::Analog) =
+                (devices.get(*owner), kind)
+            {
+                other_socket.owner == device.computer && other_socket.port == Some(device.port)
+            } else if let (Ok(consumer), PortKind::Power) = (consumers.get(*owner), kind) {
+                other_socket.owner == consumer.bus
+            } else {
+                false
+            }
+        };
+        let Some((other, _)) = sockets.iter().find(wanted) else {
+            continue;
+        };
+
+        taken.insert(socket);
+        taken.insert(other);
+        commands.spawn_cable(socket, other, Vec::new());
+    }
+}
+
+// A cable the player's carrying about, plugged in at one end
+#[derive(Resource, Default)]
+struct CableInHand {
+    from: Option<Entity>,
+    // Where it's been clipped so far
+    route: Vec<Vec3>,
+}
+
+// C on a socket picks a new cable up from it, or unplugs the cable that's in it and picks
+// that up instead. Then C on another socket plugs the loose end in, C on the socket it
+// came from puts it down again, and C anywhere else clips it in place there, so it can be
+// routed around things.
+fn route_cables(
+    mut commands: Commands,
+    key: Res<ButtonInput<KeyCode>>,
+    mut in_hand: ResMut<CableInHand>,
+    sockets: Query<(Entity, &Socket, Has<LookedAt>)>,
+    cables: Query<(Entity, &Cable)>,
+    interactors: Query<&GlobalTransform, With<Interactor>>,
+) {
+    if !key.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+
+    let looked_at = sockets
+        .iter()
+        .find(|(_, _, looked_at)| *looked_at)
+        .map(|(entity, socket, _)| (entity, socket));
+    let cable_in = |socket: Entity| {
+        cables
+            .iter()
+            .find(|(_, cable)| cable.ends.contains(&socket))
+    };
+
+    match (in_hand.from, looked_at) {
+        (None, None) => {}
+        (None, Some((socket, _))) => {
+            in_hand.route.clear();
+            in_hand.from = Some(socket);
+            // Unplugging one end of a cable leaves it hanging from the other, so it can be
+            // plugged in somewhere else
+            if let Some((entity, cable)) = cable_in(socket) {
+                commands.entity(entity).despawn_recursive();
+                in_hand.route = cable.route.clone();
+                if cable.ends[0] == socket {
+                    in_hand.from = Some(cable.ends[1]);
+                    in_hand.route.reverse();
+                } else {
+                    in_hand.from = Some(cable.ends[0]);
+                }
+            }
+        }
+        (Some(from), Some((socket, _))) if socket == from => {
+            in_hand.from = None;
+            in_hand.route.clear();
+        }
+        (Some(from), Some((socket, Socket { kind, name, .. }))) => {
+            let Ok((_, from_socket, _)) = sockets.get(from) else {
+                // Whatever it was plugged into has gone
+                in_hand.from = None;
+                return;
+            };
+            if cable_in(socket).is_some() {
+                info!("{} has already got a cable in it", name);
+            } else if from_socket.kind != *kind {
+                info!(
+                    "A {:?} cable won't go in a {:?} socket",
+                    from_socket.kind, kind
+                );
+            } else {
+                let route = std::mem::take(&mut in_hand.route);
+                commands.spawn_cable(from, socket, route);
+                in_hand.from = None;
+            }
+        }
+        (Some(_), None) => {
+            if let Some(hand) = interactors.iter().next().map(hand_position) {
+                in_hand.route.push(hand);
+            }
+        }
+    }
+}
+
+fn hand_position(eyes: &GlobalTransform) -> Vec3 {
+    eyes.translation() + eyes.forward() * HAND_DISTANCE
+}
+
+// Works out what's connected to what from the cables, whenever they change
+#[allow(clippy::too_many_arguments)]
+fn update_connections(
+    cables: Query<&Cable>,
+    changed_cables: Query<(), Changed<Cable>>,
+    new_sockets: Query<(), Added<Socket>>,
+    mut removed_cables: RemovedComponents<Cable>,
+    sockets: Query<(Entity, &Socket)>,
+    mut devices: Query<&mut BusDevice>,
+    computers: Query<&ShipOS>,
+    mut consumers: Query<&mut PowerConsumer>,
+    buses: Query<(), With<PowerBus>>,
+) {
+    let removed = removed_cables.read().count() > 0;
+    if changed_cables.is_empty() && new_sockets.is_empty() && !removed {
+        return;
+    }
+
+    let mut other_end = HashMap::new();
+    for cable in cables.iter() {
+        other_end.insert(cable.ends[0], cable.ends[1]);
+        other_end.insert(cable.ends[1], cable.ends[0]);
+    }
+
+    for (entity, socket) in sockets.iter() {
+        let other = other_end
+            .get(&entity)
+            .and_then(|other| sockets.get(*other).ok())
+            .map(|(_, other)| other);
+
+        if socket.kind == PortKind::Power {
+            let Ok(mut consumer) = consumers.get_mut(socket.owner) else {
+                continue;
+            };
+            match other.map(|other| other.owner) {
+                Some(bus) if buses.contains(bus) => {
+                    consumer.bus = bus;
+                    consumer.connected = true;
+                }
+                _ => consumer.connected = false,
+            }
+            continue;
+        }
+
+        let Ok(mut device) = devices.get_mut(socket.owner) else {
+            continue;
+        };
+        // Out of wherever it was, and into wherever it is now
+        for ship_os in computers.iter() {
+            ship_os
+                .bus()
+                .lock()
+                .expect("Bus lock")
+                .detach(&device.registers);
+        }
+        let Some((computer, port)) = other.and_then(|other| Some((other.owner, other.port?)))
+        else {
+            continue;
+        };
+        let Ok(ship_os) = computers.get(computer) else {
+            continue;
+        };
+        ship_os
+            .bus()
+            .lock()
+            .expect("Bus lock")
+            .attach(port, device.registers.clone());
+        device.computer = computer;
+        device.port = port;
+    }
+}
+
+// Gives new cables something to look at
+fn draw_cables(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<CableAssets>,
+    cables: Query<(Entity, &Cable), Without<Handle<Mesh>>>,
+    sockets: Query<(&Socket, &GlobalTransform)>,
+) {
+    for (entity, cable) in cables.iter() {
+        let (Ok((socket, from)), Ok((_, to))) =
+            (sockets.get(cable.ends[0]), sockets.get(cable.ends[1]))
+        else {
+            continue;
+        };
+
+        let mut points = vec![from.translation()];
+        points.extend(cable.route.iter().copied());
+        points.push(to.translation());
+        commands.entity(entity).insert(PbrBundle {
+            mesh: meshes.add(tube_mesh(&hang(&points), CABLE_RADIUS)),
+            material: assets.material(socket.kind),
+            ..default()
+        });
+    }
+}
+
+// Shows the cable being carried, from where it's plugged in to the player's hand
+fn draw_cable_in_hand(
+    mut gizmos: Gizmos,
+    in_hand: Res<CableInHand>,
+    sockets: Query<(&Socket, &GlobalTransform)>,
+    interactors: Query<&GlobalTransform, With<Interactor>>,
+) {
+    let Some((socket, from)) = in_hand.from.and_then(|from| sockets.get(from).ok()) else {
+        return;
+    };
+    let Some(hand) = interactors.iter().next().map(hand_position) else {
+        return;
+    };
+
+    let mut points = vec![from.translation()];
+    points.extend(in_hand.route.iter().copied());
+    points.push(hand);
+    gizmos.linestrip(hang(&points), socket.kind.colour());
+}
+
+// The curve a cable makes hanging between the points it's held at. It can't go through the
+// floor, so anything that'd be below it lies on it instead.
+fn hang(points: &[Vec3]) -> Vec<Vec3> {
+    let mut curve = points[..1].to_vec();
+    for pair in points.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let length = start.distance(end);
+        let steps = ((length / 0.05).ceil() as usize).max(1);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let mut point = start.lerp(end, t) - Vec3::Y * CABLE_SAG * length * 4.0 * t * (1.0 - t);
+            point.y = point.y.max(CABLE_RADIUS);
+            curve.push(point);
+        }
+    }
+    curve
+}
+
+// A tube along a line, with its cross-section kept from twisting as the line bends
+fn tube_mesh(points: &[Vec3], radius: f32) -> Mesh {
+    const SIDES: usize = 6;
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut side = Vec3::ZERO;
+    for (idx, point) in points.iter().enumerate() {
+        let before = points[idx.saturating_sub(1)];
+        let after = points[(idx + 1).min(points.len() - 1)];
+        let along = (after - before).normalize_or(Vec3::X);
+        side = (side - along * side.dot(along))
+            .try_normalize()
+            .unwrap_or_else(|| along.any_orthonormal_vector());
+        let up = along.cross(side);
+
+        for corner in 0..SIDES {
+            let angle = corner as f32 * TAU / SIDES as f32;
+            let normal = side * angle.cos() + up * angle.sin();
+            positions.push((*point + normal * radius).to_array());
+            normals.push(normal.to_array());
+        }
+    }
+
+    let mut indices = Vec::new();
+    for ring in 0..points.len().saturating_sub(1) {
+        for corner in 0..SIDES {
+            let a = (ring * SIDES + corner) as u32;
+            let b = (ring * SIDES + (corner + 1) % SIDES) as u32;
+            let (c, d) = (a + SIDES as u32, b + SIDES as u32);
+            indices.extend([a, b, c, b, d, c]);
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::computer::bus::{Port, IO_BASE, PORT_SIZE};
+    use crate::power::{BusState, LoadPriority};
+
+    fn socket(world: &mut World, owner: Entity, kind: PortKind, port: Option<usize>) -> Entity {
+        let name = format!("{:?}", kind);
+        world
+            .spawn(Socket {
+                owner,
+                name,
+                kind,
+                port,
+            })
+            .id()
+    }
+
+    fn plug(world: &mut World, from: Entity, to: Entity) {
+        SpawnCable {
+            from,
+            to,
+            route: Vec::new(),
+        }
+        .apply(world);
+    }
+
+    fn cables(world: &mut World) -> Vec<[Entity; 2]> {
+        world
+            .query::<&Cable>()
+            .iter(world)
+            .map(|cable| cable.ends)
+            .collect()
+    }
+
+    #[test]
+    fn cables_only_go_between_free_sockets_of_the_same_kind() {
+        let mut world = World::new();
+        let owner = Entity::PLACEHOLDER;
+        let serial = socket(&mut world, owner, PortKind::Serial, None);
+        let other_serial = socket(&mut world, owner, PortKind::Serial, None);
+        let spare_serial = socket(&mut world, owner, PortKind::Serial, None);
+        let parallel = socket(&mut world, owner, PortKind::Parallel, None);
+
+        plug(&mut world, serial, parallel);
+        plug(&mut world, serial, serial);
+        assert!(cables(&mut world).is_empty());
+
+        plug(&mut world, serial, other_serial);
+        plug(&mut world, spare_serial, other_serial);
+        assert_eq!(cables(&mut world), [[serial, other_serial]]);
+    }
+
+    #[test]
+    fn devices_and_consumers_are_on_whatever_theyre_plugged_into() {
+        let mut world = World::new();
+        let computer = world.spawn(ShipOS::new(20, 6)).id();
+        let port_5 = socket(&mut world, computer, PortKind::Serial, Some(5));
+        let registers = Port::shared();
+        let device = world
+            .spawn(BusDevice {
+                computer: Entity::PLACEHOLDER,
+                port: 0,
+                registers: registers.clone(),
+            })
+            .id();
+        let device_socket = socket(&mut world, device, PortKind::Serial, None);
+
+        let bus = world
+            .spawn(PowerBus {
+                nominal_voltage: 28.0,
+                voltage: 28.0,
+                state: BusState::Normal,
+                generation: 0.0,
+                demand: 0.0,
+                load: 0.0,
+                battery_flow: 0.0,
+                battery_charge: 1.0,
+                shed: 0,
+                tripped: 0,
+            })
+            .id();
+        let outlet = socket(&mut world, bus, PortKind::Power, None);
+        let lamp = world
+            .spawn(PowerConsumer::new(
+                Entity::PLACEHOLDER,
+                60.0,
+                LoadPriority::Low,
+            ))
+            .id();
+        let plug_socket = socket(&mut world, lamp, PortKind::Power, None);
+
+        // Nothing's plugged in yet
+        world.run_system_once(update_connections);
+        assert!(!world.get::<PowerConsumer>(lamp).unwrap().connected);
+
+        plug(&mut world, port_5, device_socket);
+        plug(&mut world, plug_socket, outlet);
+        world.run_system_once(update_connections);
+
+        let consumer = world.get::<PowerConsumer>(lamp).unwrap();
+        assert!(consumer.connected);
+        assert_eq!(consumer.bus, bus);
+        let plugged_in = world.get::<BusDevice>(device).unwrap();
+        assert_eq!((plugged_in.computer, plugged_in.port), (computer, 5));
+        registers.lock().unwrap().registers[3] = 42;
+        let address = IO_BASE + 5 * PORT_SIZE as u16 + 3;
+        let bus_of = |world: &World| world.get::<ShipOS>(computer).unwrap().bus().clone();
+        assert_eq!(bus_of(&world).lock().unwrap().read(address), 42);
+
+        // Pulling the cables out unplugs them again
+        let cable_entities: Vec<Entity> = world
+            .query_filtered::<Entity, With<Cable>>()
+            .iter(&world)
+            .collect();
+        for cable in cable_entities {
+            world.despawn(cable);
+        }
+        world.run_system_once(update_connections);
+        assert!(!world.get::<PowerConsumer>(lamp).unwrap().connected);
+        assert_ne!(bus_of(&world).lock().unwrap().read(address), 42);
+    }
+
+    #[test]
+    fn cables_hang_down_but_not_through_the_floor() {
+        let (start, end) = (Vec3::new(0.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0));
+        let curve = hang(&[start, end]);
+        assert_eq!((curve[0], curve[curve.len() - 1]), (start, end));
+        // Halfway along, it's dropped by the sag times the distance
+        let lowest = curve.iter().map(|point| point.y).fold(f32::MAX, f32::min);
+        assert!((lowest - (1.0 - CABLE_SAG * 2.0)).abs() < 1e-5);
+
+        let low = hang(&[Vec3::new(0.0, 0.1, 0.0), Vec3::new(4.0, 0.1, 0.0)]);
+        assert!(low.iter().all(|point| point.y >= CABLE_RADIUS));
+        assert!(low.iter().any(|point| point.y == CABLE_RADIUS));
+    }
+}
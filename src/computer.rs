@@ -1,11 +1,17 @@
+pub mod bus;
+#[cfg(feature = "terminal")]
 mod ibm_byte_map;
+#[cfg(feature = "terminal")]
 mod os;
 mod ship_os;
+#[cfg(feature = "terminal")]
 mod terminal;
 
+use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use bevy::input::keyboard::KeyboardInput;
+use bevy::ecs::world::Command;
 use bevy::input::ButtonState;
 use bevy::prelude::*;
 use bevy::render::render_resource::Extent3d;
@@ -17,91 +23,503 @@ use bevy::render::view::RenderLayers;
 use bevy::sprite::Anchor;
 use bevy::text::Text;
 use bevy::text::Text2dBounds;
-use ship_os::ShipOS;
+use bus::{connect_bus_devices, N_PORTS};
+use ship_os::binding::{find_source_entity, index_names, read_source, NamedEntities};
+use ship_os::filesystem::FileSystem;
+use ship_os::layout::{ScreenLayout, ScreenLayoutLoader};
+use ship_os::Modifiers;
+pub use ship_os::ShipOS;
+#[cfg(feature = "terminal")]
+use terminal::Terminal;
 
-use crate::core::system_sets::SpawningSet;
-use crate::interaction::Interactable;
+use crate::cabling::{PortKind, SpawnSocket, PORT_KINDS};
+use crate::console::{DockingPose, SeatedAt, STAND_UP_KEY};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::interaction::{Interactable, Obstacle, ScreenPointerEvent};
+use crate::power::{setup_power_grid, LoadPriority, PowerConsumer, ShipGrid};
+use crate::thermal::{setup_cooling, ShipCooling, ThermalBody, ThermalState};
 
 pub struct ComputerPlugin;
 impl Plugin for ComputerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_computer.in_set(SpawningSet));
-        app.add_systems(Update, draw_screen);
+        // Computers need something to plug into and something to cool them. Everything else
+        // gets plugged into them by its own plugin, once they're there.
+        app.add_systems(
+            Startup,
+            setup_computers
+                .in_set(SpawningSet)
+                .after(setup_power_grid)
+                .after(setup_cooling),
+        );
+        app.configure_sets(Startup, FittingSet.after(SpawningSet));
+        app.init_resource::<ShipComputers>();
+        app.init_resource::<SampleFiles>();
+        app.init_resource::<NamedEntities>();
+        app.init_asset::<ScreenLayout>();
+        app.init_asset_loader::<ScreenLayoutLoader>();
+        app.add_systems(
+            Update,
+            (
+                connect_bus_devices,
+                run_computer_hardware,
+                apply_screen_layouts,
+                index_names,
+                update_screen_bindings,
+                tick_computers,
+                capture_keyboard,
+                deliver_pointer_events,
+                draw_screen,
+            )
+                .chain(),
+        );
     }
 }
 
+// Runs computers that are nothing but the computer: no screen, no keyboard, and no power or
+// cooling to worry about. They tick along with the fixed timestep rather than the frame
+// rate, so a program always does exactly the same thing from one run to the next, which is
+// what testing guidance programs headless needs.
+pub struct HeadlessComputerPlugin;
+impl Plugin for HeadlessComputerPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(Startup, FittingSet.after(SpawningSet));
+        app.init_resource::<ShipComputers>();
+        app.add_systems(FixedUpdate, (connect_bus_devices, tick_computers).chain());
+    }
+}
+
+// The names of the ship's computers, for plugins to find them by
+pub const DIAGNOSTICS_COMPUTER: &str = "Diagnostics computer";
+pub const POWER_COMPUTER: &str = "Power computer";
+pub const REACTOR_CONSOLE: &str = "Reactor console";
+pub const HELM: &str = "Helm";
+
+// Every computer there is, by name. Plugins look up the ones they want in `FittingSet` and
+// plug their devices into them, and leave them be if they're not there (running headless,
+// say, there's only the helm).
+#[derive(Resource, Default)]
+pub struct ShipComputers(HashMap<String, Entity>);
+
+impl ShipComputers {
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.0.get(name).copied()
+    }
+}
+
+fn register_computer(world: &mut World, name: &str, computer: Entity) {
+    world
+        .get_resource_or_insert_with(ShipComputers::default)
+        .0
+        .insert(name.to_owned(), computer);
+}
+
+// What goes on every computer's disk besides README.TXT and HELLO.BAS: example programs for
+// whatever plugins plug in, and a paragraph each on the end of the README saying what's what
+#[derive(Resource, Default)]
+pub struct SampleFiles {
+    readme: Vec<&'static str>,
+    files: Vec<(&'static str, &'static str)>,
+}
+
+impl SampleFiles {
+    fn copy_to(&self, disk: &mut FileSystem) {
+        let mut readme = disk.read_to_string("README.TXT").unwrap_or_default();
+        for paragraph in &self.readme {
+            readme.push('\n');
+            readme.push_str(paragraph);
+        }
+        disk.write("README.TXT", readme.as_bytes())
+            .expect("Sample file should fit");
+        for (name, contents) in &self.files {
+            disk.write(name, contents.as_bytes())
+                .expect("Sample file should fit");
+        }
+    }
+}
+
+pub trait AddSampleFilesExt {
+    // Adds a paragraph to the end of README.TXT
+    fn add_readme_paragraph(&mut self, paragraph: &'static str) -> &mut Self;
+    fn add_sample_file(&mut self, name: &'static str, contents: &'static str) -> &mut Self;
+}
+
+impl AddSampleFilesExt for App {
+    fn add_readme_paragraph(&mut self, paragraph: &'static str) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(SampleFiles::default)
+            .readme
+            .push(paragraph);
+        self
+    }
+
+    fn add_sample_file(&mut self, name: &'static str, contents: &'static str) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(SampleFiles::default)
+            .files
+            .push((name, contents));
+        self
+    }
+}
+
+// Every character of the IBM VGA font is 8x16 pixels
+const CHAR_WIDTH: usize = 8;
+const CHAR_HEIGHT: usize = 16;
+
+// How big each character is on the physical screen, in metres.
+// These give an 80x25 screen the same 4:3 shape as the original monitors.
+const CHAR_WORLD_WIDTH: f32 = 0.003;
+const CHAR_WORLD_HEIGHT: f32 = 0.0072;
+
+// How tall the strip of sockets under the screen is, in metres
+const SOCKET_STRIP_HEIGHT: f32 = 0.03;
+
+// How much power a computer draws, in watts. Screen included.
+const COMPUTER_POWER: f32 = 150.0;
+
+// How much heat the CPU gives off running flat out, in watts. Running slower, it gives off
+// proportionally less.
+const CPU_HEAT: f32 = 60.0;
+
 #[derive(Component)]
 struct ScreenCuboid;
 
-fn setup_computer(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-    asset_server: Res<AssetServer>,
-) {
-    // The code in here comes largely from the Bevy "render to texture" example
-    // https://github.com/bevyengine/bevy/blob/latest/examples/3d/render_to_texture.rs
-    // I did this in an evening while getting slowly more drunk and I'm extremely proud of myself
-    // it was really hard
-    // you know when you sit back in your chair and think "damn, I'm really clever"
-    let font = asset_server.load("fonts/oldschool_pc_font_pack/Mx437_IBM_VGA_8x16.ttf");
-    let text_style = TextStyle {
-        font: font.clone(),
-        font_size: 16.0,
-        ..default()
-    };
+// Links a screen mesh to the computer entity whose display it shows
+#[derive(Component)]
+pub struct Screen {
+    pub computer: Entity,
+    // The size of the character grid the screen displays
+    pub n_columns: usize,
+    pub n_rows: usize,
+}
 
-    let size = Extent3d {
-        width: 640,
-        // you may notice that we set the height to 400 here, but later
-        // set the height of the cuboid to a 4:3 ratio, i.e. 480.
-        // this is because the font we're using, which is an IBM VGA font,
-        // was originally stretched slightly in this exact aspect ratio (i.e. it was
-        // rendered to a 640x400 pixel grid, but that grid was stretched on the CRT monitor
-        // to fill a 640x480 area).
-        // See the font website: https://int10h.org/oldschool-pc-fonts/fontlist/font?ibm_vga_8x16
-        height: 400,
-        ..default()
-    };
-    // The image object the screen will be rendered to
-    let mut image = Image {
-        texture_descriptor: TextureDescriptor {
-            label: None,
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Bgra8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        },
-        ..default()
-    };
-    image.resize(size);
+// Hands out render layers, so each computer's text only ends up on its own screen.
+// Layer 0 is the main 3D world, so we start from 1.
+#[derive(Resource)]
+struct ScreenRenderLayers {
+    next: usize,
+}
 
-    // Add to assets, create handles
-    let image_handle = images.add(image);
-    let first_pass_layer = RenderLayers::layer(1);
+impl Default for ScreenRenderLayers {
+    fn default() -> Self {
+        Self { next: 1 }
+    }
+}
 
-    // The stuff to render to the screen
-    commands.spawn((
-        ShipOS::new(80, 25),
-        Text2dBundle {
-            text: Text::from_section("", text_style.clone()),
-            text_anchor: Anchor::BottomLeft,
-            // I solemnly apologise for using magic numbers here, and I promise I will fix it
-            transform: Transform::from_xyz(-320., -200., 0.),
-            text_2d_bounds: Text2dBounds {
-                size: Vec2::new(640., 400.),
+// Everything the computer entity itself needs. The screen mesh and the camera that renders
+// the text onto it are separate entities, which `SpawnComputer` takes care of.
+#[derive(Bundle)]
+pub struct ComputerBundle {
+    name: Name,
+    ship_os: ShipOS,
+    // What to show on the screen. Gets applied to the ShipOS once it's loaded, and again
+    // whenever the file changes.
+    layout: Handle<ScreenLayout>,
+    text: Text2dBundle,
+    render_layers: RenderLayers,
+    power: PowerConsumer,
+    cpu: ThermalBody,
+}
+
+// Spawns a computer, complete with its own screen, render target and camera.
+// Use `Commands::spawn_computer` rather than adding this directly.
+pub struct SpawnComputer {
+    pub computer: Entity,
+    pub name: String,
+    pub transform: Transform,
+    pub n_columns: usize,
+    pub n_rows: usize,
+    // Asset path of the screen layout to show
+    pub layout: String,
+    // The power bus it's plugged into
+    pub power_bus: Entity,
+    // The coolant loop its CPU's on
+    pub coolant_loop: Entity,
+}
+
+pub trait SpawnComputerExt {
+    // Spawns a computer with an 80x25 screen, returning the computer entity
+    fn spawn_computer(
+        &mut self,
+        name: &str,
+        transform: Transform,
+        layout: &str,
+        power_bus: Entity,
+        coolant_loop: Entity,
+    ) -> Entity;
+}
+
+impl SpawnComputerExt for Commands<'_, '_> {
+    fn spawn_computer(
+        &mut self,
+        name: &str,
+        transform: Transform,
+        layout: &str,
+        power_bus: Entity,
+        coolant_loop: Entity,
+    ) -> Entity {
+        let computer = self.spawn_empty().id();
+        self.add(SpawnComputer {
+            computer,
+            name: name.to_owned(),
+            transform,
+            n_columns: 80,
+            n_rows: 25,
+            layout: layout.to_owned(),
+            power_bus,
+            coolant_loop,
+        });
+        computer
+    }
+}
+
+// A computer with no screen, which types a BASIC program in and runs it.
+// Use `Commands::spawn_headless_computer` rather than adding this directly.
+pub struct SpawnHeadlessComputer {
+    pub computer: Entity,
+    pub name: String,
+    pub program: String,
+}
+
+pub trait SpawnHeadlessComputerExt {
+    fn spawn_headless_computer(&mut self, name: &str, program: &str) -> Entity;
+}
+
+impl SpawnHeadlessComputerExt for Commands<'_, '_> {
+    fn spawn_headless_computer(&mut self, name: &str, program: &str) -> Entity {
+        let computer = self.spawn_empty().id();
+        self.add(SpawnHeadlessComputer {
+            computer,
+            name: name.to_owned(),
+            program: program.to_owned(),
+        });
+        computer
+    }
+}
+
+impl Command for SpawnHeadlessComputer {
+    fn apply(self, world: &mut World) {
+        let mut ship_os = ShipOS::new(80, 25);
+        ship_os.launch_named("BASIC");
+        for line in self.program.lines() {
+            ship_os.type_text(line);
+            ship_os.type_text("\n");
+        }
+        ship_os.type_text("RUN\n");
+        if let Some(samples) = world.get_resource::<SampleFiles>() {
+            samples.copy_to(&mut ship_os.files().lock().expect("Disk shouldn't be poisoned"));
+        }
+        register_computer(world, &self.name, self.computer);
+        world
+            .entity_mut(self.computer)
+            .insert((Name::new(self.name), ship_os));
+    }
+}
+
+// What's on a computer's screen, as plain text
+pub fn read_screen(world: &mut World, computer: Entity) -> Option<String> {
+    let mut ship_os = world.get_mut::<ShipOS>(computer)?;
+    ship_os.redraw();
+    Some(
+        ship_os
+            .get_screen()
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect(),
+    )
+}
+
+impl Command for SpawnComputer {
+    fn apply(self, world: &mut World) {
+        // The code in here comes largely from the Bevy "render to texture" example
+        // https://github.com/bevyengine/bevy/blob/latest/examples/3d/render_to_texture.rs
+        // I did this in an evening while getting slowly more drunk and I'm extremely proud of myself
+        // it was really hard
+        // you know when you sit back in your chair and think "damn, I'm really clever"
+        let asset_server = world.resource::<AssetServer>();
+        let font = asset_server.load("fonts/oldschool_pc_font_pack/Mx437_IBM_VGA_8x16.ttf");
+        let layout = asset_server.load(self.layout);
+        let text_style = TextStyle {
+            font,
+            font_size: CHAR_HEIGHT as f32,
+            ..default()
+        };
+
+        let width = (self.n_columns * CHAR_WIDTH) as u32;
+        let height = (self.n_rows * CHAR_HEIGHT) as u32;
+        let size = Extent3d {
+            width,
+            // you may notice that we set the height to 400 here (for an 80x25 screen), but
+            // later set the height of the cuboid to a 4:3 ratio, i.e. 480.
+            // this is because the font we're using, which is an IBM VGA font,
+            // was originally stretched slightly in this exact aspect ratio (i.e. it was
+            // rendered to a 640x400 pixel grid, but that grid was stretched on the CRT monitor
+            // to fill a 640x480 area).
+            // See the font website: https://int10h.org/oldschool-pc-fonts/fontlist/font?ibm_vga_8x16
+            height,
+            ..default()
+        };
+        // The image object the screen will be rendered to
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Bgra8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
             },
             ..default()
-        },
-        first_pass_layer.clone(),
-    ));
+        };
+        image.resize(size);
+
+        // Add to assets, create handles
+        let image_handle = world.resource_mut::<Assets<Image>>().add(image);
+        let first_pass_layer = {
+            let mut layers = world.get_resource_or_insert_with(ScreenRenderLayers::default);
+            let layer = RenderLayers::layer(layers.next);
+            layers.next += 1;
+            layer
+        };
 
+        let ship_os = ShipOS::new(self.n_columns, self.n_rows);
+        if let Some(samples) = world.get_resource::<SampleFiles>() {
+            samples.copy_to(&mut ship_os.files().lock().expect("Disk shouldn't be poisoned"));
+        }
+        register_computer(world, &self.name, self.computer);
+
+        // The stuff to render to the screen
+        world.entity_mut(self.computer).insert(ComputerBundle {
+            name: Name::new(self.name),
+            ship_os,
+            layout,
+            text: Text2dBundle {
+                text: Text::from_section("", text_style),
+                text_anchor: Anchor::BottomLeft,
+                // Put the bottom left of the text at the bottom left of the camera's view
+                transform: Transform::from_xyz(-(width as f32) / 2., -(height as f32) / 2., 0.),
+                text_2d_bounds: Text2dBounds {
+                    size: Vec2::new(width as f32, height as f32),
+                },
+                ..default()
+            },
+            render_layers: first_pass_layer.clone(),
+            // The ship can't be flown without them, so they're never shed
+            power: PowerConsumer::new(self.power_bus, COMPUTER_POWER, LoadPriority::Essential),
+            // A heatsink, which starts slowing down at 85C and gives up at 100C
+            cpu: ThermalBody::new(200.0, self.coolant_loop, 10.0, 358.15, 373.15),
+        });
+
+        // Camera that "sees" the text to render
+        world.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    order: -1,
+                    target: image_handle.clone().into(),
+                    ..default()
+                },
+                ..default()
+            },
+            first_pass_layer,
+        ));
+
+        // Cube
+        let screen_width = self.n_columns as f32 * CHAR_WORLD_WIDTH;
+        let screen_height = self.n_rows as f32 * CHAR_WORLD_HEIGHT;
+        let cube_handle = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::new(screen_width, screen_height, 0.03));
+        let material_handle = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color_texture: Some(image_handle),
+                reflectance: 0.02,
+                unlit: false,
+                ..default()
+            });
+
+        // Far enough back that the whole screen fits in the default 45 degree field of view,
+        // with a little bit of breathing room
+        let docking_distance = 1.15 * (screen_height / 2.0) / (PI / 8.0).tan();
+
+        let screen = world
+            .spawn((
+                PbrBundle {
+                    mesh: cube_handle,
+                    material: material_handle,
+                    transform: self.transform,
+                    ..default()
+                },
+                ScreenCuboid,
+                Screen {
+                    computer: self.computer,
+                    n_columns: self.n_columns,
+                    n_rows: self.n_rows,
+                },
+                DockingPose(
+                    Transform::from_xyz(0.0, 0.0, -docking_distance)
+                        .looking_at(Vec3::ZERO, Vec3::Y),
+                ),
+                Interactable,
+            ))
+            .id();
+
+        // A strip along the bottom with the sockets in: the power, then one for each port.
+        // Devices with sockets of their own are only on the bus if they're cabled up to one.
+        let strip_mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::new(screen_width, SOCKET_STRIP_HEIGHT, 0.03));
+        let strip_material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgb(0.3, 0.3, 0.3),
+                ..default()
+            });
+        let strip = world
+            .spawn((
+                PbrBundle {
+                    mesh: strip_mesh,
+                    material: strip_material,
+                    transform: Transform::from_xyz(
+                        0.0,
+                        -(screen_height + SOCKET_STRIP_HEIGHT) / 2.0,
+                        0.0,
+                    ),
+                    ..default()
+                },
+                Obstacle,
+            ))
+            .set_parent(screen)
+            .id();
+        let spacing = screen_width / (N_PORTS + 1) as f32;
+        for idx in 0..=N_PORTS {
+            // On the front, which is the strip's -z side, same as the screen
+            let transform = Transform::from_xyz(
+                (idx as f32 + 0.5) * spacing - screen_width / 2.0,
+                0.0,
+                -0.018,
+            );
+            let (name, kind, port) = match idx.checked_sub(1) {
+                None => ("POWER".to_owned(), PortKind::Power, None),
+                Some(port) => (format!("PORT {}", port), PORT_KINDS[port], Some(port)),
+            };
+            SpawnSocket {
+                owner: self.computer,
+                parent: strip,
+                name,
+                kind,
+                port,
+                transform,
+            }
+            .apply(world);
+        }
+    }
+}
+
+pub fn setup_computers(mut commands: Commands, grid: Res<ShipGrid>, cooling: Res<ShipCooling>) {
     // Light
     commands.spawn((
         PointLightBundle {
@@ -111,57 +529,233 @@ fn setup_computer(
         RenderLayers::layer(0),
     ));
 
-    // Camera that "sees" the text to render
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                order: -1,
-                target: image_handle.clone().into(),
-                ..default()
-            },
-            ..default()
-        },
-        first_pass_layer,
-    ));
+    // The text reads the right way round on each screen's local -z face, so turn that
+    // face towards the player
+    let screen_rotation = Quat::from_euler(EulerRot::YXZ, PI, PI / 10.0, 0.0);
+    commands.spawn_computer(
+        DIAGNOSTICS_COMPUTER,
+        Transform::from_xyz(0.0, 1.5, -0.5).with_rotation(screen_rotation),
+        "layouts/diagnostics.layout.ron",
+        grid.main_bus,
+        cooling.primary_loop,
+    );
+    commands.spawn_computer(
+        POWER_COMPUTER,
+        Transform::from_xyz(0.6, 1.5, -0.5).with_rotation(screen_rotation),
+        "layouts/power.layout.ron",
+        grid.main_bus,
+        cooling.primary_loop,
+    );
+    // The reactor console. Nothing keeps the reactor going but whatever's running on this.
+    commands.spawn_computer(
+        REACTOR_CONSOLE,
+        Transform::from_xyz(-0.6, 1.5, -0.5).with_rotation(screen_rotation),
+        "layouts/reactor.layout.ron",
+        grid.main_bus,
+        cooling.primary_loop,
+    );
+    commands.spawn_computer(
+        HELM,
+        Transform::from_xyz(-1.2, 1.5, -0.5).with_rotation(screen_rotation),
+        "layouts/helm.layout.ron",
+        grid.main_bus,
+        cooling.primary_loop,
+    );
+}
 
-    // Cube
-    let cube_handle = meshes.add(Cuboid::new(0.24, 0.18, 0.03));
-    let material_handle = materials.add(StandardMaterial {
-        base_color_texture: Some(image_handle),
-        reflectance: 0.02,
-        unlit: false,
-        ..default()
-    });
-    commands.spawn((
-        PbrBundle {
-            mesh: cube_handle,
-            material: material_handle,
-            transform: Transform::from_xyz(0.0, 1.5, -0.5).with_rotation(Quat::from_euler(
-                EulerRot::YXZ,
-                PI,
-                PI / 10.0,
-                0.0,
-            )),
-            ..default()
-        },
-        ScreenCuboid,
-        Interactable,
-    ));
+fn apply_screen_layouts(
+    mut evr_layouts: EventReader<AssetEvent<ScreenLayout>>,
+    layouts: Res<Assets<ScreenLayout>>,
+    mut computers: Query<(&mut ShipOS, &Handle<ScreenLayout>)>,
+) {
+    for ev in evr_layouts.read() {
+        // Modified is what we get when the file is hot-reloaded
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = ev else {
+            continue;
+        };
+        let Some(layout) = layouts.get(*id) else {
+            continue;
+        };
+
+        for (mut ship_os, handle) in computers.iter_mut() {
+            if handle.id() == *id {
+                ship_os.apply_layout(layout);
+            }
+        }
+    }
 }
 
-fn draw_screen(mut query: Query<(&mut Text, &ShipOS)>) {
-    for (mut text, processor) in query.iter_mut() {
-        text.sections[0].value = processor.get_screen().to_string();
+// Reads the values that screens have bound their widgets to.
+// This needs the whole world, because a binding can point at any component on any entity.
+fn update_screen_bindings(world: &mut World) {
+    let now = world.resource::<Time>().elapsed_seconds_f64();
+
+    let mut computers = world.query::<(Entity, &ShipOS)>();
+    let mut values = Vec::new();
+    for (computer, ship_os) in computers.iter(world) {
+        for (idx, source, entity) in ship_os.due_bindings(now) {
+            // Only look the entity up again if the one found last time has gone
+            let entity = entity
+                .filter(|entity| world.get_entity(*entity).is_some())
+                .or_else(|| find_source_entity(world, source));
+            let value = entity.and_then(|entity| read_source(world, entity, source));
+            values.push((computer, idx, entity, value));
+        }
+    }
+
+    for (computer, idx, entity, value) in values {
+        if let Some(mut ship_os) = world.get_mut::<ShipOS>(computer) {
+            ship_os.update_binding(idx, entity, value.as_ref(), now);
+        }
     }
 }
 
-// TODO: Handle keyboard capturing being passed between computers and the player character
-fn _capture_keyboard(mut query: Query<&mut ShipOS>, mut evr_kbd: EventReader<KeyboardInput>) {
-    let mut computer = query.single_mut();
-    for ev in evr_kbd.read() {
-        if ev.state == ButtonState::Released {
+// Runs each computer as fast as its power and temperature allow, and warms its CPU up to
+// match. A CPU that's overheated badly enough shuts the whole computer down.
+fn run_computer_hardware(mut computers: Query<(&mut ShipOS, &PowerConsumer, &mut ThermalBody)>) {
+    for (mut ship_os, power, mut cpu) in computers.iter_mut() {
+        let supply = if cpu.state == ThermalState::ShutDown {
+            0.0
+        } else {
+            power.supply
+        };
+        ship_os.set_supply(supply);
+        ship_os.set_clock(cpu.throttle());
+        cpu.heat_output = CPU_HEAT * ship_os.clock();
+    }
+}
+
+fn tick_computers(time: Res<Time>, mut query: Query<&mut ShipOS>) {
+    for mut ship_os in query.iter_mut() {
+        ship_os.update(time.delta_seconds());
+    }
+}
+
+fn draw_screen(mut query: Query<(&mut Text, &mut ShipOS)>) {
+    for (mut text, mut processor) in query.iter_mut() {
+        if !processor.redraw() {
+            continue;
+        }
+
+        // One text section per run of characters with the same attribute, all sharing the
+        // font of the first section
+        let style = text.sections[0].style.clone();
+        text.sections = processor
+            .get_screen()
+            .into_iter()
+            .map(|(value, attribute)| TextSection {
+                value,
+                style: TextStyle {
+                    color: attribute.colour(),
+                    ..style.clone()
+                },
+            })
+            .collect();
+    }
+}
+
+fn capture_keyboard(
+    seated: Option<Res<SeatedAt>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut query: Query<&mut ShipOS>,
+    mut evr_kbd: EventReader<KeyboardInput>,
+) {
+    // Always drain the events, even when nobody's sat at a computer, otherwise the
+    // key that sat us down would be the first thing the computer receives
+    let events: Vec<&KeyboardInput> = evr_kbd.read().collect();
+
+    let Some(seated) = seated else {
+        return;
+    };
+    let mut computer = match query.get_mut(seated.computer) {
+        Ok(computer) => computer,
+        Err(_) => return,
+    };
+
+    let modifiers = Modifiers {
+        shift: keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        ctrl: keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+        alt: keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+    };
+
+    for ev in events {
+        if ev.state == ButtonState::Released || ev.key_code == STAND_UP_KEY {
             continue;
         }
-        computer.handle_keyboard_input(&ev.logical_key);
+        computer.handle_keyboard_input(&ev.logical_key, modifiers);
+    }
+}
+
+fn deliver_pointer_events(
+    mut evr_pointer: EventReader<ScreenPointerEvent>,
+    mut ship_oses: Query<&mut ShipOS>,
+    #[cfg(feature = "terminal")] mut terminals: Query<&mut Terminal>,
+) {
+    for ev in evr_pointer.read() {
+        if let Ok(mut ship_os) = ship_oses.get_mut(ev.computer) {
+            ship_os.handle_pointer_input(ev.row, ev.column, ev.kind);
+        }
+        #[cfg(feature = "terminal")]
+        if let Ok(mut terminal) = terminals.get_mut(ev.computer) {
+            terminal.handle_pointer_input(ev.row, ev.column, ev.kind);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ship_os::binding::BindingSource;
+
+    #[test]
+    fn sample_files_go_on_the_disk_after_the_readme() {
+        let mut app = App::new();
+        app.add_readme_paragraph("FIRST\n")
+            .add_readme_paragraph("SECOND\n")
+            .add_sample_file("TEST.BAS", "10 PRINT 1\n");
+        let mut disk = FileSystem::formatted();
+        app.world().resource::<SampleFiles>().copy_to(&mut disk);
+
+        let readme = disk.read_to_string("README.TXT").unwrap();
+        assert!(readme.starts_with("WELCOME TO SHIPOS"), "{readme}");
+        assert!(
+            readme.ends_with("RUN.\n\nFIRST\n\nSECOND\n"),
+            "{readme}"
+        );
+        assert_eq!(disk.read_to_string("TEST.BAS").unwrap(), "10 PRINT 1\n");
+        assert!(disk.exists("HELLO.BAS"));
+    }
+
+    #[test]
+    fn bindings_find_their_entity_by_name() {
+        let mut app = App::new();
+        app.init_resource::<NamedEntities>();
+        app.add_systems(Update, index_names);
+        let source = BindingSource {
+            entity: "Reactor".to_owned(),
+            component: "Transform".to_owned(),
+            field: String::new(),
+        };
+
+        let reactor = app.world_mut().spawn(Name::new("Reactor")).id();
+        app.world_mut().spawn(Name::new("Pump"));
+        app.update();
+        assert_eq!(find_source_entity(app.world(), &source), Some(reactor));
+
+        // Renamed, it can't be found by its old name any more
+        app.world_mut()
+            .entity_mut(reactor)
+            .insert(Name::new("Old reactor"));
+        app.update();
+        assert_eq!(find_source_entity(app.world(), &source), None);
+
+        // And a new one with the name takes over
+        let reactor = app.world_mut().spawn(Name::new("Reactor")).id();
+        app.update();
+        assert_eq!(find_source_entity(app.world(), &source), Some(reactor));
+
+        app.world_mut().despawn(reactor);
+        app.update();
+        assert_eq!(find_source_entity(app.world(), &source), None);
     }
 }
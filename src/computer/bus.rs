@@ -0,0 +1,162 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use super::ship_os::ShipOS;
+
+// How much RAM the computer has, starting from address 0. The same as the biggest PET 2001.
+pub const RAM_SIZE: usize = 0x8000;
+
+// Devices show up as blocks of registers in the I/O page, which is where the PET kept its
+// I/O chips too. Port n starts at IO_BASE + n * PORT_SIZE.
+pub const IO_BASE: u16 = 0xe800;
+pub const PORT_SIZE: usize = 16;
+pub const N_PORTS: usize = 16;
+
+// What reading from an address with nothing behind it gives. On the real thing it'd be
+// whatever was last on the data bus, but this is close enough.
+const OPEN_BUS: u8 = 0xff;
+
+// Everything the computer can read and write: RAM, plus the registers of whatever devices are
+// plugged in.
+pub struct DeviceBus {
+    ram: Vec<u8>,
+    ports: [Option<SharedPort>; N_PORTS],
+    // How noisy the line to each port is: the chance that reading from it gets a bit
+    // flipped on the way. Nothing but faults make them noisy.
+    line_noise: [f64; N_PORTS],
+    // Seeded, so the same noise turns up in the same places every time
+    noise: SmallRng,
+}
+
+pub type SharedBus = Arc<Mutex<DeviceBus>>;
+
+// A device's registers. Both sides can read and write all of them; which registers the device
+// sets and which it just reacts to is up to the device.
+#[derive(Default)]
+pub struct Port {
+    pub registers: [u8; PORT_SIZE],
+}
+
+// The device keeps hold of its port, so it sees whatever the computer writes straight away
+pub type SharedPort = Arc<Mutex<Port>>;
+
+impl Port {
+    pub fn shared() -> SharedPort {
+        Arc::new(Mutex::new(Port::default()))
+    }
+}
+
+impl Default for DeviceBus {
+    fn default() -> Self {
+        Self {
+            ram: vec![0; RAM_SIZE],
+            ports: Default::default(),
+            line_noise: [0.0; N_PORTS],
+            noise: SmallRng::seed_from_u64(6502),
+        }
+    }
+}
+
+impl DeviceBus {
+    pub fn shared(self) -> SharedBus {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn read(&mut self, address: u16) -> u8 {
+        if let Some(byte) = self.ram.get(address as usize) {
+            return *byte;
+        }
+        let Some((port, register)) = self.port_register(address) else {
+            return OPEN_BUS;
+        };
+        let value = port.lock().expect("Port lock").registers[register];
+        let chance = self.line_noise[(address - IO_BASE) as usize / PORT_SIZE];
+        if chance > 0.0 && self.noise.gen_bool(chance.min(1.0)) {
+            value ^ 1 << self.noise.gen_range(0..8)
+        } else {
+            value
+        }
+    }
+
+    // Writing to an address with nothing behind it does nothing
+    pub fn write(&mut self, address: u16, value: u8) {
+        if let Some(byte) = self.ram.get_mut(address as usize) {
+            *byte = value;
+            return;
+        }
+        if let Some((port, register)) = self.port_register(address) {
+            port.lock().expect("Port lock").registers[register] = value;
+        }
+    }
+
+    // Plugs a device into a port, replacing whatever was there
+    pub fn attach(&mut self, port: usize, device: SharedPort) {
+        if let Some(slot) = self.ports.get_mut(port) {
+            *slot = Some(device);
+        }
+    }
+
+    // Unplugs a device from whichever port it's in, if it's in one
+    pub fn detach(&mut self, device: &SharedPort) {
+        for slot in self.ports.iter_mut() {
+            if slot.as_ref().is_some_and(|port| Arc::ptr_eq(port, device)) {
+                *slot = None;
+            }
+        }
+    }
+
+    // RAM doesn't survive losing power
+    pub fn clear_ram(&mut self) {
+        self.ram.fill(0);
+    }
+
+    // A cosmic ray, or a dodgy chip. Does nothing outside RAM.
+    pub fn flip_ram_bit(&mut self, address: u16, bit: u8) {
+        if let Some(byte) = self.ram.get_mut(address as usize) {
+            *byte ^= 1 << (bit % 8);
+        }
+    }
+
+    // Makes the line to a port noisy, with `chance` of any read from it getting a bit flipped
+    pub fn set_line_noise(&mut self, port: usize, chance: f64) {
+        if let Some(noise) = self.line_noise.get_mut(port) {
+            *noise = chance;
+        }
+    }
+
+    // The device plugged into an address, and which of its registers the address is
+    fn port_register(&self, address: u16) -> Option<(&SharedPort, usize)> {
+        let offset = address.checked_sub(IO_BASE)? as usize;
+        let port = self.ports.get(offset / PORT_SIZE)?.as_ref()?;
+        Some((port, offset % PORT_SIZE))
+    }
+}
+
+// Something plugged into one of a computer's ports
+#[derive(Component)]
+pub struct BusDevice {
+    pub computer: Entity,
+    // Which of the computer's ports it's plugged into
+    pub port: usize,
+    pub registers: SharedPort,
+}
+
+pub fn connect_bus_devices(
+    devices: Query<&BusDevice, Added<BusDevice>>,
+    computers: Query<&ShipOS>,
+) {
+    for device in devices.iter() {
+        let Ok(ship_os) = computers.get(device.computer) else {
+            warn!("Device plugged into something that isn't a computer");
+            continue;
+        };
+        ship_os
+            .bus()
+            .lock()
+            .expect("Bus lock")
+            .attach(device.port, device.registers.clone());
+    }
+}
@@ -1,241 +1,789 @@
+pub mod actions;
+pub mod apps;
+pub mod binding;
+pub mod canvas;
+pub mod charts;
+pub mod filesystem;
+pub mod layout;
+pub mod widgets;
+pub mod windows;
+
+use apps::dashboard::Dashboard;
+use apps::{App, AppContext, PROGRAMS};
 use array2d::Array2D;
-use bevy::{input::keyboard::Key, prelude::Component};
+use bevy::{
+    color::Color,
+    input::keyboard::Key,
+    prelude::{Component, Entity},
+};
+use binding::{BindingSource, BoundValue};
+use canvas::Edges;
+use filesystem::{FileSystem, SharedFileSystem};
+use layout::ScreenLayout;
+use serde::Deserialize;
+
+use super::bus::{DeviceBus, SharedBus};
+use crate::interaction::PointerEventKind;
+
+// How long the app switcher stays up after the last Alt+Tab, in seconds
+const SWITCHER_TIME: f32 = 1.0;
 
+// The fraction of its proper supply the computer needs to keep running. Any less and it
+// resets, the same as a real one would in a brown-out.
+const BROWNOUT_RESET: f32 = 0.85;
+
+// The operating system, or really the shell: it runs apps, and lets you start them and switch
+// between them. The bottom row of the screen is a status bar; the apps get the rest.
+// Keyboard controls:
+// - F10 opens the launcher, which lists everything that can be run
+// - Alt+Tab switches to the next running app
+// - Alt+F4 closes the app in the foreground
+// Everything else goes to the app in the foreground.
 #[derive(Component)]
 pub struct ShipOS {
+    display: Display,
+    apps: Vec<Box<dyn App>>,
+    // Which of the apps is being shown
+    foreground: usize,
+    // The latest screen layout, for apps that are built out of one
+    layout: Option<ScreenLayout>,
+    // Which program is highlighted in the launcher, if it's open
+    launcher: Option<usize>,
+    // How much longer to show the switcher for, if it's up
+    switcher: Option<f32>,
+    // Whether everything needs drawing again, e.g. because a different app is in front now
+    full_redraw: bool,
+    // Whether the launcher or switcher need drawing
+    overlay_changed: bool,
+    // The character cell the mouse is over, if it's over the screen at all
+    pointer: Option<(usize, usize)>,
+    // Whether the mouse has moved since the screen was last drawn
+    pointer_moved: bool,
+    // The computer's disk, which all the apps share
+    files: SharedFileSystem,
+    // RAM and devices, which the apps share too
+    bus: SharedBus,
+    // Whether there's enough power to run. Without it, nothing runs and the screen's blank.
+    powered: bool,
+    // How fast the CPU's running, as a fraction of full speed. Gets turned down if it's too hot.
+    clock: f32,
+}
+
+// The character grid, and everything needed to draw on it
+pub struct Display {
     n_columns: usize,
     n_rows: usize,
     screen: Array2D<char>,
+    attributes: Array2D<Attribute>,
+    // The lines running through each cell, so that lines drawn over them join up properly
+    edges: Array2D<Edges>,
+    // Drawing only affects the screen inside this region
+    clip: Dimensions,
+}
+
+// How a character is displayed. The old text-mode displays stored one of these alongside
+// every character on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Attribute {
+    Normal,
+    Bright,
+    Dim,
+    Warning,
+    Alert,
+    // Picked out, e.g. selected text
+    Highlight,
+}
+
+impl Attribute {
+    pub fn colour(&self) -> Color {
+        // The light grey/white/dark grey of the VGA text-mode palette
+        match self {
+            Attribute::Normal => Color::srgb_u8(0xaa, 0xaa, 0xaa),
+            Attribute::Bright => Color::srgb_u8(0xff, 0xff, 0xff),
+            Attribute::Dim => Color::srgb_u8(0x55, 0x55, 0x55),
+            // Yellow and light red
+            Attribute::Warning => Color::srgb_u8(0xff, 0xff, 0x55),
+            Attribute::Alert => Color::srgb_u8(0xff, 0x55, 0x55),
+            // Light cyan
+            Attribute::Highlight => Color::srgb_u8(0x55, 0xff, 0xff),
+        }
+    }
+}
+
+// Which modifier keys were held down when a key was pressed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
 }
 
 impl ShipOS {
+    // Starts up with the dashboard running, which stays empty until a layout is applied
     pub fn new(n_columns: usize, n_rows: usize) -> Self {
-        let mut result = Self {
-            n_columns,
-            n_rows,
-            screen: Array2D::filled_with(' ', n_rows, n_columns),
+        let mut ship_os = Self {
+            display: Display::new(n_columns, n_rows),
+            apps: Vec::new(),
+            foreground: 0,
+            layout: None,
+            launcher: None,
+            switcher: None,
+            full_redraw: true,
+            overlay_changed: false,
+            pointer: None,
+            pointer_moved: false,
+            files: FileSystem::formatted().shared(),
+            bus: DeviceBus::default().shared(),
+            powered: true,
+            clock: 1.0,
         };
+        ship_os.start(Box::new(Dashboard::default()));
+        ship_os
+    }
+
+    // Replaces the screen layout, and rebuilds any dashboards using it
+    pub fn apply_layout(&mut self, layout: &ScreenLayout) {
+        for app in &mut self.apps {
+            if let Some(dashboard) = app.as_any_mut().downcast_mut::<Dashboard>() {
+                dashboard.apply_layout(layout);
+            }
+        }
+        self.layout = Some(layout.clone());
+        self.full_redraw = true;
+    }
 
-        result.draw_box(
-            Dimensions { top: 0, bottom: 10, left: 2, right: 30 },
-            BoxStyle::Single,
-        );
-        result.draw_box(
-            Dimensions { top: 5, bottom: 20, left: 20, right: 70 },
-            BoxStyle::Double,
-        );
+    // The bindings that are due an update, by index, along with where their values come from
+    // and the entity they were found on last time
+    pub fn due_bindings(
+        &self,
+        now: f64,
+    ) -> impl Iterator<Item = (usize, &BindingSource, Option<Entity>)> {
+        self.dashboard()
+            .into_iter()
+            .flat_map(move |dashboard| dashboard.due_bindings(now))
+    }
 
-        result
+    // Shows a freshly read value in a binding's widget, `None` meaning it couldn't be read
+    pub fn update_binding(
+        &mut self,
+        idx: usize,
+        entity: Option<Entity>,
+        value: Option<&BoundValue>,
+        now: f64,
+    ) {
+        if let Some(dashboard) = self.dashboard_mut() {
+            dashboard.update_binding(idx, entity, value, now);
+        }
     }
 
-    pub fn get_screen(&self) -> String {
-        let mut result = String::new();
+    fn dashboard(&self) -> Option<&Dashboard> {
+        self.apps
+            .iter()
+            .find_map(|app| app.as_any().downcast_ref::<Dashboard>())
+    }
+
+    fn dashboard_mut(&mut self) -> Option<&mut Dashboard> {
+        self.apps
+            .iter_mut()
+            .find_map(|app| app.as_any_mut().downcast_mut::<Dashboard>())
+    }
 
-        for row in self.screen.rows_iter() {
-            result.push_str(row.collect::<String>().as_str());
-            result.push('\n');
+    // Tells the computer how much of the power it wants it's getting, from 0 to 1.
+    // Losing power loses everything that was running, and whatever was in RAM; the disk
+    // keeps its files. When the power comes back, the computer starts up from scratch.
+    pub fn set_supply(&mut self, supply: f32) {
+        let powered = supply >= BROWNOUT_RESET;
+        if powered == self.powered {
+            return;
         }
+        self.powered = powered;
 
-        // Remove final newline
-        result.pop();
+        if powered {
+            self.start(Box::new(Dashboard::default()));
+            return;
+        }
+        self.apps.clear();
+        self.foreground = 0;
+        self.launcher = None;
+        self.switcher = None;
+        self.pointer = None;
+        self.bus.lock().expect("Bus lock").clear_ram();
+        self.display.reset_clip();
+        self.display.clear();
+        self.full_redraw = true;
+    }
 
-        result
+    // Slows the CPU down (or speeds it back up), from 0 to 1
+    pub fn set_clock(&mut self, clock: f32) {
+        self.clock = clock;
     }
 
-    pub fn handle_keyboard_input(&mut self, _key: &Key) {}
+    // How fast the CPU's actually running. Without power, it isn't at all.
+    pub fn clock(&self) -> f32 {
+        if self.powered {
+            self.clock
+        } else {
+            0.0
+        }
+    }
 
-    fn draw_box(&mut self, dimensions: Dimensions, style: BoxStyle) {
-        // Checks
-        if dimensions.top >= dimensions.bottom {
-            panic!("Top must be less than bottom (was top: {}, bottom: {})", dimensions.top, dimensions.bottom);
+    // Called every tick
+    pub fn update(&mut self, delta: f32) {
+        if !self.powered {
+            return;
         }
-        if dimensions.left >= dimensions.right {
-            panic!("Left must be less than right (was left: {}, right: {})", dimensions.left, dimensions.right);
+        // As far as the apps are concerned, a slower CPU means less time passes. That even
+        // goes for the clock, which is how software clocks on old computers behaved too.
+        for app in &mut self.apps {
+            app.update(delta * self.clock);
         }
-        if dimensions.bottom >= self.n_rows {
-            panic!("Bottom must be less than n_rows (was bottom: {}, n_rows: {})", dimensions.bottom, self.n_rows);
+
+        if let Some(remaining) = &mut self.switcher {
+            *remaining -= delta;
+            if *remaining <= 0.0 {
+                self.switcher = None;
+                // Whatever the switcher was covering up needs drawing again
+                self.full_redraw = true;
+            }
         }
-        if dimensions.right >= self.n_columns {
-            panic!("Right must be less than n_columns (was right: {}, n_columns: {})", dimensions.right, self.n_columns);
+    }
+
+    // The computer's RAM and devices
+    pub fn bus(&self) -> &SharedBus {
+        &self.bus
+    }
+
+    // The computer's disk
+    pub fn files(&self) -> &SharedFileSystem {
+        &self.files
+    }
+
+    // Starts an app and brings it to the foreground
+    pub fn start(&mut self, mut app: Box<dyn App>) {
+        app.init(&AppContext {
+            area: self.app_area(),
+            layout: self.layout.as_ref(),
+            files: self.files.clone(),
+            bus: self.bus.clone(),
+        });
+        self.apps.push(app);
+        self.foreground = self.apps.len() - 1;
+        self.full_redraw = true;
+    }
+
+    // Where apps get drawn: everything but the status bar
+    fn app_area(&self) -> Dimensions {
+        Dimensions {
+            top: 0,
+            bottom: self.display.n_rows - 2,
+            left: 0,
+            right: self.display.n_columns - 1,
         }
+    }
 
-        // Strap in for this next bit.
-        // I genuinely can't think of a nicer way of doing this
+    // Redraws whichever parts of the screen have changed since last time.
+    // Returns whether anything did, so the caller knows whether it needs to update what's displayed.
+    pub fn redraw(&mut self) -> bool {
+        if !self.powered {
+            // The screen was cleared when the power went, and there's nothing to draw since
+            return std::mem::take(&mut self.full_redraw);
+        }
+        let full = std::mem::take(&mut self.full_redraw);
+        let area = self.app_area();
 
-        // Top left corner
-        let top_left = self.screen
-            .get_mut(dimensions.top, dimensions.left)
-            .expect("Out of bounds");
+        if full {
+            self.display.reset_clip();
+            self.display.clear();
+        }
 
-        *top_left = match style {
-            BoxStyle::Single => match top_left {
-                '│' | '└' | '├' | '╞' | '╘' => '├',
-                '╖' | '┐' | '┬' | '─' | '╥' => '┬',
-                '┤' | '┴' | '┼' | '┘' => '┼',
-                _ => '┌',
-            },
-            BoxStyle::Double => match top_left {
-                '║' | '╟' | '╚' | '╠' | '╙' => '╠',
-                '╕' | '╗' | '╦' | '═' | '╤' => '╦',
-                '╣' | '╝' | '╩' | '╬' => '╬',
-                _ => '╔',
-            },
+        self.display.set_clip(area.clone());
+        let mut redrawn = match self.apps.get_mut(self.foreground) {
+            Some(app) => app.render(&mut self.display, &area, full),
+            None => {
+                if full {
+                    self.display.put_str(
+                        area.top,
+                        area.left,
+                        "Nothing running. Press F10 to start a program.",
+                        self.display.n_columns,
+                        Attribute::Dim,
+                    );
+                }
+                full
+            }
         };
+        self.display.reset_clip();
 
-        // Top border
-        for col in (dimensions.left + 1)..dimensions.right {
-            let ch = self.screen
-                .get_mut(dimensions.top, col)
-                .expect("Out of bounds");
+        if full {
+            self.draw_status_bar();
+        }
+        // The app might have drawn over the overlays, so they go back on top
+        if redrawn || std::mem::take(&mut self.overlay_changed) {
+            self.draw_overlays();
+            redrawn = true;
+        }
+
+        redrawn || std::mem::take(&mut self.pointer_moved)
+    }
+
+    // The screen, split into runs of characters that share the same attribute
+    pub fn get_screen(&self) -> Vec<(String, Attribute)> {
+        let display = &self.display;
+        let mut result: Vec<(String, Attribute)> = Vec::new();
+
+        for (row_idx, row) in display.screen.rows_iter().enumerate() {
+            for (col_idx, ch) in row.enumerate() {
+                let attribute = display.attributes[(row_idx, col_idx)];
+                let ch = if self.pointer == Some((row_idx, col_idx)) {
+                    // Text-mode mouse cursor, like the old DOS mouse drivers used to draw
+                    '▓'
+                } else {
+                    *ch
+                };
+
+                match result.last_mut() {
+                    Some((text, last_attribute)) if *last_attribute == attribute => text.push(ch),
+                    _ => result.push((ch.to_string(), attribute)),
+                }
+            }
+            if row_idx < display.n_rows - 1 {
+                match result.last_mut() {
+                    Some((text, _)) => text.push('\n'),
+                    None => result.push(("\n".to_owned(), Attribute::Normal)),
+                }
+            }
+        }
 
-            *ch = match style {
-                BoxStyle::Single => match ch {
-                    '│' | '┤' | '╡' | '╛' | '└' | '┴' | '├' | '┼' | '╞' | '╧' | '╘' | '╪' | '┘' => '┴',
-                    '╢' | '╣' | '║' | '╝' | '╜' | '╟' | '╚' | '╩' | '╠' | '╬' | '╨' | '╙' | '╫' => '╨',
-                    _ => '─',
-                },
-                BoxStyle::Double => match ch {
-                    '│' | '┤' | '╡' | '╛' | '└' | '┴' | '├' | '┼' | '╞' | '╧' | '╘' | '╪' | '┘' => '╧',
-                    '╢' | '╣' | '║' | '╝' | '╜' | '╟' | '╚' | '╩' | '╠' | '╬' | '╨' | '╙' | '╫' => '╩',
-                    _ => '═',
-                },
+        result
+    }
+
+    pub fn handle_keyboard_input(&mut self, key: &Key, modifiers: Modifiers) {
+        if !self.powered {
+            return;
+        }
+        if modifiers.alt && *key == Key::Tab {
+            self.switch_app();
+            return;
+        }
+        if modifiers.alt && *key == Key::F4 {
+            self.close_foreground();
+            return;
+        }
+        if *key == Key::F10 {
+            self.launcher = match self.launcher {
+                Some(_) => None,
+                None => Some(0),
             };
+            self.full_redraw = true;
+            return;
         }
 
-        // Top right corner
-        let top_right = self.screen
-            .get_mut(dimensions.top, dimensions.right)
-            .expect("Out of bounds");
+        if let Some(selected) = self.launcher {
+            self.launcher = match key {
+                Key::ArrowUp => Some(selected.saturating_sub(1)),
+                Key::ArrowDown => Some((selected + 1).min(PROGRAMS.len() - 1)),
+                Key::Enter => {
+                    self.launch(selected);
+                    return;
+                }
+                _ => return,
+            };
+            self.overlay_changed = true;
+            return;
+        }
 
-        *top_right = match style {
-            BoxStyle::Single => match top_right {
-                '│' | '┘' | '┤' | '╡' | '╛' => '┤',
-                '╓' | '┌' | '┬' | '─' | '╥' => '┬',
-                '├' | '┴' | '┼' | '└' => '┼',
-                _ => '┐',
-            },
-            BoxStyle::Double => match top_right {
-                '║' | '╢' | '╝' | '╣' | '╜' => '╣',
-                '╒' | '╔' | '╦' | '═' | '╤' => '╦',
-                '╠' | '╚' | '╩' | '╬' => '╬',
-                _ => '╗',
-            },
+        if let Some(app) = self.apps.get_mut(self.foreground) {
+            app.handle_keyboard_input(key, modifiers);
+        }
+    }
+
+    pub fn handle_pointer_input(&mut self, row: usize, column: usize, kind: PointerEventKind) {
+        if !self.powered {
+            return;
+        }
+        self.pointer = match kind {
+            PointerEventKind::Hover | PointerEventKind::Click => Some((row, column)),
+            PointerEventKind::Leave => None,
         };
+        self.pointer_moved = true;
 
-        // Middle rows
-        for row in (dimensions.top + 1)..dimensions.bottom {
-            // Left border
-            let left = self.screen
-                .get_mut(row, dimensions.left)
-                .expect("Out of bounds");
+        if self.launcher.is_some() {
+            // While the launcher's open, clicking a program starts it and clicking anywhere
+            // else closes the launcher
+            if kind == PointerEventKind::Click {
+                match self.launcher_item_at(row, column) {
+                    Some(idx) => self.launch(idx),
+                    None => {
+                        self.launcher = None;
+                        self.full_redraw = true;
+                    }
+                }
+            }
+            return;
+        }
+
+        let area = self.app_area();
+        if !area.contains(row, column) {
+            if kind == PointerEventKind::Click {
+                self.click_status_bar(column);
+            }
+            return;
+        }
+        if let Some(app) = self.apps.get_mut(self.foreground) {
+            app.handle_pointer_input(row - area.top, column - area.left, kind);
+        }
+    }
+
+    // Brings the next app to the foreground, and shows the switcher so you can see where
+    // you've ended up
+    fn switch_app(&mut self) {
+        if self.apps.is_empty() {
+            return;
+        }
+        self.foreground = (self.foreground + 1) % self.apps.len();
+        self.switcher = Some(SWITCHER_TIME);
+        self.full_redraw = true;
+    }
+
+    fn close_foreground(&mut self) {
+        if self.foreground >= self.apps.len() {
+            return;
+        }
+        self.apps.remove(self.foreground);
+        self.foreground = self.foreground.saturating_sub(1);
+        self.full_redraw = true;
+    }
+
+    // Starts the program with this name, or switches to it if it's already running. Returns
+    // whether there was one.
+    pub fn launch_named(&mut self, name: &str) -> bool {
+        match PROGRAMS.iter().position(|program| program.name == name) {
+            Some(idx) => {
+                self.launch(idx);
+                true
+            }
+            None => false,
+        }
+    }
 
-            *left = match style {
-                BoxStyle::Single => match left {
-                    '┤' | '╢' | '╖' | '╜' | '┐' | '┴' | '┬' | '─' | '┼' | '╨' | '╥' | '╫' | '┘' => '┤',
-                    '╡' | '╕' | '╣' | '╗' | '╝' | '╛' | '╩' | '╦' | '═' | '╬' | '╧' | '╤' | '╪' => '╡',
-                    _ => '│',
-                },
-                BoxStyle::Double => match left {
-                    '┤' | '╢' | '╖' | '╜' | '┐' | '┴' | '┬' | '─' | '┼' | '╨' | '╥' | '╫' | '┘' => '╢',
-                    '╡' | '╕' | '╣' | '╗' | '╝' | '╛' | '╩' | '╦' | '═' | '╬' | '╧' | '╤' | '╪' => '╣',
-                    _ => '║',
-                },
+    // Types text in as if it was coming from the keyboard, with newlines for Enter. For
+    // computers without anyone sat at them.
+    pub fn type_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            let key = match ch {
+                '\n' => Key::Enter,
+                _ => Key::Character(ch.to_string().into()),
             };
+            self.handle_keyboard_input(&key, Modifiers::default());
+        }
+    }
 
-            // Middle
-            for col in (dimensions.left + 1)..dimensions.right {
-                self.screen.set(row, col, ' ').expect("Out of bounds");
+    // Switches to the program if it's already running, otherwise starts it
+    fn launch(&mut self, idx: usize) {
+        let program = &PROGRAMS[idx];
+        self.launcher = None;
+        match self.apps.iter().position(|app| app.name() == program.name) {
+            Some(running) => {
+                self.foreground = running;
+                self.full_redraw = true;
             }
+            None => self.start((program.start)()),
+        }
+    }
 
-            // Right border
-            let right = self.screen
-                .get_mut(row, dimensions.right)
-                .expect("Out of bounds");
+    // Where the launcher goes: the bottom left, just above the status bar, like a start menu
+    fn launcher_area(&self) -> Dimensions {
+        let width = PROGRAMS
+            .iter()
+            .map(|program| program.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(" PROGRAMS ".len())
+            + 6;
+        let bottom = self.display.n_rows - 2;
+        Dimensions {
+            top: bottom.saturating_sub(PROGRAMS.len() + 1),
+            bottom,
+            left: 0,
+            right: width - 1,
+        }
+    }
+
+    fn launcher_item_at(&self, row: usize, column: usize) -> Option<usize> {
+        let area = self.launcher_area();
+        if row <= area.top || row >= area.bottom || column <= area.left || column >= area.right {
+            return None;
+        }
+        Some(row - area.top - 1)
+    }
+
+    // Each running app gets a numbered button on the status bar, after the launcher's
+    fn status_bar_items(&self) -> Vec<(usize, String)> {
+        let mut column = 0;
+        let mut items = Vec::new();
+        for label in
+            std::iter::once(" F10 Programs ".to_owned()).chain(self.apps.iter().enumerate().map(
+                |(idx, app)| format!(" {} {} ", idx + 1, app.name()),
+            ))
+        {
+            let width = label.chars().count();
+            items.push((column, label));
+            column += width + 1;
+        }
+        items
+    }
+
+    fn click_status_bar(&mut self, column: usize) {
+        let clicked = self
+            .status_bar_items()
+            .into_iter()
+            .position(|(start, label)| (start..start + label.chars().count()).contains(&column));
+        match clicked {
+            Some(0) => {
+                self.launcher = Some(0);
+                self.overlay_changed = true;
+            }
+            Some(item) => {
+                self.foreground = item - 1;
+                self.full_redraw = true;
+            }
+            None => {}
+        }
+    }
 
-            *right = match style {
-                BoxStyle::Single => match right {
-                    '├' | '╟' | '╓' | '╙' | '┌' | '┴' | '┬' | '─' | '┼' | '╨' | '╥' | '╫' | '└' => '├',
-                    '╞' | '╒' | '╠' | '╔' | '╚' | '╘' | '╩' | '╦' | '═' | '╬' | '╧' | '╤' | '╪' => '╞',
-                    _ => '│',
-                },
-                BoxStyle::Double => match right {
-                    '├' | '╟' | '╓' | '╙' | '┌' | '┴' | '┬' | '─' | '┼' | '╨' | '╥' | '╫' | '└' => '╟',
-                    '╞' | '╒' | '╠' | '╔' | '╚' | '╘' | '╩' | '╦' | '═' | '╬' | '╧' | '╤' | '╪' => '╠',
-                    _ => '║',
-                },
+    fn draw_status_bar(&mut self) {
+        let row = self.display.n_rows - 1;
+        for (idx, (column, label)) in self.status_bar_items().into_iter().enumerate() {
+            let attribute = if idx > 0 && idx - 1 == self.foreground {
+                Attribute::Bright
+            } else {
+                Attribute::Dim
             };
+            self.display
+                .put_str(row, column, &label, label.chars().count(), attribute);
         }
+    }
 
-        // Bottom left corner
-        let bottom_left = self.screen
-            .get_mut(dimensions.bottom, dimensions.left)
-            .expect("Out of bounds");
+    // Overlays sit on top of everything else, so their borders don't join up with anything
+    // underneath them
+    fn draw_overlay_box(&mut self, area: &Dimensions) {
+        self.display.set_clip(area.clone());
+        self.display.clear();
+        self.display.reset_clip();
+        self.display
+            .draw_box(area.clone(), BoxStyle::Double, Attribute::Bright);
+    }
 
-        *bottom_left = match style {
-            BoxStyle::Single => match bottom_left {
-                '│' | '┌' | '├' | '╞' | '╒' => '├',
-                '╜' | '┘' | '┴' | '─' | '╨' => '┴',
-                '┤' | '┬' | '┼' | '┐' => '┼',
-                _ => '└',
-            },
-            BoxStyle::Double => match bottom_left {
-                '║' | '╟' | '╔' | '╠' | '╓' => '╠',
-                '╛' | '╝' | '╩' | '═' | '╧' => '╩',
-                '╣' | '╗' | '╦' | '╬' => '╬',
-                _ => '╚',
+    fn draw_overlays(&mut self) {
+        if let Some(selected) = self.launcher {
+            let area = self.launcher_area();
+            self.draw_overlay_box(&area);
+            self.display
+                .put_str(area.top, area.left + 2, " PROGRAMS ", 10, Attribute::Bright);
+            for (idx, program) in PROGRAMS.iter().enumerate() {
+                let (marker, attribute) = if idx == selected {
+                    ('►', Attribute::Bright)
+                } else {
+                    (' ', Attribute::Normal)
+                };
+                self.display.put_str(
+                    area.top + 1 + idx,
+                    area.left + 1,
+                    &format!("{} {}", marker, program.name),
+                    area.right - area.left - 1,
+                    attribute,
+                );
+            }
+        }
+
+        if self.switcher.is_some() && !self.apps.is_empty() {
+            // In the middle of the screen, with the app we've switched to highlighted
+            let width = self
+                .apps
+                .iter()
+                .map(|app| app.name().chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(" SWITCH TO ".len())
+                + 6;
+            let width = width.min(self.display.n_columns);
+            // With more apps running than fit on the screen, the list scrolls to keep the one
+            // that's been switched to in view
+            let height = (self.apps.len() + 2).min(self.display.n_rows);
+            let shown = height.saturating_sub(2);
+            let first = (self.foreground + 1).saturating_sub(shown);
+            let top = (self.display.n_rows - height) / 2;
+            let left = (self.display.n_columns - width) / 2;
+            let area = Dimensions {
+                top,
+                bottom: top + height - 1,
+                left,
+                right: left + width - 1,
+            };
+            self.draw_overlay_box(&area);
+            self.display
+                .put_str(top, left + 2, " SWITCH TO ", 11, Attribute::Bright);
+            for (idx, app) in self.apps.iter().enumerate().skip(first).take(shown) {
+                let (marker, attribute) = if idx == self.foreground {
+                    ('►', Attribute::Bright)
+                } else {
+                    (' ', Attribute::Normal)
+                };
+                self.display.put_str(
+                    top + 1 + idx - first,
+                    left + 1,
+                    &format!("{} {}", marker, app.name()),
+                    width - 2,
+                    attribute,
+                );
+            }
+        }
+    }
+}
+
+impl Display {
+    pub fn new(n_columns: usize, n_rows: usize) -> Self {
+        Self {
+            n_columns,
+            n_rows,
+            screen: Array2D::filled_with(' ', n_rows, n_columns),
+            attributes: Array2D::filled_with(Attribute::Normal, n_rows, n_columns),
+            edges: Array2D::filled_with(Edges::default(), n_rows, n_columns),
+            clip: Dimensions {
+                top: 0,
+                bottom: n_rows - 1,
+                left: 0,
+                right: n_columns - 1,
             },
+        }
+    }
+
+    // Restricts drawing to part of the screen. The clipping region can't go off the screen.
+    pub fn set_clip(&mut self, clip: Dimensions) {
+        self.clip = Dimensions {
+            bottom: clip.bottom.min(self.n_rows - 1),
+            right: clip.right.min(self.n_columns - 1),
+            ..clip
         };
+    }
 
-        // Bottom border
-        for col in (dimensions.left + 1)..dimensions.right {
-            let ch = self.screen
-                .get_mut(dimensions.bottom, col)
-                .expect("Out of bounds");
+    pub fn reset_clip(&mut self) {
+        self.clip = Dimensions {
+            top: 0,
+            bottom: self.n_rows - 1,
+            left: 0,
+            right: self.n_columns - 1,
+        };
+    }
 
-            *ch = match style {
-                BoxStyle::Single => match ch {
-                    '│' | '┤' | '╡' | '╕' | '┌' | '┬' | '├' | '┼' | '╞' | '╤' | '╒' | '╪' | '┐' => '┬',
-                    '╢' | '╣' | '║' | '╗' | '╖' | '╟' | '╔' | '╦' | '╠' | '╬' | '╥' | '╓' | '╫' => '╥',
-                    _ => '─',
-                },
-                BoxStyle::Double => match ch {
-                    '│' | '┤' | '╡' | '╕' | '┌' | '┬' | '├' | '┼' | '╞' | '╤' | '╒' | '╪' | '┐' => '╤',
-                    '╢' | '╣' | '║' | '╗' | '╖' | '╟' | '╔' | '╦' | '╠' | '╬' | '╥' | '╓' | '╫' => '╦',
-                    _ => '═',
-                },
-            };
+    // Blanks out everything inside the clipping region
+    pub fn clear(&mut self) {
+        for row in self.clip.top..=self.clip.bottom {
+            for col in self.clip.left..=self.clip.right {
+                self.put_char(row, col, ' ', Attribute::Normal);
+            }
         }
+    }
 
-        // Bottom right corner
-        let bottom_right = self.screen
-            .get_mut(dimensions.bottom, dimensions.right)
-            .expect("Out of bounds");
+    // Writes a string to the screen, starting at the given position, cutting it off if it's
+    // longer than `max_width` characters or runs out of the clipping region
+    pub fn put_str(
+        &mut self,
+        row: usize,
+        column: usize,
+        text: &str,
+        max_width: usize,
+        attribute: Attribute,
+    ) {
+        for (col, ch) in (column..self.n_columns).zip(text.chars()).take(max_width) {
+            self.put_char(row, col, ch, attribute);
+        }
+    }
 
-        *bottom_right = match style {
-            BoxStyle::Single => match bottom_right {
-                '│' | '┐' | '┤' | '╡' | '╕' => '┤',
-                '╙' | '└' | '┴' | '─' | '╨' => '┴',
-                '├' | '┬' | '┼' | '┌' => '┼',
-                _ => '┘',
-            },
-            BoxStyle::Double => match bottom_right {
-                '║' | '╢' | '╗' | '╣' | '╖' => '╣',
-                '╘' | '╚' | '╩' | '═' | '╧' => '╩',
-                '╠' | '╔' | '╦' | '╬' => '╬',
-                _ => '╝',
-            },
-        };
+    // Writes a single character, if it's inside the clipping region. Whatever lines were
+    // running through the cell are gone now.
+    pub fn put_char(&mut self, row: usize, column: usize, ch: char, attribute: Attribute) {
+        if self.clip.contains(row, column) {
+            self.screen.set(row, column, ch).expect("Out of bounds");
+            self.attributes
+                .set(row, column, attribute)
+                .expect("Out of bounds");
+            self.edges
+                .set(row, column, Edges::default())
+                .expect("Out of bounds");
+        }
     }
 }
 
-struct Dimensions {
-    top: usize,
-    bottom: usize,
-    left: usize,
-    right: usize,
+// All inclusive, so a box with `left: 0, right: 2` is three characters wide
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dimensions {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Dimensions {
+    pub fn contains(&self, row: usize, column: usize) -> bool {
+        (self.top..=self.bottom).contains(&row) && (self.left..=self.right).contains(&column)
+    }
+
+    // The area covered by both, if they overlap at all
+    pub fn intersection(&self, other: &Dimensions) -> Option<Dimensions> {
+        let result = Dimensions {
+            top: self.top.max(other.top),
+            bottom: self.bottom.min(other.bottom),
+            left: self.left.max(other.left),
+            right: self.right.min(other.right),
+        };
+        if result.top <= result.bottom && result.left <= result.right {
+            Some(result)
+        } else {
+            None
+        }
+    }
 }
 
-enum BoxStyle {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BoxStyle {
     Single,
     Double,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::apps::clock::Clock;
+    use super::*;
+
+    #[test]
+    fn launching_a_running_program_switches_to_it() {
+        let mut ship_os = ShipOS::new(80, 25);
+        for program in PROGRAMS {
+            assert!(ship_os.launch_named(program.name));
+            assert!(ship_os.launch_named(program.name));
+        }
+        // The dashboard was already running
+        assert_eq!(ship_os.apps.len(), PROGRAMS.len());
+        assert!(ship_os.launch_named(PROGRAMS[1].name));
+        assert_eq!(ship_os.apps[ship_os.foreground].name(), PROGRAMS[1].name);
+    }
+
+    #[test]
+    fn switcher_fits_on_the_screen_however_many_apps_are_running() {
+        let mut ship_os = ShipOS::new(20, 6);
+        for _ in 0..10 {
+            ship_os.start(Box::new(Clock::default()));
+        }
+        for _ in 0..12 {
+            ship_os.switch_app();
+            ship_os.redraw();
+        }
+        let screen: String = ship_os
+            .get_screen()
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert!(screen.contains('►'));
+    }
+}
@@ -0,0 +1,183 @@
+use serde::Deserialize;
+
+use super::widgets::{Widget, WidgetEvent};
+use super::windows::{WindowId, WindowManager};
+
+// Something for a screen to do when one of its widgets gets used, e.g. writing to a log when
+// a button's pressed. Like bindings, these live in the screen layout, so a screen's buttons
+// and lists can do things without anyone writing any code for them.
+#[derive(Clone, Deserialize)]
+pub struct Reaction {
+    // The name of the widget that gets used
+    pub widget: String,
+    pub when: Trigger,
+    // Done in order
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Trigger {
+    Pressed,
+    Checked,
+    Unchecked,
+    // The highlighted item in a list box has changed
+    Selected,
+    // Enter was pressed on an item in a list box
+    Activated,
+    // Enter was pressed in a text input
+    Submitted,
+    // A progress bar has been filled up by `AddProgress`
+    Filled,
+}
+
+// `{}` in any of the text stands for what the widget was used on: the list item for
+// `Selected` and `Activated`, and what was typed for `Submitted`
+#[derive(Clone, Deserialize)]
+pub enum Action {
+    // Changes what a label says
+    SetText {
+        widget: String,
+        text: String,
+    },
+    // Adds a line to the bottom of a scroll text
+    Log {
+        widget: String,
+        text: String,
+    },
+    // Empties a text input
+    Clear {
+        widget: String,
+    },
+    SetProgress {
+        widget: String,
+        progress: f32,
+    },
+    // Moves a progress bar along, stopping at full. Filling it up sets off its `Filled`
+    // reactions.
+    AddProgress {
+        widget: String,
+        amount: f32,
+    },
+    OpenDialog {
+        title: String,
+        lines: Vec<String>,
+        #[serde(default = "default_buttons")]
+        buttons: Vec<String>,
+    },
+}
+
+fn default_buttons() -> Vec<String> {
+    vec!["OK".to_owned()]
+}
+
+// Does whatever the reactions say to for a widget event
+pub fn react(
+    reactions: &[Reaction],
+    windows: &mut WindowManager,
+    window: WindowId,
+    event: &WidgetEvent,
+) {
+    let Some(name) = windows
+        .widget_name(window, event.widget())
+        .map(str::to_owned)
+    else {
+        return;
+    };
+    let (trigger, value) = match event {
+        WidgetEvent::Pressed(_) => (Trigger::Pressed, String::new()),
+        WidgetEvent::Toggled(_, true) => (Trigger::Checked, String::new()),
+        WidgetEvent::Toggled(_, false) => (Trigger::Unchecked, String::new()),
+        WidgetEvent::Selected(_, idx) => (Trigger::Selected, list_item(windows, &name, *idx)),
+        WidgetEvent::Activated(_, idx) => (Trigger::Activated, list_item(windows, &name, *idx)),
+        WidgetEvent::Submitted(_, text) => (Trigger::Submitted, text.clone()),
+    };
+
+    let filled = run(reactions, windows, &name, trigger, &value);
+    // Only one level of this, so that reactions to a bar filling up can't set each other off
+    // forever
+    for bar in filled {
+        run(reactions, windows, &bar, Trigger::Filled, "");
+    }
+}
+
+// Does the actions of every reaction that matches, and gives back the names of any progress
+// bars that they filled up
+fn run(
+    reactions: &[Reaction],
+    windows: &mut WindowManager,
+    name: &str,
+    trigger: Trigger,
+    value: &str,
+) -> Vec<String> {
+    let mut filled = Vec::new();
+    let matching = reactions
+        .iter()
+        .filter(|reaction| reaction.widget == name && reaction.when == trigger);
+    for action in matching.flat_map(|reaction| &reaction.actions) {
+        match action {
+            Action::SetText { widget, text } => update_widget(windows, widget, |widget| {
+                if let Widget::Label(label) = widget {
+                    label.text = text.replace("{}", value);
+                }
+            }),
+            Action::Log { widget, text } => update_widget(windows, widget, |widget| {
+                if let Widget::ScrollText(log) = widget {
+                    log.push_line(&text.replace("{}", value));
+                }
+            }),
+            Action::Clear { widget } => update_widget(windows, widget, |widget| {
+                if let Widget::TextInput(input) = widget {
+                    input.clear();
+                }
+            }),
+            Action::SetProgress { widget, progress } => update_widget(windows, widget, |widget| {
+                if let Widget::ProgressBar(bar) = widget {
+                    bar.progress = progress.clamp(0.0, 1.0);
+                }
+            }),
+            Action::AddProgress {
+                widget: name,
+                amount,
+            } => update_widget(windows, name, |widget| {
+                if let Widget::ProgressBar(bar) = widget {
+                    let was_full = bar.progress >= 1.0;
+                    bar.progress = (bar.progress + amount).clamp(0.0, 1.0);
+                    if !was_full && bar.progress >= 1.0 {
+                        filled.push(name.clone());
+                    }
+                }
+            }),
+            Action::OpenDialog {
+                title,
+                lines,
+                buttons,
+            } => {
+                let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                let buttons: Vec<&str> = buttons.iter().map(String::as_str).collect();
+                windows.open_dialog(title, &lines, &buttons);
+            }
+        }
+    }
+    filled
+}
+
+// Changes a named widget, and makes sure the change gets drawn
+fn update_widget(windows: &mut WindowManager, name: &str, update: impl FnOnce(&mut Widget)) {
+    let Some((window, widget)) = windows.find_widget(name) else {
+        return;
+    };
+    if let Some(window_contents) = windows.get_mut(window) {
+        update(window_contents.widgets.get_mut(widget));
+        windows.invalidate(window);
+    }
+}
+
+fn list_item(windows: &mut WindowManager, name: &str, idx: usize) -> String {
+    let mut item = String::new();
+    update_widget(windows, name, |widget| {
+        if let Widget::ListBox(list_box) = widget {
+            item = list_box.items.get(idx).cloned().unwrap_or_default();
+        }
+    });
+    item
+}
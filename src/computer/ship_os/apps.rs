@@ -0,0 +1,88 @@
+pub mod basic;
+pub mod clock;
+pub mod console;
+pub mod dashboard;
+pub mod editor;
+pub mod forth;
+
+use std::any::Any;
+
+use bevy::input::keyboard::Key;
+
+use super::filesystem::SharedFileSystem;
+use super::layout::ScreenLayout;
+use super::{Dimensions, Display, Modifiers};
+use crate::computer::bus::SharedBus;
+use crate::interaction::PointerEventKind;
+
+// A program running on ShipOS. Several can be running at once, but only the one in the
+// foreground gets drawn and hears about input.
+pub trait App: Send + Sync {
+    // Shown in the launcher, the switcher and the status bar
+    fn name(&self) -> &str;
+
+    // So ShipOS can find a particular kind of app among the ones running (the dashboard, say).
+    // Each app just gives back `self`.
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    // Called once, when the app starts
+    fn init(&mut self, _context: &AppContext) {}
+
+    // Called every tick, whether or not the app is in the foreground
+    fn update(&mut self, _delta: f32) {}
+
+    // Draws the app into `area`. If `full` is set, everything needs drawing (e.g. because the
+    // app has just come to the foreground); otherwise only whatever's changed since last time.
+    // Drawing is clipped to `area`, so there's no need to worry about going over the edges.
+    // Returns whether anything was drawn.
+    fn render(&mut self, display: &mut Display, area: &Dimensions, full: bool) -> bool;
+
+    fn handle_keyboard_input(&mut self, _key: &Key, _modifiers: Modifiers) {}
+
+    // Positions are relative to the top left of the app's area
+    fn handle_pointer_input(&mut self, _row: usize, _column: usize, _kind: PointerEventKind) {}
+}
+
+// What an app gets to know about the computer it's running on when it starts
+pub struct AppContext<'a> {
+    // Where the app will be drawn
+    pub area: Dimensions,
+    // The computer's current screen layout, if it has one
+    pub layout: Option<&'a ScreenLayout>,
+    // The computer's disk
+    pub files: SharedFileSystem,
+    // The computer's RAM and devices
+    pub bus: SharedBus,
+}
+
+// Something the launcher can start. Its name has to be the same one its app gives, or the
+// launcher can't tell it's already running, so each app has a `NAME` for both to use.
+pub struct Program {
+    pub name: &'static str,
+    pub start: fn() -> Box<dyn App>,
+}
+
+// Everything that's installed, in the order the launcher shows it
+pub const PROGRAMS: &[Program] = &[
+    Program {
+        name: dashboard::NAME,
+        start: || Box::new(dashboard::Dashboard::default()),
+    },
+    Program {
+        name: clock::NAME,
+        start: || Box::new(clock::Clock::default()),
+    },
+    Program {
+        name: basic::NAME,
+        start: || Box::new(basic::Basic::default()),
+    },
+    Program {
+        name: editor::NAME,
+        start: || Box::new(editor::Editor::default()),
+    },
+    Program {
+        name: forth::NAME,
+        start: || Box::new(forth::Forth::default()),
+    },
+];
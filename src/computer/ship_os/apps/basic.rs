@@ -0,0 +1,168 @@
+mod interpreter;
+mod parser;
+
+use std::any::Any;
+
+use bevy::input::keyboard::Key;
+use thiserror::Error;
+
+use super::console::Console;
+use super::{App, AppContext};
+use crate::computer::ship_os::filesystem::FileSystemError;
+use crate::computer::ship_os::{Dimensions, Display, Modifiers};
+use interpreter::Interpreter;
+
+// How fast programs run. The PET managed somewhere around a thousand simple statements a
+// second, so this is a bit generous.
+const STATEMENTS_PER_SECOND: f32 = 2000.0;
+
+const BANNER: &str = "\n    *** SHIPOS BASIC V2 ***\n\n 32768 BYTES RAM SYSTEM\n\nREADY.\n";
+
+// The errors BASIC reports, worded the way Commodore BASIC words them
+#[derive(Debug, Clone, Error)]
+pub enum BasicError {
+    #[error("SYNTAX")]
+    Syntax,
+    #[error("UNDEF'D STATEMENT")]
+    UndefinedStatement,
+    #[error("RETURN WITHOUT GOSUB")]
+    ReturnWithoutGosub,
+    #[error("NEXT WITHOUT FOR")]
+    NextWithoutFor,
+    #[error("TYPE MISMATCH")]
+    TypeMismatch,
+    #[error("DIVISION BY ZERO")]
+    DivisionByZero,
+    #[error("OVERFLOW")]
+    Overflow,
+    #[error("ILLEGAL QUANTITY")]
+    IllegalQuantity,
+    #[error("BAD SUBSCRIPT")]
+    BadSubscript,
+    #[error("REDIM'D ARRAY")]
+    RedimmedArray,
+    #[error("OUT OF MEMORY")]
+    OutOfMemory,
+    #[error("ILLEGAL DIRECT")]
+    IllegalDirect,
+    #[error("MISSING FILE NAME")]
+    MissingFileName,
+    #[error("FILE NOT FOUND")]
+    FileNotFound,
+    #[error("BAD FILE NAME")]
+    BadFileName,
+    #[error("DISK FULL")]
+    DiskFull,
+}
+
+impl From<FileSystemError> for BasicError {
+    fn from(error: FileSystemError) -> Self {
+        match error {
+            FileSystemError::NotFound(_) => BasicError::FileNotFound,
+            FileSystemError::InvalidName(_) => BasicError::BadFileName,
+            FileSystemError::DiskFull => BasicError::DiskFull,
+        }
+    }
+}
+
+pub const NAME: &str = "BASIC";
+
+// A BASIC prompt, like the one the PET starts up into.
+// Lines typed in with a number in front get stored in the program; anything else runs
+// straight away. Escape does what RUN/STOP did, and stops whatever's running.
+#[derive(Default)]
+pub struct Basic {
+    // Only missing until the app's started
+    interpreter: Option<Interpreter>,
+    console: Console,
+    // Statements' worth of time saved up, so that programs run at the same speed whatever
+    // the frame rate
+    budget: f32,
+}
+
+impl Basic {
+    // Picks up anything the interpreter's printed
+    fn collect_output(&mut self) {
+        let Some(interpreter) = &mut self.interpreter else {
+            return;
+        };
+        let output = interpreter.take_output();
+        if !output.is_empty() {
+            self.console.print(&output);
+        }
+    }
+}
+
+impl App for Basic {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn init(&mut self, context: &AppContext) {
+        self.interpreter = Some(Interpreter::new(context.files.clone(), context.bus.clone()));
+        // The PET only had capitals
+        self.console.capitals_only = true;
+        self.console.print(BANNER);
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.console.update(delta);
+
+        let Some(interpreter) = &mut self.interpreter else {
+            return;
+        };
+        if !interpreter.is_busy() || interpreter.is_waiting_for_input() {
+            self.budget = 0.0;
+            return;
+        }
+        self.budget += delta * STATEMENTS_PER_SECOND;
+        let statements = self.budget as usize;
+        self.budget -= statements as f32;
+        interpreter.run(statements);
+        self.collect_output();
+    }
+
+    fn render(&mut self, display: &mut Display, area: &Dimensions, full: bool) -> bool {
+        // The cursor only shows when something can be typed in
+        let accepting_input = self.interpreter.as_ref().is_some_and(|interpreter| {
+            !interpreter.is_busy() || interpreter.is_waiting_for_input()
+        });
+        self.console.render(display, area, full, accepting_input)
+    }
+
+    fn handle_keyboard_input(&mut self, key: &Key, _modifiers: Modifiers) {
+        let Some(interpreter) = &mut self.interpreter else {
+            return;
+        };
+
+        if *key == Key::Escape {
+            interpreter.break_program();
+            self.console.clear_input();
+            self.collect_output();
+            return;
+        }
+        // Typing doesn't do anything while a program's running, unless it's asking for input
+        if interpreter.is_busy() && !interpreter.is_waiting_for_input() {
+            return;
+        }
+
+        let Some(line) = self.console.handle_keyboard_input(key) else {
+            return;
+        };
+        self.console.print(&format!("{}\n", line));
+        if interpreter.is_waiting_for_input() {
+            interpreter.provide_input(&line);
+        } else {
+            interpreter.enter(&line);
+        }
+        self.collect_output();
+    }
+}
@@ -0,0 +1,1108 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::parser::{
+    normalise, parse_line, split_line_number, BinaryOp, Expr, Function, LValue, PrintItem,
+    Statement,
+};
+use super::BasicError;
+use crate::computer::bus::SharedBus;
+use crate::computer::ship_os::filesystem::SharedFileSystem;
+
+// How wide PRINT's comma columns are
+const ZONE_WIDTH: usize = 10;
+
+// How deep GOSUBs and FOR loops can nest before running out of stack
+const MAX_STACK: usize = 64;
+
+// How big an array gets if it's used without being DIMmed first
+const DEFAULT_ARRAY_SIZE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Str(String),
+}
+
+// Where the next statement to run is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Location {
+    // In whatever was typed in without a line number
+    Immediate(usize),
+    // Line number, and which statement on the line
+    Line(u32, usize),
+}
+
+struct ProgramLine {
+    // As typed in, for LIST and SAVE
+    text: String,
+    // A line that doesn't parse can still be stored; it's only an error if it gets run
+    statements: Result<Vec<Statement>, BasicError>,
+}
+
+struct Array {
+    // How big each dimension is
+    sizes: Vec<usize>,
+    values: Vec<Value>,
+}
+
+struct ForLoop {
+    variable: String,
+    to: f64,
+    step: f64,
+    // Where the body of the loop starts
+    body: Option<Location>,
+}
+
+struct Subroutine {
+    return_to: Option<Location>,
+    // FOR loops started inside the subroutine get thrown away when it returns
+    for_depth: usize,
+}
+
+struct PendingInput {
+    prompt: String,
+    targets: Vec<LValue>,
+    // How many of the targets have been filled in so far
+    filled: usize,
+    // The line with the INPUT on it, for error messages
+    line: Option<u32>,
+}
+
+// Runs BASIC programs. Everything it prints goes into a buffer, which whoever's showing it on
+// the screen picks up with `take_output`.
+pub struct Interpreter {
+    program: BTreeMap<u32, ProgramLine>,
+    // The statements typed in without a line number
+    immediate: Vec<Statement>,
+    variables: HashMap<String, Value>,
+    arrays: HashMap<String, Array>,
+    for_loops: Vec<ForLoop>,
+    subroutines: Vec<Subroutine>,
+    // What runs next, if anything's running
+    location: Option<Location>,
+    // Set while INPUT is waiting for something to be typed in
+    input: Option<PendingInput>,
+    output: String,
+    // Where the cursor is on the current line, for lining up PRINT's columns
+    column: usize,
+    random_state: u64,
+    files: SharedFileSystem,
+    bus: SharedBus,
+}
+
+impl Interpreter {
+    pub fn new(files: SharedFileSystem, bus: SharedBus) -> Self {
+        Self {
+            program: BTreeMap::new(),
+            immediate: Vec::new(),
+            variables: HashMap::new(),
+            arrays: HashMap::new(),
+            for_loops: Vec::new(),
+            subroutines: Vec::new(),
+            location: None,
+            input: None,
+            output: String::new(),
+            column: 0,
+            random_state: 0x2545_f491_4f6c_dd1d,
+            files,
+            bus,
+        }
+    }
+
+    // Everything that's been printed since last time
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    // Whether something's running (including waiting for input)
+    pub fn is_busy(&self) -> bool {
+        self.location.is_some() || self.input.is_some()
+    }
+
+    pub fn is_waiting_for_input(&self) -> bool {
+        self.input.is_some()
+    }
+
+    // Handles a line typed in at the READY prompt. Lines starting with a number get stored in
+    // the program (or deleted, if there's nothing after the number); anything else runs
+    // straight away.
+    pub fn enter(&mut self, line: &str) {
+        let line = normalise(line);
+        if line.is_empty() {
+            return;
+        }
+
+        match split_line_number(&line) {
+            Ok((Some(number), rest)) => self.store_line(number, rest),
+            Ok((None, _)) => match parse_line(&line) {
+                Ok(statements) => {
+                    self.immediate = statements;
+                    self.location = Some(Location::Immediate(0));
+                }
+                Err(error) => self.fail(error, None),
+            },
+            Err(error) => self.fail(error, None),
+        }
+    }
+
+    // Gives INPUT what was typed in
+    pub fn provide_input(&mut self, line: &str) {
+        let Some(mut pending) = self.input.take() else {
+            return;
+        };
+        self.column = 0;
+
+        let values: Vec<&str> = line.split(',').collect();
+        let mut values = values.into_iter();
+        while pending.filled < pending.targets.len() {
+            let Some(text) = values.next() else {
+                // Not enough to go round, so ask for the rest
+                self.print("?? ");
+                self.input = Some(pending);
+                return;
+            };
+
+            let target = pending.targets[pending.filled].clone();
+            let value = if target.name.ends_with('$') {
+                Value::Str(text.trim().to_owned())
+            } else {
+                match text.trim().parse::<f64>() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => {
+                        // Start again from the beginning
+                        self.print("?REDO FROM START\n");
+                        self.print(&format!("{}? ", pending.prompt));
+                        pending.filled = 0;
+                        self.input = Some(pending);
+                        return;
+                    }
+                }
+            };
+            if let Err(error) = self.assign(&target, value) {
+                self.fail(error, pending.line);
+                return;
+            }
+            pending.filled += 1;
+        }
+
+        if values.next().is_some() {
+            self.print("?EXTRA IGNORED\n");
+        }
+        // The INPUT might have been the last thing in the program
+        if self.location.is_none() {
+            self.finish();
+        }
+    }
+
+    // What RUN/STOP does
+    pub fn break_program(&mut self) {
+        if !self.is_busy() {
+            return;
+        }
+        // If it's waiting for input, the line with the INPUT on it is the one that got stopped
+        let line = match self.input.take() {
+            Some(pending) => pending.line,
+            None => self.location.and_then(line_of),
+        };
+        self.location = None;
+        self.new_line();
+        match line {
+            Some(number) => self.print(&format!("BREAK IN {}\n", number)),
+            None => self.print("BREAK\n"),
+        }
+        self.print("READY.\n");
+    }
+
+    // Runs up to `budget` statements, stopping early if the program finishes or is waiting
+    // for input
+    pub fn run(&mut self, budget: usize) {
+        for _ in 0..budget {
+            if self.input.is_some() {
+                return;
+            }
+            let Some(location) = self.location else {
+                return;
+            };
+
+            let statement = match self.statement_at(location) {
+                Ok(Some(statement)) => statement,
+                // Run off the end of the program
+                Ok(None) => {
+                    self.finish();
+                    return;
+                }
+                Err(error) => {
+                    self.fail(error, line_of(location));
+                    return;
+                }
+            };
+
+            self.location = self.after(location);
+            if let Err(error) = self.execute(statement, location) {
+                self.fail(error, line_of(location));
+                return;
+            }
+            if self.location.is_none() && self.input.is_none() {
+                self.finish();
+                return;
+            }
+        }
+    }
+
+    fn store_line(&mut self, number: u32, text: &str) {
+        // Changing the program throws away the variables, same as on the real thing
+        self.clear();
+        if text.is_empty() {
+            self.program.remove(&number);
+            return;
+        }
+        self.program.insert(
+            number,
+            ProgramLine {
+                text: text.to_owned(),
+                statements: parse_line(text),
+            },
+        );
+    }
+
+    // Forgets all the variables, and anything that was running
+    fn clear(&mut self) {
+        self.variables.clear();
+        self.arrays.clear();
+        self.for_loops.clear();
+        self.subroutines.clear();
+    }
+
+    fn finish(&mut self) {
+        self.location = None;
+        self.new_line();
+        self.print("READY.\n");
+    }
+
+    fn fail(&mut self, error: BasicError, line: Option<u32>) {
+        self.location = None;
+        self.input = None;
+        self.new_line();
+        match line {
+            Some(line) => self.print(&format!("?{} ERROR IN {}\n", error, line)),
+            None => self.print(&format!("?{} ERROR\n", error)),
+        }
+        self.print("READY.\n");
+    }
+
+    fn print(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch == '\n' {
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.output.push_str(text);
+    }
+
+    // Makes sure whatever comes next starts on a line of its own
+    fn new_line(&mut self) {
+        if self.column != 0 {
+            self.print("\n");
+        }
+    }
+
+    fn statement_at(&self, location: Location) -> Result<Option<Statement>, BasicError> {
+        match location {
+            Location::Immediate(idx) => Ok(self.immediate.get(idx).cloned()),
+            Location::Line(number, idx) => match self.program.get(&number) {
+                Some(line) => match &line.statements {
+                    Ok(statements) => Ok(statements.get(idx).cloned()),
+                    Err(error) => Err(error.clone()),
+                },
+                None => Ok(None),
+            },
+        }
+    }
+
+    // Where to carry on from after a statement
+    fn after(&self, location: Location) -> Option<Location> {
+        match location {
+            Location::Immediate(idx) => {
+                (idx + 1 < self.immediate.len()).then_some(Location::Immediate(idx + 1))
+            }
+            Location::Line(number, idx) => {
+                let n_statements = match self.program.get(&number) {
+                    Some(ProgramLine {
+                        statements: Ok(statements),
+                        ..
+                    }) => statements.len(),
+                    _ => 0,
+                };
+                if idx + 1 < n_statements {
+                    Some(Location::Line(number, idx + 1))
+                } else {
+                    self.line_after(number)
+                }
+            }
+        }
+    }
+
+    fn line_after(&self, number: u32) -> Option<Location> {
+        self.program
+            .range(number + 1..)
+            .next()
+            .map(|(next, _)| Location::Line(*next, 0))
+    }
+
+    fn jump(&mut self, number: u32) -> Result<(), BasicError> {
+        if !self.program.contains_key(&number) {
+            return Err(BasicError::UndefinedStatement);
+        }
+        self.location = Some(Location::Line(number, 0));
+        Ok(())
+    }
+
+    fn execute(&mut self, statement: Statement, location: Location) -> Result<(), BasicError> {
+        match statement {
+            Statement::Print(items) => {
+                let mut new_line = true;
+                for item in &items {
+                    new_line = false;
+                    match item {
+                        PrintItem::Expr(expr) => {
+                            new_line = true;
+                            match self.evaluate(expr)? {
+                                // Numbers get a space after them, as well as the one in front
+                                Value::Number(number) => {
+                                    self.print(&format!("{} ", format_number(number)))
+                                }
+                                Value::Str(text) => self.print(&text),
+                            }
+                        }
+                        PrintItem::NextZone => {
+                            let spaces = ZONE_WIDTH - self.column % ZONE_WIDTH;
+                            self.print(&" ".repeat(spaces));
+                        }
+                        PrintItem::Join => {}
+                    }
+                }
+                if new_line {
+                    self.print("\n");
+                }
+            }
+            Statement::Input { prompt, targets } => {
+                let Location::Line(line, _) = location else {
+                    return Err(BasicError::IllegalDirect);
+                };
+                let prompt = prompt.unwrap_or_default();
+                self.print(&format!("{}? ", prompt));
+                self.input = Some(PendingInput {
+                    prompt,
+                    targets,
+                    filled: 0,
+                    line: Some(line),
+                });
+            }
+            Statement::Let(target, expr) => {
+                let value = self.evaluate(&expr)?;
+                self.assign(&target, value)?;
+            }
+            Statement::Goto(number) => self.jump(number)?,
+            Statement::Gosub(number) => {
+                if self.subroutines.len() >= MAX_STACK {
+                    return Err(BasicError::OutOfMemory);
+                }
+                let return_to = self.location;
+                self.jump(number)?;
+                self.subroutines.push(Subroutine {
+                    return_to,
+                    for_depth: self.for_loops.len(),
+                });
+            }
+            Statement::Return => {
+                let subroutine = self
+                    .subroutines
+                    .pop()
+                    .ok_or(BasicError::ReturnWithoutGosub)?;
+                self.for_loops.truncate(subroutine.for_depth);
+                self.location = subroutine.return_to;
+            }
+            Statement::For {
+                variable,
+                from,
+                to,
+                step,
+            } => {
+                let from = self.evaluate_number(&from)?;
+                let to = self.evaluate_number(&to)?;
+                let step = match step {
+                    Some(step) => self.evaluate_number(&step)?,
+                    None => 1.0,
+                };
+                self.assign(
+                    &LValue {
+                        name: variable.clone(),
+                        indices: Vec::new(),
+                    },
+                    Value::Number(from),
+                )?;
+
+                // Starting a loop again throws away the old one, along with anything inside it
+                if let Some(idx) = self.for_loops.iter().position(|l| l.variable == variable) {
+                    self.for_loops.truncate(idx);
+                }
+                if self.for_loops.len() >= MAX_STACK {
+                    return Err(BasicError::OutOfMemory);
+                }
+                self.for_loops.push(ForLoop {
+                    variable,
+                    to,
+                    step,
+                    body: self.location,
+                });
+            }
+            Statement::Next(variables) => {
+                // NEXT J,I is the same as NEXT J:NEXT I
+                let variables: Vec<Option<String>> = if variables.is_empty() {
+                    vec![None]
+                } else {
+                    variables.into_iter().map(Some).collect()
+                };
+                for variable in variables {
+                    let idx = match &variable {
+                        Some(variable) => {
+                            self.for_loops.iter().rposition(|l| &l.variable == variable)
+                        }
+                        None => self.for_loops.len().checked_sub(1),
+                    }
+                    .ok_or(BasicError::NextWithoutFor)?;
+                    // Any loops inside this one are finished with
+                    self.for_loops.truncate(idx + 1);
+
+                    let for_loop = &self.for_loops[idx];
+                    let counter = match self.variables.get(&for_loop.variable) {
+                        Some(Value::Number(number)) => *number,
+                        _ => 0.0,
+                    } + for_loop.step;
+                    let done = if for_loop.step >= 0.0 {
+                        counter > for_loop.to
+                    } else {
+                        counter < for_loop.to
+                    };
+                    let (name, body) = (for_loop.variable.clone(), for_loop.body);
+                    self.variables.insert(name, Value::Number(counter));
+
+                    if !done {
+                        self.location = body;
+                        return Ok(());
+                    }
+                    self.for_loops.pop();
+                }
+            }
+            Statement::If(condition) => {
+                let condition = match self.evaluate(&condition)? {
+                    Value::Number(number) => number != 0.0,
+                    Value::Str(text) => !text.is_empty(),
+                };
+                if !condition {
+                    self.location = match location {
+                        Location::Line(number, _) => self.line_after(number),
+                        Location::Immediate(_) => None,
+                    };
+                }
+            }
+            Statement::Dim(arrays) => {
+                for (name, sizes) in arrays {
+                    if self.arrays.contains_key(&name) {
+                        return Err(BasicError::RedimmedArray);
+                    }
+                    let mut dimensions = Vec::new();
+                    for size in &sizes {
+                        let size = self.evaluate_number(size)?;
+                        if size < 0.0 {
+                            return Err(BasicError::IllegalQuantity);
+                        }
+                        // Anything this big wouldn't fit in memory anyway, and checking now
+                        // stops the +1 below overflowing
+                        if size >= u16::MAX as f64 {
+                            return Err(BasicError::OutOfMemory);
+                        }
+                        // DIM A(10) makes A(0) to A(10)
+                        dimensions.push(size as usize + 1);
+                    }
+                    self.create_array(&name, dimensions)?;
+                }
+            }
+            Statement::Poke(address, value) => {
+                let address = self.evaluate_number(&address)?;
+                let value = self.evaluate_number(&value)?;
+                if !(0.0..=65535.0).contains(&address) || !(0.0..=255.0).contains(&value) {
+                    return Err(BasicError::IllegalQuantity);
+                }
+                self.bus
+                    .lock()
+                    .expect("Bus lock")
+                    .write(address as u16, value as u8);
+            }
+            Statement::End => self.location = None,
+            Statement::Stop => {
+                self.location = None;
+                if let Location::Line(number, _) = location {
+                    self.new_line();
+                    self.print(&format!("BREAK IN {}\n", number));
+                }
+            }
+            Statement::Rem => {}
+            Statement::Run(from) => {
+                self.clear();
+                self.location = None;
+                match from {
+                    Some(number) => self.jump(number)?,
+                    None => {
+                        self.location = self.program.keys().next().map(|n| Location::Line(*n, 0))
+                    }
+                }
+            }
+            Statement::List(from, to) => {
+                let from = from.unwrap_or(0);
+                let to = to.unwrap_or(u32::MAX);
+                if from > to {
+                    return Ok(());
+                }
+                let listing: Vec<String> = self
+                    .program
+                    .range(from..=to)
+                    .map(|(number, line)| format!("{} {}\n", number, line.text))
+                    .collect();
+                for line in listing {
+                    self.print(&line);
+                }
+            }
+            Statement::New => {
+                self.program.clear();
+                self.clear();
+                self.location = None;
+            }
+            Statement::Clr => self.clear(),
+            Statement::Save(name) => {
+                let name = self.file_name(&name)?;
+                self.print(&format!("SAVING {}\n", name));
+                let mut contents = String::new();
+                for (number, line) in &self.program {
+                    contents.push_str(&format!("{} {}\n", number, line.text));
+                }
+                self.files
+                    .lock()
+                    .expect("Filesystem lock")
+                    .write(&name, contents.as_bytes())?;
+            }
+            Statement::Load(name) => {
+                let name = self.file_name(&name)?;
+                self.print(&format!("SEARCHING FOR {}\n", name));
+                let contents = self
+                    .files
+                    .lock()
+                    .expect("Filesystem lock")
+                    .read_to_string(&name)?;
+                self.print("LOADING\n");
+
+                let mut program = Vec::new();
+                for line in contents.lines() {
+                    let line = normalise(line);
+                    if line.is_empty() {
+                        continue;
+                    }
+                    match split_line_number(&line)? {
+                        (Some(number), rest) => program.push((number, rest.to_owned())),
+                        (None, _) => return Err(BasicError::Syntax),
+                    }
+                }
+
+                self.program.clear();
+                for (number, text) in program {
+                    self.store_line(number, &text);
+                }
+                self.location = None;
+            }
+        }
+        Ok(())
+    }
+
+    // Program files are .BAS unless they say otherwise
+    fn file_name(&mut self, name: &Expr) -> Result<String, BasicError> {
+        let name = match self.evaluate(name)? {
+            Value::Str(name) => name,
+            Value::Number(_) => return Err(BasicError::TypeMismatch),
+        };
+        if name.is_empty() {
+            return Err(BasicError::MissingFileName);
+        }
+        if name.contains('.') {
+            Ok(name)
+        } else {
+            Ok(format!("{}.BAS", name))
+        }
+    }
+
+    fn assign(&mut self, target: &LValue, value: Value) -> Result<(), BasicError> {
+        let value = match (target.name.ends_with('$'), value) {
+            (true, Value::Str(text)) => Value::Str(text),
+            (false, Value::Number(number)) => {
+                // Integer variables drop anything after the decimal point
+                if target.name.ends_with('%') {
+                    Value::Number(number.trunc())
+                } else {
+                    Value::Number(number)
+                }
+            }
+            _ => return Err(BasicError::TypeMismatch),
+        };
+
+        if target.indices.is_empty() {
+            self.variables.insert(target.name.clone(), value);
+        } else {
+            let idx = self.element_index(target)?;
+            self.arrays
+                .get_mut(&target.name)
+                .expect("Array should exist")
+                .values[idx] = value;
+        }
+        Ok(())
+    }
+
+    // Where an element lives in its array, creating the array if it's never been DIMmed
+    fn element_index(&mut self, element: &LValue) -> Result<usize, BasicError> {
+        let mut indices = Vec::new();
+        for index in &element.indices {
+            let index = self.evaluate_number(index)?;
+            if index < 0.0 {
+                return Err(BasicError::IllegalQuantity);
+            }
+            indices.push(index as usize);
+        }
+
+        if !self.arrays.contains_key(&element.name) {
+            self.create_array(&element.name, vec![DEFAULT_ARRAY_SIZE + 1; indices.len()])?;
+        }
+        let array = &self.arrays[&element.name];
+        if array.sizes.len() != indices.len() {
+            return Err(BasicError::BadSubscript);
+        }
+
+        let mut idx = 0;
+        for (index, size) in indices.iter().zip(&array.sizes) {
+            if index >= size {
+                return Err(BasicError::BadSubscript);
+            }
+            idx = idx * size + index;
+        }
+        Ok(idx)
+    }
+
+    fn create_array(&mut self, name: &str, sizes: Vec<usize>) -> Result<(), BasicError> {
+        let n_elements = sizes
+            .iter()
+            .try_fold(1usize, |total, size| total.checked_mul(*size))
+            .filter(|n| *n <= u16::MAX as usize)
+            .ok_or(BasicError::OutOfMemory)?;
+        self.arrays.insert(
+            name.to_owned(),
+            Array {
+                sizes,
+                values: vec![default_value(name); n_elements],
+            },
+        );
+        Ok(())
+    }
+
+    fn evaluate_number(&mut self, expr: &Expr) -> Result<f64, BasicError> {
+        match self.evaluate(expr)? {
+            Value::Number(number) => Ok(number),
+            Value::Str(_) => Err(BasicError::TypeMismatch),
+        }
+    }
+
+    fn evaluate_string(&mut self, expr: &Expr) -> Result<String, BasicError> {
+        match self.evaluate(expr)? {
+            Value::Str(text) => Ok(text),
+            Value::Number(_) => Err(BasicError::TypeMismatch),
+        }
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, BasicError> {
+        let value = match expr {
+            Expr::Number(number) => Value::Number(*number),
+            Expr::Str(text) => Value::Str(text.clone()),
+            Expr::Variable(variable) if variable.indices.is_empty() => self
+                .variables
+                .get(&variable.name)
+                .cloned()
+                .unwrap_or_else(|| default_value(&variable.name)),
+            Expr::Variable(element) => {
+                let idx = self.element_index(element)?;
+                self.arrays[&element.name].values[idx].clone()
+            }
+            Expr::Negate(inner) => Value::Number(-self.evaluate_number(inner)?),
+            Expr::Not(inner) => {
+                let inner = self.evaluate_number(inner)?;
+                Value::Number(!to_integer(inner)? as f64)
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(*op, left, right)?
+            }
+            Expr::Call(function, arguments) => self.call(*function, arguments)?,
+        };
+
+        match value {
+            Value::Number(number) if !number.is_finite() => Err(BasicError::Overflow),
+            value => Ok(value),
+        }
+    }
+
+    fn call(&mut self, function: Function, arguments: &[Expr]) -> Result<Value, BasicError> {
+        let number = |interpreter: &mut Self, idx: usize| -> Result<f64, BasicError> {
+            interpreter.evaluate_number(&arguments[idx])
+        };
+
+        Ok(match function {
+            Function::Abs => Value::Number(number(self, 0)?.abs()),
+            Function::Int => Value::Number(number(self, 0)?.floor()),
+            Function::Sgn => {
+                let x = number(self, 0)?;
+                Value::Number(if x == 0.0 { 0.0 } else { x.signum() })
+            }
+            Function::Sqr => {
+                let x = number(self, 0)?;
+                if x < 0.0 {
+                    return Err(BasicError::IllegalQuantity);
+                }
+                Value::Number(x.sqrt())
+            }
+            Function::Sin => Value::Number(number(self, 0)?.sin()),
+            Function::Cos => Value::Number(number(self, 0)?.cos()),
+            Function::Tan => Value::Number(number(self, 0)?.tan()),
+            Function::Atn => Value::Number(number(self, 0)?.atan()),
+            Function::Exp => Value::Number(number(self, 0)?.exp()),
+            Function::Log => {
+                let x = number(self, 0)?;
+                if x <= 0.0 {
+                    return Err(BasicError::IllegalQuantity);
+                }
+                Value::Number(x.ln())
+            }
+            Function::Rnd => {
+                // A negative argument reseeds the generator, so sequences can be repeated
+                let x = number(self, 0)?;
+                if x < 0.0 {
+                    self.random_state = x.to_bits() | 1;
+                }
+                Value::Number(self.random())
+            }
+            Function::Peek => {
+                let address = number(self, 0)?;
+                if !(0.0..=65535.0).contains(&address) {
+                    return Err(BasicError::IllegalQuantity);
+                }
+                let byte = self.bus.lock().expect("Bus lock").read(address as u16);
+                Value::Number(byte as f64)
+            }
+            Function::Len => {
+                Value::Number(self.evaluate_string(&arguments[0])?.chars().count() as f64)
+            }
+            Function::Val => {
+                let text = self.evaluate_string(&arguments[0])?;
+                Value::Number(leading_number(&text))
+            }
+            Function::Asc => {
+                let text = self.evaluate_string(&arguments[0])?;
+                let ch = text.chars().next().ok_or(BasicError::IllegalQuantity)?;
+                Value::Number(ch as u32 as f64)
+            }
+            Function::Str => Value::Str(format_number(number(self, 0)?)),
+            Function::Chr => {
+                let code = number(self, 0)?;
+                if !(0.0..=255.0).contains(&code) {
+                    return Err(BasicError::IllegalQuantity);
+                }
+                Value::Str((code as u8 as char).to_string())
+            }
+            Function::Left | Function::Right | Function::Mid => {
+                let text: Vec<char> = self.evaluate_string(&arguments[0])?.chars().collect();
+                let mut count = |idx: usize| -> Result<usize, BasicError> {
+                    let n = number(self, idx)?;
+                    if !(0.0..=255.0).contains(&n) {
+                        return Err(BasicError::IllegalQuantity);
+                    }
+                    Ok(n as usize)
+                };
+                let (start, length) = match function {
+                    Function::Left => (0, count(1)?),
+                    Function::Right => {
+                        let length = count(1)?.min(text.len());
+                        (text.len() - length, length)
+                    }
+                    _ => {
+                        // MID$ counts from 1
+                        let start = count(1)?;
+                        if start == 0 {
+                            return Err(BasicError::IllegalQuantity);
+                        }
+                        let length = match arguments.len() {
+                            3 => count(2)?,
+                            _ => usize::MAX,
+                        };
+                        (start - 1, length)
+                    }
+                };
+                Value::Str(text.iter().skip(start).take(length).collect())
+            }
+        })
+    }
+
+    // A number from 0 up to (but not including) 1. Xorshift, which is plenty random enough.
+    fn random(&mut self) -> f64 {
+        let mut x = self.random_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.random_state = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn line_of(location: Location) -> Option<u32> {
+    match location {
+        Location::Line(number, _) => Some(number),
+        Location::Immediate(_) => None,
+    }
+}
+
+// Variables that haven't been set yet are 0, or empty if they're strings
+fn default_value(name: &str) -> Value {
+    if name.ends_with('$') {
+        Value::Str(String::new())
+    } else {
+        Value::Number(0.0)
+    }
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, BasicError> {
+    // True is -1 (all bits set), so that AND, OR and NOT work on conditions too
+    let truth = |condition: bool| Value::Number(if condition { -1.0 } else { 0.0 });
+
+    Ok(match (op, left, right) {
+        (BinaryOp::Add, Value::Str(left), Value::Str(right)) => Value::Str(left + &right),
+        (
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::Greater
+            | BinaryOp::LessOrEqual
+            | BinaryOp::GreaterOrEqual,
+            Value::Str(left),
+            Value::Str(right),
+        ) => truth(compare(op, left.cmp(&right))),
+        (op, Value::Number(left), Value::Number(right)) => match op {
+            BinaryOp::Add => Value::Number(left + right),
+            BinaryOp::Subtract => Value::Number(left - right),
+            BinaryOp::Multiply => Value::Number(left * right),
+            BinaryOp::Divide => {
+                if right == 0.0 {
+                    return Err(BasicError::DivisionByZero);
+                }
+                Value::Number(left / right)
+            }
+            BinaryOp::Power => Value::Number(left.powf(right)),
+            BinaryOp::And => Value::Number((to_integer(left)? & to_integer(right)?) as f64),
+            BinaryOp::Or => Value::Number((to_integer(left)? | to_integer(right)?) as f64),
+            _ => truth(compare(
+                op,
+                left.partial_cmp(&right).ok_or(BasicError::Overflow)?,
+            )),
+        },
+        _ => return Err(BasicError::TypeMismatch),
+    })
+}
+
+fn compare(op: BinaryOp, ordering: std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering::*;
+    match op {
+        BinaryOp::Equal => ordering == Equal,
+        BinaryOp::NotEqual => ordering != Equal,
+        BinaryOp::Less => ordering == Less,
+        BinaryOp::Greater => ordering == Greater,
+        BinaryOp::LessOrEqual => ordering != Greater,
+        _ => ordering != Less,
+    }
+}
+
+// AND, OR and NOT work on 16-bit signed integers
+fn to_integer(number: f64) -> Result<i16, BasicError> {
+    let number = number.floor();
+    if !(i16::MIN as f64..=i16::MAX as f64).contains(&number) {
+        return Err(BasicError::IllegalQuantity);
+    }
+    Ok(number as i16)
+}
+
+// Formats numbers the way Commodore BASIC does: a space where the minus sign would go, no 0
+// before the decimal point, and scientific notation for anything very big or small
+pub fn format_number(number: f64) -> String {
+    let magnitude = number.abs();
+    let digits = if magnitude == 0.0 {
+        "0".to_owned()
+    } else if !(0.01..1e9).contains(&magnitude) {
+        let text = format!("{:E}", magnitude);
+        let (mantissa, exponent) = text.split_once('E').expect("Should have an exponent");
+        let mantissa = format!("{:.8}", mantissa.parse::<f64>().unwrap_or_default());
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        let exponent: i32 = exponent.parse().unwrap_or_default();
+        format!(
+            "{}E{}{:02}",
+            mantissa,
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else if magnitude.fract() == 0.0 {
+        format!("{}", magnitude)
+    } else {
+        // Nine significant digits, which is about what fits in Commodore's 5-byte floats
+        let decimals = 8usize.saturating_sub(magnitude.log10().floor().max(0.0) as usize);
+        let text = format!("{:.*}", decimals, magnitude);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        text.strip_prefix('0').unwrap_or(text).to_owned()
+    };
+
+    if number < 0.0 {
+        format!("-{}", digits)
+    } else {
+        format!(" {}", digits)
+    }
+}
+
+// VAL reads as much of a number as there is at the start, and gives 0 if there isn't one
+fn leading_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    (1..=text.len())
+        .rev()
+        .filter(|end| text.is_char_boundary(*end))
+        .find_map(|end| {
+            text[..end]
+                .trim_end()
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+        })
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::computer::bus::DeviceBus;
+    use crate::computer::ship_os::filesystem::FileSystem;
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(
+            Arc::new(Mutex::new(FileSystem::default())),
+            Arc::new(Mutex::new(DeviceBus::default())),
+        )
+    }
+
+    // Types in each line, runs whatever that starts, and gives back everything printed
+    fn run(lines: &[&str]) -> String {
+        let mut interpreter = interpreter();
+        for line in lines {
+            interpreter.enter(line);
+            interpreter.run(10_000);
+        }
+        interpreter.take_output()
+    }
+
+    #[test]
+    fn dim_makes_arrays_one_bigger_than_asked_for() {
+        let output = run(&["DIM A(3):A(3)=7:PRINT A(3)"]);
+        assert_eq!(output, " 7 \nREADY.\n");
+        let output = run(&["DIM A(3):A(4)=7"]);
+        assert!(output.contains("?BAD SUBSCRIPT ERROR"), "{output}");
+    }
+
+    #[test]
+    fn huge_dims_run_out_of_memory_instead_of_panicking() {
+        for size in ["1E30", "65535", "300,300"] {
+            let output = run(&[&format!("DIM A({size})")]);
+            assert!(
+                output.contains("?OUT OF MEMORY ERROR"),
+                "DIM A({size}): {output}"
+            );
+        }
+        let output = run(&["DIM A(1E300*1E300)"]);
+        assert!(output.contains("?OVERFLOW ERROR"), "{output}");
+        let output = run(&["DIM A(-1)"]);
+        assert!(output.contains("?ILLEGAL QUANTITY ERROR"), "{output}");
+    }
+
+    #[test]
+    fn dimming_twice_is_an_error() {
+        let output = run(&["DIM A(2):DIM A(2)"]);
+        assert!(output.contains("?REDIM'D ARRAY ERROR"), "{output}");
+        // Using an array DIMs it to 10 behind the scenes
+        let output = run(&["A(1)=1:DIM A(2)"]);
+        assert!(output.contains("?REDIM'D ARRAY ERROR"), "{output}");
+        let output = run(&["A(11)=1"]);
+        assert!(output.contains("?BAD SUBSCRIPT ERROR"), "{output}");
+        let output = run(&["A(1)=1:A(1,1)=1"]);
+        assert!(output.contains("?BAD SUBSCRIPT ERROR"), "{output}");
+    }
+
+    #[test]
+    fn runtime_errors_say_which_line_they_happened_on() {
+        let cases = [
+            ("10 GOTO 100", "?UNDEF'D STATEMENT ERROR IN 10"),
+            ("10 RETURN", "?RETURN WITHOUT GOSUB ERROR IN 10"),
+            ("10 NEXT I", "?NEXT WITHOUT FOR ERROR IN 10"),
+            ("10 A=\"X\"", "?TYPE MISMATCH ERROR IN 10"),
+            ("10 PRINT 1/0", "?DIVISION BY ZERO ERROR IN 10"),
+            ("10 PRINT 1E300*1E300", "?OVERFLOW ERROR IN 10"),
+            ("10 PRINT SQR(-1)", "?ILLEGAL QUANTITY ERROR IN 10"),
+            ("10 POKE 70000,1", "?ILLEGAL QUANTITY ERROR IN 10"),
+            ("10 GOSUB 10", "?OUT OF MEMORY ERROR IN 10"),
+            ("10 PRINT (", "?SYNTAX ERROR IN 10"),
+            ("10 LOAD \"NOTHERE\"", "?FILE NOT FOUND ERROR IN 10"),
+            ("10 SAVE \"\"", "?MISSING FILE NAME ERROR IN 10"),
+        ];
+        for (line, error) in cases {
+            let output = run(&[line, "RUN"]);
+            assert!(output.contains(error), "{line}: {output}");
+        }
+        let output = run(&["INPUT A"]);
+        assert!(output.contains("?ILLEGAL DIRECT ERROR"), "{output}");
+    }
+
+    #[test]
+    fn the_sample_program_on_a_new_disk_runs() {
+        let mut interpreter = Interpreter::new(
+            FileSystem::formatted().shared(),
+            Arc::new(Mutex::new(DeviceBus::default())),
+        );
+        for line in ["LOAD \"HELLO\"", "RUN"] {
+            interpreter.enter(line);
+            interpreter.run(10_000);
+        }
+        let output = interpreter.take_output();
+        assert!(output.contains("HELLO 5 \n"), "{output}");
+        assert!(!output.contains('?'), "{output}");
+    }
+
+    #[test]
+    fn errors_stop_the_program() {
+        let mut interpreter = interpreter();
+        interpreter.enter("10 PRINT 1/0");
+        interpreter.enter("20 PRINT \"AFTER\"");
+        interpreter.enter("RUN");
+        interpreter.run(100);
+        assert!(!interpreter.is_busy());
+        assert!(!interpreter.take_output().contains("AFTER"));
+    }
+}
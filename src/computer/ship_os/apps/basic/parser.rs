@@ -0,0 +1,657 @@
+use super::BasicError;
+
+// Words that mean something to BASIC, and so can't be used as variable names
+const KEYWORDS: &[&str] = &[
+    "AND", "CLR", "DIM", "END", "FOR", "GOSUB", "GOTO", "IF", "INPUT", "LET", "LIST", "LOAD",
+    "NEW", "NEXT", "NOT", "OR", "POKE", "PRINT", "REM", "RETURN", "RUN", "SAVE", "STEP", "STOP",
+    "THEN", "TO",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    // A keyword or a name, always in upper case. String variables keep their `$` on the end.
+    Word(String),
+    Symbol(&'static str),
+}
+
+// Longer symbols first, so that `<=` doesn't come out as `<` then `=`
+const SYMBOLS: &[&str] = &[
+    "<>", "<=", ">=", "+", "-", "*", "/", "^", "=", "<", ">", "(", ")", ",", ";", ":", "?",
+];
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Print(Vec<PrintItem>),
+    Input {
+        prompt: Option<String>,
+        targets: Vec<LValue>,
+    },
+    Let(LValue, Expr),
+    Goto(u32),
+    Gosub(u32),
+    Return,
+    For {
+        variable: String,
+        from: Expr,
+        to: Expr,
+        step: Option<Expr>,
+    },
+    // Empty means whichever loop was started most recently
+    Next(Vec<String>),
+    // If the condition's false, the rest of the line gets skipped
+    If(Expr),
+    Dim(Vec<(String, Vec<Expr>)>),
+    Poke(Expr, Expr),
+    End,
+    Stop,
+    Rem,
+    Run(Option<u32>),
+    List(Option<u32>, Option<u32>),
+    New,
+    Clr,
+    Save(Expr),
+    Load(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub enum PrintItem {
+    Expr(Expr),
+    // `,` moves on to the next column
+    NextZone,
+    // `;` just sticks things together
+    Join,
+}
+
+// Something that can be assigned to: a variable, or an element of an array
+#[derive(Debug, Clone)]
+pub struct LValue {
+    pub name: String,
+    // Empty for a plain variable
+    pub indices: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(f64),
+    Str(String),
+    Variable(LValue),
+    Call(Function, Vec<Expr>),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Function {
+    Abs,
+    Int,
+    Sgn,
+    Sqr,
+    Sin,
+    Cos,
+    Tan,
+    Atn,
+    Exp,
+    Log,
+    Rnd,
+    Peek,
+    Len,
+    Val,
+    Asc,
+    Str,
+    Chr,
+    Left,
+    Right,
+    Mid,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ABS" => Function::Abs,
+            "INT" => Function::Int,
+            "SGN" => Function::Sgn,
+            "SQR" => Function::Sqr,
+            "SIN" => Function::Sin,
+            "COS" => Function::Cos,
+            "TAN" => Function::Tan,
+            "ATN" => Function::Atn,
+            "EXP" => Function::Exp,
+            "LOG" => Function::Log,
+            "RND" => Function::Rnd,
+            "PEEK" => Function::Peek,
+            "LEN" => Function::Len,
+            "VAL" => Function::Val,
+            "ASC" => Function::Asc,
+            "STR$" => Function::Str,
+            "CHR$" => Function::Chr,
+            "LEFT$" => Function::Left,
+            "RIGHT$" => Function::Right,
+            "MID$" => Function::Mid,
+            _ => return None,
+        })
+    }
+
+    // How many arguments it takes, at least and at most
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Function::Left | Function::Right => (2, 2),
+            Function::Mid => (2, 3),
+            _ => (1, 1),
+        }
+    }
+}
+
+// Puts a line in upper case, apart from anything in quotes. The PET only had upper case
+// letters (unless you switched character sets), so that's what programs are written in.
+pub fn normalise(line: &str) -> String {
+    let mut in_string = false;
+    line.trim()
+        .chars()
+        .map(|ch| {
+            if ch == '"' {
+                in_string = !in_string;
+            }
+            if in_string {
+                ch
+            } else {
+                ch.to_ascii_uppercase()
+            }
+        })
+        .collect()
+}
+
+// Splits a line into its line number, if it has one, and the rest
+pub fn split_line_number(line: &str) -> Result<(Option<u32>, &str), BasicError> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return Ok((None, line));
+    }
+    // The PET only goes up to 63999
+    let number = line[..digits]
+        .parse::<u32>()
+        .ok()
+        .filter(|number| *number <= 63999)
+        .ok_or(BasicError::Syntax)?;
+    Ok((Some(number), line[digits..].trim_start()))
+}
+
+// Turns a line (without its line number) into the statements on it
+pub fn parse_line(line: &str) -> Result<Vec<Statement>, BasicError> {
+    let mut parser = Parser {
+        tokens: tokenise(line)?,
+        position: 0,
+    };
+
+    let mut statements = Vec::new();
+    loop {
+        // Colons with nothing between them are fine
+        while parser.eat_symbol(":") {}
+        if parser.at_end() {
+            break;
+        }
+
+        let statement = parser.statement()?;
+        let is_if = matches!(statement, Statement::If(_));
+        statements.push(statement);
+
+        // `IF X THEN 100` is short for `IF X THEN GOTO 100`, and anything after THEN is just
+        // the next statement
+        if is_if {
+            if let Some(Token::Number(_)) = parser.peek() {
+                statements.push(Statement::Goto(parser.line_number()?));
+            }
+            continue;
+        }
+
+        if !parser.at_end() && !parser.eat_symbol(":") {
+            return Err(BasicError::Syntax);
+        }
+    }
+
+    // An empty line still needs to be somewhere to GOTO
+    if statements.is_empty() {
+        statements.push(Statement::Rem);
+    }
+    Ok(statements)
+}
+
+fn tokenise(line: &str) -> Result<Vec<Token>, BasicError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+        } else if ch.is_ascii_digit()
+            || (ch == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // An exponent, like 1E6 or 2.5E-3
+            if chars.get(i) == Some(&'E') {
+                let mut end = i + 1;
+                if matches!(chars.get(end), Some('+' | '-')) {
+                    end += 1;
+                }
+                if chars.get(end).is_some_and(char::is_ascii_digit) {
+                    i = end;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse().map_err(|_| BasicError::Syntax)?;
+            tokens.push(Token::Number(number));
+        } else if ch == '"' {
+            // A missing closing quote is fine: the string just runs to the end of the line
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            tokens.push(Token::Str(chars[start..i].iter().collect()));
+            i += 1;
+        } else if ch.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            if matches!(chars.get(i), Some('$' | '%')) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            // Everything after REM is a comment
+            if word == "REM" {
+                tokens.push(Token::Word(word));
+                break;
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or(BasicError::Syntax)?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.chars().count();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn at_statement_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Symbol(":")))
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(w)) if w == word) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), BasicError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(BasicError::Syntax)
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), BasicError> {
+        if self.eat_word(word) {
+            Ok(())
+        } else {
+            Err(BasicError::Syntax)
+        }
+    }
+
+    // A variable name, which mustn't be a keyword
+    fn name(&mut self) -> Result<String, BasicError> {
+        match self.next() {
+            Some(Token::Word(word)) if !KEYWORDS.contains(&word.as_str()) => Ok(word),
+            _ => Err(BasicError::Syntax),
+        }
+    }
+
+    fn line_number(&mut self) -> Result<u32, BasicError> {
+        match self.next() {
+            Some(Token::Number(number)) if number.fract() == 0.0 && number >= 0.0 => {
+                Ok(number as u32)
+            }
+            _ => Err(BasicError::Syntax),
+        }
+    }
+
+    fn optional_line_number(&mut self) -> Result<Option<u32>, BasicError> {
+        match self.peek() {
+            Some(Token::Number(_)) => Ok(Some(self.line_number()?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, BasicError> {
+        let word = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            // `?` is short for PRINT
+            Some(Token::Symbol("?")) => "PRINT".to_owned(),
+            _ => return Err(BasicError::Syntax),
+        };
+        if !KEYWORDS.contains(&word.as_str()) {
+            // No keyword means it's an assignment, with the LET left out
+            return self.assignment();
+        }
+        self.position += 1;
+
+        Ok(match word.as_str() {
+            "PRINT" => self.print()?,
+            "INPUT" => {
+                let prompt = match self.peek() {
+                    Some(Token::Str(prompt)) => {
+                        let prompt = prompt.clone();
+                        self.position += 1;
+                        self.expect_symbol(";")?;
+                        Some(prompt)
+                    }
+                    _ => None,
+                };
+                let mut targets = vec![self.lvalue()?];
+                while self.eat_symbol(",") {
+                    targets.push(self.lvalue()?);
+                }
+                Statement::Input { prompt, targets }
+            }
+            "LET" => self.assignment()?,
+            "GOTO" => Statement::Goto(self.line_number()?),
+            "GOSUB" => Statement::Gosub(self.line_number()?),
+            "RETURN" => Statement::Return,
+            "FOR" => {
+                let variable = self.name()?;
+                if variable.ends_with('$') {
+                    return Err(BasicError::TypeMismatch);
+                }
+                self.expect_symbol("=")?;
+                let from = self.expression()?;
+                self.expect_word("TO")?;
+                let to = self.expression()?;
+                let step = if self.eat_word("STEP") {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                Statement::For {
+                    variable,
+                    from,
+                    to,
+                    step,
+                }
+            }
+            "NEXT" => {
+                let mut variables = Vec::new();
+                if !self.at_statement_end() {
+                    variables.push(self.name()?);
+                    while self.eat_symbol(",") {
+                        variables.push(self.name()?);
+                    }
+                }
+                Statement::Next(variables)
+            }
+            "IF" => {
+                let condition = self.expression()?;
+                // `IF X GOTO 100` works as well as `IF X THEN 100`
+                if !self.eat_word("THEN")
+                    && !matches!(self.peek(), Some(Token::Word(w)) if w == "GOTO")
+                {
+                    return Err(BasicError::Syntax);
+                }
+                Statement::If(condition)
+            }
+            "DIM" => {
+                let mut arrays = Vec::new();
+                loop {
+                    let name = self.name()?;
+                    self.expect_symbol("(")?;
+                    let sizes = self.arguments()?;
+                    arrays.push((name, sizes));
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+                Statement::Dim(arrays)
+            }
+            "POKE" => {
+                let address = self.expression()?;
+                self.expect_symbol(",")?;
+                Statement::Poke(address, self.expression()?)
+            }
+            "END" => Statement::End,
+            "STOP" => Statement::Stop,
+            "REM" => Statement::Rem,
+            "RUN" => Statement::Run(self.optional_line_number()?),
+            "LIST" => {
+                // LIST, LIST 10, LIST 10-50, LIST -50 and LIST 10-
+                let from = self.optional_line_number()?;
+                if self.eat_symbol("-") {
+                    Statement::List(from, self.optional_line_number()?)
+                } else {
+                    Statement::List(from, from)
+                }
+            }
+            "NEW" => Statement::New,
+            "CLR" => Statement::Clr,
+            "SAVE" => Statement::Save(self.expression()?),
+            "LOAD" => Statement::Load(self.expression()?),
+            _ => return Err(BasicError::Syntax),
+        })
+    }
+
+    fn print(&mut self) -> Result<Statement, BasicError> {
+        let mut items = Vec::new();
+        while !self.at_statement_end() {
+            if self.eat_symbol(",") {
+                items.push(PrintItem::NextZone);
+            } else if self.eat_symbol(";") {
+                items.push(PrintItem::Join);
+            } else {
+                items.push(PrintItem::Expr(self.expression()?));
+            }
+        }
+        Ok(Statement::Print(items))
+    }
+
+    fn assignment(&mut self) -> Result<Statement, BasicError> {
+        let target = self.lvalue()?;
+        self.expect_symbol("=")?;
+        Ok(Statement::Let(target, self.expression()?))
+    }
+
+    fn lvalue(&mut self) -> Result<LValue, BasicError> {
+        let name = self.name()?;
+        let indices = if self.eat_symbol("(") {
+            self.arguments()?
+        } else {
+            Vec::new()
+        };
+        Ok(LValue { name, indices })
+    }
+
+    // A comma separated list of expressions, and the closing bracket after them
+    fn arguments(&mut self) -> Result<Vec<Expr>, BasicError> {
+        let mut arguments = vec![self.expression()?];
+        while self.eat_symbol(",") {
+            arguments.push(self.expression()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(arguments)
+    }
+
+    // Everything from here down is an expression, loosest binding first
+    fn expression(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.and()?;
+        while self.eat_word("OR") {
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.not()?;
+        while self.eat_word("AND") {
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, BasicError> {
+        if self.eat_word("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.sum()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("=")) => BinaryOp::Equal,
+                Some(Token::Symbol("<>")) => BinaryOp::NotEqual,
+                Some(Token::Symbol("<")) => BinaryOp::Less,
+                Some(Token::Symbol(">")) => BinaryOp::Greater,
+                Some(Token::Symbol("<=")) => BinaryOp::LessOrEqual,
+                Some(Token::Symbol(">=")) => BinaryOp::GreaterOrEqual,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.sum()?));
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOp::Multiply,
+                Some(Token::Symbol("/")) => BinaryOp::Divide,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    // Powers bind tighter than minus signs, so -2^2 is -4
+    fn unary(&mut self) -> Result<Expr, BasicError> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat_symbol("+") {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, BasicError> {
+        let mut left = self.primary()?;
+        while self.eat_symbol("^") {
+            left = Expr::Binary(BinaryOp::Power, Box::new(left), Box::new(self.primary()?));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Expr, BasicError> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Str(text)) => Ok(Expr::Str(text)),
+            Some(Token::Symbol("(")) => {
+                let inner = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(inner)
+            }
+            Some(Token::Word(word)) => {
+                if let Some(function) = Function::from_name(&word) {
+                    self.expect_symbol("(")?;
+                    let arguments = self.arguments()?;
+                    let (min, max) = function.arity();
+                    if arguments.len() < min || arguments.len() > max {
+                        return Err(BasicError::Syntax);
+                    }
+                    return Ok(Expr::Call(function, arguments));
+                }
+                // Not a function, so it must be a variable
+                self.position -= 1;
+                Ok(Expr::Variable(self.lvalue()?))
+            }
+            _ => Err(BasicError::Syntax),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::any::Any;
+
+use super::App;
+use crate::computer::ship_os::{Attribute, BoxStyle, Dimensions, Display};
+
+pub const NAME: &str = "Clock";
+
+// Shows how long the computer has been running. About as simple as an app gets, which makes
+// it a handy example.
+#[derive(Default)]
+pub struct Clock {
+    // Seconds since the app started
+    elapsed: f64,
+    // What's currently on the screen, so we only redraw when the time changes
+    shown: String,
+}
+
+impl App for Clock {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.elapsed += delta as f64;
+    }
+
+    fn render(&mut self, display: &mut Display, area: &Dimensions, full: bool) -> bool {
+        let seconds = self.elapsed as u64;
+        let time = format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        );
+        if !full && time == self.shown {
+            return false;
+        }
+        self.shown = time;
+
+        let width = 20;
+        let top = area.top + (area.bottom - area.top).saturating_sub(4) / 2;
+        let left = area.left + (area.right - area.left + 1).saturating_sub(width) / 2;
+        let clock_area = Dimensions {
+            top,
+            bottom: top + 4,
+            left,
+            right: left + width - 1,
+        };
+
+        if full {
+            display.clear();
+        }
+        display.draw_box(clock_area, BoxStyle::Double, Attribute::Normal);
+        display.put_str(top, left + 2, " SHIP TIME ", width - 4, Attribute::Bright);
+        display.put_str(
+            top + 2,
+            left + (width - self.shown.len()) / 2,
+            &self.shown,
+            self.shown.len(),
+            Attribute::Bright,
+        );
+
+        true
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+
+use bevy::input::keyboard::Key;
+
+use crate::computer::ship_os::{Attribute, Dimensions, Display};
+
+// How many lines to remember; anything older has scrolled off the top for good
+const SCROLLBACK: usize = 200;
+
+// How long the cursor spends on and off when it blinks, in seconds
+const BLINK_TIME: f32 = 0.5;
+
+// A scrolling text console with a line of input at the bottom, for apps that work like an
+// old-fashioned prompt (BASIC, Forth and so on)
+#[derive(Default)]
+pub struct Console {
+    // Everything on the screen, most recent last. The last line is the one the cursor's on.
+    lines: VecDeque<String>,
+    // What's been typed in so far on the current line
+    input: String,
+    // Whether typing comes out in capitals whatever the shift key's doing
+    pub capitals_only: bool,
+    cursor_shown: bool,
+    blink_timer: f32,
+    // Whether anything's changed since the last time we were drawn
+    changed: bool,
+}
+
+impl Console {
+    pub fn print(&mut self, text: &str) {
+        if self.lines.is_empty() {
+            self.lines.push_back(String::new());
+        }
+        for ch in text.chars() {
+            if ch == '\n' {
+                self.lines.push_back(String::new());
+                if self.lines.len() > SCROLLBACK {
+                    self.lines.pop_front();
+                }
+            } else {
+                self.lines
+                    .back_mut()
+                    .expect("There's always a current line")
+                    .push(ch);
+            }
+        }
+        self.changed = true;
+    }
+
+    // Blinks the cursor
+    pub fn update(&mut self, delta: f32) {
+        self.blink_timer += delta;
+        if self.blink_timer >= BLINK_TIME {
+            self.blink_timer = 0.0;
+            self.cursor_shown = !self.cursor_shown;
+            self.changed = true;
+        }
+    }
+
+    // Edits the input line, returning the whole line when Enter's pressed.
+    // The line doesn't get printed; that's up to whoever's reading it.
+    pub fn handle_keyboard_input(&mut self, key: &Key) -> Option<String> {
+        // Keep the cursor visible while typing
+        self.cursor_shown = true;
+        self.blink_timer = 0.0;
+        self.changed = true;
+
+        match key {
+            Key::Enter => return Some(std::mem::take(&mut self.input)),
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Space => self.input.push(' '),
+            Key::Character(text) if !text.chars().any(|c| c.is_control()) => {
+                if self.capitals_only {
+                    self.input.push_str(&text.to_uppercase());
+                } else {
+                    self.input.push_str(text);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+        self.changed = true;
+    }
+
+    // Draws the most recent lines, filling `area` from the bottom up. The cursor only shows if
+    // `accepting_input` is set.
+    pub fn render(
+        &mut self,
+        display: &mut Display,
+        area: &Dimensions,
+        full: bool,
+        accepting_input: bool,
+    ) -> bool {
+        if !full && !std::mem::take(&mut self.changed) {
+            return false;
+        }
+
+        let width = area.right - area.left + 1;
+        let height = area.bottom - area.top + 1;
+
+        let mut current = format!(
+            "{}{}",
+            self.lines.back().map(String::as_str).unwrap_or_default(),
+            self.input
+        );
+        if accepting_input && self.cursor_shown {
+            current.push('█');
+        }
+
+        // Long lines wrap onto the next row, so work out the rows from the bottom up
+        let mut rows = Vec::new();
+        for line in std::iter::once(&current).chain(self.lines.iter().rev().skip(1)) {
+            let chars: Vec<char> = line.chars().collect();
+            let mut line_rows: Vec<String> = chars
+                .chunks(width)
+                .map(|chunk| chunk.iter().collect())
+                .collect();
+            if line_rows.is_empty() {
+                line_rows.push(String::new());
+            }
+            rows.extend(line_rows.into_iter().rev());
+            if rows.len() >= height {
+                break;
+            }
+        }
+        rows.truncate(height);
+
+        display.clear();
+        for (idx, row) in rows.iter().rev().enumerate() {
+            display.put_str(area.top + idx, area.left, row, width, Attribute::Normal);
+        }
+        true
+    }
+}
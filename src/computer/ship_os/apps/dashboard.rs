@@ -0,0 +1,320 @@
+use std::any::Any;
+
+use bevy::input::keyboard::Key;
+use bevy::prelude::Entity;
+
+use super::{App, AppContext};
+use crate::computer::ship_os::actions::{react, Reaction};
+use crate::computer::ship_os::binding::{Binding, BindingSource, BoundValue, CHART_INTERVAL};
+use crate::computer::ship_os::layout::{ScreenLayout, WidgetLayout};
+use crate::computer::ship_os::widgets::{Widget, WidgetId, WidgetTree};
+use crate::computer::ship_os::windows::WindowManager;
+use crate::computer::ship_os::{Dimensions, Display, Modifiers};
+use crate::interaction::PointerEventKind;
+
+pub const NAME: &str = "Dashboard";
+
+// Shows whatever's in the computer's screen layout: windows full of widgets, some of them
+// bound to live values from around the ship
+#[derive(Default)]
+pub struct Dashboard {
+    windows: WindowManager,
+    bindings: Vec<LiveBinding>,
+    reactions: Vec<Reaction>,
+    // The size of the area we're drawn in, for when the layout changes
+    n_columns: usize,
+    n_rows: usize,
+}
+
+struct LiveBinding {
+    binding: Binding,
+    // Seconds between updates, which is the binding's own interval unless it's a chart
+    interval: f64,
+    // When the widget's next due an update, in seconds since startup. `None` means straight
+    // away.
+    next_update: Option<f64>,
+    // Where the value was found last time, so it doesn't have to be looked for again
+    entity: Option<Entity>,
+}
+
+impl Dashboard {
+    // Throws away whatever's on the screen, and replaces it with the windows in the layout
+    pub fn apply_layout(&mut self, layout: &ScreenLayout) {
+        self.windows = WindowManager::new(self.n_columns, self.n_rows);
+
+        for window_layout in &layout.windows {
+            let window = self
+                .windows
+                .open(&window_layout.title, window_layout.area.clone());
+            let widgets = &mut self
+                .windows
+                .get_mut(window)
+                .expect("Window should exist")
+                .widgets;
+            for widget_layout in &window_layout.widgets {
+                add_widget_layout(widgets, None, widget_layout);
+            }
+        }
+
+        self.bindings = layout
+            .bindings
+            .iter()
+            .map(|binding| {
+                let interval = if binding.interval <= 0.0 && self.is_chart(&binding.widget) {
+                    CHART_INTERVAL
+                } else {
+                    binding.interval
+                };
+                LiveBinding {
+                    binding: binding.clone(),
+                    interval,
+                    next_update: None,
+                    entity: None,
+                }
+            })
+            .collect();
+        self.reactions = layout.reactions.clone();
+    }
+
+    // The bindings that are due an update, by index, along with where their values come from
+    // and the entity they were found on last time
+    pub fn due_bindings(
+        &self,
+        now: f64,
+    ) -> impl Iterator<Item = (usize, &BindingSource, Option<Entity>)> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(move |(_, live)| !live.next_update.is_some_and(|next| now < next))
+            .map(|(idx, live)| (idx, &live.binding.source, live.entity))
+    }
+
+    // Shows a freshly read value in a binding's widget, `None` meaning it couldn't be read.
+    // `entity` is where it was read from, if that could be found.
+    pub fn update_binding(
+        &mut self,
+        idx: usize,
+        entity: Option<Entity>,
+        value: Option<&BoundValue>,
+        now: f64,
+    ) {
+        let Some(live) = self.bindings.get_mut(idx) else {
+            return;
+        };
+        live.entity = entity;
+        // Counting on from when it was due rather than from now keeps the steps even, but a
+        // binding that's fallen a long way behind (say, after a hitch) doesn't try to catch up
+        let due = live.next_update.unwrap_or(now);
+        live.next_update = Some((due + live.interval).max(now));
+
+        let binding = &live.binding;
+        let Some((window, widget)) = self.windows.find_widget(&binding.widget) else {
+            return;
+        };
+        let Some(window_contents) = self.windows.get_mut(window) else {
+            return;
+        };
+        // Only redraw if the value actually looks any different
+        if binding.apply(window_contents.widgets.get_mut(widget), value) {
+            self.windows.invalidate(window);
+        }
+    }
+
+    fn is_chart(&mut self, name: &str) -> bool {
+        let Some((window, widget)) = self.windows.find_widget(name) else {
+            return false;
+        };
+        self.windows
+            .get_mut(window)
+            .is_some_and(|window| matches!(window.widgets.get_mut(widget), Widget::Chart(_)))
+    }
+}
+
+impl App for Dashboard {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn init(&mut self, context: &AppContext) {
+        self.n_columns = context.area.right - context.area.left + 1;
+        self.n_rows = context.area.bottom - context.area.top + 1;
+        self.windows = WindowManager::new(self.n_columns, self.n_rows);
+        if let Some(layout) = context.layout {
+            self.apply_layout(layout);
+        }
+    }
+
+    fn render(&mut self, display: &mut Display, _area: &Dimensions, full: bool) -> bool {
+        if full {
+            self.windows.invalidate_all();
+        }
+        self.windows.render(display)
+    }
+
+    fn handle_keyboard_input(&mut self, key: &Key, modifiers: Modifiers) {
+        if let Some((window, event)) = self.windows.handle_keyboard_input(key, modifiers) {
+            react(&self.reactions, &mut self.windows, window, &event);
+        }
+    }
+
+    fn handle_pointer_input(&mut self, row: usize, column: usize, kind: PointerEventKind) {
+        if kind != PointerEventKind::Click {
+            return;
+        }
+        if let Some((window, event)) = self.windows.handle_click(row, column) {
+            react(&self.reactions, &mut self.windows, window, &event);
+        }
+    }
+}
+
+fn add_widget_layout(widgets: &mut WidgetTree, parent: Option<WidgetId>, layout: &WidgetLayout) {
+    let id = widgets.add(parent, layout.area.clone(), layout.widget.to_widget());
+    if let Some(name) = &layout.name {
+        widgets.set_name(id, name);
+    }
+    for child in &layout.children {
+        add_widget_layout(widgets, Some(id), child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::ship_os::widgets::WidgetEvent;
+
+    fn dashboard() -> Dashboard {
+        let layout: ScreenLayout = ron::de::from_str(
+            r#"(
+                windows: [(
+                    title: "TEST",
+                    area: (top: 0, bottom: 10, left: 0, right: 40),
+                    widgets: [
+                        (
+                            name: Some("label"),
+                            area: (top: 0, bottom: 0, left: 0, right: 20),
+                            widget: Label(text: ""),
+                        ),
+                        (
+                            name: Some("chart"),
+                            area: (top: 1, bottom: 5, left: 0, right: 20),
+                            widget: Chart(style: Sparkline),
+                        ),
+                    ],
+                )],
+                bindings: [
+                    (widget: "label", source: (entity: "Thing", component: "Transform")),
+                    (widget: "chart", source: (entity: "Thing", component: "Transform")),
+                ],
+            )"#,
+        )
+        .expect("Layout should parse");
+        let mut dashboard = Dashboard {
+            n_columns: 80,
+            n_rows: 25,
+            ..Default::default()
+        };
+        dashboard.apply_layout(&layout);
+        dashboard
+    }
+
+    fn due(dashboard: &Dashboard, now: f64) -> Vec<usize> {
+        dashboard.due_bindings(now).map(|(idx, _, _)| idx).collect()
+    }
+
+    #[test]
+    fn charts_are_sampled_at_a_steady_rate_whatever_the_frame_rate() {
+        let mut dashboard = dashboard();
+        let mut samples = 0;
+        // A frame rate that doesn't divide into the chart's interval
+        for frame in 0..=70 {
+            let now = frame as f64 / 7.0;
+            for idx in due(&dashboard, now) {
+                if idx == 1 {
+                    samples += 1;
+                }
+                dashboard.update_binding(idx, None, None, now);
+            }
+        }
+        // Ten seconds, plus the first sample straight away
+        assert_eq!(samples, (10.0 / CHART_INTERVAL) as usize + 1);
+    }
+
+    #[test]
+    fn labels_still_update_every_frame() {
+        let mut dashboard = dashboard();
+        dashboard.update_binding(0, None, None, 0.0);
+        dashboard.update_binding(1, None, None, 0.0);
+        assert_eq!(due(&dashboard, 0.01), [0]);
+    }
+
+    #[test]
+    fn the_entity_a_value_was_found_on_is_remembered() {
+        let mut dashboard = dashboard();
+        assert_eq!(dashboard.due_bindings(0.0).next().unwrap().2, None);
+        let entity = Entity::from_raw(7);
+        dashboard.update_binding(0, Some(entity), None, 0.0);
+        assert_eq!(dashboard.due_bindings(1.0).next().unwrap().2, Some(entity));
+    }
+
+    #[test]
+    fn widgets_do_what_the_layout_says_when_theyre_used() {
+        let layout: ScreenLayout = ron::de::from_str(
+            r#"(
+                windows: [(
+                    title: "TEST",
+                    area: (top: 0, bottom: 10, left: 0, right: 40),
+                    widgets: [
+                        (name: Some("go"), area: (top: 0, bottom: 0, left: 0, right: 9), widget: Button(label: "Go")),
+                        (name: Some("bar"), area: (top: 1, bottom: 1, left: 0, right: 20), widget: ProgressBar()),
+                        (name: Some("log"), area: (top: 2, bottom: 5, left: 0, right: 30), widget: ScrollText()),
+                    ],
+                )],
+                reactions: [
+                    (widget: "go", when: Pressed, actions: [
+                        AddProgress(widget: "bar", amount: 0.5),
+                        Log(widget: "log", text: "Going"),
+                    ]),
+                    (widget: "bar", when: Filled, actions: [Log(widget: "log", text: "Gone")]),
+                ],
+            )"#,
+        )
+        .expect("Layout should parse");
+        let mut dashboard = Dashboard {
+            n_columns: 80,
+            n_rows: 25,
+            ..Default::default()
+        };
+        dashboard.apply_layout(&layout);
+
+        let (window, go) = dashboard.windows.find_widget("go").unwrap();
+        for _ in 0..3 {
+            react(
+                &dashboard.reactions,
+                &mut dashboard.windows,
+                window,
+                &WidgetEvent::Pressed(go),
+            );
+        }
+
+        let (_, bar) = dashboard.windows.find_widget("bar").unwrap();
+        let (_, log) = dashboard.windows.find_widget("log").unwrap();
+        let widgets = &mut dashboard.windows.get_mut(window).unwrap().widgets;
+        let Widget::ProgressBar(bar) = widgets.get_mut(bar) else {
+            panic!("Should be a progress bar");
+        };
+        assert_eq!(bar.progress, 1.0);
+        // It only fills up once, however many more times the button's pressed
+        let Widget::ScrollText(log) = widgets.get_mut(log) else {
+            panic!("Should be a scroll text");
+        };
+        assert_eq!(log.lines, ["Going", "Going", "Gone", "Going"]);
+    }
+}
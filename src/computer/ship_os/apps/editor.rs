@@ -0,0 +1,779 @@
+use std::any::Any;
+
+use bevy::input::keyboard::Key;
+
+use super::{App, AppContext};
+use crate::computer::ship_os::filesystem::SharedFileSystem;
+use crate::computer::ship_os::{Attribute, BoxStyle, Dimensions, Display, Modifiers};
+
+// How many spaces Tab puts in
+const TAB_WIDTH: usize = 4;
+
+// How long the cursor spends on and off when it blinks, in seconds
+const BLINK_TIME: f32 = 0.5;
+
+const HELP: &str = "^O Open  ^S Save  ^N New  ^F Find  F3 Next  ^R Replace  Ins Mode";
+
+pub const NAME: &str = "Edit";
+
+// A full-screen text editor, along the lines of the old MS-DOS EDIT.COM.
+// Keyboard controls:
+// - Arrows, Home/End, PageUp/PageDown move the cursor; Ctrl+Home/End go to the start/end
+// - Holding Shift while moving selects a block
+// - Ctrl+C, Ctrl+X and Ctrl+V copy, cut and paste the block
+// - Insert switches between inserting and overwriting
+// - Ctrl+O opens a file, Ctrl+S saves, Ctrl+N starts a new one
+// - Ctrl+F finds, F3 finds the next one, Ctrl+R replaces
+#[derive(Default)]
+pub struct Editor {
+    files: Option<SharedFileSystem>,
+    // The file being edited, if it's been saved or opened
+    name: Option<String>,
+    lines: Vec<Vec<char>>,
+    cursor: Position,
+    // The other end of the selected block, if there is one
+    anchor: Option<Position>,
+    overwrite: bool,
+    // The cursor blinks, so the character underneath it can be seen
+    cursor_shown: bool,
+    blink_timer: f32,
+    // Whether there are unsaved changes
+    modified: bool,
+    clipboard: String,
+    // The first line and column on the screen
+    scroll: Position,
+    // How many lines fit on the screen, as of the last time it was drawn
+    page_height: usize,
+    // Asking for something on the status line, e.g. a file name
+    prompt: Option<Prompt>,
+    last_search: String,
+    // Shown on the status line until the next key press
+    message: String,
+    // Set after warning about unsaved changes, so doing the same thing again goes ahead
+    confirm_discard: bool,
+    // Whether anything's changed since the last time we were drawn
+    changed: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+enum PromptKind {
+    Open,
+    SaveAs,
+    Find,
+    ReplaceFind,
+    // Holds what's being replaced
+    ReplaceWith(String),
+}
+
+impl PromptKind {
+    fn label(&self) -> &str {
+        match self {
+            PromptKind::Open => "Open file",
+            PromptKind::SaveAs => "Save as",
+            PromptKind::Find | PromptKind::ReplaceFind => "Find",
+            PromptKind::ReplaceWith(_) => "Replace with",
+        }
+    }
+}
+
+impl Editor {
+    fn new_document(&mut self) {
+        self.name = None;
+        self.lines = vec![Vec::new()];
+        self.cursor = Position::default();
+        self.anchor = None;
+        self.scroll = Position::default();
+        self.modified = false;
+    }
+
+    fn open(&mut self, name: &str) {
+        let Some(files) = &self.files else {
+            return;
+        };
+        let contents = files.lock().expect("Filesystem lock").read_to_string(name);
+        match contents {
+            Ok(contents) => {
+                self.new_document();
+                self.lines = contents
+                    .lines()
+                    .map(|line| line.chars().collect())
+                    .collect();
+                if self.lines.is_empty() {
+                    self.lines.push(Vec::new());
+                }
+                self.name = Some(name.trim().to_ascii_uppercase());
+                self.message = format!("Opened {}", name.trim().to_ascii_uppercase());
+            }
+            Err(error) => self.message = error.to_string(),
+        }
+    }
+
+    fn save(&mut self, name: &str) {
+        let Some(files) = &self.files else {
+            return;
+        };
+        let mut contents: String = self
+            .lines
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        contents.push('\n');
+
+        let result = files
+            .lock()
+            .expect("Filesystem lock")
+            .write(name, contents.as_bytes());
+        match result {
+            Ok(()) => {
+                let name = name.trim().to_ascii_uppercase();
+                self.message = format!("Saved {}", name);
+                self.name = Some(name);
+                self.modified = false;
+            }
+            Err(error) => self.message = error.to_string(),
+        }
+    }
+
+    // Lists what's on the disk, to help with picking a file to open
+    fn file_list(&self) -> String {
+        let Some(files) = &self.files else {
+            return String::new();
+        };
+        let files = files.lock().expect("Filesystem lock");
+        let names: Vec<&str> = files.list().map(|(name, _)| name).collect();
+        format!("Files: {}", names.join(" "))
+    }
+
+    // Guards against throwing away unsaved changes. The first time, this warns about them and
+    // returns false; doing the same thing again goes ahead.
+    fn can_discard(&mut self) -> bool {
+        if !self.modified || self.confirm_discard {
+            return true;
+        }
+        self.confirm_discard = true;
+        self.message = "Unsaved changes! Do that again to throw them away.".to_owned();
+        false
+    }
+
+    fn handle_command(&mut self, letter: char) {
+        match letter {
+            'c' => self.clipboard = self.selected_text(),
+            'x' => {
+                self.clipboard = self.selected_text();
+                self.delete_selection();
+            }
+            'v' => {
+                let text = self.clipboard.clone();
+                self.insert_text(&text);
+            }
+            'o' if self.can_discard() => {
+                self.message = self.file_list();
+                self.start_prompt(PromptKind::Open);
+            }
+            's' => match self.name.clone() {
+                Some(name) => self.save(&name),
+                None => self.start_prompt(PromptKind::SaveAs),
+            },
+            'n' if self.can_discard() => {
+                self.new_document();
+                self.message = "New file".to_owned();
+            }
+            'f' => self.start_prompt(PromptKind::Find),
+            'r' => self.start_prompt(PromptKind::ReplaceFind),
+            _ => {}
+        }
+    }
+
+    fn start_prompt(&mut self, kind: PromptKind) {
+        let input = match kind {
+            PromptKind::Find | PromptKind::ReplaceFind => self.last_search.clone(),
+            _ => String::new(),
+        };
+        self.prompt = Some(Prompt { kind, input });
+    }
+
+    fn handle_prompt_key(&mut self, key: &Key) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        match key {
+            Key::Escape => self.prompt = None,
+            Key::Backspace => {
+                prompt.input.pop();
+            }
+            Key::Space => prompt.input.push(' '),
+            Key::Character(text) if !text.chars().any(|c| c.is_control()) => {
+                prompt.input.push_str(text);
+            }
+            Key::Enter => {
+                let Prompt { kind, input } = self.prompt.take().expect("Prompt should exist");
+                // Nothing entered is the same as giving up, except for the replacement, where
+                // it means deleting whatever was found
+                if input.is_empty() && !matches!(kind, PromptKind::ReplaceWith(_)) {
+                    return;
+                }
+                match kind {
+                    PromptKind::Open => self.open(&input),
+                    PromptKind::SaveAs => self.save(&input),
+                    PromptKind::Find => {
+                        self.last_search = input;
+                        self.find_next();
+                    }
+                    PromptKind::ReplaceFind => {
+                        self.last_search = input.clone();
+                        self.prompt = Some(Prompt {
+                            kind: PromptKind::ReplaceWith(input),
+                            input: String::new(),
+                        });
+                    }
+                    PromptKind::ReplaceWith(find) => self.replace_all(&find, &input),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_editing_key(&mut self, key: &Key, modifiers: Modifiers) {
+        let select = modifiers.shift;
+        let Position { line, column } = self.cursor;
+        let page = self.page_height.max(1);
+
+        match key {
+            Key::ArrowLeft => {
+                let to = if column > 0 {
+                    Position {
+                        line,
+                        column: column - 1,
+                    }
+                } else if line > 0 {
+                    self.end_of(line - 1)
+                } else {
+                    self.cursor
+                };
+                self.move_cursor(to, select);
+            }
+            Key::ArrowRight => {
+                let to = if column < self.lines[line].len() {
+                    Position {
+                        line,
+                        column: column + 1,
+                    }
+                } else if line + 1 < self.lines.len() {
+                    Position {
+                        line: line + 1,
+                        column: 0,
+                    }
+                } else {
+                    self.cursor
+                };
+                self.move_cursor(to, select);
+            }
+            Key::ArrowUp => self.move_cursor(self.clamped(line.saturating_sub(1), column), select),
+            Key::ArrowDown => self.move_cursor(self.clamped(line + 1, column), select),
+            Key::PageUp => {
+                self.move_cursor(self.clamped(line.saturating_sub(page), column), select)
+            }
+            Key::PageDown => self.move_cursor(self.clamped(line + page, column), select),
+            Key::Home if modifiers.ctrl => self.move_cursor(Position::default(), select),
+            Key::End if modifiers.ctrl => {
+                self.move_cursor(self.end_of(self.lines.len() - 1), select)
+            }
+            Key::Home => self.move_cursor(Position { line, column: 0 }, select),
+            Key::End => self.move_cursor(self.end_of(line), select),
+            Key::Insert => self.overwrite = !self.overwrite,
+            Key::F3 => self.find_next(),
+            Key::Enter => self.insert_text("\n"),
+            Key::Tab => {
+                let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                self.insert_text(&" ".repeat(spaces));
+            }
+            Key::Backspace => self.delete_char(Key::ArrowLeft),
+            Key::Delete => self.delete_char(Key::ArrowRight),
+            Key::Space => self.type_char(' '),
+            Key::Character(text) if !text.chars().any(|c| c.is_control()) => {
+                for ch in text.chars() {
+                    self.type_char(ch);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Deletes the selected block, or if there isn't one, the character the arrow key would
+    // move the cursor over
+    fn delete_char(&mut self, direction: Key) {
+        if self.delete_selection() {
+            return;
+        }
+        let from = self.cursor;
+        self.handle_editing_key(&direction, Modifiers::default());
+        self.anchor = Some(from);
+        self.delete_selection();
+    }
+
+    fn end_of(&self, line: usize) -> Position {
+        Position {
+            line,
+            column: self.lines[line].len(),
+        }
+    }
+
+    // The position, moved onto the text if it's off the end of a line or past the last line
+    fn clamped(&self, line: usize, column: usize) -> Position {
+        let line = line.min(self.lines.len() - 1);
+        Position {
+            line,
+            column: column.min(self.lines[line].len()),
+        }
+    }
+
+    fn move_cursor(&mut self, to: Position, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = to;
+    }
+
+    // The start and end of the selected block, if there is one
+    fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.cursor) {
+            std::cmp::Ordering::Less => Some((anchor, self.cursor)),
+            std::cmp::Ordering::Greater => Some((self.cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn selected_text(&self) -> String {
+        let Some((start, end)) = self.selection() else {
+            return String::new();
+        };
+        let mut text = String::new();
+        for line in start.line..=end.line {
+            let from = if line == start.line { start.column } else { 0 };
+            let to = if line == end.line {
+                end.column
+            } else {
+                self.lines[line].len()
+            };
+            text.extend(&self.lines[line][from..to]);
+            if line != end.line {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    // Gets rid of the selected block, returning whether there was one
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+
+        let tail = self.lines[end.line].split_off(end.column);
+        self.lines[start.line].truncate(start.column);
+        self.lines[start.line].extend(tail);
+        self.lines.drain((start.line + 1)..=end.line);
+
+        self.cursor = start;
+        self.anchor = None;
+        self.modified = true;
+        true
+    }
+
+    fn type_char(&mut self, ch: char) {
+        let had_selection = self.delete_selection();
+        let Position { line, column } = self.cursor;
+        if self.overwrite && !had_selection && column < self.lines[line].len() {
+            self.lines[line][column] = ch;
+            self.cursor.column += 1;
+            self.modified = true;
+        } else {
+            self.insert_text(&ch.to_string());
+        }
+    }
+
+    // Inserts text at the cursor, in place of the selected block if there is one
+    fn insert_text(&mut self, text: &str) {
+        self.delete_selection();
+        if text.is_empty() {
+            return;
+        }
+
+        let Position { line, column } = self.cursor;
+        let tail = self.lines[line].split_off(column);
+        let mut pieces = text.split('\n');
+        let first = pieces.next().unwrap_or_default();
+        self.lines[line].extend(first.chars());
+
+        let mut current = line;
+        for piece in pieces {
+            current += 1;
+            self.lines.insert(current, piece.chars().collect());
+        }
+
+        self.cursor = self.end_of(current);
+        self.lines[current].extend(tail);
+        self.modified = true;
+    }
+
+    // Looks for the last thing searched for after the cursor, wrapping round to the start.
+    // Finds are case insensitive, and select what they find.
+    fn find_next(&mut self) {
+        if self.last_search.is_empty() {
+            self.start_prompt(PromptKind::Find);
+            return;
+        }
+        let pattern: Vec<char> = self
+            .last_search
+            .chars()
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect();
+
+        let n_lines = self.lines.len();
+        // Starting from the cursor, which is left at the end of whatever was found last time,
+        // so finding again moves on to the next one. Going round one line more than there is
+        // comes back to the start of the cursor's line, in case there's only the one match.
+        let start = self.cursor;
+        for offset in 0..=n_lines {
+            let line = (start.line + offset) % n_lines;
+            let from = if offset == 0 { start.column } else { 0 };
+            if let Some(column) = find_in_line(&self.lines[line], &pattern, from) {
+                self.anchor = Some(Position { line, column });
+                self.cursor = Position {
+                    line,
+                    column: column + pattern.len(),
+                };
+                return;
+            }
+        }
+
+        self.message = format!("Not found: {}", self.last_search);
+    }
+
+    fn replace_all(&mut self, find: &str, replacement: &str) {
+        let pattern: Vec<char> = find.chars().map(|ch| ch.to_ascii_lowercase()).collect();
+        let replacement: Vec<char> = replacement.chars().collect();
+
+        let mut count = 0;
+        for line in &mut self.lines {
+            let mut from = 0;
+            while let Some(column) = find_in_line(line, &pattern, from) {
+                line.splice(column..column + pattern.len(), replacement.iter().copied());
+                from = column + replacement.len();
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            self.modified = true;
+            self.anchor = None;
+            self.cursor = self.clamped(self.cursor.line, self.cursor.column);
+        }
+        self.message = format!("Replaced {} occurrence(s)", count);
+    }
+
+    // Moves the view so the cursor's on the screen
+    fn scroll_to_cursor(&mut self, height: usize, width: usize) {
+        if self.cursor.line < self.scroll.line {
+            self.scroll.line = self.cursor.line;
+        } else if self.cursor.line >= self.scroll.line + height {
+            self.scroll.line = self.cursor.line + 1 - height;
+        }
+        if self.cursor.column < self.scroll.column {
+            self.scroll.column = self.cursor.column;
+        } else if self.cursor.column >= self.scroll.column + width {
+            self.scroll.column = self.cursor.column + 1 - width;
+        }
+    }
+
+    fn draw_status_line(&self, display: &mut Display, row: usize, left: usize, width: usize) {
+        if let Some(prompt) = &self.prompt {
+            let text = format!(" {}: {}", prompt.kind.label(), prompt.input);
+            display.put_str(row, left, &text, width, Attribute::Bright);
+            let cursor = left + text.chars().count();
+            if cursor < left + width {
+                display.put_str(row, cursor, "█", 1, Attribute::Bright);
+            }
+            // Anything that might help with answering, e.g. the list of files
+            let used = text.chars().count() + 3;
+            display.put_str(
+                row,
+                left + used,
+                &self.message,
+                width.saturating_sub(used),
+                Attribute::Dim,
+            );
+            return;
+        }
+
+        let position = format!(
+            " {}{} │ Line {} Col {} │ {} │ ",
+            self.name.as_deref().unwrap_or("UNTITLED"),
+            if self.modified { "*" } else { "" },
+            self.cursor.line + 1,
+            self.cursor.column + 1,
+            if self.overwrite { "OVR" } else { "INS" },
+        );
+        display.put_str(row, left, &position, width, Attribute::Bright);
+
+        let used = position.chars().count();
+        let (text, attribute) = if self.message.is_empty() {
+            (HELP, Attribute::Dim)
+        } else {
+            (self.message.as_str(), Attribute::Warning)
+        };
+        display.put_str(
+            row,
+            left + used,
+            text,
+            width.saturating_sub(used),
+            attribute,
+        );
+    }
+}
+
+impl App for Editor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn init(&mut self, context: &AppContext) {
+        self.files = Some(context.files.clone());
+        self.cursor_shown = true;
+        self.new_document();
+        self.changed = true;
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.blink_timer += delta;
+        if self.blink_timer >= BLINK_TIME {
+            self.blink_timer = 0.0;
+            self.cursor_shown = !self.cursor_shown;
+            self.changed = true;
+        }
+    }
+
+    fn render(&mut self, display: &mut Display, area: &Dimensions, full: bool) -> bool {
+        if !full && !std::mem::take(&mut self.changed) {
+            return false;
+        }
+
+        // The text goes in a box, with the status line underneath
+        let status_row = area.bottom;
+        let text_box = Dimensions {
+            bottom: area.bottom - 1,
+            ..area.clone()
+        };
+        let height = text_box.bottom - text_box.top - 1;
+        let width = text_box.right - text_box.left - 1;
+        self.page_height = height;
+        self.scroll_to_cursor(height, width);
+
+        display.clear();
+        display.draw_box(text_box.clone(), BoxStyle::Double, Attribute::Normal);
+        let title = format!(" {} ", self.name.as_deref().unwrap_or("UNTITLED"));
+        let title_column = text_box.left + (width + 2).saturating_sub(title.chars().count()) / 2;
+        display.put_str(text_box.top, title_column, &title, width, Attribute::Bright);
+
+        let selection = self.selection();
+        for row in 0..height {
+            let line_idx = self.scroll.line + row;
+            let Some(line) = self.lines.get(line_idx) else {
+                break;
+            };
+            for (offset, ch) in line.iter().skip(self.scroll.column).take(width).enumerate() {
+                let position = Position {
+                    line: line_idx,
+                    column: self.scroll.column + offset,
+                };
+                let selected =
+                    selection.is_some_and(|(start, end)| start <= position && position < end);
+                let attribute = if selected {
+                    Attribute::Highlight
+                } else {
+                    Attribute::Normal
+                };
+                display.put_char(
+                    text_box.top + 1 + row,
+                    text_box.left + 1 + offset,
+                    *ch,
+                    attribute,
+                );
+            }
+        }
+
+        // A block cursor for overwriting, a lower half one for inserting
+        if self.prompt.is_none() && self.cursor_shown {
+            let cursor = if self.overwrite { "█" } else { "▄" };
+            display.put_str(
+                text_box.top + 1 + self.cursor.line - self.scroll.line,
+                text_box.left + 1 + self.cursor.column - self.scroll.column,
+                cursor,
+                1,
+                Attribute::Bright,
+            );
+        }
+
+        self.draw_status_line(display, status_row, area.left, area.right - area.left + 1);
+        true
+    }
+
+    fn handle_keyboard_input(&mut self, key: &Key, modifiers: Modifiers) {
+        self.changed = true;
+        // Keep the cursor visible while typing
+        self.cursor_shown = true;
+        self.blink_timer = 0.0;
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key);
+            return;
+        }
+
+        // Messages only last until the next key press, and so does the chance to confirm
+        // throwing away changes
+        self.message.clear();
+        let confirming = std::mem::take(&mut self.confirm_discard);
+
+        if modifiers.ctrl {
+            if let Some(letter) = control_letter(key) {
+                self.confirm_discard = confirming;
+                self.handle_command(letter);
+                if !self.message.starts_with("Unsaved") {
+                    self.confirm_discard = false;
+                }
+                return;
+            }
+        }
+        self.handle_editing_key(key, modifiers);
+    }
+}
+
+// The letter pressed along with Ctrl, if it was one. Depending on the platform, that comes
+// through either as the letter itself or as an ASCII control character.
+fn control_letter(key: &Key) -> Option<char> {
+    let Key::Character(text) = key else {
+        return None;
+    };
+    let ch = text.chars().next()?;
+    match ch {
+        '\u{1}'..='\u{1a}' => Some((b'a' + ch as u8 - 1) as char),
+        _ if ch.is_ascii_alphabetic() => Some(ch.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+// Where the (lower case) pattern next appears in the line, starting from `from`, ignoring case
+fn find_in_line(line: &[char], pattern: &[char], from: usize) -> Option<usize> {
+    if pattern.is_empty() || line.len() < pattern.len() {
+        return None;
+    }
+    (from..=line.len() - pattern.len()).find(|&start| {
+        line[start..start + pattern.len()]
+            .iter()
+            .zip(pattern)
+            .all(|(a, b)| a.to_ascii_lowercase() == *b)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &[&str]) -> Editor {
+        Editor {
+            lines: text.iter().map(|line| line.chars().collect()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn type_into_prompt(editor: &mut Editor, text: &str) {
+        for ch in text.chars() {
+            editor.handle_prompt_key(&Key::Character(ch.to_string().into()));
+        }
+        editor.handle_prompt_key(&Key::Enter);
+    }
+
+    fn text(editor: &Editor) -> Vec<String> {
+        editor
+            .lines
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn finding_again_finds_a_match_straight_after_the_last_one() {
+        let mut editor = editor(&["abab", "ab"]);
+        editor.last_search = "ab".to_owned();
+        let mut found = Vec::new();
+        for _ in 0..4 {
+            editor.find_next();
+            found.push((editor.anchor.unwrap(), editor.cursor));
+        }
+        let at = |line, column| Position { line, column };
+        assert_eq!(
+            found,
+            [
+                (at(0, 0), at(0, 2)),
+                (at(0, 2), at(0, 4)),
+                (at(1, 0), at(1, 2)),
+                (at(0, 0), at(0, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn finding_the_only_match_wraps_round_to_it() {
+        let mut editor = editor(&["one", "two"]);
+        editor.last_search = "ONE".to_owned();
+        editor.find_next();
+        editor.find_next();
+        assert_eq!(editor.anchor, Some(Position { line: 0, column: 0 }));
+        assert!(editor.message.is_empty());
+
+        editor.last_search = "three".to_owned();
+        editor.find_next();
+        assert_eq!(editor.message, "Not found: three");
+    }
+
+    #[test]
+    fn replacing_with_nothing_deletes_what_was_found() {
+        let mut editor = editor(&["a-b-c"]);
+        editor.start_prompt(PromptKind::ReplaceFind);
+        type_into_prompt(&mut editor, "-");
+        type_into_prompt(&mut editor, "");
+        assert_eq!(text(&editor), ["abc"]);
+    }
+
+    #[test]
+    fn escape_gives_up_on_replacing() {
+        let mut editor = editor(&["a-b-c"]);
+        editor.start_prompt(PromptKind::ReplaceFind);
+        type_into_prompt(&mut editor, "-");
+        editor.handle_prompt_key(&Key::Escape);
+        assert!(editor.prompt.is_none());
+        assert_eq!(text(&editor), ["a-b-c"]);
+    }
+}
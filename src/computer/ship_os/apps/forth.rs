@@ -0,0 +1,158 @@
+mod machine;
+
+use std::any::Any;
+
+use bevy::input::keyboard::Key;
+use thiserror::Error;
+
+use super::console::Console;
+use super::{App, AppContext};
+use crate::computer::ship_os::filesystem::FileSystemError;
+use crate::computer::ship_os::{Dimensions, Display, Modifiers};
+use machine::{Machine, WORDS_FILE};
+
+// How many words and instructions get run per second. Forth was always a lot quicker than
+// BASIC, so this is too.
+const WORDS_PER_SECOND: f32 = 20000.0;
+
+const BANNER: &str = "SHIPOS FORTH\n\
+P@ ( port reg -- n ) and P! ( n port reg -- ) talk to devices.\n\
+SAVE-WORDS keeps your definitions for next time.\n";
+
+#[derive(Debug, Error)]
+pub enum ForthError {
+    // The traditional response to a word Forth's never heard of
+    #[error("{0} ?")]
+    Undefined(String),
+    #[error("Stack underflow")]
+    StackUnderflow,
+    #[error("Return stack underflow")]
+    ReturnStackUnderflow,
+    #[error("Return stack overflow")]
+    ReturnStackOverflow,
+    #[error("Stack overflow")]
+    StackOverflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Invalid address")]
+    InvalidAddress,
+    #[error("Out of memory")]
+    OutOfMemory,
+    #[error("{0} is compile only")]
+    CompileOnly(String),
+    #[error("Unbalanced control structure")]
+    Unbalanced,
+    #[error("Missing name")]
+    MissingName,
+    #[error("{0} is built in")]
+    BuiltIn(String),
+    #[error("Interrupted")]
+    Interrupted,
+    #[error(transparent)]
+    Disk(#[from] FileSystemError),
+}
+
+pub const NAME: &str = "Forth";
+
+// A Forth prompt. Whatever's typed in gets interpreted when Enter's pressed, and Escape stops
+// anything that's running. Words saved with SAVE-WORDS get loaded again at startup.
+#[derive(Default)]
+pub struct Forth {
+    // Only missing until the app's started
+    machine: Option<Machine>,
+    console: Console,
+    // Words' worth of time saved up, so that things run at the same speed whatever the
+    // frame rate
+    budget: f32,
+}
+
+impl Forth {
+    fn collect_output(&mut self) {
+        let Some(machine) = &mut self.machine else {
+            return;
+        };
+        let output = machine.take_output();
+        if !output.is_empty() {
+            self.console.print(&output);
+        }
+    }
+}
+
+impl App for Forth {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn init(&mut self, context: &AppContext) {
+        let mut machine = Machine::new(context.files.clone(), context.bus.clone());
+        self.console.print(BANNER);
+
+        let saved = context
+            .files
+            .lock()
+            .expect("Filesystem lock")
+            .exists(WORDS_FILE);
+        if saved {
+            let line = format!("INCLUDE {}", WORDS_FILE);
+            self.console.print(&format!("{} ", line));
+            machine.enter(&line);
+        }
+        self.machine = Some(machine);
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.console.update(delta);
+
+        let Some(machine) = &mut self.machine else {
+            return;
+        };
+        if !machine.is_busy() {
+            self.budget = 0.0;
+            return;
+        }
+        self.budget += delta * WORDS_PER_SECOND;
+        let words = self.budget as usize;
+        self.budget -= words as f32;
+        machine.run(delta, words);
+        self.collect_output();
+    }
+
+    fn render(&mut self, display: &mut Display, area: &Dimensions, full: bool) -> bool {
+        let accepting_input = self
+            .machine
+            .as_ref()
+            .is_some_and(|machine| !machine.is_busy());
+        self.console.render(display, area, full, accepting_input)
+    }
+
+    fn handle_keyboard_input(&mut self, key: &Key, _modifiers: Modifiers) {
+        let Some(machine) = &mut self.machine else {
+            return;
+        };
+
+        if *key == Key::Escape {
+            machine.interrupt();
+            self.console.clear_input();
+            self.collect_output();
+            return;
+        }
+        if machine.is_busy() {
+            return;
+        }
+
+        let Some(line) = self.console.handle_keyboard_input(key) else {
+            return;
+        };
+        // Forth answers on the same line
+        self.console.print(&format!("{} ", line));
+        machine.enter(&line);
+    }
+}
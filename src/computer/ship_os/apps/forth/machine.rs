@@ -0,0 +1,1360 @@
+use std::collections::VecDeque;
+
+use super::ForthError;
+use crate::computer::bus::{SharedBus, IO_BASE, N_PORTS, PORT_SIZE};
+use crate::computer::ship_os::filesystem::SharedFileSystem;
+
+// Where SAVE-WORDS puts everything that's been defined, and where it gets loaded from at
+// startup
+pub const WORDS_FILE: &str = "WORDS.FTH";
+
+// How deep the stacks and calls can go
+const MAX_STACK: usize = 256;
+
+// How many cells of data space there are for variables
+const MAX_MEMORY: usize = 4096;
+
+// Words that just do something when they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    MinusRot,
+    Nip,
+    Tuck,
+    TwoDup,
+    TwoDrop,
+    QuestionDup,
+    Depth,
+    Pick,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Mod,
+    DivMod,
+    Negate,
+    Abs,
+    Min,
+    Max,
+    OnePlus,
+    OneMinus,
+    TwoStar,
+    TwoSlash,
+    And,
+    Or,
+    Xor,
+    Invert,
+    LShift,
+    RShift,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    ZeroEqual,
+    ZeroLess,
+    ZeroGreater,
+    True,
+    False,
+    Dot,
+    UDot,
+    DotS,
+    Cr,
+    Emit,
+    Space,
+    Spaces,
+    Fetch,
+    Store,
+    PlusStore,
+    CFetch,
+    CStore,
+    PortFetch,
+    PortStore,
+    Here,
+    Allot,
+    Cells,
+    CellPlus,
+    ToR,
+    RFrom,
+    RFetch,
+    I,
+    J,
+    Hex,
+    Decimal,
+    Ms,
+    Words,
+    SaveWords,
+}
+
+const PRIMITIVES: &[(&str, Primitive)] = &[
+    ("DUP", Primitive::Dup),
+    ("DROP", Primitive::Drop),
+    ("SWAP", Primitive::Swap),
+    ("OVER", Primitive::Over),
+    ("ROT", Primitive::Rot),
+    ("-ROT", Primitive::MinusRot),
+    ("NIP", Primitive::Nip),
+    ("TUCK", Primitive::Tuck),
+    ("2DUP", Primitive::TwoDup),
+    ("2DROP", Primitive::TwoDrop),
+    ("?DUP", Primitive::QuestionDup),
+    ("DEPTH", Primitive::Depth),
+    ("PICK", Primitive::Pick),
+    ("+", Primitive::Add),
+    ("-", Primitive::Subtract),
+    ("*", Primitive::Multiply),
+    ("/", Primitive::Divide),
+    ("MOD", Primitive::Mod),
+    ("/MOD", Primitive::DivMod),
+    ("NEGATE", Primitive::Negate),
+    ("ABS", Primitive::Abs),
+    ("MIN", Primitive::Min),
+    ("MAX", Primitive::Max),
+    ("1+", Primitive::OnePlus),
+    ("1-", Primitive::OneMinus),
+    ("2*", Primitive::TwoStar),
+    ("2/", Primitive::TwoSlash),
+    ("AND", Primitive::And),
+    ("OR", Primitive::Or),
+    ("XOR", Primitive::Xor),
+    ("INVERT", Primitive::Invert),
+    ("LSHIFT", Primitive::LShift),
+    ("RSHIFT", Primitive::RShift),
+    ("=", Primitive::Equal),
+    ("<>", Primitive::NotEqual),
+    ("<", Primitive::Less),
+    (">", Primitive::Greater),
+    ("0=", Primitive::ZeroEqual),
+    ("0<", Primitive::ZeroLess),
+    ("0>", Primitive::ZeroGreater),
+    ("TRUE", Primitive::True),
+    ("FALSE", Primitive::False),
+    (".", Primitive::Dot),
+    ("U.", Primitive::UDot),
+    (".S", Primitive::DotS),
+    ("CR", Primitive::Cr),
+    ("EMIT", Primitive::Emit),
+    ("SPACE", Primitive::Space),
+    ("SPACES", Primitive::Spaces),
+    ("@", Primitive::Fetch),
+    ("!", Primitive::Store),
+    ("+!", Primitive::PlusStore),
+    ("C@", Primitive::CFetch),
+    ("C!", Primitive::CStore),
+    ("P@", Primitive::PortFetch),
+    ("P!", Primitive::PortStore),
+    ("HERE", Primitive::Here),
+    ("ALLOT", Primitive::Allot),
+    ("CELLS", Primitive::Cells),
+    ("CELL+", Primitive::CellPlus),
+    (">R", Primitive::ToR),
+    ("R>", Primitive::RFrom),
+    ("R@", Primitive::RFetch),
+    ("I", Primitive::I),
+    ("J", Primitive::J),
+    ("HEX", Primitive::Hex),
+    ("DECIMAL", Primitive::Decimal),
+    ("MS", Primitive::Ms),
+    ("WORDS", Primitive::Words),
+    ("SAVE-WORDS", Primitive::SaveWords),
+];
+
+// Words that act as soon as they're read, even in the middle of a definition: they either
+// read ahead in the input or build control structures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Special {
+    Colon,
+    Semicolon,
+    Paren,
+    Backslash,
+    DotQuote,
+    DotParen,
+    If,
+    Else,
+    Then,
+    Begin,
+    Until,
+    Again,
+    While,
+    Repeat,
+    Do,
+    Loop,
+    PlusLoop,
+    Leave,
+    Exit,
+    Recurse,
+    Variable,
+    Constant,
+    Forget,
+    Include,
+}
+
+const SPECIALS: &[(&str, Special)] = &[
+    (":", Special::Colon),
+    (";", Special::Semicolon),
+    ("(", Special::Paren),
+    ("\\", Special::Backslash),
+    (".\"", Special::DotQuote),
+    (".(", Special::DotParen),
+    ("IF", Special::If),
+    ("ELSE", Special::Else),
+    ("THEN", Special::Then),
+    ("BEGIN", Special::Begin),
+    ("UNTIL", Special::Until),
+    ("AGAIN", Special::Again),
+    ("WHILE", Special::While),
+    ("REPEAT", Special::Repeat),
+    ("DO", Special::Do),
+    ("LOOP", Special::Loop),
+    ("+LOOP", Special::PlusLoop),
+    ("LEAVE", Special::Leave),
+    ("EXIT", Special::Exit),
+    ("RECURSE", Special::Recurse),
+    ("VARIABLE", Special::Variable),
+    ("CONSTANT", Special::Constant),
+    ("FORGET", Special::Forget),
+    ("INCLUDE", Special::Include),
+];
+
+struct Word {
+    name: String,
+    kind: WordKind,
+    // How to define it again, for words defined by the user, so SAVE-WORDS can save them
+    source: Option<String>,
+}
+
+enum WordKind {
+    Primitive(Primitive),
+    Special(Special),
+    Colon(Vec<Instruction>),
+    // Pushes the address of its cell in data space
+    Variable(usize),
+    Constant(i32),
+}
+
+// What colon definitions get compiled into
+#[derive(Debug, Clone)]
+enum Instruction {
+    Literal(i32),
+    // Runs the word at this index in the dictionary
+    Call(usize),
+    Branch(usize),
+    BranchIfZero(usize),
+    // Moves the limit and starting index onto the return stack
+    Do,
+    // Jump back to the start of the loop if it isn't finished
+    Loop(usize),
+    PlusLoop(usize),
+    // Jump past the end of the loop
+    Leave(usize),
+    Print(String),
+    Exit,
+}
+
+// A colon definition that's part way through being compiled
+struct Definition {
+    name: String,
+    code: Vec<Instruction>,
+    // Unfinished IFs, loops and so on
+    control: Vec<Control>,
+    source: String,
+    // Where the bit of the current line that's part of the definition starts
+    source_from: usize,
+}
+
+enum Control {
+    // Where the branch that needs pointing at the end is
+    If(usize),
+    Begin(usize),
+    While { begin: usize, branch: usize },
+    Do { start: usize, leaves: Vec<usize> },
+}
+
+// A colon definition that's running
+struct Frame {
+    word: usize,
+    // The next instruction
+    ip: usize,
+}
+
+// The line being interpreted, and how far through it we are
+struct InputLine {
+    text: Vec<char>,
+    position: usize,
+}
+
+// A Forth system: the dictionary, the stacks, and the outer interpreter that reads words and
+// either runs or compiles them. Everything printed goes into a buffer, which whoever's
+// showing it on the screen picks up with `take_output`.
+pub struct Machine {
+    dictionary: Vec<Word>,
+    // How many of the words in the dictionary are built in
+    n_builtins: usize,
+    stack: Vec<i32>,
+    return_stack: Vec<i32>,
+    calls: Vec<Frame>,
+    // Data space, where variables live. Addresses count cells rather than bytes.
+    memory: Vec<i32>,
+    base: u32,
+    compiling: Option<Definition>,
+    input: Option<InputLine>,
+    // More lines to interpret after this one, e.g. from a file being INCLUDEd
+    queued: VecDeque<String>,
+    // How long to wait before carrying on, in seconds, after MS
+    sleep: f32,
+    output: String,
+    files: SharedFileSystem,
+    bus: SharedBus,
+}
+
+impl Machine {
+    pub fn new(files: SharedFileSystem, bus: SharedBus) -> Self {
+        let mut dictionary: Vec<Word> = PRIMITIVES
+            .iter()
+            .map(|(name, primitive)| Word {
+                name: (*name).to_owned(),
+                kind: WordKind::Primitive(*primitive),
+                source: None,
+            })
+            .collect();
+        dictionary.extend(SPECIALS.iter().map(|(name, special)| Word {
+            name: (*name).to_owned(),
+            kind: WordKind::Special(*special),
+            source: None,
+        }));
+
+        Self {
+            n_builtins: dictionary.len(),
+            dictionary,
+            stack: Vec::new(),
+            return_stack: Vec::new(),
+            calls: Vec::new(),
+            memory: Vec::new(),
+            base: 10,
+            compiling: None,
+            input: None,
+            queued: VecDeque::new(),
+            sleep: 0.0,
+            output: String::new(),
+            files,
+            bus,
+        }
+    }
+
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    // Whether there's still something to get through from the last line entered
+    pub fn is_busy(&self) -> bool {
+        self.input.is_some() || !self.calls.is_empty()
+    }
+
+    pub fn enter(&mut self, line: &str) {
+        self.start_line(line);
+    }
+
+    // Stops whatever's running, like pressing Ctrl+C
+    pub fn interrupt(&mut self) {
+        if self.is_busy() {
+            self.abort(ForthError::Interrupted);
+        }
+    }
+
+    // Gets through up to `budget` words and instructions, unless it's waiting after MS
+    pub fn run(&mut self, delta: f32, budget: usize) {
+        if self.sleep > 0.0 {
+            self.sleep -= delta;
+            return;
+        }
+
+        for _ in 0..budget {
+            if self.sleep > 0.0 {
+                return;
+            }
+            let result = if !self.calls.is_empty() {
+                self.step()
+            } else if let Some(token) = self.next_word() {
+                self.interpret(&token)
+            } else if !self.next_line() {
+                // Everything's been done
+                self.output.push_str(if self.compiling.is_some() {
+                    " compiled\n"
+                } else {
+                    " ok\n"
+                });
+                return;
+            } else {
+                Ok(())
+            };
+
+            if let Err(error) = result {
+                self.abort(error);
+                return;
+            }
+        }
+    }
+
+    fn start_line(&mut self, line: &str) {
+        self.input = Some(InputLine {
+            text: line.chars().collect(),
+            position: 0,
+        });
+        if let Some(definition) = &mut self.compiling {
+            definition.source_from = 0;
+        }
+    }
+
+    // Moves on to the next queued line, if there is one
+    fn next_line(&mut self) -> bool {
+        // Whatever's left of the line is part of the definition being compiled
+        if let (Some(input), Some(definition)) = (&self.input, &mut self.compiling) {
+            let rest: String = input.text[definition.source_from.min(input.text.len())..]
+                .iter()
+                .collect();
+            definition.source.push_str(&rest);
+            definition.source.push('\n');
+        }
+
+        match self.queued.pop_front() {
+            Some(line) => {
+                self.start_line(&line);
+                true
+            }
+            None => {
+                self.input = None;
+                false
+            }
+        }
+    }
+
+    // Gives up on everything, and empties the stacks
+    fn abort(&mut self, error: ForthError) {
+        self.output.push_str(&format!(" {}\n", error));
+        self.stack.clear();
+        self.return_stack.clear();
+        self.calls.clear();
+        self.compiling = None;
+        self.input = None;
+        self.queued.clear();
+        self.sleep = 0.0;
+    }
+
+    fn next_word(&mut self) -> Option<String> {
+        let input = self.input.as_mut()?;
+        while input
+            .text
+            .get(input.position)
+            .is_some_and(|ch| ch.is_whitespace())
+        {
+            input.position += 1;
+        }
+        let start = input.position;
+        while input
+            .text
+            .get(input.position)
+            .is_some_and(|ch| !ch.is_whitespace())
+        {
+            input.position += 1;
+        }
+        (input.position > start).then(|| input.text[start..input.position].iter().collect())
+    }
+
+    // Reads everything up to `delimiter`, which gets skipped over
+    fn parse_until(&mut self, delimiter: char) -> String {
+        let Some(input) = self.input.as_mut() else {
+            return String::new();
+        };
+        // The space after the word that's doing the reading isn't part of the text
+        input.position += 1;
+        let start = input.position.min(input.text.len());
+        while input
+            .text
+            .get(input.position)
+            .is_some_and(|ch| *ch != delimiter)
+        {
+            input.position += 1;
+        }
+        let text = input.text[start..input.position.min(input.text.len())]
+            .iter()
+            .collect();
+        input.position += 1;
+        text
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.dictionary
+            .iter()
+            .rposition(|word| word.name.eq_ignore_ascii_case(name))
+    }
+
+    fn interpret(&mut self, token: &str) -> Result<(), ForthError> {
+        if let Some(idx) = self.find(token) {
+            if let WordKind::Special(special) = self.dictionary[idx].kind {
+                return self.special(special);
+            }
+            match &mut self.compiling {
+                Some(definition) => definition.code.push(Instruction::Call(idx)),
+                None => self.execute(idx)?,
+            }
+            return Ok(());
+        }
+
+        let number = self
+            .parse_number(token)
+            .ok_or_else(|| ForthError::Undefined(token.to_owned()))?;
+        match &mut self.compiling {
+            Some(definition) => definition.code.push(Instruction::Literal(number)),
+            None => self.push(number)?,
+        }
+        Ok(())
+    }
+
+    // Numbers are in the current base, unless they start with $ (hex) or # (decimal)
+    fn parse_number(&self, token: &str) -> Option<i32> {
+        let (negative, token) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token),
+        };
+        let (base, digits) = if let Some(digits) = token.strip_prefix('$') {
+            (16, digits)
+        } else if let Some(digits) = token.strip_prefix('#') {
+            (10, digits)
+        } else {
+            (self.base, token)
+        };
+        // Wrapping round, the same as arithmetic does
+        let number = u32::from_str_radix(digits, base).ok()? as i32;
+        Some(if negative {
+            number.wrapping_neg()
+        } else {
+            number
+        })
+    }
+
+    fn format_number(&self, number: i32) -> String {
+        match self.base {
+            16 if number < 0 => format!("-{:X}", number.unsigned_abs()),
+            16 => format!("{:X}", number),
+            _ => number.to_string(),
+        }
+    }
+
+    // Runs a word. Colon definitions just get started; `step` does the rest.
+    fn execute(&mut self, idx: usize) -> Result<(), ForthError> {
+        match &self.dictionary[idx].kind {
+            WordKind::Primitive(primitive) => self.primitive(*primitive),
+            WordKind::Colon(_) => {
+                if self.calls.len() >= MAX_STACK {
+                    return Err(ForthError::StackOverflow);
+                }
+                self.calls.push(Frame { word: idx, ip: 0 });
+                Ok(())
+            }
+            WordKind::Variable(address) => self.push(*address as i32),
+            WordKind::Constant(value) => self.push(*value),
+            WordKind::Special(_) => Err(ForthError::CompileOnly(self.dictionary[idx].name.clone())),
+        }
+    }
+
+    // Runs the next instruction of whichever colon definition is running
+    fn step(&mut self) -> Result<(), ForthError> {
+        let Some(frame) = self.calls.last_mut() else {
+            return Ok(());
+        };
+        let WordKind::Colon(code) = &self.dictionary[frame.word].kind else {
+            self.calls.pop();
+            return Ok(());
+        };
+        let Some(instruction) = code.get(frame.ip).cloned() else {
+            // Off the end of the definition
+            self.calls.pop();
+            return Ok(());
+        };
+        frame.ip += 1;
+
+        match instruction {
+            Instruction::Literal(value) => self.push(value)?,
+            Instruction::Call(idx) => self.execute(idx)?,
+            Instruction::Branch(target) => self.jump(target),
+            Instruction::BranchIfZero(target) => {
+                if self.pop()? == 0 {
+                    self.jump(target);
+                }
+            }
+            Instruction::Do => {
+                let start = self.pop()?;
+                let limit = self.pop()?;
+                self.push_return(limit)?;
+                self.push_return(start)?;
+            }
+            Instruction::Loop(target) => {
+                let (limit, index) = self.loop_counters()?;
+                self.finish_iteration(limit, index, index.wrapping_add(1), target);
+            }
+            Instruction::PlusLoop(target) => {
+                let step = self.pop()?;
+                let (limit, index) = self.loop_counters()?;
+                self.finish_iteration(limit, index, index.wrapping_add(step), target);
+            }
+            Instruction::Leave(target) => {
+                self.loop_counters()?;
+                self.return_stack.truncate(self.return_stack.len() - 2);
+                self.jump(target);
+            }
+            Instruction::Print(text) => self.output.push_str(&text),
+            Instruction::Exit => {
+                self.calls.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn jump(&mut self, target: usize) {
+        if let Some(frame) = self.calls.last_mut() {
+            frame.ip = target;
+        }
+    }
+
+    fn loop_counters(&self) -> Result<(i32, i32), ForthError> {
+        match self.return_stack.as_slice() {
+            [.., limit, index] => Ok((*limit, *index)),
+            _ => Err(ForthError::ReturnStackUnderflow),
+        }
+    }
+
+    // The loop's finished once the index crosses over from limit - 1 to the limit, whichever
+    // way it's going
+    fn finish_iteration(&mut self, limit: i32, old: i32, new: i32, target: usize) {
+        let crossed = (old.wrapping_sub(limit) ^ new.wrapping_sub(limit)) < 0;
+        if crossed {
+            self.return_stack.truncate(self.return_stack.len() - 2);
+        } else {
+            *self.return_stack.last_mut().expect("Loop index") = new;
+            self.jump(target);
+        }
+    }
+
+    fn compile_only(&mut self, name: &str) -> Result<&mut Definition, ForthError> {
+        self.compiling
+            .as_mut()
+            .ok_or_else(|| ForthError::CompileOnly(name.to_owned()))
+    }
+
+    fn special(&mut self, special: Special) -> Result<(), ForthError> {
+        let name = SPECIALS
+            .iter()
+            .find(|(_, s)| *s == special)
+            .map(|(name, _)| *name)
+            .unwrap_or_default();
+
+        match special {
+            Special::Colon => {
+                if self.compiling.is_some() {
+                    return Err(ForthError::Unbalanced);
+                }
+                // The colon itself is just before wherever we've got up to
+                let source_from = self
+                    .input
+                    .as_ref()
+                    .map_or(0, |input| input.position.saturating_sub(1));
+                let word = self.next_word().ok_or(ForthError::MissingName)?;
+                // Numbers in the definition are in whatever base was in use at the time
+                let source = if self.base == 16 { "HEX " } else { "" };
+                self.compiling = Some(Definition {
+                    name: word.to_ascii_uppercase(),
+                    code: Vec::new(),
+                    control: Vec::new(),
+                    source: source.to_owned(),
+                    source_from,
+                });
+            }
+            Special::Semicolon => {
+                let mut definition = self
+                    .compiling
+                    .take()
+                    .ok_or_else(|| ForthError::CompileOnly(name.to_owned()))?;
+                if !definition.control.is_empty() {
+                    return Err(ForthError::Unbalanced);
+                }
+                if let Some(input) = &self.input {
+                    let end = input.position.min(input.text.len());
+                    let text: String = input.text[definition.source_from..end].iter().collect();
+                    definition.source.push_str(text.trim());
+                }
+                if self.base == 16 {
+                    definition.source.push_str(" DECIMAL");
+                }
+                self.dictionary.push(Word {
+                    name: definition.name,
+                    kind: WordKind::Colon(definition.code),
+                    source: Some(definition.source),
+                });
+            }
+            Special::Paren => {
+                self.parse_until(')');
+            }
+            Special::Backslash => {
+                if let Some(input) = &mut self.input {
+                    input.position = input.text.len();
+                }
+            }
+            Special::DotQuote => {
+                let text = self.parse_until('"');
+                match &mut self.compiling {
+                    Some(definition) => definition.code.push(Instruction::Print(text)),
+                    None => self.output.push_str(&text),
+                }
+            }
+            Special::DotParen => {
+                let text = self.parse_until(')');
+                self.output.push_str(&text);
+            }
+            Special::If => {
+                let definition = self.compile_only(name)?;
+                definition.control.push(Control::If(definition.code.len()));
+                definition.code.push(Instruction::BranchIfZero(0));
+            }
+            Special::Else => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::If(branch)) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                definition.control.push(Control::If(definition.code.len()));
+                definition.code.push(Instruction::Branch(0));
+                patch(&mut definition.code, branch);
+            }
+            Special::Then => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::If(branch)) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                patch(&mut definition.code, branch);
+            }
+            Special::Begin => {
+                let definition = self.compile_only(name)?;
+                definition
+                    .control
+                    .push(Control::Begin(definition.code.len()));
+            }
+            Special::Until | Special::Again => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::Begin(begin)) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                definition.code.push(if special == Special::Until {
+                    Instruction::BranchIfZero(begin)
+                } else {
+                    Instruction::Branch(begin)
+                });
+            }
+            Special::While => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::Begin(begin)) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                definition.control.push(Control::While {
+                    begin,
+                    branch: definition.code.len(),
+                });
+                definition.code.push(Instruction::BranchIfZero(0));
+            }
+            Special::Repeat => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::While { begin, branch }) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                definition.code.push(Instruction::Branch(begin));
+                patch(&mut definition.code, branch);
+            }
+            Special::Do => {
+                let definition = self.compile_only(name)?;
+                definition.code.push(Instruction::Do);
+                definition.control.push(Control::Do {
+                    start: definition.code.len(),
+                    leaves: Vec::new(),
+                });
+            }
+            Special::Loop | Special::PlusLoop => {
+                let definition = self.compile_only(name)?;
+                let Some(Control::Do { start, leaves }) = definition.control.pop() else {
+                    return Err(ForthError::Unbalanced);
+                };
+                definition.code.push(if special == Special::Loop {
+                    Instruction::Loop(start)
+                } else {
+                    Instruction::PlusLoop(start)
+                });
+                for leave in leaves {
+                    patch(&mut definition.code, leave);
+                }
+            }
+            Special::Leave => {
+                let definition = self.compile_only(name)?;
+                let position = definition.code.len();
+                let Some(Control::Do { leaves, .. }) = definition
+                    .control
+                    .iter_mut()
+                    .rev()
+                    .find(|control| matches!(control, Control::Do { .. }))
+                else {
+                    return Err(ForthError::Unbalanced);
+                };
+                leaves.push(position);
+                definition.code.push(Instruction::Leave(0));
+            }
+            Special::Exit => {
+                self.compile_only(name)?.code.push(Instruction::Exit);
+            }
+            Special::Recurse => {
+                // The word being defined goes on the end of the dictionary once it's done
+                let idx = self.dictionary.len();
+                self.compile_only(name)?.code.push(Instruction::Call(idx));
+            }
+            Special::Variable => {
+                let word = self.next_word().ok_or(ForthError::MissingName)?;
+                let address = self.allot(1)?;
+                self.dictionary.push(Word {
+                    source: Some(format!("VARIABLE {}", word)),
+                    name: word.to_ascii_uppercase(),
+                    kind: WordKind::Variable(address),
+                });
+            }
+            Special::Constant => {
+                let word = self.next_word().ok_or(ForthError::MissingName)?;
+                let value = self.pop()?;
+                self.dictionary.push(Word {
+                    source: Some(format!("#{} CONSTANT {}", value, word)),
+                    name: word.to_ascii_uppercase(),
+                    kind: WordKind::Constant(value),
+                });
+            }
+            Special::Forget => {
+                let word = self.next_word().ok_or(ForthError::MissingName)?;
+                let idx = self
+                    .find(&word)
+                    .ok_or_else(|| ForthError::Undefined(word.clone()))?;
+                if idx < self.n_builtins {
+                    return Err(ForthError::BuiltIn(word));
+                }
+                // Everything defined after it goes too, since it might use it
+                self.dictionary.truncate(idx);
+            }
+            Special::Include => {
+                let file = self.next_word().ok_or(ForthError::MissingName)?;
+                self.include(&file)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Interprets a file, as if it had been typed in, before carrying on with the rest of the
+    // current line
+    fn include(&mut self, file: &str) -> Result<(), ForthError> {
+        let contents = self
+            .files
+            .lock()
+            .expect("Filesystem lock")
+            .read_to_string(file)?;
+
+        if let Some(input) = self.input.take() {
+            let rest: String = input.text[input.position.min(input.text.len())..]
+                .iter()
+                .collect();
+            self.queued.push_front(rest);
+        }
+        for line in contents.lines().rev() {
+            self.queued.push_front(line.to_owned());
+        }
+        // Starts the file's first line
+        self.next_line();
+        Ok(())
+    }
+
+    fn allot(&mut self, cells: usize) -> Result<usize, ForthError> {
+        let address = self.memory.len();
+        if address + cells > MAX_MEMORY {
+            return Err(ForthError::OutOfMemory);
+        }
+        self.memory.resize(address + cells, 0);
+        Ok(address)
+    }
+
+    fn push(&mut self, value: i32) -> Result<(), ForthError> {
+        if self.stack.len() >= MAX_STACK {
+            return Err(ForthError::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn push_return(&mut self, value: i32) -> Result<(), ForthError> {
+        if self.return_stack.len() >= MAX_STACK {
+            return Err(ForthError::ReturnStackOverflow);
+        }
+        self.return_stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i32, ForthError> {
+        self.stack.pop().ok_or(ForthError::StackUnderflow)
+    }
+
+    fn pop_two(&mut self) -> Result<(i32, i32), ForthError> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        Ok((a, b))
+    }
+
+    fn peek(&self, depth: usize) -> Result<i32, ForthError> {
+        self.stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|idx| self.stack[idx])
+            .ok_or(ForthError::StackUnderflow)
+    }
+
+    fn cell(&mut self, address: i32) -> Result<&mut i32, ForthError> {
+        usize::try_from(address)
+            .ok()
+            .and_then(|address| self.memory.get_mut(address))
+            .ok_or(ForthError::InvalidAddress)
+    }
+
+    fn bus_address(address: i32) -> Result<u16, ForthError> {
+        u16::try_from(address).map_err(|_| ForthError::InvalidAddress)
+    }
+
+    // The bus address of one of a port's registers
+    fn port_address(port: i32, register: i32) -> Result<u16, ForthError> {
+        let port = usize::try_from(port)
+            .ok()
+            .filter(|port| *port < N_PORTS)
+            .ok_or(ForthError::InvalidAddress)?;
+        let register = usize::try_from(register)
+            .ok()
+            .filter(|register| *register < PORT_SIZE)
+            .ok_or(ForthError::InvalidAddress)?;
+        Ok(IO_BASE + (port * PORT_SIZE + register) as u16)
+    }
+
+    fn primitive(&mut self, primitive: Primitive) -> Result<(), ForthError> {
+        // Forth's idea of true is all bits set
+        let flag = |condition: bool| if condition { -1 } else { 0 };
+
+        match primitive {
+            Primitive::Dup => self.push(self.peek(0)?)?,
+            Primitive::Drop => {
+                self.pop()?;
+            }
+            Primitive::Swap => {
+                let (a, b) = self.pop_two()?;
+                self.stack.extend([b, a]);
+            }
+            Primitive::Over => self.push(self.peek(1)?)?,
+            Primitive::Rot => {
+                let c = self.pop()?;
+                let (a, b) = self.pop_two()?;
+                self.stack.extend([b, c, a]);
+            }
+            Primitive::MinusRot => {
+                let c = self.pop()?;
+                let (a, b) = self.pop_two()?;
+                self.stack.extend([c, a, b]);
+            }
+            Primitive::Nip => {
+                let (_, b) = self.pop_two()?;
+                self.stack.push(b);
+            }
+            Primitive::Tuck => {
+                let (a, b) = self.pop_two()?;
+                self.stack.extend([b, a]);
+                self.push(b)?;
+            }
+            Primitive::TwoDup => {
+                let (a, b) = (self.peek(1)?, self.peek(0)?);
+                self.push(a)?;
+                self.push(b)?;
+            }
+            Primitive::TwoDrop => {
+                self.pop_two()?;
+            }
+            Primitive::QuestionDup => {
+                let a = self.peek(0)?;
+                if a != 0 {
+                    self.push(a)?;
+                }
+            }
+            Primitive::Depth => self.push(self.stack.len() as i32)?,
+            Primitive::Pick => {
+                let depth = usize::try_from(self.pop()?).map_err(|_| ForthError::StackUnderflow)?;
+                self.push(self.peek(depth)?)?;
+            }
+            Primitive::Add
+            | Primitive::Subtract
+            | Primitive::Multiply
+            | Primitive::Divide
+            | Primitive::Mod
+            | Primitive::Min
+            | Primitive::Max
+            | Primitive::And
+            | Primitive::Or
+            | Primitive::Xor
+            | Primitive::LShift
+            | Primitive::RShift
+            | Primitive::Equal
+            | Primitive::NotEqual
+            | Primitive::Less
+            | Primitive::Greater => {
+                let (a, b) = self.pop_two()?;
+                let result = match primitive {
+                    Primitive::Add => a.wrapping_add(b),
+                    Primitive::Subtract => a.wrapping_sub(b),
+                    Primitive::Multiply => a.wrapping_mul(b),
+                    // Dividing the most negative number by -1 wraps round, like everything else
+                    Primitive::Divide => a.wrapping_div_euclid(nonzero(b)?),
+                    Primitive::Mod => a.wrapping_rem_euclid(nonzero(b)?),
+                    Primitive::Min => a.min(b),
+                    Primitive::Max => a.max(b),
+                    Primitive::And => a & b,
+                    Primitive::Or => a | b,
+                    Primitive::Xor => a ^ b,
+                    Primitive::LShift => (a as u32).checked_shl(b as u32).unwrap_or(0) as i32,
+                    Primitive::RShift => (a as u32).checked_shr(b as u32).unwrap_or(0) as i32,
+                    Primitive::Equal => flag(a == b),
+                    Primitive::NotEqual => flag(a != b),
+                    Primitive::Less => flag(a < b),
+                    _ => flag(a > b),
+                };
+                self.stack.push(result);
+            }
+            Primitive::DivMod => {
+                let (a, b) = self.pop_two()?;
+                let b = nonzero(b)?;
+                self.stack
+                    .extend([a.wrapping_rem_euclid(b), a.wrapping_div_euclid(b)]);
+            }
+            Primitive::Negate => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_neg());
+            }
+            Primitive::Abs => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_abs());
+            }
+            Primitive::OnePlus => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_add(1));
+            }
+            Primitive::OneMinus => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_sub(1));
+            }
+            Primitive::TwoStar => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_shl(1));
+            }
+            Primitive::TwoSlash => {
+                let a = self.pop()?;
+                self.stack.push(a >> 1);
+            }
+            Primitive::Invert => {
+                let a = self.pop()?;
+                self.stack.push(!a);
+            }
+            Primitive::ZeroEqual => {
+                let a = self.pop()?;
+                self.stack.push(flag(a == 0));
+            }
+            Primitive::ZeroLess => {
+                let a = self.pop()?;
+                self.stack.push(flag(a < 0));
+            }
+            Primitive::ZeroGreater => {
+                let a = self.pop()?;
+                self.stack.push(flag(a > 0));
+            }
+            Primitive::True => self.push(-1)?,
+            Primitive::False => self.push(0)?,
+            Primitive::Dot => {
+                let a = self.pop()?;
+                let text = format!("{} ", self.format_number(a));
+                self.output.push_str(&text);
+            }
+            Primitive::UDot => {
+                let a = self.pop()? as u32;
+                let text = match self.base {
+                    16 => format!("{:X} ", a),
+                    _ => format!("{} ", a),
+                };
+                self.output.push_str(&text);
+            }
+            Primitive::DotS => {
+                let mut text = format!("<{}> ", self.stack.len());
+                for value in &self.stack {
+                    text.push_str(&format!("{} ", self.format_number(*value)));
+                }
+                self.output.push_str(&text);
+            }
+            Primitive::Cr => self.output.push('\n'),
+            Primitive::Emit => {
+                let a = self.pop()?;
+                let ch = u8::try_from(a).map(char::from).unwrap_or('?');
+                self.output.push(ch);
+            }
+            Primitive::Space => self.output.push(' '),
+            Primitive::Spaces => {
+                let n = self.pop()?.clamp(0, 255) as usize;
+                self.output.push_str(&" ".repeat(n));
+            }
+            Primitive::Fetch => {
+                let address = self.pop()?;
+                let value = *self.cell(address)?;
+                self.stack.push(value);
+            }
+            Primitive::Store => {
+                let (value, address) = self.pop_two()?;
+                *self.cell(address)? = value;
+            }
+            Primitive::PlusStore => {
+                let (value, address) = self.pop_two()?;
+                let cell = self.cell(address)?;
+                *cell = cell.wrapping_add(value);
+            }
+            Primitive::CFetch => {
+                let address = Self::bus_address(self.pop()?)?;
+                let value = self.bus.lock().expect("Bus lock").read(address);
+                self.stack.push(value as i32);
+            }
+            Primitive::CStore => {
+                let (value, address) = self.pop_two()?;
+                let address = Self::bus_address(address)?;
+                self.bus
+                    .lock()
+                    .expect("Bus lock")
+                    .write(address, value as u8);
+            }
+            Primitive::PortFetch => {
+                let (port, register) = self.pop_two()?;
+                let address = Self::port_address(port, register)?;
+                let value = self.bus.lock().expect("Bus lock").read(address);
+                self.stack.push(value as i32);
+            }
+            Primitive::PortStore => {
+                let register = self.pop()?;
+                let (value, port) = self.pop_two()?;
+                let address = Self::port_address(port, register)?;
+                self.bus
+                    .lock()
+                    .expect("Bus lock")
+                    .write(address, value as u8);
+            }
+            Primitive::Here => self.push(self.memory.len() as i32)?,
+            Primitive::Allot => {
+                let cells = usize::try_from(self.pop()?).map_err(|_| ForthError::OutOfMemory)?;
+                self.allot(cells)?;
+                // Arrays are a variable with some extra cells after it, so make sure they
+                // come back the same size
+                if let Some(Word {
+                    kind: WordKind::Variable(_),
+                    source: Some(source),
+                    ..
+                }) = self.dictionary.last_mut()
+                {
+                    source.push_str(&format!(" #{} ALLOT", cells));
+                }
+            }
+            // Addresses count cells, so these don't have much to do
+            Primitive::Cells => {
+                self.peek(0)?;
+            }
+            Primitive::CellPlus => {
+                let a = self.pop()?;
+                self.stack.push(a.wrapping_add(1));
+            }
+            Primitive::ToR => {
+                let a = self.pop()?;
+                self.push_return(a)?;
+            }
+            Primitive::RFrom => {
+                let a = self
+                    .return_stack
+                    .pop()
+                    .ok_or(ForthError::ReturnStackUnderflow)?;
+                self.push(a)?;
+            }
+            Primitive::RFetch | Primitive::I => {
+                let a = *self
+                    .return_stack
+                    .last()
+                    .ok_or(ForthError::ReturnStackUnderflow)?;
+                self.push(a)?;
+            }
+            Primitive::J => {
+                // Underneath the inner loop's limit and index
+                let a = self
+                    .return_stack
+                    .len()
+                    .checked_sub(3)
+                    .map(|idx| self.return_stack[idx])
+                    .ok_or(ForthError::ReturnStackUnderflow)?;
+                self.push(a)?;
+            }
+            Primitive::Hex => self.base = 16,
+            Primitive::Decimal => self.base = 10,
+            Primitive::Ms => {
+                let milliseconds = self.pop()?.max(0);
+                self.sleep = milliseconds as f32 / 1000.0;
+            }
+            Primitive::Words => {
+                let names: Vec<&str> = self
+                    .dictionary
+                    .iter()
+                    .rev()
+                    .map(|word| word.name.as_str())
+                    .collect();
+                let text = names.join(" ");
+                self.output.push('\n');
+                self.output.push_str(&text);
+            }
+            Primitive::SaveWords => {
+                let mut contents = String::new();
+                let mut count = 0;
+                for word in &self.dictionary {
+                    if let Some(source) = &word.source {
+                        contents.push_str(source.trim_end());
+                        contents.push('\n');
+                        count += 1;
+                    }
+                }
+                self.files
+                    .lock()
+                    .expect("Filesystem lock")
+                    .write(WORDS_FILE, contents.as_bytes())?;
+                self.output
+                    .push_str(&format!("{} words saved to {} ", count, WORDS_FILE));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Points a forward branch at the end of the code so far
+fn patch(code: &mut [Instruction], branch: usize) {
+    let target = code.len();
+    match &mut code[branch] {
+        Instruction::Branch(to) | Instruction::BranchIfZero(to) | Instruction::Leave(to) => {
+            *to = target
+        }
+        _ => {}
+    }
+}
+
+// Division only goes wrong when there's nothing to divide by
+fn nonzero(divisor: i32) -> Result<i32, ForthError> {
+    if divisor == 0 {
+        return Err(ForthError::DivisionByZero);
+    }
+    Ok(divisor)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::computer::bus::DeviceBus;
+    use crate::computer::ship_os::filesystem::FileSystem;
+
+    fn machine() -> Machine {
+        Machine::new(
+            Arc::new(Mutex::new(FileSystem::default())),
+            Arc::new(Mutex::new(DeviceBus::default())),
+        )
+    }
+
+    // Runs a line to the end, and gives back everything printed along with what's left on the
+    // stack
+    fn run(machine: &mut Machine, line: &str) -> (String, Vec<i32>) {
+        machine.enter(line);
+        machine.run(0.0, 100_000);
+        assert!(!machine.is_busy(), "{line} should have finished");
+        (machine.take_output(), machine.stack.clone())
+    }
+
+    fn stack_after(line: &str) -> Vec<i32> {
+        let (output, stack) = run(&mut machine(), line);
+        assert!(output.ends_with(" ok\n"), "{line}: {output}");
+        stack
+    }
+
+    fn error_from(line: &str) -> String {
+        run(&mut machine(), line).0
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(stack_after("2 3 + 10 4 - 6 7 *"), [5, 6, 42]);
+        assert_eq!(stack_after("7 2 / 7 2 MOD 7 2 /MOD"), [3, 1, 1, 3]);
+        // Division rounds towards minus infinity, so the remainder's never negative
+        assert_eq!(stack_after("-7 2 / -7 2 MOD"), [-4, 1]);
+        assert_eq!(stack_after("2147483647 1+"), [i32::MIN]);
+    }
+
+    #[test]
+    fn dividing_the_most_negative_number_by_minus_one_wraps() {
+        let min = i32::MIN.to_string();
+        assert_eq!(stack_after(&format!("{min} -1 /")), [i32::MIN]);
+        assert_eq!(stack_after(&format!("{min} -1 MOD")), [0]);
+        assert_eq!(stack_after(&format!("{min} -1 /MOD")), [0, i32::MIN]);
+    }
+
+    #[test]
+    fn dividing_by_zero_is_an_error() {
+        for line in ["1 0 /", "1 0 MOD", "1 0 /MOD"] {
+            let output = error_from(line);
+            assert!(output.contains("Division by zero"), "{line}: {output}");
+        }
+    }
+
+    #[test]
+    fn stacks_have_limits() {
+        let output = error_from(": FILL 1000 0 DO I LOOP ; FILL");
+        assert!(output.contains("Stack overflow"), "{output}");
+        let output = error_from(": FILL 1000 0 DO I >R LOOP ; FILL");
+        assert!(output.contains("Return stack overflow"), "{output}");
+        let output = error_from(": DEEP RECURSE ; DEEP");
+        assert!(output.contains("Stack overflow"), "{output}");
+        assert!(error_from("DROP").contains("Stack underflow"));
+        assert!(error_from(": X R> ; X").contains("Return stack underflow"));
+    }
+
+    #[test]
+    fn errors_empty_the_stacks() {
+        let mut machine = machine();
+        run(&mut machine, "1 2 3 0 /");
+        assert!(machine.stack.is_empty());
+        assert!(machine.return_stack.is_empty());
+    }
+
+    #[test]
+    fn definitions_and_control_structures() {
+        let mut machine = machine();
+        run(&mut machine, ": SQUARE DUP * ;");
+        run(
+            &mut machine,
+            ": SIGN DUP 0< IF DROP -1 ELSE 0> IF 1 ELSE 0 THEN THEN ;",
+        );
+        assert_eq!(run(&mut machine, "5 SQUARE -3 SIGN 0 SIGN").1, [25, -1, 0]);
+        let (output, _) = run(&mut machine, ": COUNT 5 0 DO I . LOOP ; COUNT");
+        assert_eq!(output, "0 1 2 3 4  ok\n");
+    }
+
+    #[test]
+    fn unknown_words_are_reported() {
+        assert!(error_from("FROBNICATE").contains("FROBNICATE ?"));
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use bevy::ecs::reflect::{AppTypeRegistry, ReflectComponent};
+use bevy::prelude::{Changed, Entity, Name, Query, RemovedComponents, ResMut, Resource, World};
+use bevy::reflect::{GetPath, Reflect, ReflectRef};
+use serde::Deserialize;
+
+use super::widgets::Widget;
+use super::Attribute;
+
+// Hooks a widget up to a value on a component somewhere in the world, so it always shows
+// the latest reading. Bindings live in the screen layout, which means a status console can
+// be put together without writing any code for it.
+// The component has to be registered with the app (`app.register_type`) and reflect
+// `Component`, otherwise there's no way of finding it by name.
+#[derive(Clone, Deserialize)]
+pub struct Binding {
+    // The name of the widget to update
+    pub widget: String,
+    pub source: BindingSource,
+    #[serde(default)]
+    pub format: ValueFormat,
+    // Minimum number of seconds between updates, so fast-changing values don't flicker or
+    // make the screen redraw every frame. The default of 0 updates every frame, except for
+    // charts, which go at `CHART_INTERVAL`.
+    #[serde(default)]
+    pub interval: f64,
+    // Checked in order, so put the more serious ones last: the last matching one wins
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    // The values at which a progress bar is empty and full
+    #[serde(default = "default_range")]
+    pub range: (f64, f64),
+    // Which bar of a bar graph the value goes in, counting from 0
+    #[serde(default)]
+    pub bar: usize,
+}
+
+// How often a chart takes a sample if its binding doesn't say. Charts go at a steady rate
+// whatever the frame rate, so that each step along the bottom is the same amount of time.
+pub const CHART_INTERVAL: f64 = 0.5;
+
+// Where the value comes from
+#[derive(Clone, Deserialize)]
+pub struct BindingSource {
+    // The entity's `Name`
+    pub entity: String,
+    // The component's type, either the short name (`Transform`) or the full path
+    // (`bevy_transform::components::transform::Transform`)
+    pub component: String,
+    // A reflection path into the component, e.g. `translation.y`. Left empty, the component
+    // itself is the value, which is handy for components that are just an enum.
+    #[serde(default)]
+    pub field: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ValueFormat {
+    // What to show, with `{}` standing in for the value
+    #[serde(default = "default_template")]
+    pub template: String,
+    // Number of decimal places for numbers. Left out, numbers are shown however they come.
+    #[serde(default)]
+    pub decimals: Option<usize>,
+    // Numbers are multiplied by this before they're shown, e.g. 0.001 to show watts as kW
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    // Goes after numbers, e.g. "kW"
+    #[serde(default)]
+    pub unit: String,
+    // What to show for true and false
+    #[serde(default = "default_on")]
+    pub on: String,
+    #[serde(default = "default_off")]
+    pub off: String,
+}
+
+impl Default for ValueFormat {
+    fn default() -> Self {
+        Self {
+            template: default_template(),
+            decimals: None,
+            scale: default_scale(),
+            unit: String::new(),
+            on: default_on(),
+            off: default_off(),
+        }
+    }
+}
+
+fn default_template() -> String {
+    "{}".to_owned()
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_on() -> String {
+    "ON".to_owned()
+}
+
+fn default_off() -> String {
+    "OFF".to_owned()
+}
+
+fn default_range() -> (f64, f64) {
+    (0.0, 1.0)
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Threshold {
+    pub when: Condition,
+    // How to show the value while the condition holds
+    pub highlight: Attribute,
+}
+
+#[derive(Clone, Deserialize)]
+pub enum Condition {
+    // Compared before scaling, so thresholds are in the component's own units
+    Above(f64),
+    Below(f64),
+    // For enums (by variant name), bools ("true" or "false") and strings
+    Is(String),
+}
+
+// The kinds of value we know how to show
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundValue {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl BoundValue {
+    pub fn from_reflect(value: &dyn Reflect) -> Option<Self> {
+        let any = value.as_any();
+        let number = if let Some(n) = any.downcast_ref::<f32>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<f64>() {
+            Some(*n)
+        } else if let Some(n) = any.downcast_ref::<u8>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<u16>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<u32>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<u64>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<usize>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<i8>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<i16>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<i32>() {
+            Some(*n as f64)
+        } else if let Some(n) = any.downcast_ref::<i64>() {
+            Some(*n as f64)
+        } else {
+            any.downcast_ref::<isize>().map(|n| *n as f64)
+        };
+        if let Some(number) = number {
+            return Some(BoundValue::Number(number));
+        }
+
+        if let Some(b) = any.downcast_ref::<bool>() {
+            return Some(BoundValue::Bool(*b));
+        }
+        if let Some(text) = any.downcast_ref::<String>() {
+            return Some(BoundValue::Text(text.clone()));
+        }
+        // Enums show up as whichever variant they are
+        match value.reflect_ref() {
+            ReflectRef::Enum(value) => Some(BoundValue::Text(value.variant_name().to_owned())),
+            _ => None,
+        }
+    }
+}
+
+// Every entity with a `Name`, by name, so a binding can find its entity without looking
+// through the whole world every time it's due. If two entities share a name, the one named
+// last is the one that gets found.
+#[derive(Resource, Default)]
+pub struct NamedEntities(HashMap<String, Entity>);
+
+// Keeps `NamedEntities` up to date as things get named, renamed and despawned
+pub fn index_names(
+    mut index: ResMut<NamedEntities>,
+    named: Query<(Entity, &Name), Changed<Name>>,
+    mut removed: RemovedComponents<Name>,
+) {
+    for entity in removed.read() {
+        index.0.retain(|_, named| *named != entity);
+    }
+    for (entity, name) in named.iter() {
+        index.0.insert(name.as_str().to_owned(), entity);
+    }
+}
+
+// Finds the entity a binding's value comes from
+pub fn find_source_entity(world: &World, source: &BindingSource) -> Option<Entity> {
+    let entity = world
+        .get_resource::<NamedEntities>()?
+        .0
+        .get(&source.entity)
+        .copied()?;
+    // Something that's been renamed since is still in the index under its old name
+    world
+        .get::<Name>(entity)
+        .is_some_and(|name| name.as_str() == source.entity)
+        .then_some(entity)
+}
+
+// Looks up the value a binding is after on its entity. Gives `None` if any part of it can't
+// be found, e.g. because the entity has been despawned.
+pub fn read_source(world: &World, entity: Entity, source: &BindingSource) -> Option<BoundValue> {
+    let entity = world.get_entity(entity)?;
+
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let registration = registry
+        .get_with_short_type_path(&source.component)
+        .or_else(|| registry.get_with_type_path(&source.component))?;
+    let component = registration.data::<ReflectComponent>()?.reflect(entity)?;
+
+    let value = if source.field.is_empty() {
+        component
+    } else {
+        component.reflect_path(source.field.as_str()).ok()?
+    };
+    BoundValue::from_reflect(value)
+}
+
+impl Binding {
+    // The text to show for a value, `None` meaning it couldn't be read
+    pub fn format(&self, value: Option<&BoundValue>) -> String {
+        let format = &self.format;
+        let text = match value {
+            Some(BoundValue::Number(number)) => {
+                let number = number * format.scale;
+                let number = match format.decimals {
+                    Some(decimals) => format!("{:.*}", decimals, number),
+                    None => number.to_string(),
+                };
+                if format.unit.is_empty() {
+                    number
+                } else {
+                    format!("{} {}", number, format.unit)
+                }
+            }
+            Some(BoundValue::Bool(true)) => format.on.clone(),
+            Some(BoundValue::Bool(false)) => format.off.clone(),
+            Some(BoundValue::Text(text)) => text.clone(),
+            None => "----".to_owned(),
+        };
+        format.template.replace("{}", &text)
+    }
+
+    // How the value should be shown, based on the thresholds
+    pub fn highlight(&self, value: Option<&BoundValue>) -> Attribute {
+        let Some(value) = value else {
+            // Nothing to read, which is worth drawing attention to in its own quiet way
+            return Attribute::Dim;
+        };
+
+        self.thresholds
+            .iter()
+            .rev()
+            .find(|threshold| match (&threshold.when, value) {
+                (Condition::Above(limit), BoundValue::Number(number)) => number > limit,
+                (Condition::Below(limit), BoundValue::Number(number)) => number < limit,
+                (Condition::Is(expected), BoundValue::Bool(b)) => *expected == b.to_string(),
+                (Condition::Is(expected), BoundValue::Text(text)) => expected == text,
+                _ => false,
+            })
+            .map_or(Attribute::Normal, |threshold| threshold.highlight)
+    }
+
+    // Puts the value into the widget. Returns whether that changed anything.
+    pub fn apply(&self, widget: &mut Widget, value: Option<&BoundValue>) -> bool {
+        let highlight = self.highlight(value);
+
+        match widget {
+            Widget::Label(label) => {
+                let text = self.format(value);
+                let changed = label.text != text || label.attribute != highlight;
+                label.text = text;
+                label.attribute = highlight;
+                changed
+            }
+            Widget::ProgressBar(bar) => {
+                let (empty, full) = self.range;
+                let progress = match value {
+                    Some(BoundValue::Number(number)) => ((number - empty) / (full - empty)) as f32,
+                    Some(BoundValue::Bool(true)) => 1.0,
+                    _ => 0.0,
+                };
+                let changed = bar.progress != progress || bar.attribute != highlight;
+                bar.progress = progress;
+                bar.attribute = highlight;
+                changed
+            }
+            Widget::Checkbox(checkbox) => {
+                let checked = match value {
+                    Some(BoundValue::Number(number)) => *number != 0.0,
+                    Some(BoundValue::Bool(b)) => *b,
+                    _ => false,
+                };
+                let changed = checkbox.checked != checked;
+                checkbox.checked = checked;
+                changed
+            }
+            Widget::Chart(chart) => {
+                // Charts keep a history, so every reading counts as a change. They're read at
+                // a steady rate (see `CHART_INTERVAL`), so this is one sample per step.
+                if let Some(BoundValue::Number(number)) = value {
+                    chart.push_sample((number * self.format.scale) as f32);
+                }
+                chart.attribute = highlight;
+                true
+            }
+            Widget::BarGraph(graph) => {
+                let number = match value {
+                    Some(BoundValue::Number(number)) => (number * self.format.scale) as f32,
+                    Some(BoundValue::Bool(true)) => 1.0,
+                    _ => 0.0,
+                };
+                let Some((_, bar)) = graph.bars.get_mut(self.bar) else {
+                    return false;
+                };
+                let changed = *bar != number || graph.attribute != highlight;
+                *bar = number;
+                graph.attribute = highlight;
+                changed
+            }
+            // Nothing sensible to show a single value in
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,484 @@
+// Line drawing. Rather than working out which character to use for every possible corner and
+// junction by hand, every cell has four edges (up, down, left and right), each of which can
+// have a single line, a double line or nothing. Drawing a line just adds edges to the cells
+// it passes through, and the character falls out of whichever edges the cell ends up with.
+// The edges are kept alongside the screen rather than worked out from the characters, as the
+// characters don't tell the whole story: the end of a line looks the same as its middle.
+// That way lines and boxes can cross each other any which way and the junctions always come
+// out right.
+
+use super::{Attribute, BoxStyle, Dimensions, Display};
+use BoxStyle::{Double as D, Single as S};
+
+// The lines leaving a cell in each direction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Edges {
+    pub up: Option<BoxStyle>,
+    pub down: Option<BoxStyle>,
+    pub left: Option<BoxStyle>,
+    pub right: Option<BoxStyle>,
+}
+
+const fn edges(
+    up: Option<BoxStyle>,
+    down: Option<BoxStyle>,
+    left: Option<BoxStyle>,
+    right: Option<BoxStyle>,
+) -> Edges {
+    Edges {
+        up,
+        down,
+        left,
+        right,
+    }
+}
+
+// Every line-drawing character in CP437, and the edges it has
+// CP437 can't mix styles along the same axis (there's no character that's single on the left
+// and double on the right, say), and has no half-lines, so `Edges::to_char` tidies those up
+// before looking anything up in here.
+#[rustfmt::skip]
+const GLYPHS: [(char, Edges); 40] = [
+    // All single
+    ('─', edges(None,    None,    Some(S), Some(S))),
+    ('│', edges(Some(S), Some(S), None,    None)),
+    ('┌', edges(None,    Some(S), None,    Some(S))),
+    ('┐', edges(None,    Some(S), Some(S), None)),
+    ('└', edges(Some(S), None,    None,    Some(S))),
+    ('┘', edges(Some(S), None,    Some(S), None)),
+    ('├', edges(Some(S), Some(S), None,    Some(S))),
+    ('┤', edges(Some(S), Some(S), Some(S), None)),
+    ('┬', edges(None,    Some(S), Some(S), Some(S))),
+    ('┴', edges(Some(S), None,    Some(S), Some(S))),
+    ('┼', edges(Some(S), Some(S), Some(S), Some(S))),
+    // All double
+    ('═', edges(None,    None,    Some(D), Some(D))),
+    ('║', edges(Some(D), Some(D), None,    None)),
+    ('╔', edges(None,    Some(D), None,    Some(D))),
+    ('╗', edges(None,    Some(D), Some(D), None)),
+    ('╚', edges(Some(D), None,    None,    Some(D))),
+    ('╝', edges(Some(D), None,    Some(D), None)),
+    ('╠', edges(Some(D), Some(D), None,    Some(D))),
+    ('╣', edges(Some(D), Some(D), Some(D), None)),
+    ('╦', edges(None,    Some(D), Some(D), Some(D))),
+    ('╩', edges(Some(D), None,    Some(D), Some(D))),
+    ('╬', edges(Some(D), Some(D), Some(D), Some(D))),
+    // Single vertical, double horizontal
+    ('╒', edges(None,    Some(S), None,    Some(D))),
+    ('╕', edges(None,    Some(S), Some(D), None)),
+    ('╘', edges(Some(S), None,    None,    Some(D))),
+    ('╛', edges(Some(S), None,    Some(D), None)),
+    ('╞', edges(Some(S), Some(S), None,    Some(D))),
+    ('╡', edges(Some(S), Some(S), Some(D), None)),
+    ('╤', edges(None,    Some(S), Some(D), Some(D))),
+    ('╧', edges(Some(S), None,    Some(D), Some(D))),
+    ('╪', edges(Some(S), Some(S), Some(D), Some(D))),
+    // Double vertical, single horizontal
+    ('╓', edges(None,    Some(D), None,    Some(S))),
+    ('╖', edges(None,    Some(D), Some(S), None)),
+    ('╙', edges(Some(D), None,    None,    Some(S))),
+    ('╜', edges(Some(D), None,    Some(S), None)),
+    ('╟', edges(Some(D), Some(D), None,    Some(S))),
+    ('╢', edges(Some(D), Some(D), Some(S), None)),
+    ('╥', edges(None,    Some(D), Some(S), Some(S))),
+    ('╨', edges(Some(D), None,    Some(S), Some(S))),
+    ('╫', edges(Some(D), Some(D), Some(S), Some(S))),
+];
+
+impl Edges {
+    // The character that best shows these edges, or `None` if there aren't any
+    pub fn to_char(self) -> Option<char> {
+        let mut edges = self;
+
+        // Each axis can only be one style. Double wins, as it's the more noticeable of the two.
+        let horizontal = stronger(edges.left, edges.right);
+        edges.left = edges.left.and(horizontal);
+        edges.right = edges.right.and(horizontal);
+        let vertical = stronger(edges.up, edges.down);
+        edges.up = edges.up.and(vertical);
+        edges.down = edges.down.and(vertical);
+
+        // A lone edge (the end of a line that doesn't meet anything) becomes a whole line
+        match edges {
+            Edges {
+                up: None,
+                down: None,
+                left,
+                right,
+            } if left.is_none() || right.is_none() => {
+                edges.left = horizontal;
+                edges.right = horizontal;
+            }
+            Edges {
+                up,
+                down,
+                left: None,
+                right: None,
+            } if up.is_none() || down.is_none() => {
+                edges.up = vertical;
+                edges.down = vertical;
+            }
+            _ => {}
+        }
+
+        GLYPHS
+            .iter()
+            .find(|(_, glyph_edges)| *glyph_edges == edges)
+            .map(|(glyph, _)| *glyph)
+    }
+
+    // Adds `other`'s edges on top of these ones. Where `other` has lines along an axis, its
+    // style takes over that whole axis, so whatever was drawn most recently gets its way.
+    pub fn merge(self, other: Edges) -> Edges {
+        let mut result = Edges {
+            up: other.up.or(self.up),
+            down: other.down.or(self.down),
+            left: other.left.or(self.left),
+            right: other.right.or(self.right),
+        };
+
+        if let Some(style) = stronger(other.left, other.right) {
+            result.left = result.left.and(Some(style));
+            result.right = result.right.and(Some(style));
+        }
+        if let Some(style) = stronger(other.up, other.down) {
+            result.up = result.up.and(Some(style));
+            result.down = result.down.and(Some(style));
+        }
+
+        result
+    }
+}
+
+fn stronger(a: Option<BoxStyle>, b: Option<BoxStyle>) -> Option<BoxStyle> {
+    match (a, b) {
+        (Some(D), _) | (_, Some(D)) => Some(D),
+        (Some(S), _) | (_, Some(S)) => Some(S),
+        (None, None) => None,
+    }
+}
+
+impl Display {
+    // Adds edges to whatever's already in a cell. Cells outside the clipping region (or off
+    // the screen altogether) are left alone.
+    pub fn put_edges(&mut self, row: usize, column: usize, edges: Edges, attribute: Attribute) {
+        if !self.clip.contains(row, column) {
+            return;
+        }
+        let Some(cell_edges) = self.edges.get_mut(row, column) else {
+            return;
+        };
+
+        *cell_edges = cell_edges.merge(edges);
+        if let Some(glyph) = cell_edges.to_char() {
+            self.screen.set(row, column, glyph).expect("Out of bounds");
+            self.attributes
+                .set(row, column, attribute)
+                .expect("Out of bounds");
+        }
+    }
+
+    // Removes some of a cell's edges. The character gets updated when the next lot of edges
+    // are put in the cell.
+    fn cover_edges(&mut self, row: usize, column: usize, cover: impl FnOnce(&mut Edges)) {
+        if self.clip.contains(row, column) {
+            if let Some(edges) = self.edges.get_mut(row, column) {
+                cover(edges);
+            }
+        }
+    }
+
+    // Draws a horizontal line from `left` to `right` inclusive
+    pub fn draw_horizontal_line(
+        &mut self,
+        row: usize,
+        left: usize,
+        right: usize,
+        style: BoxStyle,
+        attribute: Attribute,
+    ) {
+        for column in left..=right {
+            let edges = Edges {
+                left: (column > left).then_some(style),
+                right: (column < right).then_some(style),
+                ..Edges::default()
+            };
+            self.put_edges(row, column, edges, attribute);
+        }
+    }
+
+    // Draws a vertical line from `top` to `bottom` inclusive
+    pub fn draw_vertical_line(
+        &mut self,
+        column: usize,
+        top: usize,
+        bottom: usize,
+        style: BoxStyle,
+        attribute: Attribute,
+    ) {
+        for row in top..=bottom {
+            let edges = Edges {
+                up: (row > top).then_some(style),
+                down: (row < bottom).then_some(style),
+                ..Edges::default()
+            };
+            self.put_edges(row, column, edges, attribute);
+        }
+    }
+
+    // Draws a box and blanks out everything inside it, merging its corners and edges with
+    // any lines already on the screen. A box with no height or width comes out as a line.
+    pub fn draw_box(&mut self, dimensions: Dimensions, style: BoxStyle, attribute: Attribute) {
+        let Dimensions {
+            top,
+            bottom,
+            left,
+            right,
+        } = dimensions;
+        if top > bottom || left > right {
+            return;
+        }
+
+        for row in (top + 1)..bottom {
+            for column in (left + 1)..right {
+                self.put_char(row, column, ' ', Attribute::Normal);
+            }
+        }
+
+        // Lines that used to carry on into the inside of the box are covered up by it now,
+        // so they should stop at its border rather than cross it
+        if bottom > top + 1 && right > left + 1 {
+            for row in (top + 1)..bottom {
+                self.cover_edges(row, left, |edges| edges.right = None);
+                self.cover_edges(row, right, |edges| edges.left = None);
+            }
+            for column in (left + 1)..right {
+                self.cover_edges(top, column, |edges| edges.down = None);
+                self.cover_edges(bottom, column, |edges| edges.up = None);
+            }
+        }
+
+        self.draw_horizontal_line(top, left, right, style, attribute);
+        self.draw_vertical_line(left, top, bottom, style, attribute);
+        if bottom > top {
+            self.draw_horizontal_line(bottom, left, right, style, attribute);
+        }
+        if right > left {
+            self.draw_vertical_line(right, top, bottom, style, attribute);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(display: &Display, row: usize) -> String {
+        (0..display.n_columns)
+            .map(|column| display.screen[(row, column)])
+            .collect()
+    }
+
+    fn rows(display: &Display) -> Vec<String> {
+        (0..display.n_rows).map(|idx| row(display, idx)).collect()
+    }
+
+    #[test]
+    fn every_glyph_comes_out_of_its_own_edges() {
+        for (glyph, glyph_edges) in GLYPHS {
+            assert_eq!(glyph_edges.to_char(), Some(glyph), "{:?}", glyph_edges);
+        }
+    }
+
+    #[test]
+    fn no_two_glyphs_have_the_same_edges() {
+        for (idx, (glyph, glyph_edges)) in GLYPHS.iter().enumerate() {
+            for (other, other_edges) in GLYPHS[idx + 1..].iter() {
+                assert_ne!(glyph_edges, other_edges, "{} and {}", glyph, other);
+            }
+        }
+    }
+
+    #[test]
+    fn no_edges_is_no_character() {
+        assert_eq!(Edges::default().to_char(), None);
+    }
+
+    #[test]
+    fn mixed_styles_on_one_axis_go_double() {
+        assert_eq!(edges(None, None, Some(S), Some(D)).to_char(), Some('═'));
+        assert_eq!(edges(Some(D), Some(S), None, None).to_char(), Some('║'));
+        assert_eq!(edges(Some(S), Some(D), Some(S), None).to_char(), Some('╢'));
+    }
+
+    #[test]
+    fn lone_edges_become_whole_lines() {
+        assert_eq!(edges(None, None, Some(S), None).to_char(), Some('─'));
+        assert_eq!(edges(None, None, None, Some(D)).to_char(), Some('═'));
+        assert_eq!(edges(Some(S), None, None, None).to_char(), Some('│'));
+        assert_eq!(edges(None, Some(D), None, None).to_char(), Some('║'));
+    }
+
+    #[test]
+    fn single_and_double_lines_cross() {
+        let single_across = edges(None, None, Some(S), Some(S));
+        let double_across = edges(None, None, Some(D), Some(D));
+        let single_upright = edges(Some(S), Some(S), None, None);
+        let double_upright = edges(Some(D), Some(D), None, None);
+
+        assert_eq!(single_across.merge(double_upright).to_char(), Some('╫'));
+        assert_eq!(double_upright.merge(single_across).to_char(), Some('╫'));
+        assert_eq!(double_across.merge(single_upright).to_char(), Some('╪'));
+        assert_eq!(single_across.merge(single_upright).to_char(), Some('┼'));
+        assert_eq!(double_across.merge(double_upright).to_char(), Some('╬'));
+    }
+
+    #[test]
+    fn the_latest_line_takes_over_its_axis() {
+        let double_across = edges(None, None, Some(D), Some(D));
+        // The end of a single line, coming in from the left
+        let single_end = edges(None, None, Some(S), None);
+        assert_eq!(double_across.merge(single_end).to_char(), Some('─'));
+
+        // A double T-junction with a single line carrying on upwards
+        let double_tee = edges(None, Some(D), Some(D), Some(D));
+        let single_up = edges(Some(S), None, None, None);
+        assert_eq!(double_tee.merge(single_up).to_char(), Some('╪'));
+    }
+
+    #[test]
+    fn merging_nothing_changes_nothing() {
+        for (_, glyph_edges) in GLYPHS {
+            assert_eq!(glyph_edges.merge(Edges::default()), glyph_edges);
+        }
+    }
+
+    #[test]
+    fn boxes_join_up_where_they_meet() {
+        let mut display = Display::new(7, 5);
+        let single = |top, bottom, left, right| Dimensions {
+            top,
+            bottom,
+            left,
+            right,
+        };
+        display.draw_box(single(0, 4, 0, 6), S, Attribute::Normal);
+        display.draw_horizontal_line(2, 0, 6, D, Attribute::Normal);
+        display.draw_vertical_line(3, 0, 4, S, Attribute::Normal);
+        assert_eq!(
+            rows(&display),
+            ["┌──┬──┐", "│  │  │", "╞══╪══╡", "│  │  │", "└──┴──┘"]
+        );
+    }
+
+    #[test]
+    fn boxes_are_clipped_at_the_edges_of_the_display() {
+        let mut display = Display::new(5, 3);
+        display.draw_box(
+            Dimensions {
+                top: 1,
+                bottom: 10,
+                left: 2,
+                right: 20,
+            },
+            D,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["     ", "  ╔══", "  ║  "]);
+
+        // Nothing past the right or bottom edges, either
+        display.put_edges(3, 0, edges(Some(S), Some(S), None, None), Attribute::Normal);
+        display.put_edges(0, 5, edges(Some(S), Some(S), None, None), Attribute::Normal);
+        assert_eq!(rows(&display), ["     ", "  ╔══", "  ║  "]);
+    }
+
+    #[test]
+    fn boxes_are_clipped_to_the_clipping_region() {
+        let mut display = Display::new(5, 3);
+        display.set_clip(Dimensions {
+            top: 0,
+            bottom: 2,
+            left: 1,
+            right: 3,
+        });
+        display.draw_box(
+            Dimensions {
+                top: 0,
+                bottom: 2,
+                left: 0,
+                right: 4,
+            },
+            S,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), [" ─── ", "     ", " ─── "]);
+    }
+
+    #[test]
+    fn empty_boxes_draw_nothing() {
+        let mut display = Display::new(3, 3);
+        display.draw_box(
+            Dimensions {
+                top: 2,
+                bottom: 1,
+                left: 0,
+                right: 2,
+            },
+            S,
+            Attribute::Normal,
+        );
+        display.draw_box(
+            Dimensions {
+                top: 0,
+                bottom: 2,
+                left: 2,
+                right: 1,
+            },
+            S,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["   ", "   ", "   "]);
+    }
+
+    #[test]
+    fn one_cell_boxes_have_no_lines_to_draw() {
+        let mut display = Display::new(3, 3);
+        display.draw_box(
+            Dimensions {
+                top: 1,
+                bottom: 1,
+                left: 1,
+                right: 1,
+            },
+            D,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["   ", "   ", "   "]);
+    }
+
+    #[test]
+    fn flat_boxes_are_lines() {
+        let mut display = Display::new(3, 3);
+        display.draw_box(
+            Dimensions {
+                top: 1,
+                bottom: 1,
+                left: 0,
+                right: 2,
+            },
+            S,
+            Attribute::Normal,
+        );
+        display.draw_box(
+            Dimensions {
+                top: 0,
+                bottom: 2,
+                left: 2,
+                right: 2,
+            },
+            D,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["  ║", "──╢", "  ║"]);
+    }
+}
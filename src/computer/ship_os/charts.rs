@@ -0,0 +1,402 @@
+// Charts, drawn with the block and shade characters. Each character cell is split into a top
+// and a bottom half (`▀`, `▄` and `█`), which doubles the vertical resolution we get out of
+// the text grid. Horizontal bars go the other way, so they end in a shade (`▒` a third of
+// the way into a cell, `▓` two thirds) instead, on a track of `░`.
+
+use serde::Deserialize;
+
+use super::canvas::Edges;
+use super::{Attribute, BoxStyle, Dimensions, Display};
+
+// The values at the bottom and top of a chart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Range {
+    // Fits the range to the values, apart from whichever ends have been fixed
+    pub fn fit(values: impl IntoIterator<Item = f32>, min: Option<f32>, max: Option<f32>) -> Range {
+        let (low, high) = values
+            .into_iter()
+            .filter(|value| value.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), value| {
+                (low.min(value), high.max(value))
+            });
+        // With nothing to go on, go for 0 to 1
+        let (low, high) = if low > high { (0.0, 1.0) } else { (low, high) };
+
+        let mut range = Range {
+            min: min.unwrap_or(low),
+            max: max.unwrap_or(high),
+        };
+        // A flat line would otherwise have nowhere to go, so give it some room either side
+        if range.max <= range.min {
+            let margin = (range.min.abs() * 0.1).max(0.5);
+            range = Range {
+                min: min.unwrap_or(range.min - margin),
+                max: max.unwrap_or(range.min + margin),
+            };
+        }
+        range
+    }
+
+    // How far up the range a value is, from 0 to 1
+    pub fn fraction(&self, value: f32) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+// Short enough to fit down the side of a chart
+pub fn format_axis_value(value: f32) -> String {
+    let magnitude = value.abs();
+    if magnitude >= 10000.0 {
+        format!("{:.0}k", value / 1000.0)
+    } else if magnitude >= 100.0 {
+        format!("{:.0}", value)
+    } else if magnitude >= 10.0 {
+        format!("{:.1}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+impl Display {
+    // Labels the top and bottom of the range down the left-hand side of the area, with a line
+    // separating them from the chart. Returns what's left for the chart itself, which is the
+    // whole area if there isn't room for the labels.
+    pub fn draw_value_axis(
+        &mut self,
+        area: &Dimensions,
+        range: Range,
+        attribute: Attribute,
+    ) -> Dimensions {
+        let max_label = format_axis_value(range.max);
+        let min_label = format_axis_value(range.min);
+        let label_width = max_label.chars().count().max(min_label.chars().count());
+        let width = area.right - area.left + 1;
+
+        // Leave at least a few columns for the chart
+        if area.bottom == area.top || width < label_width + 4 {
+            return area.clone();
+        }
+
+        let axis = area.left + label_width;
+        self.put_str(
+            area.top,
+            area.left,
+            &format!("{:>1$}", max_label, label_width),
+            label_width,
+            attribute,
+        );
+        self.put_str(
+            area.bottom,
+            area.left,
+            &format!("{:>1$}", min_label, label_width),
+            label_width,
+            attribute,
+        );
+        self.draw_vertical_line(axis, area.top, area.bottom, BoxStyle::Single, attribute);
+        // Ticks next to the labels
+        let tick = Edges {
+            left: Some(BoxStyle::Single),
+            ..Edges::default()
+        };
+        self.put_edges(area.top, axis, tick, attribute);
+        self.put_edges(area.bottom, axis, tick, attribute);
+
+        Dimensions {
+            left: axis + 1,
+            ..area.clone()
+        }
+    }
+
+    // A filled-in line graph of the most recent values, newest on the right
+    pub fn draw_sparkline(
+        &mut self,
+        area: &Dimensions,
+        values: &[f32],
+        range: Range,
+        attribute: Attribute,
+    ) {
+        let (width, half_rows) = half_block_size(area);
+        let values = &values[values.len().saturating_sub(width)..];
+        let offset = width - values.len();
+
+        let mut pixels = vec![vec![false; width]; half_rows];
+        for (idx, value) in values.iter().enumerate() {
+            // Always show at least a sliver, so the line doesn't disappear at the bottom
+            let height = ((range.fraction(*value) * half_rows as f32).round() as usize).max(1);
+            for pixel_row in &mut pixels[(half_rows - height)..] {
+                pixel_row[offset + idx] = true;
+            }
+        }
+
+        self.draw_half_blocks(area, &pixels, attribute);
+    }
+
+    // An oscilloscope-style trace of the most recent values, newest on the right. Neighbouring
+    // points are joined up, so steep changes still come out as a continuous line.
+    pub fn draw_scope_trace(
+        &mut self,
+        area: &Dimensions,
+        values: &[f32],
+        range: Range,
+        attribute: Attribute,
+    ) {
+        let (width, half_rows) = half_block_size(area);
+        let values = &values[values.len().saturating_sub(width)..];
+        let offset = width - values.len();
+
+        // Faint graticule across the middle, as on a real scope
+        let middle = area.top + (area.bottom - area.top) / 2;
+        let graticule = "░".repeat(width);
+        self.put_str(middle, area.left, &graticule, width, Attribute::Dim);
+
+        let mut pixels = vec![vec![false; width]; half_rows];
+        let mut previous: Option<usize> = None;
+        for (idx, value) in values.iter().enumerate() {
+            let fraction = range.fraction(*value);
+            let row = ((1.0 - fraction) * (half_rows - 1) as f32).round() as usize;
+            let (from, to) = match previous {
+                Some(previous) if previous < row => (previous + 1, row),
+                Some(previous) if previous > row => (row, previous - 1),
+                _ => (row, row),
+            };
+            for pixel_row in pixels.iter_mut().take(to + 1).skip(from) {
+                pixel_row[offset + idx] = true;
+            }
+            previous = Some(row);
+        }
+
+        // Only draw the cells the trace goes through, so the graticule shows behind it
+        for (row_idx, pair) in pixels.chunks(2).enumerate() {
+            for column in 0..width {
+                let top = pair[0][column];
+                let bottom = pair.get(1).is_some_and(|row| row[column]);
+                if let Some(ch) = half_block(top, bottom) {
+                    self.put_char(area.top + row_idx, area.left + column, ch, attribute);
+                }
+            }
+        }
+    }
+
+    // One bar per value, labelled with the first part of its label. Horizontal bars go from
+    // left to right, one per row; vertical ones from the bottom up, side by side.
+    pub fn draw_bar_graph(
+        &mut self,
+        area: &Dimensions,
+        bars: &[(String, f32)],
+        range: Range,
+        orientation: Orientation,
+        attribute: Attribute,
+    ) {
+        let width = area.right - area.left + 1;
+        let height = area.bottom - area.top + 1;
+        if bars.is_empty() {
+            return;
+        }
+
+        match orientation {
+            Orientation::Horizontal => {
+                let label_width = bars
+                    .iter()
+                    .map(|(label, _)| label.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .min(width / 2);
+                let bar_width = width - label_width - usize::from(label_width > 0);
+
+                for (row, (label, value)) in bars.iter().take(height).enumerate() {
+                    let row = area.top + row;
+                    self.put_str(row, area.left, label, label_width, Attribute::Normal);
+
+                    // In thirds of a cell
+                    let thirds = (range.fraction(*value) * bar_width as f32 * 3.0).round();
+                    let (filled, part) = (thirds as usize / 3, thirds as usize % 3);
+                    let mut bar = "█".repeat(filled);
+                    bar.push_str(["", "▒", "▓"][part]);
+                    bar.push_str(&"░".repeat(bar_width - filled - usize::from(part > 0)));
+                    self.put_str(row, area.right + 1 - bar_width, &bar, bar_width, attribute);
+                }
+            }
+            Orientation::Vertical => {
+                // Labels along the bottom, with a gap between each bar
+                let bar_area = Dimensions {
+                    bottom: area.bottom.saturating_sub(1).max(area.top),
+                    ..area.clone()
+                };
+                let (_, half_rows) = half_block_size(&bar_area);
+                let slot = (width / bars.len()).max(1);
+                let bar_width = slot.saturating_sub(1).max(1);
+
+                let mut pixels = vec![vec![false; width]; half_rows];
+                for (idx, (label, value)) in bars.iter().enumerate() {
+                    let left = idx * slot;
+                    if left >= width {
+                        break;
+                    }
+                    if bar_area.bottom < area.bottom {
+                        self.put_str(
+                            area.bottom,
+                            area.left + left,
+                            label,
+                            bar_width,
+                            Attribute::Normal,
+                        );
+                    }
+
+                    let filled = (range.fraction(*value) * half_rows as f32).round() as usize;
+                    let right = (left + bar_width).min(width);
+                    for pixel_row in &mut pixels[(half_rows - filled)..] {
+                        pixel_row[left..right].fill(true);
+                    }
+                }
+
+                self.draw_half_blocks(&bar_area, &pixels, attribute);
+            }
+        }
+    }
+
+    // Draws a grid of half-character "pixels", two rows of pixels to each row of the area
+    fn draw_half_blocks(&mut self, area: &Dimensions, pixels: &[Vec<bool>], attribute: Attribute) {
+        for (row_idx, pair) in pixels.chunks(2).enumerate() {
+            let row = area.top + row_idx;
+            for (column, &top) in pair[0].iter().enumerate() {
+                let bottom = pair.get(1).is_some_and(|row| row[column]);
+                let ch = half_block(top, bottom).unwrap_or(' ');
+                self.put_char(row, area.left + column, ch, attribute);
+            }
+        }
+    }
+}
+
+// The width of the area, and how many rows of half-character pixels fit in it
+fn half_block_size(area: &Dimensions) -> (usize, usize) {
+    (area.right - area.left + 1, (area.bottom - area.top + 1) * 2)
+}
+
+fn half_block(top: bool, bottom: bool) -> Option<char> {
+    match (top, bottom) {
+        (true, true) => Some('█'),
+        (true, false) => Some('▀'),
+        (false, true) => Some('▄'),
+        (false, false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(display: &Display) -> Vec<String> {
+        (0..display.n_rows)
+            .map(|row| {
+                (0..display.n_columns)
+                    .map(|column| display.screen[(row, column)])
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn whole(display: &Display) -> Dimensions {
+        Dimensions {
+            top: 0,
+            bottom: display.n_rows - 1,
+            left: 0,
+            right: display.n_columns - 1,
+        }
+    }
+
+    const ZERO_TO_ONE: Range = Range { min: 0.0, max: 1.0 };
+
+    #[test]
+    fn flat_lines_get_some_room_and_fixed_ends_stay_put() {
+        assert_eq!(
+            Range::fit([5.0, 5.0], None, None),
+            Range { min: 4.5, max: 5.5 }
+        );
+        assert_eq!(
+            Range::fit([2.0, f32::NAN, 3.0], Some(0.0), None),
+            Range { min: 0.0, max: 3.0 }
+        );
+        assert_eq!(Range::fit([], None, None), ZERO_TO_ONE);
+    }
+
+    #[test]
+    fn sparklines_scale_to_the_range_and_keep_the_newest_values() {
+        let mut display = Display::new(4, 2);
+        let area = whole(&display);
+        let range = Range { min: 0.0, max: 4.0 };
+
+        // The first two don't fit, and the lowest still shows up
+        display.draw_sparkline(
+            &area,
+            &[9.0, 9.0, 0.0, 1.0, 2.0, 4.0],
+            range,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["   █", "▄▄██"]);
+
+        // Too few to fill it, so they go on the right
+        display.draw_sparkline(&area, &[4.0], range, Attribute::Normal);
+        assert_eq!(rows(&display), ["   █", "   █"]);
+    }
+
+    #[test]
+    fn scope_traces_are_clipped_to_the_range_and_joined_up() {
+        let mut display = Display::new(4, 2);
+        display.draw_scope_trace(
+            &whole(&display),
+            &[-10.0, 10.0, 10.0, 0.5],
+            ZERO_TO_ONE,
+            Attribute::Normal,
+        );
+        // The graticule's across the top row, which is the middle of a 2-row scope
+        assert_eq!(rows(&display), ["░█▀▄", "▄▀ ▀"]);
+    }
+
+    #[test]
+    fn horizontal_bars_round_to_thirds_of_a_cell() {
+        let mut display = Display::new(8, 4);
+        let bars: Vec<(String, f32)> = [("A", 0.5), ("B", 0.2), ("C", 0.3), ("D", 2.0)]
+            .into_iter()
+            .map(|(label, value)| (label.to_owned(), value))
+            .collect();
+        display.draw_bar_graph(
+            &whole(&display),
+            &bars,
+            ZERO_TO_ONE,
+            Orientation::Horizontal,
+            Attribute::Normal,
+        );
+        assert_eq!(
+            rows(&display),
+            ["A ███░░░", "B █▒░░░░", "C █▓░░░░", "D ██████"]
+        );
+    }
+
+    #[test]
+    fn vertical_bars_round_to_half_a_cell() {
+        let mut display = Display::new(6, 3);
+        let bars = [("X".to_owned(), 0.4), ("Y".to_owned(), 1.0)];
+        display.draw_bar_graph(
+            &whole(&display),
+            &bars,
+            ZERO_TO_ONE,
+            Orientation::Vertical,
+            Attribute::Normal,
+        );
+        assert_eq!(rows(&display), ["   ██ ", "██ ██ ", "X  Y  "]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use rand::Rng;
+use thiserror::Error;
+
+// How much fits on the disk, in bytes. The same as a double-density 5.25" floppy. Files take
+// up whole sectors, so a disk full of small files holds a lot less than this.
+pub const DISK_CAPACITY: usize = 360 * 1024;
+
+// How the disk's split up, in bytes. Each file starts at the beginning of a sector and takes
+// up as many as it needs, one file after another in alphabetical order.
+pub const SECTOR_SIZE: usize = 512;
+
+// A flat filesystem, like the one on the early DOS floppies: no directories, just files with
+// 8.3 names. Names aren't case sensitive, and always get stored in upper case.
+#[derive(Default)]
+pub struct FileSystem {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+// Every app on the computer shares the same disk
+pub type SharedFileSystem = Arc<Mutex<FileSystem>>;
+
+#[derive(Debug, Error)]
+pub enum FileSystemError {
+    #[error("File not found: {0}")]
+    NotFound(String),
+    #[error("Invalid file name: {0}")]
+    InvalidName(String),
+    #[error("Disk full")]
+    DiskFull,
+}
+
+// What's on the disk when a computer is first switched on
+const README: &str = "\
+WELCOME TO SHIPOS
+
+Press F10 for the list of programs, and Alt+Tab to switch
+between the ones that are running. F12 gets you up from the
+computer.
+
+HELLO.BAS is an example program to get you started. Open
+BASIC, then type LOAD \"HELLO\" and RUN.
+";
+
+const HELLO_BAS: &str = "\
+10 REM PRINTS HELLO FIVE TIMES, COUNTING AS IT GOES
+20 FOR I=1 TO 5
+30 PRINT \"HELLO\";I
+40 NEXT I
+";
+
+impl FileSystem {
+    // A disk with a few files on it to get started with
+    pub fn formatted() -> Self {
+        let mut files = Self::default();
+        files
+            .write("README.TXT", README.as_bytes())
+            .expect("Sample file should fit");
+        files
+            .write("HELLO.BAS", HELLO_BAS.as_bytes())
+            .expect("Sample file should fit");
+        files
+    }
+
+    pub fn shared(self) -> SharedFileSystem {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, FileSystemError> {
+        let name = normalise_name(name)?;
+        self.files
+            .get(&name)
+            .cloned()
+            .ok_or(FileSystemError::NotFound(name))
+    }
+
+    // Reads a text file. Anything that isn't valid text comes out as `�`.
+    pub fn read_to_string(&self, name: &str) -> Result<String, FileSystemError> {
+        Ok(String::from_utf8_lossy(&self.read(name)?).into_owned())
+    }
+
+    // Creates the file, or replaces it if it's already there
+    pub fn write(&mut self, name: &str, contents: &[u8]) -> Result<(), FileSystemError> {
+        let name = normalise_name(name)?;
+        let existing = self.files.get(&name).map_or(0, Vec::len);
+        let sectors = self.used_sectors() - sectors_for(existing) + sectors_for(contents.len());
+        if sectors * SECTOR_SIZE > DISK_CAPACITY {
+            return Err(FileSystemError::DiskFull);
+        }
+        self.files.insert(name, contents.to_vec());
+        Ok(())
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        normalise_name(name).is_ok_and(|name| self.files.contains_key(&name))
+    }
+
+    // Every file's name and size, in alphabetical order
+    pub fn list(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.files
+            .iter()
+            .map(|(name, contents)| (name.as_str(), contents.len()))
+    }
+
+    // How many sectors the files take up between them
+    pub fn used_sectors(&self) -> usize {
+        self.files
+            .values()
+            .map(|contents| sectors_for(contents.len()))
+            .sum()
+    }
+
+    // Scrambles a sector, flipping a bit in about one byte in eight (and always at least
+    // one). Gives the name of the file that was in it, if there was one.
+    pub fn corrupt_sector(&mut self, sector: usize, rng: &mut impl Rng) -> Option<String> {
+        let mut first = 0;
+        for (name, contents) in self.files.iter_mut() {
+            let sectors = sectors_for(contents.len());
+            if sector >= first + sectors {
+                first += sectors;
+                continue;
+            }
+
+            let start = (sector - first) * SECTOR_SIZE;
+            let end = (start + SECTOR_SIZE).min(contents.len());
+            let bytes = &mut contents[start..end];
+            let always = rng.gen_range(0..bytes.len());
+            for (idx, byte) in bytes.iter_mut().enumerate() {
+                if idx == always || rng.gen_bool(0.125) {
+                    *byte ^= 1 << rng.gen_range(0..8);
+                }
+            }
+            return Some(name.clone());
+        }
+        None
+    }
+}
+
+// How many sectors a file of `len` bytes takes up
+fn sectors_for(len: usize) -> usize {
+    len.div_ceil(SECTOR_SIZE)
+}
+
+// Checks a name is a valid 8.3 name, and puts it in upper case
+fn normalise_name(name: &str) -> Result<String, FileSystemError> {
+    let upper = name.trim().to_ascii_uppercase();
+    let (stem, extension) = upper.split_once('.').unwrap_or((&upper, ""));
+
+    let valid_part = |part: &str, max: usize| {
+        part.len() <= max
+            && part
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+    };
+    if stem.is_empty() || !valid_part(stem, 8) || !valid_part(extension, 3) {
+        return Err(FileSystemError::InvalidName(name.to_owned()));
+    }
+
+    Ok(upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_take_up_whole_sectors_of_the_disk() {
+        let mut disk = FileSystem::default();
+        let n_sectors = DISK_CAPACITY / SECTOR_SIZE;
+        for idx in 0..n_sectors {
+            disk.write(&format!("{idx}.TXT"), b"X").unwrap();
+        }
+        assert_eq!(disk.used_sectors(), n_sectors);
+        assert!(matches!(
+            disk.write("ONE.TXT", b"X"),
+            Err(FileSystemError::DiskFull)
+        ));
+
+        // A file can still grow into the rest of its sector
+        disk.write("0.TXT", &[0; SECTOR_SIZE]).unwrap();
+        assert!(matches!(
+            disk.write("0.TXT", &[0; SECTOR_SIZE + 1]),
+            Err(FileSystemError::DiskFull)
+        ));
+    }
+}
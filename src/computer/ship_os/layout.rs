@@ -0,0 +1,282 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::reflect::TypePath;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::actions::Reaction;
+use super::binding::Binding;
+use super::charts::Orientation;
+use super::widgets::{ChartStyle, Widget};
+use super::{BoxStyle, Dimensions};
+
+// Describes everything on a ShipOS screen, so screens can be put together in a text file
+// (see assets/layouts) instead of in code.
+// These get hot-reloaded, so you can tweak a layout while the game is running and see the
+// changes straight away.
+#[derive(Asset, TypePath, Clone, Deserialize)]
+pub struct ScreenLayout {
+    pub windows: Vec<WindowLayout>,
+    // Widgets that show live values from the world
+    #[serde(default)]
+    pub bindings: Vec<Binding>,
+    // What to do when widgets get used
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct WindowLayout {
+    pub title: String,
+    // Includes the window's border
+    pub area: Dimensions,
+    #[serde(default)]
+    pub widgets: Vec<WidgetLayout>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct WidgetLayout {
+    // What the code, bindings and reactions refer to the widget as
+    #[serde(default)]
+    pub name: Option<String>,
+    // Relative to the inside of the parent's border, or of the window if it has no parent
+    pub area: Dimensions,
+    pub widget: WidgetKind,
+    #[serde(default)]
+    pub children: Vec<WidgetLayout>,
+}
+
+#[derive(Clone, Deserialize)]
+pub enum WidgetKind {
+    // A panel with no title (and no children) is just a box
+    Panel {
+        #[serde(default)]
+        title: String,
+        style: BoxStyle,
+    },
+    Label {
+        text: String,
+    },
+    Button {
+        label: String,
+    },
+    ListBox {
+        items: Vec<String>,
+    },
+    ProgressBar {
+        #[serde(default)]
+        progress: f32,
+    },
+    Checkbox {
+        label: String,
+        #[serde(default)]
+        checked: bool,
+    },
+    TextInput,
+    ScrollText {
+        #[serde(default)]
+        lines: Vec<String>,
+    },
+    // Leave out `min` or `max` to have that end of the chart scale itself
+    Chart {
+        style: ChartStyle,
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+    BarGraph {
+        bars: Vec<String>,
+        orientation: Orientation,
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+}
+
+impl WidgetKind {
+    pub fn to_widget(&self) -> Widget {
+        match self {
+            WidgetKind::Panel { title, style } => Widget::panel(title, *style),
+            WidgetKind::Label { text } => Widget::label(text),
+            WidgetKind::Button { label } => Widget::button(label),
+            WidgetKind::ListBox { items } => {
+                let items: Vec<&str> = items.iter().map(String::as_str).collect();
+                Widget::list_box(&items)
+            }
+            WidgetKind::ProgressBar { progress } => Widget::progress_bar(*progress),
+            WidgetKind::Checkbox { label, checked } => Widget::checkbox(label, *checked),
+            WidgetKind::TextInput => Widget::text_input(),
+            WidgetKind::ScrollText { lines } => {
+                let mut widget = Widget::scroll_text();
+                if let Widget::ScrollText(scroll_text) = &mut widget {
+                    for line in lines {
+                        scroll_text.push_line(line);
+                    }
+                }
+                widget
+            }
+            WidgetKind::Chart { style, min, max } => Widget::chart(*style, *min, *max),
+            WidgetKind::BarGraph {
+                bars,
+                orientation,
+                min,
+                max,
+            } => {
+                let labels: Vec<&str> = bars.iter().map(String::as_str).collect();
+                Widget::bar_graph(&labels, *orientation, *min, *max)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScreenLayoutLoader;
+
+#[derive(Debug, Error)]
+pub enum ScreenLayoutLoaderError {
+    #[error("Couldn't read screen layout: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse screen layout: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("The area of {0} is inside out: right is before left, or bottom is above top")]
+    InsideOut(String),
+    #[error("Window \"{0}\" is too small: it needs to be at least 3x3 to fit its border")]
+    TooSmall(String),
+}
+
+impl ScreenLayout {
+    // Areas that are inside out would make the widths and heights underflow when the screen's
+    // drawn, and so would windows with no room inside their borders, so they get turned away
+    // as soon as the file's loaded
+    fn check_areas(&self) -> Result<(), ScreenLayoutLoaderError> {
+        for window in &self.windows {
+            check_area(&window.area, || format!("window \"{}\"", window.title))?;
+            if window.area.bottom - window.area.top < 2 || window.area.right - window.area.left < 2
+            {
+                return Err(ScreenLayoutLoaderError::TooSmall(window.title.clone()));
+            }
+            check_widget_areas(&window.widgets, &window.title)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_widget_areas(
+    widgets: &[WidgetLayout],
+    window_title: &str,
+) -> Result<(), ScreenLayoutLoaderError> {
+    for widget in widgets {
+        check_area(&widget.area, || match &widget.name {
+            Some(name) => format!("widget \"{}\"", name),
+            None => format!("a widget in window \"{}\"", window_title),
+        })?;
+        check_widget_areas(&widget.children, window_title)?;
+    }
+    Ok(())
+}
+
+fn check_area(
+    area: &Dimensions,
+    describe: impl FnOnce() -> String,
+) -> Result<(), ScreenLayoutLoaderError> {
+    if area.right < area.left || area.bottom < area.top {
+        return Err(ScreenLayoutLoaderError::InsideOut(describe()));
+    }
+    Ok(())
+}
+
+impl AssetLoader for ScreenLayoutLoader {
+    type Asset = ScreenLayout;
+    type Settings = ();
+    type Error = ScreenLayoutLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<ScreenLayout, ScreenLayoutLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let layout: ScreenLayout = ron::de::from_bytes(&bytes)?;
+        layout.check_areas()?;
+        Ok(layout)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["layout.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Result<(), ScreenLayoutLoaderError> {
+        let layout: ScreenLayout = ron::de::from_str(text).expect("Layout should parse");
+        layout.check_areas()
+    }
+
+    #[test]
+    fn the_layouts_that_come_with_the_game_are_fine() {
+        for text in [
+            include_str!("../../../assets/layouts/diagnostics.layout.ron"),
+            include_str!("../../../assets/layouts/helm.layout.ron"),
+            include_str!("../../../assets/layouts/power.layout.ron"),
+            include_str!("../../../assets/layouts/reactor.layout.ron"),
+        ] {
+            check(text).unwrap();
+        }
+    }
+
+    #[test]
+    fn inside_out_windows_are_turned_away() {
+        let result = check(
+            r#"(windows: [(title: "BACKWARDS", area: (top: 0, bottom: 4, left: 10, right: 5))])"#,
+        );
+        assert!(
+            matches!(&result, Err(ScreenLayoutLoaderError::InsideOut(what)) if what.contains("BACKWARDS"))
+        );
+    }
+
+    #[test]
+    fn inside_out_widgets_are_turned_away_however_deep_they_are() {
+        let result = check(
+            r#"(windows: [(
+                title: "OUTER",
+                area: (top: 0, bottom: 10, left: 0, right: 20),
+                widgets: [(
+                    area: (top: 0, bottom: 5, left: 0, right: 10),
+                    widget: Panel(style: Single),
+                    children: [(
+                        name: Some("upside_down"),
+                        area: (top: 3, bottom: 1, left: 0, right: 4),
+                        widget: Label(text: ""),
+                    )],
+                )],
+            )])"#,
+        );
+        assert!(
+            matches!(&result, Err(ScreenLayoutLoaderError::InsideOut(what)) if what.contains("upside_down"))
+        );
+    }
+
+    #[test]
+    fn windows_with_no_room_inside_their_borders_are_turned_away() {
+        for area in [
+            "(top: 0, bottom: 0, left: 0, right: 10)",
+            "(top: 3, bottom: 4, left: 0, right: 10)",
+            "(top: 0, bottom: 10, left: 5, right: 6)",
+        ] {
+            let result = check(&format!(r#"(windows: [(title: "TINY", area: {area})])"#));
+            assert!(
+                matches!(&result, Err(ScreenLayoutLoaderError::TooSmall(title)) if title == "TINY"),
+                "{area}"
+            );
+        }
+        check(r#"(windows: [(title: "SNUG", area: (top: 0, bottom: 2, left: 0, right: 2))])"#)
+            .unwrap();
+    }
+}
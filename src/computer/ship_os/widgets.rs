@@ -0,0 +1,812 @@
+use std::collections::VecDeque;
+
+use bevy::input::keyboard::Key;
+use serde::Deserialize;
+
+use super::charts::{Orientation, Range};
+use super::{Attribute, BoxStyle, Dimensions, Display, Modifiers};
+
+// How many samples a chart hangs on to. Easily enough to fill the width of the screen.
+const CHART_HISTORY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetId(usize);
+
+// Things that happen to widgets that whoever owns the widget tree might care about
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetEvent {
+    Pressed(WidgetId),
+    Toggled(WidgetId, bool),
+    // The highlighted item in a list box has changed
+    Selected(WidgetId, usize),
+    // Enter was pressed on an item in a list box
+    Activated(WidgetId, usize),
+    // Enter was pressed in a text input
+    Submitted(WidgetId, String),
+}
+
+impl WidgetEvent {
+    // Which widget the event happened to
+    pub fn widget(&self) -> WidgetId {
+        match self {
+            WidgetEvent::Pressed(id)
+            | WidgetEvent::Toggled(id, _)
+            | WidgetEvent::Selected(id, _)
+            | WidgetEvent::Activated(id, _)
+            | WidgetEvent::Submitted(id, _) => *id,
+        }
+    }
+}
+
+pub enum Widget {
+    Panel(Panel),
+    Label(Label),
+    Button(Button),
+    ListBox(ListBox),
+    ProgressBar(ProgressBar),
+    Checkbox(Checkbox),
+    TextInput(TextInput),
+    ScrollText(ScrollText),
+    Chart(Chart),
+    BarGraph(BarGraph),
+}
+
+// A box, optionally with a title, that other widgets can be put inside
+pub struct Panel {
+    pub title: String,
+    pub style: BoxStyle,
+}
+
+pub struct Label {
+    pub text: String,
+    // Bound labels get highlighted when their value crosses a threshold
+    pub attribute: Attribute,
+}
+
+pub struct Button {
+    pub label: String,
+}
+
+pub struct ListBox {
+    pub items: Vec<String>,
+    pub selected: usize,
+    scroll: usize,
+}
+
+pub struct ProgressBar {
+    // From 0 (empty) to 1 (full)
+    pub progress: f32,
+    pub attribute: Attribute,
+}
+
+pub struct Checkbox {
+    pub label: String,
+    pub checked: bool,
+}
+
+pub struct TextInput {
+    pub value: String,
+    // Counted in characters, not bytes
+    cursor: usize,
+    scroll: usize,
+}
+
+pub struct ScrollText {
+    pub lines: Vec<String>,
+    scroll: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ChartStyle {
+    // Filled in underneath, for trends
+    Sparkline,
+    // Just the line, for waveforms
+    Scope,
+}
+
+// A history of values, with the newest on the right
+pub struct Chart {
+    pub style: ChartStyle,
+    pub samples: VecDeque<f32>,
+    // The values at the bottom and top of the chart. Whichever ones are `None` get worked out
+    // from whatever's currently on the chart.
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub attribute: Attribute,
+}
+
+pub struct BarGraph {
+    // Each bar's label and value
+    pub bars: Vec<(String, f32)>,
+    pub orientation: Orientation,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub attribute: Attribute,
+}
+
+impl Widget {
+    pub fn panel(title: &str, style: BoxStyle) -> Self {
+        Self::Panel(Panel {
+            title: title.to_owned(),
+            style,
+        })
+    }
+
+    pub fn label(text: &str) -> Self {
+        Self::Label(Label {
+            text: text.to_owned(),
+            attribute: Attribute::Normal,
+        })
+    }
+
+    pub fn button(label: &str) -> Self {
+        Self::Button(Button {
+            label: label.to_owned(),
+        })
+    }
+
+    pub fn list_box(items: &[&str]) -> Self {
+        Self::ListBox(ListBox {
+            items: items.iter().map(|&item| item.to_owned()).collect(),
+            selected: 0,
+            scroll: 0,
+        })
+    }
+
+    pub fn progress_bar(progress: f32) -> Self {
+        Self::ProgressBar(ProgressBar {
+            progress,
+            attribute: Attribute::Normal,
+        })
+    }
+
+    pub fn checkbox(label: &str, checked: bool) -> Self {
+        Self::Checkbox(Checkbox {
+            label: label.to_owned(),
+            checked,
+        })
+    }
+
+    pub fn text_input() -> Self {
+        Self::TextInput(TextInput {
+            value: String::new(),
+            cursor: 0,
+            scroll: 0,
+        })
+    }
+
+    pub fn scroll_text() -> Self {
+        Self::ScrollText(ScrollText {
+            lines: Vec::new(),
+            scroll: 0,
+        })
+    }
+
+    pub fn chart(style: ChartStyle, min: Option<f32>, max: Option<f32>) -> Self {
+        Self::Chart(Chart {
+            style,
+            samples: VecDeque::new(),
+            min,
+            max,
+            attribute: Attribute::Normal,
+        })
+    }
+
+    pub fn bar_graph(
+        labels: &[&str],
+        orientation: Orientation,
+        min: Option<f32>,
+        max: Option<f32>,
+    ) -> Self {
+        Self::BarGraph(BarGraph {
+            bars: labels
+                .iter()
+                .map(|&label| (label.to_owned(), 0.0))
+                .collect(),
+            orientation,
+            min,
+            max,
+            attribute: Attribute::Normal,
+        })
+    }
+
+    // Whether the widget can have keyboard focus
+    fn is_focusable(&self) -> bool {
+        match self {
+            Widget::Panel(_)
+            | Widget::Label(_)
+            | Widget::ProgressBar(_)
+            | Widget::Chart(_)
+            | Widget::BarGraph(_) => false,
+            Widget::Button(_)
+            | Widget::ListBox(_)
+            | Widget::Checkbox(_)
+            | Widget::TextInput(_)
+            | Widget::ScrollText(_) => true,
+        }
+    }
+}
+
+impl ScrollText {
+    // Adds a line to the end, and scrolls down to it
+    pub fn push_line(&mut self, line: &str) {
+        self.lines.push(line.to_owned());
+        self.scroll = self.lines.len();
+    }
+}
+
+impl Chart {
+    // Adds a value on the right, dropping the oldest ones once there are too many
+    pub fn push_sample(&mut self, value: f32) {
+        self.samples.push_back(value);
+        while self.samples.len() > CHART_HISTORY {
+            self.samples.pop_front();
+        }
+    }
+}
+
+impl TextInput {
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+}
+
+struct WidgetNode {
+    widget: Widget,
+    // Optional, so the widget can be looked up without having to hang on to its id
+    name: Option<String>,
+    // Where the widget is, relative to the top left of whatever the tree is drawn into.
+    // Unlike the dimensions passed to `WidgetTree::add`, this isn't relative to the parent.
+    area: Dimensions,
+}
+
+// A retained tree of widgets, which gets drawn into the character grid whenever it changes.
+// Widgets are stored in the order they were added, which means parents always come before
+// their children. That gives us both the drawing order and the tab order for free.
+#[derive(Default)]
+pub struct WidgetTree {
+    nodes: Vec<WidgetNode>,
+    focus: Option<WidgetId>,
+}
+
+impl WidgetTree {
+    // Adds a widget to the tree. If it has a parent, `area` is relative to the inside of the
+    // parent's border; otherwise it's relative to the top left of the tree.
+    pub fn add(&mut self, parent: Option<WidgetId>, area: Dimensions, widget: Widget) -> WidgetId {
+        let (row_offset, column_offset) = match parent {
+            Some(WidgetId(idx)) => (self.nodes[idx].area.top + 1, self.nodes[idx].area.left + 1),
+            None => (0, 0),
+        };
+
+        let id = WidgetId(self.nodes.len());
+        let focusable = widget.is_focusable();
+        self.nodes.push(WidgetNode {
+            widget,
+            name: None,
+            area: Dimensions {
+                top: area.top + row_offset,
+                bottom: area.bottom + row_offset,
+                left: area.left + column_offset,
+                right: area.right + column_offset,
+            },
+        });
+
+        // Nothing has focus until something focusable turns up
+        if self.focus.is_none() && focusable {
+            self.focus = Some(id);
+        }
+
+        id
+    }
+
+    pub fn get_mut(&mut self, WidgetId(idx): WidgetId) -> &mut Widget {
+        &mut self.nodes[idx].widget
+    }
+
+    pub fn set_name(&mut self, WidgetId(idx): WidgetId, name: &str) {
+        self.nodes[idx].name = Some(name.to_owned());
+    }
+
+    pub fn name(&self, WidgetId(idx): WidgetId) -> Option<&str> {
+        self.nodes.get(idx)?.name.as_deref()
+    }
+
+    pub fn find(&self, name: &str) -> Option<WidgetId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.as_deref() == Some(name))
+            .map(WidgetId)
+    }
+
+    pub fn handle_keyboard_input(
+        &mut self,
+        key: &Key,
+        modifiers: Modifiers,
+    ) -> Option<WidgetEvent> {
+        // Tab moves between widgets, whichever one has focus
+        if *key == Key::Tab {
+            self.move_focus(!modifiers.shift);
+            return None;
+        }
+
+        let id = self.focus?;
+        let node = &mut self.nodes[id.0];
+        let page_size = node.area.bottom - node.area.top + 1;
+
+        match &mut node.widget {
+            Widget::Button(_) => match key {
+                Key::Enter | Key::Space => Some(WidgetEvent::Pressed(id)),
+                _ => None,
+            },
+            Widget::Checkbox(checkbox) => match key {
+                Key::Enter | Key::Space => {
+                    checkbox.checked = !checkbox.checked;
+                    Some(WidgetEvent::Toggled(id, checkbox.checked))
+                }
+                _ => None,
+            },
+            Widget::ListBox(list_box) => {
+                let last = list_box.items.len().saturating_sub(1);
+                let selected = match key {
+                    Key::Enter => return Some(WidgetEvent::Activated(id, list_box.selected)),
+                    Key::ArrowUp => list_box.selected.saturating_sub(1),
+                    Key::ArrowDown => (list_box.selected + 1).min(last),
+                    Key::PageUp => list_box.selected.saturating_sub(page_size),
+                    Key::PageDown => (list_box.selected + page_size).min(last),
+                    Key::Home => 0,
+                    Key::End => last,
+                    _ => return None,
+                };
+                if selected == list_box.selected {
+                    return None;
+                }
+                list_box.selected = selected;
+                Some(WidgetEvent::Selected(id, selected))
+            }
+            Widget::TextInput(input) => {
+                let length = input.value.chars().count();
+                match key {
+                    Key::Enter => return Some(WidgetEvent::Submitted(id, input.value.clone())),
+                    Key::ArrowLeft => input.cursor = input.cursor.saturating_sub(1),
+                    Key::ArrowRight => input.cursor = (input.cursor + 1).min(length),
+                    Key::Home => input.cursor = 0,
+                    Key::End => input.cursor = length,
+                    Key::Backspace if input.cursor > 0 => {
+                        input.cursor -= 1;
+                        remove_char(&mut input.value, input.cursor);
+                    }
+                    Key::Delete if input.cursor < length => {
+                        remove_char(&mut input.value, input.cursor);
+                    }
+                    Key::Space => {
+                        insert_char(&mut input.value, input.cursor, ' ');
+                        input.cursor += 1;
+                    }
+                    Key::Character(text) if !text.chars().any(|c| c.is_control()) => {
+                        for ch in text.chars() {
+                            insert_char(&mut input.value, input.cursor, ch);
+                            input.cursor += 1;
+                        }
+                    }
+                    _ => {}
+                }
+                None
+            }
+            Widget::ScrollText(scroll_text) => {
+                scroll_text.scroll = match key {
+                    Key::ArrowUp => scroll_text.scroll.saturating_sub(1),
+                    Key::ArrowDown => scroll_text.scroll + 1,
+                    Key::PageUp => scroll_text.scroll.saturating_sub(page_size),
+                    Key::PageDown => scroll_text.scroll + page_size,
+                    Key::Home => 0,
+                    Key::End => scroll_text.lines.len(),
+                    _ => return None,
+                };
+                // Gets clamped properly when it's next drawn
+                None
+            }
+            Widget::Panel(_)
+            | Widget::Label(_)
+            | Widget::ProgressBar(_)
+            | Widget::Chart(_)
+            | Widget::BarGraph(_) => None,
+        }
+    }
+
+    pub fn handle_click(&mut self, row: usize, column: usize) -> Option<WidgetEvent> {
+        // Later widgets are drawn on top of earlier ones, so check them first
+        let idx = self
+            .nodes
+            .iter()
+            .rposition(|node| node.area.contains(row, column) && node.widget.is_focusable())?;
+        let id = WidgetId(idx);
+        self.focus = Some(id);
+
+        let node = &mut self.nodes[idx];
+        match &mut node.widget {
+            Widget::Button(_) => Some(WidgetEvent::Pressed(id)),
+            Widget::Checkbox(checkbox) => {
+                checkbox.checked = !checkbox.checked;
+                Some(WidgetEvent::Toggled(id, checkbox.checked))
+            }
+            Widget::ListBox(list_box) => {
+                let clicked = list_box.scroll + row - node.area.top;
+                if clicked >= list_box.items.len() || clicked == list_box.selected {
+                    return None;
+                }
+                list_box.selected = clicked;
+                Some(WidgetEvent::Selected(id, clicked))
+            }
+            Widget::TextInput(input) => {
+                input.cursor =
+                    (input.scroll + column - node.area.left).min(input.value.chars().count());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn move_focus(&mut self, forwards: bool) {
+        let focusable: Vec<usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.widget.is_focusable())
+            .map(|(idx, _)| idx)
+            .collect();
+        if focusable.is_empty() {
+            return;
+        }
+
+        let current = self
+            .focus
+            .and_then(|WidgetId(focus)| focusable.iter().position(|&idx| idx == focus));
+        let next = match (current, forwards) {
+            (Some(pos), true) => (pos + 1) % focusable.len(),
+            (Some(pos), false) => (pos + focusable.len() - 1) % focusable.len(),
+            (None, _) => 0,
+        };
+        self.focus = Some(WidgetId(focusable[next]));
+    }
+
+    // Draws the whole tree onto the screen, with its top left corner at the given position.
+    // The focused widget is only highlighted if the tree is `active`, i.e. if it's what the
+    // keyboard is currently talking to.
+    // Takes `&mut self` because drawing is also when scrolling widgets work out what they need
+    // to scroll to.
+    pub fn render(
+        &mut self,
+        display: &mut Display,
+        origin_row: usize,
+        origin_column: usize,
+        active: bool,
+    ) {
+        for (idx, node) in self.nodes.iter_mut().enumerate() {
+            let focused = active && self.focus == Some(WidgetId(idx));
+            let area = &Dimensions {
+                top: node.area.top + origin_row,
+                bottom: node.area.bottom + origin_row,
+                left: node.area.left + origin_column,
+                right: node.area.right + origin_column,
+            };
+            let width = area.right - area.left + 1;
+            let height = area.bottom - area.top + 1;
+            let attribute = if focused {
+                Attribute::Bright
+            } else {
+                Attribute::Normal
+            };
+
+            match &mut node.widget {
+                Widget::Panel(panel) => {
+                    display.draw_box(area.clone(), panel.style, Attribute::Normal);
+                    if !panel.title.is_empty() {
+                        let title = format!(" {} ", panel.title);
+                        display.put_str(
+                            area.top,
+                            area.left + 2,
+                            &title,
+                            width.saturating_sub(4),
+                            Attribute::Bright,
+                        );
+                    }
+                }
+                Widget::Label(label) => {
+                    display.put_str(area.top, area.left, &label.text, width, label.attribute);
+                }
+                Widget::Button(button) => {
+                    let text = if focused {
+                        format!("[►{}◄]", button.label)
+                    } else {
+                        format!("[ {} ]", button.label)
+                    };
+                    display.put_str(area.top, area.left, &text, width, attribute);
+                }
+                Widget::Checkbox(checkbox) => {
+                    let mark = if checkbox.checked { 'X' } else { ' ' };
+                    let text = format!("[{}] {}", mark, checkbox.label);
+                    display.put_str(area.top, area.left, &text, width, attribute);
+                }
+                Widget::ProgressBar(bar) => {
+                    let filled = (bar.progress.clamp(0.0, 1.0) * width as f32).round() as usize;
+                    let text = "█".repeat(filled) + &"░".repeat(width - filled);
+                    display.put_str(area.top, area.left, &text, width, bar.attribute);
+                }
+                Widget::ListBox(list_box) => {
+                    list_box.selected = list_box
+                        .selected
+                        .min(list_box.items.len().saturating_sub(1));
+                    // Keep the selected item in view
+                    if list_box.selected < list_box.scroll {
+                        list_box.scroll = list_box.selected;
+                    } else if list_box.selected >= list_box.scroll + height {
+                        list_box.scroll = list_box.selected + 1 - height;
+                    }
+
+                    let text_width =
+                        draw_scrollbar(display, area, list_box.items.len(), list_box.scroll);
+                    for (offset, item) in list_box
+                        .items
+                        .iter()
+                        .skip(list_box.scroll)
+                        .take(height)
+                        .enumerate()
+                    {
+                        let selected = list_box.scroll + offset == list_box.selected;
+                        let marker = if selected { '►' } else { ' ' };
+                        let item_attribute = if selected {
+                            attribute
+                        } else {
+                            Attribute::Normal
+                        };
+                        display.put_str(
+                            area.top + offset,
+                            area.left,
+                            &format!("{}{}", marker, item),
+                            text_width,
+                            item_attribute,
+                        );
+                    }
+                }
+                Widget::TextInput(input) => {
+                    // Scroll sideways to keep the cursor in view, leaving room for it at the end
+                    if input.cursor < input.scroll {
+                        input.scroll = input.cursor;
+                    } else if input.cursor >= input.scroll + width {
+                        input.scroll = input.cursor + 1 - width;
+                    }
+
+                    let mut text: String =
+                        input.value.chars().skip(input.scroll).take(width).collect();
+                    let padding = width - text.chars().count();
+                    text.push_str(&"_".repeat(padding));
+                    display.put_str(area.top, area.left, &text, width, attribute);
+                    if focused {
+                        display.put_str(
+                            area.top,
+                            area.left + input.cursor - input.scroll,
+                            "█",
+                            1,
+                            attribute,
+                        );
+                    }
+                }
+                Widget::ScrollText(scroll_text) => {
+                    scroll_text.scroll = scroll_text
+                        .scroll
+                        .min(scroll_text.lines.len().saturating_sub(height));
+
+                    let text_width =
+                        draw_scrollbar(display, area, scroll_text.lines.len(), scroll_text.scroll);
+                    for (offset, line) in scroll_text
+                        .lines
+                        .iter()
+                        .skip(scroll_text.scroll)
+                        .take(height)
+                        .enumerate()
+                    {
+                        display.put_str(
+                            area.top + offset,
+                            area.left,
+                            line,
+                            text_width,
+                            Attribute::Normal,
+                        );
+                    }
+                }
+                Widget::Chart(chart) => {
+                    // Only autoscale to what's actually going to be shown
+                    let (front, back) = chart.samples.as_slices();
+                    let samples = [front, back].concat();
+                    let visible = &samples[samples.len().saturating_sub(width)..];
+                    let range = Range::fit(visible.iter().copied(), chart.min, chart.max);
+
+                    let plot = display.draw_value_axis(area, range, Attribute::Dim);
+                    match chart.style {
+                        ChartStyle::Sparkline => {
+                            display.draw_sparkline(&plot, &samples, range, chart.attribute)
+                        }
+                        ChartStyle::Scope => {
+                            display.draw_scope_trace(&plot, &samples, range, chart.attribute)
+                        }
+                    }
+                }
+                Widget::BarGraph(graph) => {
+                    let range = Range::fit(
+                        graph.bars.iter().map(|(_, value)| *value),
+                        graph.min,
+                        graph.max,
+                    );
+                    let plot = match graph.orientation {
+                        Orientation::Horizontal => area.clone(),
+                        Orientation::Vertical => {
+                            display.draw_value_axis(area, range, Attribute::Dim)
+                        }
+                    };
+                    display.draw_bar_graph(
+                        &plot,
+                        &graph.bars,
+                        range,
+                        graph.orientation,
+                        graph.attribute,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Draws a scrollbar down the right-hand side of the area, if there's more content than fits.
+// Returns how much width is left over for the content itself.
+fn draw_scrollbar(
+    display: &mut Display,
+    area: &Dimensions,
+    n_lines: usize,
+    scroll: usize,
+) -> usize {
+    let width = area.right - area.left + 1;
+    let height = area.bottom - area.top + 1;
+    if n_lines <= height {
+        return width;
+    }
+
+    let max_scroll = n_lines - height;
+    let thumb = (scroll * (height - 1) + max_scroll / 2) / max_scroll;
+    for offset in 0..height {
+        let ch = if offset == thumb { "█" } else { "░" };
+        display.put_str(area.top + offset, area.right, ch, 1, Attribute::Dim);
+    }
+
+    width - 1
+}
+
+fn insert_char(text: &mut String, char_idx: usize, ch: char) {
+    let byte_idx = text
+        .char_indices()
+        .nth(char_idx)
+        .map_or(text.len(), |(idx, _)| idx);
+    text.insert(byte_idx, ch);
+}
+
+fn remove_char(text: &mut String, char_idx: usize) {
+    if let Some((byte_idx, _)) = text.char_indices().nth(char_idx) {
+        text.remove(byte_idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(top: usize, bottom: usize, left: usize, right: usize) -> Dimensions {
+        Dimensions {
+            top,
+            bottom,
+            left,
+            right,
+        }
+    }
+
+    fn press(tree: &mut WidgetTree, key: Key) -> Option<WidgetEvent> {
+        tree.handle_keyboard_input(&key, Modifiers::default())
+    }
+
+    #[test]
+    fn tab_goes_round_whatever_can_have_focus() {
+        let mut tree = WidgetTree::default();
+        let panel = tree.add(None, area(0, 9, 0, 29), Widget::panel("", BoxStyle::Single));
+        tree.add(Some(panel), area(0, 0, 0, 9), Widget::label("Label"));
+        let button = tree.add(Some(panel), area(1, 1, 0, 9), Widget::button("OK"));
+        let checkbox = tree.add(Some(panel), area(2, 2, 0, 9), Widget::checkbox("On", false));
+        tree.add(Some(panel), area(3, 3, 0, 9), Widget::progress_bar(0.5));
+        let list = tree.add(Some(panel), area(4, 7, 0, 9), Widget::list_box(&["A"]));
+
+        // The first thing that can have it gets it to start with
+        assert_eq!(tree.focus, Some(button));
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            press(&mut tree, Key::Tab);
+            order.push(tree.focus.unwrap());
+        }
+        assert_eq!(order, [checkbox, list, button]);
+
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+        tree.handle_keyboard_input(&Key::Tab, shift);
+        assert_eq!(tree.focus, Some(list));
+    }
+
+    #[test]
+    fn list_boxes_only_say_when_the_selection_moves() {
+        let mut tree = WidgetTree::default();
+        let items = ["A", "B", "C", "D", "E", "F"];
+        let list = tree.add(None, area(0, 2, 0, 9), Widget::list_box(&items));
+
+        assert_eq!(press(&mut tree, Key::ArrowUp), None);
+        assert_eq!(
+            press(&mut tree, Key::ArrowDown),
+            Some(WidgetEvent::Selected(list, 1))
+        );
+        // A page is as many items as it's got rows
+        assert_eq!(
+            press(&mut tree, Key::PageDown),
+            Some(WidgetEvent::Selected(list, 4))
+        );
+        assert_eq!(
+            press(&mut tree, Key::PageDown),
+            Some(WidgetEvent::Selected(list, 5))
+        );
+        assert_eq!(press(&mut tree, Key::End), None);
+        assert_eq!(
+            press(&mut tree, Key::Enter),
+            Some(WidgetEvent::Activated(list, 5))
+        );
+
+        // Clicking picks whichever item's on that row, but not the space below the last one
+        let mut tree = WidgetTree::default();
+        let list = tree.add(None, area(0, 2, 0, 9), Widget::list_box(&["A", "B"]));
+        assert_eq!(
+            tree.handle_click(1, 3),
+            Some(WidgetEvent::Selected(list, 1))
+        );
+        assert_eq!(tree.handle_click(2, 3), None);
+        assert!(matches!(
+            tree.get_mut(list),
+            Widget::ListBox(ListBox { selected: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn checkboxes_toggle_from_the_keyboard_and_the_mouse() {
+        let mut tree = WidgetTree::default();
+        let checkbox = tree.add(None, area(0, 0, 0, 9), Widget::checkbox("On", false));
+        let button = tree.add(None, area(1, 1, 0, 9), Widget::button("OK"));
+
+        assert_eq!(
+            press(&mut tree, Key::Space),
+            Some(WidgetEvent::Toggled(checkbox, true))
+        );
+        assert_eq!(
+            press(&mut tree, Key::Enter),
+            Some(WidgetEvent::Toggled(checkbox, false))
+        );
+        assert_eq!(press(&mut tree, Key::Character("x".into())), None);
+
+        // Clicking gives it focus as well
+        press(&mut tree, Key::Tab);
+        assert_eq!(tree.focus, Some(button));
+        assert_eq!(
+            tree.handle_click(0, 5),
+            Some(WidgetEvent::Toggled(checkbox, true))
+        );
+        assert_eq!(tree.focus, Some(checkbox));
+        assert!(matches!(
+            tree.get_mut(checkbox),
+            Widget::Checkbox(Checkbox { checked: true, .. })
+        ));
+    }
+}
@@ -0,0 +1,468 @@
+use bevy::input::keyboard::Key;
+
+use super::widgets::{Widget, WidgetEvent, WidgetId, WidgetTree};
+use super::{Attribute, BoxStyle, Dimensions, Display, Modifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowId(usize);
+
+pub struct Window {
+    id: WindowId,
+    pub title: String,
+    // The outside of the window, including its border
+    area: Dimensions,
+    // Widget positions are relative to the inside of the window's border
+    pub widgets: WidgetTree,
+    // Whether the window is a dialog that has to be dealt with before anything else
+    modal: bool,
+}
+
+impl Window {
+    fn interior(&self) -> Dimensions {
+        Dimensions {
+            top: self.area.top + 1,
+            bottom: self.area.bottom - 1,
+            left: self.area.left + 1,
+            right: self.area.right - 1,
+        }
+    }
+}
+
+// Keeps track of the windows on the screen, which one is on top of which, and which bits of
+// the screen need redrawing.
+// Keyboard controls:
+// - F6 switches to the next window
+// - Ctrl+arrows move the active window
+// - Ctrl+Shift+arrows resize it
+// Everything else gets passed on to the widgets in the active window.
+#[derive(Default)]
+pub struct WindowManager {
+    n_columns: usize,
+    n_rows: usize,
+    // In z-order, so the last window is on top (and is the active one)
+    windows: Vec<Window>,
+    next_id: usize,
+    // Regions of the screen that have changed since they were last drawn
+    dirty: Vec<Dimensions>,
+}
+
+impl WindowManager {
+    pub fn new(n_columns: usize, n_rows: usize) -> Self {
+        Self {
+            n_columns,
+            n_rows,
+            windows: Vec::new(),
+            next_id: 0,
+            // Nothing has been drawn yet, so everything needs drawing
+            dirty: vec![Dimensions {
+                top: 0,
+                bottom: n_rows - 1,
+                left: 0,
+                right: n_columns - 1,
+            }],
+        }
+    }
+
+    // Opens a new window on top of all the others. The area includes the border.
+    pub fn open(&mut self, title: &str, area: Dimensions) -> WindowId {
+        self.open_window(title, area, false)
+    }
+
+    // Opens a modal dialog in the middle of the screen, showing a message and a row of buttons.
+    // Pressing any of the buttons closes the dialog.
+    pub fn open_dialog(&mut self, title: &str, lines: &[&str], buttons: &[&str]) -> WindowId {
+        let button_widths: Vec<usize> = buttons
+            .iter()
+            .map(|button| button.chars().count() + 4)
+            .collect();
+        let buttons_width = button_widths.iter().sum::<usize>() + buttons.len().saturating_sub(1);
+        let longest_line = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        // Leave room for the border and a space either side of everything
+        let width = (title.chars().count() + 6)
+            .max(longest_line + 4)
+            .max(buttons_width + 4)
+            .min(self.n_columns);
+        let height = (lines.len() + 5).min(self.n_rows);
+        let top = (self.n_rows - height) / 2;
+        let left = (self.n_columns - width) / 2;
+
+        let id = self.open_window(
+            title,
+            Dimensions {
+                top,
+                bottom: top + height - 1,
+                left,
+                right: left + width - 1,
+            },
+            true,
+        );
+        let window = self.get_mut(id).expect("Dialog should exist");
+
+        for (idx, line) in lines.iter().enumerate() {
+            window.widgets.add(
+                None,
+                Dimensions {
+                    top: idx + 1,
+                    bottom: idx + 1,
+                    left: 1,
+                    right: width - 4,
+                },
+                Widget::label(line),
+            );
+        }
+
+        // Centre the buttons along the bottom
+        let mut column = (width - 2 - buttons_width) / 2;
+        for (button, button_width) in buttons.iter().zip(button_widths) {
+            window.widgets.add(
+                None,
+                Dimensions {
+                    top: lines.len() + 2,
+                    bottom: lines.len() + 2,
+                    left: column,
+                    right: column + button_width - 1,
+                },
+                Widget::button(button),
+            );
+            column += button_width + 1;
+        }
+
+        id
+    }
+
+    fn open_window(&mut self, title: &str, area: Dimensions, modal: bool) -> WindowId {
+        let area = self.clamp_to_screen(area);
+        let id = WindowId(self.next_id);
+        self.next_id += 1;
+
+        // Whatever was on top before is about to become inactive, and look it
+        if let Some(previous) = self.windows.last() {
+            self.dirty.push(previous.area.clone());
+        }
+        self.dirty.push(area.clone());
+        self.windows.push(Window {
+            id,
+            title: title.to_owned(),
+            area,
+            widgets: WidgetTree::default(),
+            modal,
+        });
+
+        id
+    }
+
+    pub fn close(&mut self, id: WindowId) {
+        if let Some(idx) = self.windows.iter().position(|window| window.id == id) {
+            let window = self.windows.remove(idx);
+            self.dirty.push(window.area);
+            // Whatever's on top now has just become active
+            if let Some(top) = self.windows.last() {
+                self.dirty.push(top.area.clone());
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id == id)
+    }
+
+    // Finds a named widget in any of the windows
+    pub fn find_widget(&self, name: &str) -> Option<(WindowId, WidgetId)> {
+        self.windows
+            .iter()
+            .find_map(|window| Some((window.id, window.widgets.find(name)?)))
+    }
+
+    pub fn widget_name(&self, window: WindowId, widget: WidgetId) -> Option<&str> {
+        self.windows
+            .iter()
+            .find(|candidate| candidate.id == window)?
+            .widgets
+            .name(widget)
+    }
+
+    // Marks the inside of a window as needing redrawing, e.g. after changing its widgets
+    pub fn invalidate(&mut self, id: WindowId) {
+        if let Some(window) = self.windows.iter().find(|window| window.id == id) {
+            self.dirty.push(window.interior());
+        }
+    }
+
+    // Marks the whole screen as needing redrawing
+    pub fn invalidate_all(&mut self) {
+        self.dirty.push(Dimensions {
+            top: 0,
+            bottom: self.n_rows - 1,
+            left: 0,
+            right: self.n_columns - 1,
+        });
+    }
+
+    pub fn handle_keyboard_input(
+        &mut self,
+        key: &Key,
+        modifiers: Modifiers,
+    ) -> Option<(WindowId, WidgetEvent)> {
+        let top_is_modal = self.windows.last()?.modal;
+
+        if modifiers.ctrl {
+            let (rows, columns) = match key {
+                Key::ArrowUp => (-1, 0),
+                Key::ArrowDown => (1, 0),
+                Key::ArrowLeft => (0, -1),
+                Key::ArrowRight => (0, 1),
+                _ => return None,
+            };
+            if modifiers.shift {
+                self.resize_active(rows, columns);
+            } else {
+                self.move_active(rows, columns);
+            }
+            return None;
+        }
+
+        // Dialogs have to be dealt with before you can switch away from them
+        if *key == Key::F6 && !top_is_modal {
+            let bottom = self.windows.remove(0);
+            self.raise(bottom);
+            return None;
+        }
+
+        let window = self.windows.last_mut()?;
+        let event = window.widgets.handle_keyboard_input(key, modifiers);
+        let id = window.id;
+        self.invalidate(id);
+        self.handle_widget_event(id, event)
+    }
+
+    pub fn handle_click(&mut self, row: usize, column: usize) -> Option<(WindowId, WidgetEvent)> {
+        let idx = self
+            .windows
+            .iter()
+            .rposition(|window| window.area.contains(row, column))?;
+
+        // While a dialog is open, nothing else can be clicked on
+        let top = self.windows.len() - 1;
+        if idx != top {
+            if self.windows[top].modal {
+                return None;
+            }
+            let window = self.windows.remove(idx);
+            self.raise(window);
+        }
+
+        let window = self.windows.last_mut()?;
+        let interior = window.interior();
+        if !interior.contains(row, column) {
+            // Clicked on the border, which just brings the window to the front
+            return None;
+        }
+
+        let event = window
+            .widgets
+            .handle_click(row - interior.top, column - interior.left);
+        let id = window.id;
+        self.invalidate(id);
+        self.handle_widget_event(id, event)
+    }
+
+    // Puts a window on top of all the others
+    fn raise(&mut self, window: Window) {
+        if let Some(previous) = self.windows.last() {
+            self.dirty.push(previous.area.clone());
+        }
+        self.dirty.push(window.area.clone());
+        self.windows.push(window);
+    }
+
+    fn handle_widget_event(
+        &mut self,
+        id: WindowId,
+        event: Option<WidgetEvent>,
+    ) -> Option<(WindowId, WidgetEvent)> {
+        let event = event?;
+
+        // Any button in a dialog closes it
+        let modal = self
+            .windows
+            .iter()
+            .any(|window| window.id == id && window.modal);
+        if modal && matches!(event, WidgetEvent::Pressed(_)) {
+            self.close(id);
+        }
+
+        Some((id, event))
+    }
+
+    fn move_active(&mut self, rows: isize, columns: isize) {
+        let Some(window) = self.windows.last() else {
+            return;
+        };
+        let area = &window.area;
+        let height = area.bottom - area.top;
+        let width = area.right - area.left;
+
+        // Keep the whole window on the screen
+        let top = area
+            .top
+            .saturating_add_signed(rows)
+            .min(self.n_rows - 1 - height);
+        let left = area
+            .left
+            .saturating_add_signed(columns)
+            .min(self.n_columns - 1 - width);
+        let moved = Dimensions {
+            top,
+            bottom: top + height,
+            left,
+            right: left + width,
+        };
+
+        self.set_active_area(moved);
+    }
+
+    fn resize_active(&mut self, rows: isize, columns: isize) {
+        let Some(window) = self.windows.last() else {
+            return;
+        };
+        let area = &window.area;
+
+        // Always leave enough room for the title, unless the screen's too small for that. The
+        // minimums can't go past the edge of the screen, or clamp would panic.
+        let min_width = window.title.chars().count() + 5;
+        let min_bottom = (area.top + 2).min(self.n_rows - 1);
+        let min_right = (area.left + min_width).min(self.n_columns - 1);
+        let bottom = area
+            .bottom
+            .saturating_add_signed(rows)
+            .clamp(min_bottom, self.n_rows - 1);
+        let right = area
+            .right
+            .saturating_add_signed(columns)
+            .clamp(min_right, self.n_columns - 1);
+        let resized = Dimensions {
+            bottom,
+            right,
+            ..area.clone()
+        };
+
+        self.set_active_area(resized);
+    }
+
+    fn set_active_area(&mut self, area: Dimensions) {
+        let Some(window) = self.windows.last_mut() else {
+            return;
+        };
+        let old = std::mem::replace(&mut window.area, area.clone());
+        self.dirty.push(old);
+        self.dirty.push(area);
+    }
+
+    fn clamp_to_screen(&self, area: Dimensions) -> Dimensions {
+        let bottom = area.bottom.min(self.n_rows - 1);
+        let right = area.right.min(self.n_columns - 1);
+        Dimensions {
+            top: area.top.min(bottom.saturating_sub(2)),
+            bottom,
+            left: area.left.min(right.saturating_sub(2)),
+            right,
+        }
+    }
+
+    // Redraws whichever parts of the screen have changed, returning whether there were any
+    pub fn render(&mut self, display: &mut Display) -> bool {
+        if self.dirty.is_empty() {
+            return false;
+        }
+
+        let top = self.windows.len().saturating_sub(1);
+        for region in std::mem::take(&mut self.dirty) {
+            // Paint the region from the bottom up: the empty desktop, then each window in turn.
+            // Windows further up the stack overwrite the ones below them, and their borders
+            // merge with any borders they land on.
+            display.set_clip(region.clone());
+            display.clear();
+
+            for (idx, window) in self.windows.iter_mut().enumerate() {
+                if window.area.intersection(&region).is_none() {
+                    continue;
+                }
+
+                // The active window gets a bright double border, the others a plain single one
+                let (style, attribute) = if idx == top {
+                    (BoxStyle::Double, Attribute::Bright)
+                } else {
+                    (BoxStyle::Single, Attribute::Normal)
+                };
+                display.draw_box(window.area.clone(), style, attribute);
+
+                let width = window.area.right - window.area.left + 1;
+                if !window.title.is_empty() {
+                    let title = format!(" {} ", window.title);
+                    display.put_str(
+                        window.area.top,
+                        window.area.left + 2,
+                        &title,
+                        width.saturating_sub(4),
+                        attribute,
+                    );
+                }
+
+                // Keep the widgets inside the window
+                let interior = window.interior();
+                if let Some(clip) = interior.intersection(&region) {
+                    display.set_clip(clip);
+                    window
+                        .widgets
+                        .render(display, interior.top, interior.left, idx == top);
+                    display.set_clip(region.clone());
+                }
+            }
+        }
+        display.reset_clip();
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctrl_shift() -> Modifiers {
+        Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resizing_a_window_with_a_long_title_near_the_edge_doesnt_panic() {
+        let mut windows = WindowManager::new(20, 10);
+        windows.open(
+            "A title much too long for the screen",
+            Dimensions {
+                top: 7,
+                bottom: 9,
+                left: 15,
+                right: 19,
+            },
+        );
+        for key in [
+            Key::ArrowRight,
+            Key::ArrowDown,
+            Key::ArrowLeft,
+            Key::ArrowUp,
+        ] {
+            windows.handle_keyboard_input(&key, ctrl_shift());
+        }
+        let area = &windows.windows.last().unwrap().area;
+        assert!(area.right < 20 && area.bottom < 10);
+    }
+}
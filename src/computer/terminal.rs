@@ -2,6 +2,7 @@ use array2d::Array2D;
 use bevy::{input::keyboard::Key, prelude::Component};
 
 use super::{ibm_byte_map::*, os::OS};
+use crate::interaction::PointerEventKind;
 
 #[derive(Component)]
 pub struct Terminal {
@@ -134,6 +135,9 @@ impl Terminal {
         }
     }
 
+    // A terminal is driven entirely by the keyboard, so there's nothing for the mouse to do
+    pub fn handle_pointer_input(&mut self, _row: usize, _column: usize, _kind: PointerEventKind) {}
+
     fn shift_lines_up(&mut self) {
         let rows = self.screen_bytes.as_rows();
         let mut rows_without_first_line: Vec<Vec<u8>> = rows.into_iter().skip(1).collect();
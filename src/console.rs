@@ -0,0 +1,193 @@
+use bevy::{prelude::*, window::CursorGrabMode};
+use bevy_mod_raycast::prelude::*;
+
+use crate::computer::Screen;
+use crate::interaction::{InteractStarted, InteractedBy, InteractionRaycastSet, Interactor};
+
+// How long (in seconds) it takes the camera to glide between free-look and a console
+const DOCKING_DURATION: f32 = 0.6;
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<ControlMode>();
+        app.add_systems(
+            Update,
+            (
+                sit_at_console.run_if(in_state(ControlMode::FreeLook)),
+                stand_up.run_if(in_state(ControlMode::Seated)),
+                animate_docking,
+            )
+                .chain(),
+        );
+        app.add_systems(OnEnter(ControlMode::Seated), free_cursor);
+        app.add_systems(OnExit(ControlMode::Seated), (forget_console, capture_cursor));
+    }
+}
+
+// Gets the player up from a computer. It never reaches the computer itself, so programs are
+// free to use every other key, Escape included.
+pub const STAND_UP_KEY: KeyCode = KeyCode::F12;
+
+// Whether the player is walking around, or sat at a computer using it
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ControlMode {
+    #[default]
+    FreeLook,
+    Seated,
+}
+
+// Which console the player is currently sat at.
+// Only exists while in `ControlMode::Seated`.
+#[derive(Resource)]
+pub struct SeatedAt {
+    pub computer: Entity,
+}
+
+// Where the camera should sit when using a screen, relative to that screen.
+// Each screen gets its own, since they won't all be the same size or angle.
+#[derive(Component)]
+pub struct DockingPose(pub Transform);
+
+enum DockTarget {
+    Screen(Entity),
+    FreeLook,
+}
+
+// Attached to the camera while it's docked, or moving to/from a dock
+#[derive(Component)]
+struct CameraDock {
+    // Where the camera was (relative to the player) before it docked,
+    // so we can put it back exactly where we found it
+    free_look_pose: Transform,
+    start: Transform,
+    target: DockTarget,
+    progress: f32,
+}
+
+fn sit_at_console(
+    mut commands: Commands,
+    mut evr_started: EventReader<InteractStarted>,
+    mut next_mode: ResMut<NextState<ControlMode>>,
+    screens: Query<&Screen, With<DockingPose>>,
+    cameras: Query<(Entity, &Transform, Option<&CameraDock>), With<Interactor>>,
+) {
+    // Only the use key sits the player down: clicking on a screen points at what's on it
+    let Some((screen, Screen { computer, .. })) = evr_started
+        .read()
+        .filter(|ev| ev.by == InteractedBy::Key)
+        .find_map(|ev| Some((ev.entity, screens.get(ev.entity).ok()?)))
+    else {
+        return;
+    };
+
+    for (camera, transform, dock) in cameras.iter() {
+        // If we're halfway through standing up, the camera isn't in its free-look pose
+        // any more, so hang on to the one we stored when we first sat down
+        let free_look_pose = match dock {
+            Some(dock) => dock.free_look_pose,
+            None => *transform,
+        };
+
+        commands.entity(camera).insert(CameraDock {
+            free_look_pose,
+            start: *transform,
+            target: DockTarget::Screen(screen),
+            progress: 0.0,
+        });
+    }
+
+    commands.insert_resource(SeatedAt { computer: *computer });
+    next_mode.set(ControlMode::Seated);
+}
+
+fn stand_up(
+    key: Res<ButtonInput<KeyCode>>,
+    mut next_mode: ResMut<NextState<ControlMode>>,
+    mut cameras: Query<(&Transform, &mut CameraDock)>,
+) {
+    if !key.just_pressed(STAND_UP_KEY) {
+        return;
+    }
+
+    for (transform, mut dock) in cameras.iter_mut() {
+        dock.start = *transform;
+        dock.target = DockTarget::FreeLook;
+        dock.progress = 0.0;
+    }
+
+    next_mode.set(ControlMode::FreeLook);
+}
+
+fn forget_console(mut commands: Commands) {
+    commands.remove_resource::<SeatedAt>();
+}
+
+// While sat at a console, the mouse is a pointer on the screen rather than a way of looking
+// around, so show the cursor and cast rays from it instead of the centre of the view
+fn free_cursor(
+    mut windows: Query<&mut Window>,
+    mut sources: Query<&mut RaycastSource<InteractionRaycastSet>, With<Interactor>>,
+) {
+    for mut window in windows.iter_mut() {
+        window.cursor.visible = true;
+        window.cursor.grab_mode = CursorGrabMode::None;
+    }
+    for mut source in sources.iter_mut() {
+        *source = RaycastSource::new_cursor();
+    }
+}
+
+fn capture_cursor(
+    mut windows: Query<&mut Window>,
+    mut sources: Query<&mut RaycastSource<InteractionRaycastSet>, With<Interactor>>,
+) {
+    for mut window in windows.iter_mut() {
+        window.cursor.visible = false;
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+    }
+    for mut source in sources.iter_mut() {
+        *source = RaycastSource::new_transform_empty();
+    }
+}
+
+fn animate_docking(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cameras: Query<(Entity, &mut Transform, &Parent, &mut CameraDock)>,
+    global_transforms: Query<&GlobalTransform>,
+    poses: Query<&DockingPose>,
+) {
+    for (camera, mut transform, parent, mut dock) in cameras.iter_mut() {
+        dock.progress = (dock.progress + time.delta_seconds() / DOCKING_DURATION).min(1.0);
+
+        // Work out where the camera is headed, in the player's local space.
+        // This gets recalculated every frame, in case the screen (or player) moves.
+        let target = match dock.target {
+            DockTarget::Screen(screen) => {
+                let (Ok(screen_transform), Ok(pose), Ok(parent_transform)) = (
+                    global_transforms.get(screen),
+                    poses.get(screen),
+                    global_transforms.get(parent.get()),
+                ) else {
+                    warn!("Lost track of the screen the camera was docking to.");
+                    continue;
+                };
+                screen_transform
+                    .mul_transform(pose.0)
+                    .reparented_to(parent_transform)
+            }
+            DockTarget::FreeLook => dock.free_look_pose,
+        };
+
+        // Smoothstep, so the camera eases in and out rather than lurching
+        let t = dock.progress * dock.progress * (3.0 - 2.0 * dock.progress);
+        transform.translation = dock.start.translation.lerp(target.translation, t);
+        transform.rotation = dock.start.rotation.slerp(target.rotation, t);
+
+        // Once we're back where we started, we don't need to keep track of any of this
+        if dock.progress >= 1.0 && matches!(dock.target, DockTarget::FreeLook) {
+            commands.entity(camera).remove::<CameraDock>();
+        }
+    }
+}
@@ -2,3 +2,8 @@ use bevy::prelude::SystemSet;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SpawningSet;
+
+// Where plugins plug their devices into the ship's computers. Comes after everything in
+// `SpawningSet`, so the computers and whatever the devices are watching are all there.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FittingSet;
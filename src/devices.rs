@@ -0,0 +1,125 @@
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+
+use crate::cabling::{PortKind, SpawnSocket};
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::FittingSet;
+use crate::interaction::Obstacle;
+
+// Things around the ship that computers can control through their ports
+pub struct DevicesPlugin;
+impl Plugin for DevicesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_lamps);
+        app.add_systems(Startup, fit_lamp.in_set(FittingSet));
+        app.add_sample_file("LAMP.BAS", include_str!("../assets/programs/LAMP.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to blink the lamp, for README.TXT
+const README_PARAGRAPH: &str = "\
+In BASIC on the diagnostics computer, LOAD \"LAMP\" then RUN
+it to blink the lamp on port 0.
+";
+
+// How brightly a lamp glows when its register is all the way up
+const LAMP_GLOW: LinearRgba = LinearRgba::rgb(8.0, 2.5, 0.5);
+
+// An indicator lamp: about as simple as a device gets.
+// Register 0 sets the brightness, from 0 (off) to 255 (fully on).
+#[derive(Component)]
+pub struct Lamp {
+    // The brightness currently showing, so the material only changes when it needs to
+    brightness: u8,
+}
+
+// Spawns a lamp plugged into one of a computer's ports.
+// Use `Commands::spawn_lamp` rather than adding this directly.
+pub struct SpawnLamp {
+    pub computer: Entity,
+    pub port: usize,
+    pub transform: Transform,
+}
+
+pub trait SpawnLampExt {
+    fn spawn_lamp(&mut self, computer: Entity, port: usize, transform: Transform);
+}
+
+impl SpawnLampExt for Commands<'_, '_> {
+    fn spawn_lamp(&mut self, computer: Entity, port: usize, transform: Transform) {
+        self.add(SpawnLamp {
+            computer,
+            port,
+            transform,
+        });
+    }
+}
+
+impl Command for SpawnLamp {
+    fn apply(self, world: &mut World) {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Sphere::new(0.02).mesh().uv(16, 8));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgb(0.3, 0.1, 0.0),
+                ..default()
+            });
+
+        let lamp = world
+            .spawn((
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: self.transform,
+                    ..default()
+                },
+                Lamp { brightness: 0 },
+                Obstacle,
+                BusDevice {
+                    computer: self.computer,
+                    port: self.port,
+                    registers: Port::shared(),
+                },
+            ))
+            .id();
+        // Underneath, so the cable hangs down out of the way
+        SpawnSocket {
+            owner: lamp,
+            parent: lamp,
+            name: "LAMP".to_owned(),
+            kind: PortKind::Parallel,
+            port: None,
+            transform: Transform::from_xyz(0.0, -0.025, 0.0),
+        }
+        .apply(world);
+    }
+}
+
+// Something for the diagnostics computer to switch on and off, on port 0 (address $E800)
+fn fit_lamp(mut commands: Commands, computers: Res<ShipComputers>) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_lamp(computer, 0, Transform::from_xyz(0.3, 1.75, -0.5));
+}
+
+fn update_lamps(
+    mut lamps: Query<(&mut Lamp, &BusDevice, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (mut lamp, device, material) in lamps.iter_mut() {
+        let brightness = device.registers.lock().expect("Port lock").registers[0];
+        if brightness == lamp.brightness {
+            continue;
+        }
+        lamp.brightness = brightness;
+
+        if let Some(material) = materials.get_mut(material) {
+            material.emissive = LAMP_GLOW * (brightness as f32 / 255.0);
+        }
+    }
+}
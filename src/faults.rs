@@ -0,0 +1,531 @@
+use std::fmt;
+use std::path::Path;
+
+use bevy::prelude::*;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::computer::bus::{N_PORTS, RAM_SIZE};
+use crate::computer::{AddSampleFilesExt, ShipOS};
+use crate::power::{Battery, Generator, PowerConsumer};
+use crate::sensors::{Sensor, SensorFailure};
+
+// Things breaking on purpose, so there's always something to fix. What breaks and when comes
+// from a scenario file, or it's left to chance (from a seed, so the same seed always breaks
+// the same things at the same times, and a bug can be chased down more than once).
+pub struct FaultsPlugin;
+impl Plugin for FaultsPlugin {
+    fn build(&self, app: &mut App) {
+        // Nothing goes wrong unless there's a scenario
+        app.init_resource::<FaultSchedule>();
+        app.add_systems(FixedUpdate, inject_faults);
+        app.add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// A warning, for README.TXT
+const README_PARAGRAPH: &str = "\
+Things break. Sensors stick, bits flip, fuses blow, disks
+go bad and lines get noisy. A breaker with a blown fuse
+won't close again, and a program that stops making sense
+might have been scrambled on the disk.
+";
+
+// How long there is between random faults, on average, if the scenario doesn't say
+const DEFAULT_MEAN_INTERVAL: f64 = 300.0;
+
+// What goes wrong, and when. Scenarios are RON files, e.g.
+//
+// (
+//     faults: [
+//         (at: 60.0, fault: Sensor(sensor: "Gyro", failure: Stuck)),
+//         (at: 90.0, fault: NoisyLine(computer: "Helm", port: 2, error_rate: 0.01)),
+//     ],
+//     random: Some((seed: 42, mean_interval: 600.0)),
+// )
+//
+// See assets/scenarios for some more.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub faults: Vec<ScheduledFault>,
+    // Random faults on top of the planned ones, if it's there
+    #[serde(default)]
+    pub random: Option<RandomFaults>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduledFault {
+    // In seconds from the start
+    pub at: f64,
+    pub fault: Fault,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RandomFaults {
+    pub seed: u64,
+    // In seconds
+    #[serde(default = "default_mean_interval")]
+    pub mean_interval: f64,
+}
+
+fn default_mean_interval() -> f64 {
+    DEFAULT_MEAN_INTERVAL
+}
+
+// Something going wrong. Whatever it happens to is picked out by name.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Fault {
+    // A sensor goes wrong, in one of the ways sensors do
+    Sensor {
+        sensor: String,
+        failure: SensorFailure,
+    },
+    // A bit in a computer's RAM flips
+    RamBitFlip {
+        computer: String,
+        address: u16,
+        bit: u8,
+    },
+    // The fuse behind something on the power grid blows, and resetting its breaker won't
+    // bring it back
+    BlownFuse {
+        device: String,
+    },
+    // One of the sectors on a computer's disk gets scrambled, along with whatever file's in it
+    CorruptSector {
+        computer: String,
+        sector: usize,
+    },
+    // The line to one of a computer's ports picks up noise, so reading from it gets a bit
+    // flipped `error_rate` of the time
+    NoisyLine {
+        computer: String,
+        port: usize,
+        error_rate: f64,
+    },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Sensor {
+                sensor,
+                failure: SensorFailure::Drifting(rate),
+            } => write!(f, "{}: Drifting({:.2})", sensor, rate),
+            Fault::Sensor { sensor, failure } => write!(f, "{}: {:?}", sensor, failure),
+            Fault::RamBitFlip {
+                computer,
+                address,
+                bit,
+            } => write!(f, "{}: bit {} of ${:04X} flipped", computer, bit, address),
+            Fault::BlownFuse { device } => write!(f, "{}: fuse blown", device),
+            Fault::CorruptSector { computer, sector } => {
+                write!(f, "{}: disk sector {} corrupted", computer, sector)
+            }
+            Fault::NoisyLine {
+                computer,
+                port,
+                error_rate,
+            } => write!(
+                f,
+                "{}: noisy line on port {} ({:.1}% errors)",
+                computer,
+                port,
+                error_rate * 100.0
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Couldn't read scenario: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse scenario: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("{0} needs a value after it")]
+    MissingValue(String),
+    #[error("Not a seed: {0}")]
+    BadSeed(String),
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let bytes = std::fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    // Nothing planned, just random faults
+    pub fn random(seed: u64) -> Self {
+        Self {
+            faults: Vec::new(),
+            random: Some(RandomFaults {
+                seed,
+                mean_interval: DEFAULT_MEAN_INTERVAL,
+            }),
+        }
+    }
+
+    // Takes `--scenario FILE` or `--faults SEED` out of the command line, if either's there,
+    // and leaves everything else
+    pub fn from_args(args: &mut Vec<String>) -> Result<Self, ScenarioError> {
+        let mut scenario = Self::default();
+        while let Some(idx) = args
+            .iter()
+            .position(|arg| arg == "--scenario" || arg == "--faults")
+        {
+            if idx + 1 >= args.len() {
+                return Err(ScenarioError::MissingValue(args[idx].clone()));
+            }
+            let value = args.remove(idx + 1);
+            scenario = if args.remove(idx) == "--scenario" {
+                Self::load(value)?
+            } else {
+                let seed = value
+                    .parse()
+                    .map_err(|_| ScenarioError::BadSeed(value.clone()))?;
+                Self::random(seed)
+            };
+        }
+        Ok(scenario)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FaultError {
+    #[error("There's nothing called {0}")]
+    NotFound(String),
+    #[error("{0} isn't a sensor")]
+    NotASensor(String),
+    #[error("{0} isn't a computer")]
+    NotAComputer(String),
+    #[error("{0} isn't on the power grid")]
+    NoFuse(String),
+    #[error("{0}'s disk hasn't got anything in sector {1}")]
+    NoSector(String, usize),
+    #[error("{0} hasn't got a port {1}")]
+    NoPort(String, usize),
+}
+
+// The faults still to come, and the ones that have already happened
+#[derive(Resource)]
+pub struct FaultSchedule {
+    // Latest first, so the next one's always on the end
+    planned: Vec<ScheduledFault>,
+    // Set if there are random faults
+    mean_interval: Option<f64>,
+    // For the random faults, and the randomness in the planned ones
+    rng: SmallRng,
+    // Everything that's gone wrong so far, and when, for owning up to afterwards
+    pub log: Vec<(f64, Fault)>,
+}
+
+impl Default for FaultSchedule {
+    fn default() -> Self {
+        Self::new(Scenario::default())
+    }
+}
+
+impl FaultSchedule {
+    pub fn new(scenario: Scenario) -> Self {
+        let mut planned = scenario.faults;
+        planned.sort_by(|a, b| b.at.total_cmp(&a.at));
+        let seed = scenario.random.as_ref().map_or(0, |random| random.seed);
+        Self {
+            planned,
+            mean_interval: scenario.random.map(|random| random.mean_interval),
+            rng: SmallRng::seed_from_u64(seed),
+            log: Vec::new(),
+        }
+    }
+}
+
+fn inject_faults(world: &mut World) {
+    let time = world.resource::<Time>();
+    let (now, delta) = (time.elapsed_seconds_f64(), time.delta_seconds_f64());
+
+    world.resource_scope(|world, mut schedule: Mut<FaultSchedule>| {
+        let mut due = Vec::new();
+        while schedule.planned.last().is_some_and(|next| next.at <= now) {
+            due.extend(schedule.planned.pop().map(|next| next.fault));
+        }
+        if let Some(mean_interval) = schedule.mean_interval {
+            if schedule
+                .rng
+                .gen_bool((delta / mean_interval).clamp(0.0, 1.0))
+            {
+                due.extend(random_fault(world, &mut schedule.rng));
+            }
+        }
+
+        for fault in due {
+            match apply_fault(world, &fault, &mut schedule.rng) {
+                Ok(()) => {
+                    info!("Fault at {:.1}s: {}", now, fault);
+                    schedule.log.push((now, fault));
+                }
+                Err(error) => warn!("Couldn't break anything ({}): {}", fault, error),
+            }
+        }
+    });
+}
+
+fn apply_fault(world: &mut World, fault: &Fault, rng: &mut SmallRng) -> Result<(), FaultError> {
+    match fault {
+        Fault::Sensor { sensor, failure } => {
+            let entity = find(world, sensor)?;
+            let mut component = world
+                .get_mut::<Sensor>(entity)
+                .ok_or_else(|| FaultError::NotASensor(sensor.clone()))?;
+            component.failure = *failure;
+        }
+        Fault::RamBitFlip {
+            computer,
+            address,
+            bit,
+        } => {
+            find_computer(world, computer)?
+                .bus()
+                .lock()
+                .expect("Bus lock")
+                .flip_ram_bit(*address, *bit);
+        }
+        Fault::BlownFuse { device } => {
+            let entity = find(world, device)?;
+            let mut entity = world.entity_mut(entity);
+            if let Some(mut generator) = entity.get_mut::<Generator>() {
+                generator.breaker.blow();
+            } else if let Some(mut battery) = entity.get_mut::<Battery>() {
+                battery.breaker.blow();
+            } else if let Some(mut consumer) = entity.get_mut::<PowerConsumer>() {
+                consumer.breaker.blow();
+            } else {
+                return Err(FaultError::NoFuse(device.clone()));
+            }
+        }
+        Fault::CorruptSector { computer, sector } => {
+            find_computer(world, computer)?
+                .files()
+                .lock()
+                .expect("Filesystem lock")
+                .corrupt_sector(*sector, rng)
+                .ok_or_else(|| FaultError::NoSector(computer.clone(), *sector))?;
+        }
+        Fault::NoisyLine {
+            computer,
+            port,
+            error_rate,
+        } => {
+            if *port >= N_PORTS {
+                return Err(FaultError::NoPort(computer.clone(), *port));
+            }
+            find_computer(world, computer)?
+                .bus()
+                .lock()
+                .expect("Bus lock")
+                .set_line_noise(*port, *error_rate);
+        }
+    }
+    Ok(())
+}
+
+// Something to break, picked at random from whatever there is
+fn random_fault(world: &mut World, rng: &mut SmallRng) -> Option<Fault> {
+    fn pick<'a, T>(rng: &mut SmallRng, things: &'a [T]) -> Option<&'a T> {
+        (!things.is_empty()).then(|| &things[rng.gen_range(0..things.len())])
+    }
+
+    let sensors: Vec<String> = world
+        .query_filtered::<&Name, With<Sensor>>()
+        .iter(world)
+        .map(ToString::to_string)
+        .collect();
+    // With how many sectors they've got in use
+    let computers: Vec<(String, usize)> = world
+        .query::<(&Name, &ShipOS)>()
+        .iter(world)
+        .map(|(name, ship_os)| {
+            let files = ship_os.files().lock().expect("Filesystem lock");
+            (name.to_string(), files.used_sectors())
+        })
+        .collect();
+    let fused: Vec<String> = world
+        .query_filtered::<&Name, Or<(With<Generator>, With<Battery>, With<PowerConsumer>)>>()
+        .iter(world)
+        .map(ToString::to_string)
+        .collect();
+
+    match rng.gen_range(0..5) {
+        0 => {
+            let sensor = pick(rng, &sensors)?.clone();
+            let failure = match rng.gen_range(0..4) {
+                0 => SensorFailure::Stuck,
+                1 => SensorFailure::Dead,
+                2 => SensorFailure::Drifting(rng.gen_range(-10.0..10.0)),
+                _ => SensorFailure::Erratic,
+            };
+            Some(Fault::Sensor { sensor, failure })
+        }
+        1 => Some(Fault::RamBitFlip {
+            computer: pick(rng, &computers)?.0.clone(),
+            address: rng.gen_range(0..RAM_SIZE) as u16,
+            bit: rng.gen_range(0..8),
+        }),
+        2 => Some(Fault::BlownFuse {
+            device: pick(rng, &fused)?.clone(),
+        }),
+        3 => {
+            let (computer, sectors) = pick(rng, &computers)?.clone();
+            (sectors > 0).then(|| Fault::CorruptSector {
+                computer,
+                sector: rng.gen_range(0..sectors),
+            })
+        }
+        _ => Some(Fault::NoisyLine {
+            computer: pick(rng, &computers)?.0.clone(),
+            port: rng.gen_range(0..N_PORTS),
+            error_rate: rng.gen_range(0.001..0.05),
+        }),
+    }
+}
+
+fn find(world: &mut World, name: &str) -> Result<Entity, FaultError> {
+    world
+        .query::<(Entity, &Name)>()
+        .iter(world)
+        .find(|(_, entity_name)| entity_name.as_str() == name)
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| FaultError::NotFound(name.to_owned()))
+}
+
+fn find_computer<'w>(world: &'w mut World, name: &str) -> Result<&'w ShipOS, FaultError> {
+    let entity = find(world, name)?;
+    world
+        .get::<ShipOS>(entity)
+        .ok_or_else(|| FaultError::NotAComputer(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::power::LoadPriority;
+    use crate::sensors::{SensorKind, SensorModel};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    // A world with a gyro and a pump in it to break
+    fn world(scenario: Scenario) -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.insert_resource(FaultSchedule::new(scenario));
+        let body = Entity::PLACEHOLDER;
+        world.spawn((
+            Name::new("Gyro"),
+            Sensor::new(SensorKind::Gyro { body }, SensorModel::GYRO),
+        ));
+        world.spawn((
+            Name::new("Pump"),
+            PowerConsumer::new(Entity::PLACEHOLDER, 100.0, LoadPriority::Normal),
+        ));
+        world
+    }
+
+    fn tick(world: &mut World) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        inject_faults(world);
+    }
+
+    fn named<'w, T: Component>(world: &'w mut World, name: &str) -> &'w T {
+        let entity = find(world, name).unwrap();
+        world.get::<T>(entity).unwrap()
+    }
+
+    #[test]
+    fn the_command_line_only_loses_the_fault_arguments() {
+        let mut line = args(&["ship", "--faults", "7", "--windowed"]);
+        let scenario = Scenario::from_args(&mut line).unwrap();
+        assert_eq!(line, args(&["ship", "--windowed"]));
+        assert_eq!(scenario.random.map(|random| random.seed), Some(7));
+
+        let mut line = args(&["ship", "--windowed"]);
+        let scenario = Scenario::from_args(&mut line).unwrap();
+        assert!(scenario.faults.is_empty() && scenario.random.is_none());
+
+        assert!(matches!(
+            Scenario::from_args(&mut args(&["ship", "--faults"])),
+            Err(ScenarioError::MissingValue(_))
+        ));
+        assert!(matches!(
+            Scenario::from_args(&mut args(&["ship", "--faults", "lots"])),
+            Err(ScenarioError::BadSeed(_))
+        ));
+    }
+
+    #[test]
+    fn planned_faults_happen_on_time_and_get_owned_up_to() {
+        // Out of order, and with one for something that isn't there
+        let scenario: Scenario = ron::from_str(
+            "(faults: [
+                (at: 2.0, fault: Sensor(sensor: \"Gyro\", failure: Stuck)),
+                (at: 1.0, fault: BlownFuse(device: \"Pump\")),
+                (at: 1.5, fault: BlownFuse(device: \"Nowhere\")),
+            ])",
+        )
+        .unwrap();
+        let mut world = world(scenario);
+
+        tick(&mut world);
+        assert!(named::<PowerConsumer>(&mut world, "Pump").breaker.blown);
+        assert_eq!(
+            named::<Sensor>(&mut world, "Gyro").failure,
+            SensorFailure::Working
+        );
+
+        tick(&mut world);
+        assert_eq!(
+            named::<Sensor>(&mut world, "Gyro").failure,
+            SensorFailure::Stuck
+        );
+        let log: Vec<String> = world
+            .resource::<FaultSchedule>()
+            .log
+            .iter()
+            .map(|(at, fault)| format!("{} {}", at, fault))
+            .collect();
+        assert_eq!(log, ["1 Pump: fuse blown", "2 Gyro: Stuck"]);
+    }
+
+    #[test]
+    fn the_same_seed_breaks_the_same_things_at_the_same_times() {
+        let history = |seed| {
+            let mut world = world(Scenario {
+                faults: Vec::new(),
+                random: Some(RandomFaults {
+                    seed,
+                    mean_interval: 10.0,
+                }),
+            });
+            for _ in 0..200 {
+                tick(&mut world);
+            }
+            let log = &world.resource::<FaultSchedule>().log;
+            log.iter()
+                .map(|(at, fault)| format!("{} {}", at, fault))
+                .collect::<Vec<_>>()
+        };
+
+        let first = history(42);
+        assert!(!first.is_empty());
+        assert_eq!(first, history(42));
+        assert_ne!(first, history(43));
+    }
+}
@@ -0,0 +1,558 @@
+use std::f64::consts::TAU;
+
+use bevy::ecs::world::Command;
+use bevy::math::{DQuat, DVec3};
+use bevy::prelude::*;
+
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, HELM};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+
+// The ship as a whole, flying through space. Nothing in here moves the inside of the ship
+// about (that's where the player is, and it stays put); it's the numbers the ship's
+// instruments read and its thrusters push on. Worked out every fixed tick, in f64, since
+// space is big.
+pub struct FlightPlugin;
+impl Plugin for FlightPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<FlightBody>()
+            .register_type::<Thruster>();
+        app.add_systems(Startup, setup_flight.in_set(SpawningSet));
+        app.add_systems(
+            FixedUpdate,
+            (
+                run_thruster_panels,
+                fire_thrusters,
+                move_bodies,
+                update_inertial_units,
+            )
+                .chain(),
+        );
+        app.add_systems(Startup, fit_helm_controls.in_set(FittingSet));
+        app.add_sample_file("STEADY.BAS", include_str!("../assets/programs/STEADY.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to keep the ship from spinning, for README.TXT
+const README_PARAGRAPH: &str = "\
+On the helm, STEADY stops the ship turning, using the
+thrusters on port 0 and the inertial unit on port 1.
+";
+
+// A full turn in binary angle units, which is how angles go in registers: 16 bits for a
+// full turn, so they wrap round by themselves
+const BINARY_TURN: f64 = 65536.0;
+
+// The ship, for anything that needs to be plugged into it when it's spawned
+#[derive(Resource)]
+pub struct ShipFlight {
+    pub body: Entity,
+}
+
+// Something that flies. Its own axes are the same as everything else's: -z is forwards, +y
+// is up, and +x is to the right.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FlightBody {
+    // In metres
+    pub position: DVec3,
+    // In m/s
+    pub velocity: DVec3,
+    pub orientation: DQuat,
+    // In rad/s, around its own axes
+    pub angular_velocity: DVec3,
+    // Without propellant, in kg
+    pub dry_mass: f64,
+    // In kg
+    pub propellant: f64,
+    pub propellant_capacity: f64,
+    // Around its own axes, in kg m^2, with a full load of propellant. Assumed to go down in
+    // proportion to the mass as the propellant gets used up, which is near enough.
+    pub moment_of_inertia: DVec3,
+    // The thrusters' total push and twist this tick, in its own axes. Worked out every tick.
+    pub force: DVec3,
+    pub torque: DVec3,
+    // The pull of anything nearby, in m/s^2. Left at zero unless something's working it out.
+    pub gravity: DVec3,
+}
+
+impl FlightBody {
+    // Sitting still, pointing down -z, full of propellant
+    pub fn new(dry_mass: f64, propellant_capacity: f64, moment_of_inertia: DVec3) -> Self {
+        Self {
+            position: DVec3::ZERO,
+            velocity: DVec3::ZERO,
+            orientation: DQuat::IDENTITY,
+            angular_velocity: DVec3::ZERO,
+            dry_mass,
+            propellant: propellant_capacity,
+            propellant_capacity,
+            moment_of_inertia,
+            force: DVec3::ZERO,
+            torque: DVec3::ZERO,
+            gravity: DVec3::ZERO,
+        }
+    }
+
+    pub fn mass(&self) -> f64 {
+        self.dry_mass + self.propellant
+    }
+
+    fn current_moment_of_inertia(&self) -> DVec3 {
+        self.moment_of_inertia * (self.mass() / (self.dry_mass + self.propellant_capacity))
+    }
+}
+
+// Pushes a flight body about. Where it is and which way it pushes are in the body's own
+// axes, from its centre of mass, so thrusters that aren't lined up with the centre of mass
+// turn it as well.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Thruster {
+    pub body: Entity,
+    // Which of its thruster panel's registers controls it
+    pub channel: usize,
+    pub position: Vec3,
+    // Which way it pushes the body, i.e. the opposite way to the exhaust
+    pub direction: Vec3,
+    // Flat out, in newtons
+    pub max_thrust: f32,
+    // How fast the exhaust comes out, in m/s. The faster, the less propellant it needs.
+    pub exhaust_velocity: f32,
+    // From 0 to 1
+    pub throttle: f32,
+}
+
+impl Thruster {
+    pub fn new(
+        body: Entity,
+        channel: usize,
+        position: Vec3,
+        direction: Vec3,
+        max_thrust: f32,
+        exhaust_velocity: f32,
+    ) -> Self {
+        Self {
+            body,
+            channel,
+            position,
+            direction: direction.normalize(),
+            max_thrust,
+            exhaust_velocity,
+            throttle: 0.0,
+        }
+    }
+}
+
+// Lets a computer fire a flight body's thrusters.
+// Registers:
+// - 0: the main engine's throttle, from 0 to 255
+// - 1-12: the manoeuvring thrusters' throttles, from 0 to 255. In pairs: the nose pushed
+//   up, down, right and left (1-4), then the tail pushed up, down, right and left (5-8),
+//   then the roll thrusters, two rolling anticlockwise (looking forwards) and two clockwise
+//   (9-12).
+// - 13: how much propellant's left, as a percentage
+#[derive(Component)]
+pub struct ThrusterPanel {
+    pub body: Entity,
+}
+
+const PANEL_CHANNELS: usize = 13;
+const PANEL_PROPELLANT: usize = 13;
+
+// Plugs a thruster panel for `body` into one of a computer's ports.
+// Use `Commands::spawn_thruster_panel` rather than adding this directly.
+pub struct SpawnThrusterPanel {
+    pub computer: Entity,
+    pub port: usize,
+    pub body: Entity,
+}
+
+pub trait SpawnThrusterPanelExt {
+    fn spawn_thruster_panel(&mut self, computer: Entity, port: usize, body: Entity);
+}
+
+impl SpawnThrusterPanelExt for Commands<'_, '_> {
+    fn spawn_thruster_panel(&mut self, computer: Entity, port: usize, body: Entity) {
+        self.add(SpawnThrusterPanel {
+            computer,
+            port,
+            body,
+        });
+    }
+}
+
+impl Command for SpawnThrusterPanel {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            ThrusterPanel { body: self.body },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+// Lets a computer feel how a flight body's moving, like the gyros and accelerometers in an
+// inertial measurement unit.
+// Registers, all 16-bit, low byte first:
+// - 0-1: heading, 2-3: pitch, and 4-5: roll, as binary angles (65536 to a full turn)
+// - 6-7: pitch rate, 8-9: yaw rate, and 10-11: roll rate, signed, in binary angle units
+//   per second
+// - 12-13: acceleration along the nose, signed, in cm/s^2. Like any accelerometer, it can't
+//   feel gravity, only the thrusters.
+// - 14-15: speed, in m/s
+// Everything's the way round a pilot would expect: heading goes clockwise like a compass,
+// and positive is nose up, nose right, and rolling clockwise looking forwards.
+#[derive(Component)]
+pub struct InertialUnit {
+    pub body: Entity,
+}
+
+// Plugs an inertial unit for `body` into one of a computer's ports.
+// Use `Commands::spawn_inertial_unit` rather than adding this directly.
+pub struct SpawnInertialUnit {
+    pub computer: Entity,
+    pub port: usize,
+    pub body: Entity,
+}
+
+pub trait SpawnInertialUnitExt {
+    fn spawn_inertial_unit(&mut self, computer: Entity, port: usize, body: Entity);
+}
+
+impl SpawnInertialUnitExt for Commands<'_, '_> {
+    fn spawn_inertial_unit(&mut self, computer: Entity, port: usize, body: Entity) {
+        self.add(SpawnInertialUnit {
+            computer,
+            port,
+            body,
+        });
+    }
+}
+
+impl Command for SpawnInertialUnit {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            InertialUnit { body: self.body },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+pub fn setup_flight(mut commands: Commands) {
+    // 20 tonnes dry and 10 tonnes of propellant, in a cylinder 20 metres long and 4 across
+    let body = commands
+        .spawn((
+            Name::new("Ship"),
+            FlightBody::new(20_000.0, 10_000.0, DVec3::new(1.03e6, 1.03e6, 6.0e4)),
+        ))
+        .id();
+
+    // The main engine, right at the back, pushing straight forwards
+    commands.spawn((
+        Name::new("Main engine"),
+        Thruster::new(
+            body,
+            0,
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::NEG_Z,
+            50_000.0,
+            3000.0,
+        ),
+    ));
+
+    // Manoeuvring thrusters, in the order they are on the thruster panel. A pair at each
+    // end turns the ship, and fired the same way at both ends pushes it sideways.
+    let ends = [("Nose", -8.0), ("Tail", 8.0)];
+    let pushes = [
+        ("up", Vec3::Y),
+        ("down", Vec3::NEG_Y),
+        ("right", Vec3::X),
+        ("left", Vec3::NEG_X),
+    ];
+    let mut channel = 1;
+    for (end, z) in ends {
+        for (push, direction) in pushes {
+            commands.spawn((
+                Name::new(format!("{end} thruster {push}")),
+                Thruster::new(
+                    body,
+                    channel,
+                    Vec3::new(0.0, 0.0, z),
+                    direction,
+                    500.0,
+                    2000.0,
+                ),
+            ));
+            channel += 1;
+        }
+    }
+    // Roll thrusters, round the middle on either side, pushing in opposite directions
+    let rolls = [
+        ("Roll thruster right, up", Vec3::X, Vec3::Y),
+        ("Roll thruster left, down", Vec3::NEG_X, Vec3::NEG_Y),
+        ("Roll thruster right, down", Vec3::X, Vec3::NEG_Y),
+        ("Roll thruster left, up", Vec3::NEG_X, Vec3::Y),
+    ];
+    for (name, side, direction) in rolls {
+        commands.spawn((
+            Name::new(name),
+            Thruster::new(body, channel, side * 2.0, direction, 500.0, 2000.0),
+        ));
+        channel += 1;
+    }
+
+    commands.insert_resource(ShipFlight { body });
+}
+
+// The helm flies the ship with the thrusters on port 0 ($E800), and feels how it's moving
+// with the inertial unit on port 1 ($E810)
+fn fit_helm_controls(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    flight: Res<ShipFlight>,
+) {
+    let Some(helm) = computers.get(HELM) else {
+        return;
+    };
+    commands.spawn_thruster_panel(helm, 0, flight.body);
+    commands.spawn_inertial_unit(helm, 1, flight.body);
+}
+
+fn run_thruster_panels(
+    panels: Query<(&ThrusterPanel, &BusDevice)>,
+    mut thrusters: Query<&mut Thruster>,
+    bodies: Query<&FlightBody>,
+) {
+    for (panel, device) in panels.iter() {
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        for mut thruster in thrusters.iter_mut() {
+            if thruster.body == panel.body && thruster.channel < PANEL_CHANNELS {
+                thruster.throttle = registers[thruster.channel] as f32 / 255.0;
+            }
+        }
+        if let Ok(body) = bodies.get(panel.body) {
+            registers[PANEL_PROPELLANT] =
+                (body.propellant / body.propellant_capacity * 100.0).round() as u8;
+        }
+    }
+}
+
+// Adds up what all the thrusters are doing to each body, and uses up the propellant they
+// need to do it. If there's not enough to go round, they all get the same share.
+fn fire_thrusters(
+    time: Res<Time>,
+    thrusters: Query<&Thruster>,
+    mut bodies: Query<&mut FlightBody>,
+) {
+    let delta = time.delta_seconds_f64();
+    for mut body in bodies.iter_mut() {
+        body.force = DVec3::ZERO;
+        body.torque = DVec3::ZERO;
+    }
+
+    // Propellant needed by each body, first
+    let mut needed: Vec<(Entity, f64)> = Vec::new();
+    for thruster in thrusters.iter() {
+        let flow = (thruster.max_thrust * thruster.throttle / thruster.exhaust_velocity) as f64;
+        match needed.iter_mut().find(|(body, _)| *body == thruster.body) {
+            Some((_, total)) => *total += flow * delta,
+            None => needed.push((thruster.body, flow * delta)),
+        }
+    }
+
+    for thruster in thrusters.iter() {
+        let Ok(mut body) = bodies.get_mut(thruster.body) else {
+            continue;
+        };
+        let total = needed
+            .iter()
+            .find(|(entity, _)| *entity == thruster.body)
+            .map_or(0.0, |(_, total)| *total);
+        let share = if total > body.propellant {
+            body.propellant / total
+        } else {
+            1.0
+        };
+        let force =
+            (thruster.direction * thruster.max_thrust * thruster.throttle).as_dvec3() * share;
+        body.force += force;
+        body.torque += thruster.position.as_dvec3().cross(force);
+    }
+
+    for (entity, total) in needed {
+        if let Ok(mut body) = bodies.get_mut(entity) {
+            body.propellant = (body.propellant - total).max(0.0);
+        }
+    }
+}
+
+pub fn move_bodies(time: Res<Time>, mut bodies: Query<&mut FlightBody>) {
+    let delta = time.delta_seconds_f64();
+    for mut body in bodies.iter_mut() {
+        // Pushing, then moving (semi-implicit Euler), which keeps things steady
+        let acceleration = body.orientation * body.force / body.mass() + body.gravity;
+        body.velocity += acceleration * delta;
+        let velocity = body.velocity;
+        body.position += velocity * delta;
+
+        // Turning, around its own axes. The gyroscopic term is what makes spinning things
+        // wobble when they're not spinning around one of their main axes.
+        let inertia = body.current_moment_of_inertia();
+        let spin = body.angular_velocity;
+        let angular_acceleration = (body.torque - spin.cross(inertia * spin)) / inertia;
+        body.angular_velocity += angular_acceleration * delta;
+        let turned = DQuat::from_scaled_axis(body.angular_velocity * delta);
+        body.orientation = (body.orientation * turned).normalize();
+    }
+}
+
+fn update_inertial_units(units: Query<(&InertialUnit, &BusDevice)>, bodies: Query<&FlightBody>) {
+    for (unit, device) in units.iter() {
+        let Ok(body) = bodies.get(unit.body) else {
+            continue;
+        };
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+
+        // Turning around +y is turning left, and around +z is rolling anticlockwise, so
+        // those two are the wrong way round for a pilot
+        let (yaw, pitch, roll) = body.orientation.to_euler(EulerRot::YXZ);
+        let angles = [-yaw, pitch, -roll].map(|angle| binary_angle(angle) as u16);
+        let rates = [
+            body.angular_velocity.x,
+            -body.angular_velocity.y,
+            -body.angular_velocity.z,
+        ]
+        .map(|rate| binary_angle(rate).clamp(i16::MIN as i64, i16::MAX as i64) as u16);
+        // Along the nose, which is -z
+        let acceleration = -body.force.z / body.mass() * 100.0;
+        let words = angles.into_iter().chain(rates).chain([
+            acceleration.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16 as u16,
+            body.velocity.length().round().min(u16::MAX as f64) as u16,
+        ]);
+        for (idx, word) in words.enumerate() {
+            let [low, high] = word.to_le_bytes();
+            registers[idx * 2] = low;
+            registers[1 + idx * 2] = high;
+        }
+    }
+}
+
+// An angle in radians, in binary angle units. Doesn't wrap round, so it works for rates too.
+fn binary_angle(radians: f64) -> i64 {
+    (radians / TAU * BINARY_TURN).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    // A 1000 kg ship (900 kg of it dry) with a thruster on its nose pushing it right
+    fn world() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        let body = world
+            .spawn(FlightBody::new(900.0, 100.0, DVec3::splat(1000.0)))
+            .id();
+        let mut thruster =
+            Thruster::new(body, 1, Vec3::new(0.0, 0.0, -5.0), Vec3::X, 100.0, 1000.0);
+        thruster.throttle = 1.0;
+        let thruster = world.spawn(thruster).id();
+        (world, body, thruster)
+    }
+
+    fn body(world: &World, body: Entity) -> &FlightBody {
+        world.get::<FlightBody>(body).unwrap()
+    }
+
+    #[test]
+    fn thrusters_off_the_centre_of_mass_turn_the_ship_as_well() {
+        let (mut world, ship, _) = world();
+        world.run_system_once(fire_thrusters);
+        let ship = body(&world, ship);
+        assert_eq!(ship.force, DVec3::new(100.0, 0.0, 0.0));
+        // Pushing the nose right turns it right, which is clockwise looking down
+        assert_eq!(ship.torque, DVec3::new(0.0, -500.0, 0.0));
+        // The thrusters work in f32
+        assert!((ship.propellant - (100.0 - 100.0 / 1000.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn thrusters_share_what_propellant_there_is() {
+        let (mut world, ship, _) = world();
+        world.spawn(Thruster {
+            throttle: 1.0,
+            ..Thruster::new(ship, 2, Vec3::ZERO, Vec3::NEG_Z, 100.0, 1000.0)
+        });
+        // Enough for half a second of both of them
+        world.get_mut::<FlightBody>(ship).unwrap().propellant = 0.1;
+
+        world.run_system_once(fire_thrusters);
+        let ship = body(&world, ship);
+        assert_eq!(ship.propellant, 0.0);
+        assert!((ship.force - DVec3::new(50.0, 0.0, -50.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn bodies_move_and_turn_the_way_theyre_pushed() {
+        let (mut world, ship, _) = world();
+        {
+            let mut ship = world.get_mut::<FlightBody>(ship).unwrap();
+            // Facing right, and being pushed forwards
+            ship.orientation = DQuat::from_rotation_y(-TAU / 4.0);
+            ship.force = DVec3::new(0.0, 0.0, -1000.0);
+            ship.torque = DVec3::new(0.0, -100.0, 0.0);
+        }
+
+        world.run_system_once(move_bodies);
+        let ship = body(&world, ship);
+        assert!((ship.velocity - DVec3::X).length() < 1e-9);
+        assert!((ship.position - DVec3::X).length() < 1e-9);
+        assert!((ship.angular_velocity - DVec3::new(0.0, -0.1, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn the_inertial_unit_reads_the_way_a_pilot_would_expect() {
+        let (mut world, ship, _) = world();
+        {
+            let mut ship = world.get_mut::<FlightBody>(ship).unwrap();
+            // A quarter turn right, turning right at a sixteenth of a turn a second
+            ship.orientation = DQuat::from_rotation_y(-TAU / 4.0);
+            ship.angular_velocity = DVec3::new(0.0, -TAU / 16.0, 0.0);
+            ship.velocity = DVec3::new(3.0, 0.0, 4.0);
+        }
+        let registers = Port::shared();
+        world.spawn((
+            InertialUnit { body: ship },
+            BusDevice {
+                computer: Entity::PLACEHOLDER,
+                port: 1,
+                registers: registers.clone(),
+            },
+        ));
+
+        world.run_system_once(update_inertial_units);
+        let registers = registers.lock().unwrap().registers;
+        let word = |idx: usize| u16::from_le_bytes([registers[idx], registers[idx + 1]]);
+        assert_eq!(word(0), 16384);
+        assert_eq!(word(2), 0);
+        assert_eq!(word(8), 4096);
+        assert_eq!(word(14), 5);
+    }
+}
@@ -0,0 +1,237 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::computer::{
+    read_screen, HeadlessComputerPlugin, ShipComputers, SpawnHeadlessComputerExt, HELM,
+};
+use crate::core::system_sets::SpawningSet;
+use crate::faults::{FaultSchedule, FaultsPlugin, Scenario};
+use crate::flight::{FlightBody, FlightPlugin, ShipFlight};
+use crate::orbit::{NavObjective, Orbit, OrbitPlugin, ShipNavigation};
+use crate::sensors::SensorsPlugin;
+
+// How long a flight gets if it's not told otherwise, in seconds
+const DEFAULT_DURATION: f64 = 3600.0;
+
+// Flies the ship with a guidance program and nothing else: no window, no player, no power
+// grid. The program's typed into a computer with the same ports as the helm (the thrusters
+// on 0, the inertial unit on 1, the orbit and target telemetry on 2 and 3, and the flight
+// sensors on 4 to 7), and the whole lot runs one fixed timestep per update, as fast as it'll
+// go. Nothing depends on the wall clock, and even the sensor noise is seeded, so the same
+// program always flies the same flight, which makes it good for testing guidance programs
+// without having to sit and watch them.
+//
+// Faults from the scenario (if there is one) get injected as they would be in the game,
+// and listed at the end.
+//
+// Returns the exit code: 0 if every objective got done, 1 if not, and 2 if it couldn't
+// even get started.
+pub fn run(args: &[String], scenario: Scenario) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!(
+            "Usage: ship_6502 [--scenario FILE | --faults SEED] --headless PROGRAM [SECONDS]"
+        );
+        return 2;
+    };
+    let duration = match args.get(1).map(|seconds| seconds.parse::<f64>()) {
+        None => DEFAULT_DURATION,
+        // Infinity would never finish
+        Some(Ok(seconds)) if seconds.is_finite() && seconds > 0.0 => seconds,
+        Some(_) => {
+            eprintln!("SECONDS should be a positive number of seconds");
+            return 2;
+        }
+    };
+    let program = match std::fs::read_to_string(path) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("Couldn't read {}: {}", path, error);
+            return 2;
+        }
+    };
+
+    match fly(&program, duration, scenario, &mut io::stdout().lock()) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => {
+            eprintln!("Couldn't write the flight out: {}", error);
+            2
+        }
+    }
+}
+
+// Flies `program` for up to `duration` seconds, writing how it went to `out`. Gives back
+// whether every objective got done.
+fn fly(program: &str, duration: f64, scenario: Scenario, out: &mut impl Write) -> io::Result<bool> {
+    let mut app = App::new();
+    let timestep = Time::<Fixed>::default().timestep();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(FlightPlugin)
+        .add_plugins(OrbitPlugin)
+        .add_plugins(SensorsPlugin)
+        .add_plugins(HeadlessComputerPlugin)
+        .add_plugins(FaultsPlugin)
+        .insert_resource(FaultSchedule::new(scenario))
+        .insert_resource(GuidanceProgram(program.to_owned()))
+        .add_systems(Startup, spawn_helm.in_set(SpawningSet))
+        // Every update moves the clock on by exactly one fixed timestep, so FixedUpdate runs
+        // exactly once per update
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    // The computer and the devices it's plugged into only see each other through the ports,
+    // so as far as the scheduler's concerned they can run in any order, and on other
+    // threads. One thread keeps them in the same order every tick.
+    app.edit_schedule(FixedUpdate, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+    app.finish();
+    app.cleanup();
+    // Startup, so the ship and the station are where they should be, and the helm's plugged
+    // into them
+    app.update();
+
+    let world = app.world();
+    let ship = world.resource::<ShipFlight>().body;
+    let target = world.resource::<ShipNavigation>().target;
+    let computer = world
+        .resource::<ShipComputers>()
+        .get(HELM)
+        .expect("The helm should have been spawned");
+
+    let ticks = (duration / timestep.as_secs_f64()).ceil() as u64;
+    let ticks_per_report = (60.0 / timestep.as_secs_f64()).round() as u64;
+    for tick in 1..=ticks {
+        app.update();
+        if tick % ticks_per_report == 0 {
+            report(app.world_mut(), ship, target, timestep * tick as u32, out)?;
+        }
+        if objectives(app.world_mut())
+            .iter()
+            .all(|(_, objective)| objective.complete)
+        {
+            break;
+        }
+    }
+
+    writeln!(out)?;
+    let objectives = objectives(app.world_mut());
+    for (name, objective) in objectives.iter() {
+        if objective.complete {
+            writeln!(out, "{:<12} done at T+{:.1}s", name, objective.completed_at)?;
+        } else {
+            writeln!(out, "{:<12} not done", name)?;
+        }
+    }
+    let faults = &app.world().resource::<FaultSchedule>().log;
+    if !faults.is_empty() {
+        writeln!(out)?;
+        for (at, fault) in faults {
+            writeln!(out, "Fault at T+{:.1}s: {}", at, fault)?;
+        }
+    }
+    if let Some(screen) = read_screen(app.world_mut(), computer) {
+        writeln!(out)?;
+        writeln!(out, "{}", screen)?;
+    }
+
+    Ok(objectives.iter().all(|(_, objective)| objective.complete))
+}
+
+// The program the helm gets typed into it
+#[derive(Resource)]
+struct GuidanceProgram(String);
+
+// It's standing in for the helm, so it gets the helm's name, and the plugins plug the helm's
+// devices into it
+fn spawn_helm(mut commands: Commands, program: Res<GuidanceProgram>) {
+    commands.spawn_headless_computer(HELM, &program.0);
+}
+
+// A line of where the ship's got to, once a minute or so
+fn report(
+    world: &mut World,
+    ship: Entity,
+    target: Entity,
+    elapsed: Duration,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let Some(orbit) = world.get::<Orbit>(ship) else {
+        return Ok(());
+    };
+    let apoapsis = match orbit.apoapsis {
+        Some(apoapsis) => format!("{:.1}km", apoapsis / 1000.0),
+        None => "escaping".to_owned(),
+    };
+    let mut line = format!(
+        "T+{:>5}s  alt {:.2}km  vs {:.1}m/s  hs {:.1}m/s  ap {}  pe {:.1}km",
+        elapsed.as_secs(),
+        orbit.altitude / 1000.0,
+        orbit.vertical_speed,
+        orbit.horizontal_speed,
+        apoapsis,
+        orbit.periapsis / 1000.0,
+    );
+
+    let ship = world.get::<FlightBody>(ship);
+    let target = world.get::<FlightBody>(target);
+    if let (Some(ship), Some(target)) = (ship, target) {
+        let range = (target.position - ship.position).length();
+        line.push_str(&format!(
+            "  range {:.0}m  fuel {:.0}kg",
+            range, ship.propellant
+        ));
+    }
+    writeln!(out, "{}", line)
+}
+
+// The objectives, in the order they're meant to be done in
+fn objectives(world: &mut World) -> Vec<(String, NavObjective)> {
+    let mut objectives: Vec<(String, NavObjective)> = world
+        .query::<(&Name, &NavObjective)>()
+        .iter(world)
+        .map(|(name, objective)| (name.to_string(), objective.clone()))
+        .collect();
+    objectives.sort_by_key(|(_, objective)| objective.step);
+    objectives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::RandomFaults;
+
+    // Full throttle for a bit, then reads whatever the telemetry and sensors say, noise and all
+    const PROGRAM: &str = "\
+10 T=59392:POKE T,255
+20 FOR I=1 TO 200:NEXT I
+30 POKE T,0
+40 FOR P=1 TO 7:A=59392+16*P
+50 PRINT P;PEEK(A);PEEK(A+1);PEEK(A+2);PEEK(A+3)
+60 NEXT P
+";
+
+    fn flight() -> String {
+        // Faults every second or so, to check that they come at the same times too
+        let scenario = Scenario {
+            faults: Vec::new(),
+            random: Some(RandomFaults {
+                seed: 6502,
+                mean_interval: 1.0,
+            }),
+        };
+        let mut out = Vec::new();
+        fly(PROGRAM, 10.0, scenario, &mut out).expect("Writing to a Vec can't fail");
+        String::from_utf8(out).expect("Output should be text")
+    }
+
+    #[test]
+    fn the_same_program_flies_the_same_flight() {
+        let first = flight();
+        assert!(first.contains("READY."), "{first}");
+        assert!(first.contains("Fault at"), "{first}");
+        assert_eq!(first, flight());
+    }
+}
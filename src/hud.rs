@@ -1,12 +1,19 @@
 use bevy::prelude::*;
 
+use crate::console::ControlMode;
+
 pub struct HudPlugin;
 impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup);
+        app.add_systems(OnEnter(ControlMode::Seated), hide_crosshair);
+        app.add_systems(OnExit(ControlMode::Seated), show_crosshair);
     }
 }
 
+#[derive(Component)]
+struct Crosshair;
+
 fn setup(mut commands: Commands) {
     // UI camera
     commands.spawn((
@@ -35,14 +42,30 @@ fn setup(mut commands: Commands) {
             ..default()
         })
         .with_children(|parent| {
-            parent.spawn(NodeBundle {
-                style: Style {
-                    width: Val::Px(2.0),
-                    height: Val::Px(2.0),
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(2.0),
+                        height: Val::Px(2.0),
+                        ..default()
+                    },
+                    background_color: Color::WHITE.into(),
                     ..default()
                 },
-                background_color: Color::WHITE.into(),
-                ..default()
-            });
+                Crosshair,
+            ));
         });
 }
+
+// The crosshair just gets in the way of reading the screen when sat at a console
+fn hide_crosshair(mut query: Query<&mut Visibility, With<Crosshair>>) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn show_crosshair(mut query: Query<&mut Visibility, With<Crosshair>>) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
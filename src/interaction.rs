@@ -1,17 +1,35 @@
 use bevy::prelude::*;
+use bevy::math::Vec3A;
+use bevy::render::mesh::VertexAttributeValues;
 use bevy_mod_outline::{OutlineBundle, OutlineMeshExt, OutlineMode, OutlineVolume};
 use bevy_mod_raycast::prelude::*;
 
-use crate::core::system_sets::SpawningSet;
+use crate::computer::Screen;
+use crate::console::ControlMode;
 
 #[derive(Reflect)]
-struct InteractionRaycastSet;
+pub struct InteractionRaycastSet;
 
 pub struct InteractionPlugin;
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_interaction.after(SpawningSet));
-        app.add_systems(Update, check_looked_at);
+        // Interactors and interactables can turn up at any time (e.g. when a computer is
+        // spawned), so set them up whenever new ones appear
+        app.add_systems(
+            Update,
+            (
+                setup_interaction,
+                setup_obstacles,
+                check_looked_at,
+                start_and_end_interactions,
+                point_at_screens,
+            )
+                .chain(),
+        );
+        app.init_resource::<Gaze>();
+        app.add_event::<ScreenPointerEvent>();
+        app.add_event::<InteractStarted>();
+        app.add_event::<InteractEnded>();
         app.add_plugins(DeferredRaycastingPlugin::<InteractionRaycastSet>::default());
         app.insert_resource(RaycastPluginState::<InteractionRaycastSet>::default());
     }
@@ -23,16 +41,84 @@ pub struct Interactor;
 #[derive(Component)]
 pub struct Interactable;
 
+// On solid things that get in the way of what's behind them, so the player can't reach
+// through a panel to the switch on the other side of it. Every one costs a ray test each
+// frame, so things that can never be in the way (the floor, say) are better off without it.
+#[derive(Component)]
+pub struct Obstacle;
+
+// How far away something can be and still be reached, in metres
+const REACH: f32 = 2.0;
+
+// Does the same as clicking on whatever's looked at. This is the only place it's read, so
+// anything else that wants to know about it listens for `InteractStarted`.
+pub const USE_KEY: KeyCode = KeyCode::KeyE;
+
+// On the interactable the player's looking at, if there's one in reach and nothing in the
+// way of it. There's only ever one.
+#[derive(Component)]
+pub struct LookedAt;
+
+// Sent when the player clicks on whatever they're looking at, or presses the use key (E)
+#[derive(Event)]
+pub struct InteractStarted {
+    pub entity: Entity,
+    // Where on it they clicked, in world space
+    pub point: Vec3,
+    pub by: InteractedBy,
+}
+
+// Sent when they let go of whichever they pressed, or look away from it first
+#[derive(Event)]
+pub struct InteractEnded {
+    pub entity: Entity,
+}
+
+// The interactable that's looked at, and where the ray hit it
+#[derive(Resource, Default)]
+struct Gaze(Option<(Entity, Vec3)>);
+
+// What started an interaction, so it ends when that's let go of. Some things only care about
+// the use key: sitting down at a screen, for one, since clicking on a screen is for pointing
+// at what's on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractedBy {
+    Mouse,
+    Key,
+}
+
+// Sent when the player points at, or clicks on, a character cell of a computer's screen
+#[derive(Event)]
+pub struct ScreenPointerEvent {
+    pub computer: Entity,
+    pub row: usize,
+    pub column: usize,
+    pub kind: PointerEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerEventKind {
+    // The pointer has moved onto this cell
+    Hover,
+    Click,
+    // The pointer has moved off this cell, and isn't on this screen any more
+    Leave,
+}
+
 fn setup_interaction(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    interactors: Query<Entity, With<Interactor>>,
-    interactables: Query<(Entity, &Handle<Mesh>), With<Interactable>>,
+    interactors: Query<Entity, Added<Interactor>>,
+    interactables: Query<(Entity, &Handle<Mesh>), Added<Interactable>>,
 ) {
+    if interactors.is_empty() && interactables.is_empty() {
+        return;
+    }
+
     info!(
-        "Setting up interactions. Interactors: {}, Interactables: {}.",
-        interactors.iter().len(),
-        interactables.iter().len(),
+        "Setting up interactions. New interactors: {}, new interactables: {}.",
+        interactors.iter().count(),
+        interactables.iter().count(),
     );
 
     for interactor in interactors.iter() {
@@ -79,10 +165,273 @@ fn setup_interaction(
     }
 }
 
+// The rays need to hit obstacles too, even though there's nothing to do with them, so that
+// they can tell when something's in the way
+fn setup_obstacles(mut commands: Commands, obstacles: Query<Entity, Added<Obstacle>>) {
+    for obstacle in obstacles.iter() {
+        commands
+            .entity(obstacle)
+            .try_insert(RaycastMesh::<InteractionRaycastSet>::default());
+    }
+}
+
+// Only the nearest thing the ray hits counts, since it's in the way of everything behind it,
+// and only if it's within reach
+fn nearest_hit<'a>(
+    hits: &'a Query<(Entity, &RaycastMesh<InteractionRaycastSet>)>,
+) -> Option<(Entity, &'a IntersectionData)> {
+    nearest_in_reach(hits.iter().flat_map(|(entity, mesh)| {
+        mesh.intersections()
+            .iter()
+            .map(move |(_, hit)| (entity, hit))
+    }))
+}
+
+fn nearest_in_reach<'a>(
+    hits: impl Iterator<Item = (Entity, &'a IntersectionData)>,
+) -> Option<(Entity, &'a IntersectionData)> {
+    hits.min_by(|a, b| a.1.distance().total_cmp(&b.1.distance()))
+        .filter(|(_, hit)| hit.distance() <= REACH)
+}
+
 fn check_looked_at(
-    mut query: Query<(&mut OutlineVolume, &RaycastMesh<InteractionRaycastSet>), With<Interactable>>,
+    mut commands: Commands,
+    mut gaze: ResMut<Gaze>,
+    hits: Query<(Entity, &RaycastMesh<InteractionRaycastSet>)>,
+    mut interactables: Query<(Entity, Option<&mut OutlineVolume>), With<Interactable>>,
+    looked_at_before: Query<(), With<LookedAt>>,
+) {
+    gaze.0 = nearest_hit(&hits)
+        .filter(|(entity, _)| interactables.contains(*entity))
+        .map(|(entity, hit)| (entity, hit.position()));
+
+    let looked_at = gaze.0.map(|(entity, _)| entity);
+    for (entity, volume) in interactables.iter_mut() {
+        let is_looked_at = looked_at == Some(entity);
+        let was_looked_at = looked_at_before.contains(entity);
+        if let Some(mut volume) = volume {
+            volume.visible = is_looked_at;
+        }
+        if is_looked_at && !was_looked_at {
+            commands.entity(entity).insert(LookedAt);
+        } else if was_looked_at && !is_looked_at {
+            commands.entity(entity).remove::<LookedAt>();
+        }
+    }
+}
+
+fn start_and_end_interactions(
+    mouse: Res<ButtonInput<MouseButton>>,
+    key: Res<ButtonInput<KeyCode>>,
+    mode: Res<State<ControlMode>>,
+    gaze: Res<Gaze>,
+    mut using: Local<Option<(Entity, InteractedBy)>>,
+    mut evw_started: EventWriter<InteractStarted>,
+    mut evw_ended: EventWriter<InteractEnded>,
+) {
+    if let Some((entity, by)) = *using {
+        let still_looking = gaze.0.is_some_and(|(looked_at, _)| looked_at == entity);
+        let let_go = match by {
+            InteractedBy::Mouse => !mouse.pressed(MouseButton::Left),
+            InteractedBy::Key => !key.pressed(USE_KEY),
+        };
+        if let_go || !still_looking {
+            evw_ended.send(InteractEnded { entity });
+            *using = None;
+        }
+    }
+
+    // While sat at a console, the mouse and keyboard are the computer's
+    if using.is_some() || *mode.get() != ControlMode::FreeLook {
+        return;
+    }
+    let by = if mouse.just_pressed(MouseButton::Left) {
+        InteractedBy::Mouse
+    } else if key.just_pressed(USE_KEY) {
+        InteractedBy::Key
+    } else {
+        return;
+    };
+    if let Some((entity, point)) = gaze.0 {
+        evw_started.send(InteractStarted { entity, point, by });
+        *using = Some((entity, by));
+    }
+}
+
+fn point_at_screens(
+    mouse: Res<ButtonInput<MouseButton>>,
+    meshes: Res<Assets<Mesh>>,
+    hits: Query<(Entity, &RaycastMesh<InteractionRaycastSet>)>,
+    screens: Query<(&Screen, &Handle<Mesh>, &GlobalTransform)>,
+    mut last_hovered: Local<Option<(Entity, usize, usize)>>,
+    mut evw_pointer: EventWriter<ScreenPointerEvent>,
 ) {
-    for (mut volume, mesh) in query.iter_mut() {
-        volume.visible = mesh.intersections().len() > 0;
+    // Find the character cell under the pointer, if the nearest thing being pointed at is
+    // the front of a screen, and it's in reach
+    let hovered = nearest_hit(&hits).and_then(|(entity, hit)| {
+        let (screen, mesh_handle, transform) = screens.get(entity).ok()?;
+        if !on_screen_face(transform, hit) {
+            return None;
+        }
+        let uv = hit_uv(meshes.get(mesh_handle)?, transform, hit)?;
+
+        // The text is rendered to fill the whole texture, so the UVs map straight onto the
+        // character grid
+        let row = ((uv.y * screen.n_rows as f32) as usize).min(screen.n_rows - 1);
+        let column = ((uv.x * screen.n_columns as f32) as usize).min(screen.n_columns - 1);
+        Some((screen.computer, row, column))
+    });
+
+    if hovered != *last_hovered {
+        if let Some((computer, row, column)) = *last_hovered {
+            // Only tell a computer the pointer has left if it's actually left that screen,
+            // rather than just moving to the next cell along
+            if hovered.map(|(next, _, _)| next) != Some(computer) {
+                evw_pointer.send(ScreenPointerEvent {
+                    computer,
+                    row,
+                    column,
+                    kind: PointerEventKind::Leave,
+                });
+            }
+        }
+        if let Some((computer, row, column)) = hovered {
+            evw_pointer.send(ScreenPointerEvent {
+                computer,
+                row,
+                column,
+                kind: PointerEventKind::Hover,
+            });
+        }
+        *last_hovered = hovered;
+    }
+
+    if mouse.just_pressed(MouseButton::Left) {
+        if let Some((computer, row, column)) = hovered {
+            evw_pointer.send(ScreenPointerEvent {
+                computer,
+                row,
+                column,
+                kind: PointerEventKind::Click,
+            });
+        }
+    }
+}
+
+// Whether a hit is on the front of a screen. Screens are cuboids with the picture on the -Z
+// face (the only one it's the right way up on), but the sides and back have UVs too, and
+// clicking on those shouldn't move the pointer.
+fn on_screen_face(transform: &GlobalTransform, hit: &IntersectionData) -> bool {
+    hit.normal().normalize_or_zero().dot(*transform.forward()) > 0.9
+}
+
+// Work out the texture coordinates of a ray hit, by finding the triangle that was hit and
+// blending the UVs of its corners using the hit's barycentric coordinates
+fn hit_uv(mesh: &Mesh, transform: &GlobalTransform, hit: &IntersectionData) -> Option<Vec2> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        return None;
+    };
+
+    // The raycaster gives us the triangle in world space, so bring it back into mesh space
+    // to compare against the mesh's own vertices
+    let world_to_mesh = transform.affine().inverse();
+    let triangle = hit.triangle()?.map(|vertex| world_to_mesh.transform_point3a(vertex));
+
+    // For indexed meshes, the "triangle index" is actually the index of the triangle's first
+    // vertex, which several triangles can share, so we have to go looking for the right one
+    let first = hit.triangle_index()?;
+    let corners = match mesh.indices() {
+        Some(indices) => {
+            let indices: Vec<usize> = indices.iter().collect();
+            let corners = indices.chunks_exact(3).find(|corners| {
+                corners[0] == first
+                    && corners.iter().zip(triangle.iter()).all(|(&corner, vertex)| {
+                        Vec3A::from(positions[corner]).distance_squared(*vertex) < 1e-6
+                    })
+            })?;
+            [corners[0], corners[1], corners[2]]
+        }
+        None => [first, first + 1, first + 2],
+    };
+
+    // The barycentric coordinates are weights for the second, third and first corners,
+    // in that order
+    let weights = hit.barycentric_coord();
+    Some(
+        Vec2::from(uvs[corners[0]]) * weights.z
+            + Vec2::from(uvs[corners[1]]) * weights.x
+            + Vec2::from(uvs[corners[2]]) * weights.y,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_mod_raycast::prelude::{ray_intersection_over_mesh, Backfaces};
+
+    use super::*;
+
+    // Fires a ray at a screen turned to face along -X, the way a screen on a wall might be
+    fn hit_screen(origin: Vec3, towards: Vec3) -> (GlobalTransform, Mesh, IntersectionData) {
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(0.0, 1.0, 0.0)
+                .with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2)),
+        );
+        let mesh = Mesh::from(Cuboid::new(0.4, 0.3, 0.03));
+        let ray = Ray3d::new(origin, towards - origin);
+        let hit =
+            ray_intersection_over_mesh(&mesh, &transform.compute_matrix(), ray, Backfaces::Cull)
+                .expect("The ray should hit the screen");
+        (transform, mesh, hit)
+    }
+
+    #[test]
+    fn hits_on_the_front_map_onto_the_picture() {
+        // Up and to the right of the middle, as seen from in front
+        let (transform, mesh, hit) =
+            hit_screen(Vec3::new(-1.0, 1.05, 0.1), Vec3::new(0.0, 1.05, 0.1));
+        assert!(on_screen_face(&transform, &hit));
+        let uv = hit_uv(&mesh, &transform, &hit).unwrap();
+        assert!((uv - Vec2::new(0.75, 1.0 / 3.0)).length() < 1e-3, "{uv}");
+    }
+
+    fn hit_at(distance: f32) -> IntersectionData {
+        IntersectionData::new(Vec3::ZERO, Vec3::Z, Vec3::ZERO, distance, None, None)
+    }
+
+    #[test]
+    fn only_the_nearest_hit_in_reach_counts() {
+        let screen = Entity::from_raw(1);
+        let wall = Entity::from_raw(2);
+        let (near, middle, far) = (hit_at(1.0), hit_at(1.5), hit_at(REACH + 1.0));
+        let nearest = |hits: &[(Entity, &IntersectionData)]| {
+            nearest_in_reach(hits.iter().copied()).map(|(entity, _)| entity)
+        };
+
+        // A wall in front of the screen hides it
+        let hits = [(screen, &middle), (wall, &near)];
+        assert_eq!(nearest(&hits), Some(wall));
+        // With the wall behind it, the screen's what's hit
+        let hits = [(screen, &near), (wall, &middle)];
+        assert_eq!(nearest(&hits), Some(screen));
+        // And a screen across the room can't be reached at all
+        let hits = [(screen, &far)];
+        assert_eq!(nearest(&hits), None);
+    }
+
+    #[test]
+    fn hits_on_the_sides_and_back_dont_count() {
+        for (origin, towards) in [
+            (Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        ] {
+            let (transform, _, hit) = hit_screen(origin, towards);
+            assert!(!on_screen_face(&transform, &hit), "From {origin}");
+        }
     }
 }
@@ -0,0 +1,784 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::cabling::{PortKind, SpawnSocket};
+use crate::computer::bus::{BusDevice, Port, PORT_SIZE};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::FittingSet;
+use crate::interaction::Obstacle;
+
+// Discrete logic: 74-series TTL chips pushed into breadboards and wired together, with the
+// breadboard plugged into a computer port so programs can drive it and read it back. The
+// chips all look at the wires at the start of a fixed tick and change their outputs at the
+// end of it, so a signal takes a tick to get through each chip (more, for chips with a delay
+// set), and a tangle of gates settles down over a few ticks, like the real thing does over a
+// few nanoseconds.
+pub struct LogicPlugin;
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CircuitLayout>();
+        app.init_asset_loader::<CircuitLayoutLoader>();
+        app.init_resource::<ChipAssets>();
+        app.add_systems(Update, apply_circuit_layouts);
+        app.add_systems(FixedUpdate, run_breadboards);
+        app.add_systems(Startup, fit_breadboard.in_set(FittingSet));
+        app.add_sample_file("DECODE.BAS", include_str!("../assets/programs/DECODE.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// What's on the breadboard, for README.TXT
+const README_PARAGRAPH: &str = "\
+The diagnostics computer has a breadboard on port 12, with a
+74138 decoder and a 74161 counter on it. DECODE tries them out.
+";
+
+// Describes what's on a breadboard, so circuits can be put together in a text file (see
+// assets/circuits) instead of in code. Nets are wires, and are named after whatever they're
+// for; "GND" and "VCC" are always there for tying pins low or high.
+// These get hot-reloaded like screen layouts, which starts the circuit again from scratch.
+#[derive(Asset, TypePath, Clone, Deserialize)]
+pub struct CircuitLayout {
+    pub chips: Vec<ChipLayout>,
+    // Nets the computer drives, from bits of the port's registers
+    #[serde(default)]
+    pub from_port: Vec<PortPinLayout>,
+    // Nets the computer can read, in bits of the port's registers
+    #[serde(default)]
+    pub to_port: Vec<PortPinLayout>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ChipLayout {
+    pub part: Part,
+    // How many extra ticks its outputs take to change
+    #[serde(default)]
+    pub delay: u32,
+    // The net each pin's wired to, by pin number. Leave a pin out and it floats high.
+    pub pins: BTreeMap<u8, String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct PortPinLayout {
+    pub register: usize,
+    pub bit: u8,
+    pub net: String,
+}
+
+#[derive(Default)]
+pub struct CircuitLayoutLoader;
+
+#[derive(Debug, Error)]
+pub enum CircuitLayoutLoaderError {
+    #[error("Couldn't read circuit: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse circuit: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for CircuitLayoutLoader {
+    type Asset = CircuitLayout;
+    type Settings = ();
+    type Error = CircuitLayoutLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<CircuitLayout, CircuitLayoutLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["circuit.ron"]
+    }
+}
+
+// The chips there are, by part number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Part {
+    // Four 2-input NAND gates
+    Nand7400,
+    // Four 2-input NOR gates
+    Nor7402,
+    // Six inverters
+    Not7404,
+    // Four 2-input AND gates
+    And7408,
+    // Four 2-input OR gates
+    Or7432,
+    // Four 2-input XOR gates
+    Xor7486,
+    // Two D flip-flops, with preset and clear
+    FlipFlop7474,
+    // A 3-to-8 line decoder, with active-low outputs
+    Decoder74138,
+    // A 4-bit binary counter, with load and clear
+    Counter74161,
+    // Eight transparent latches, with tri-state outputs
+    Latch74373,
+}
+
+impl Part {
+    fn n_pins(self) -> u8 {
+        match self {
+            Part::Nand7400
+            | Part::Nor7402
+            | Part::Not7404
+            | Part::And7408
+            | Part::Or7432
+            | Part::Xor7486
+            | Part::FlipFlop7474 => 14,
+            Part::Decoder74138 | Part::Counter74161 => 16,
+            Part::Latch74373 => 20,
+        }
+    }
+
+    // Ground's always the pin at the bottom right, and Vcc the one opposite it at the top
+    // left. They're wired up by the breadboard's power rails, not by hand.
+    fn is_power(self, pin: u8) -> bool {
+        pin == self.n_pins() / 2 || pin == self.n_pins()
+    }
+
+    // The two inputs and the output of each gate, for the chips with four 2-input gates. The
+    // 7402 has its outputs first; the rest all have the same pinout as the 7400.
+    fn gates(self) -> [[u8; 3]; 4] {
+        if self == Part::Nor7402 {
+            [[2, 3, 1], [5, 6, 4], [8, 9, 10], [11, 12, 13]]
+        } else {
+            [[1, 2, 3], [4, 5, 6], [9, 10, 8], [12, 13, 11]]
+        }
+    }
+}
+
+// A signal from one chip's output (or the computer) to wherever it's going. Nothing driving
+// it means it floats high, like a real TTL input left unconnected. More than one thing
+// driving it means whichever's pulling it low wins.
+type Net = usize;
+
+// Ground and Vcc are always the first two nets
+const GROUND: Net = 0;
+const VCC: Net = 1;
+
+#[derive(Debug, Error)]
+pub enum CircuitError {
+    #[error("A {0:?} hasn't got a pin {1}")]
+    NoSuchPin(Part, u8),
+    #[error("Pin {1} of a {0:?} is a power pin, and gets wired up by itself")]
+    PowerPin(Part, u8),
+    #[error("Register {0} isn't a register")]
+    NoSuchRegister(usize),
+    #[error("Bit {0} isn't a bit")]
+    NoSuchBit(u8),
+}
+
+// A chip pushed into the breadboard
+struct Chip {
+    part: Part,
+    // Which net each pin is wired to, by pin number (so pins[0] is unused). Unwired pins
+    // float high.
+    pins: Vec<Option<Net>>,
+    // How many extra ticks its outputs take to change
+    delay: u32,
+    // Whatever it's holding on to: the flip-flops' outputs, the count, or the latches
+    state: u8,
+    // The clock inputs last tick, so it can tell when they've gone high
+    clocks: u8,
+    // Outputs on their way, if it's got a delay. An output of None isn't driving its pin.
+    pending: VecDeque<Vec<(u8, Option<bool>)>>,
+}
+
+impl Chip {
+    // What the chip's outputs are given what's on its inputs. Clocked chips move on here too.
+    fn evaluate(&mut self, levels: &[bool]) -> Vec<(u8, Option<bool>)> {
+        let level = |pin: u8| {
+            self.pins[pin as usize]
+                .map(|net| levels[net])
+                .unwrap_or(true)
+        };
+        let mut outputs = Vec::new();
+        match self.part {
+            Part::Nand7400 | Part::Nor7402 | Part::And7408 | Part::Or7432 | Part::Xor7486 => {
+                for [a, b, y] in self.part.gates() {
+                    let (a, b) = (level(a), level(b));
+                    let out = match self.part {
+                        Part::Nand7400 => !(a && b),
+                        Part::Nor7402 => !(a || b),
+                        Part::And7408 => a && b,
+                        Part::Or7432 => a || b,
+                        _ => a != b,
+                    };
+                    outputs.push((y, Some(out)));
+                }
+            }
+            Part::Not7404 => {
+                for [a, y] in [[1, 2], [3, 4], [5, 6], [9, 8], [11, 10], [13, 12]] {
+                    outputs.push((y, Some(!level(a))));
+                }
+            }
+            Part::FlipFlop7474 => {
+                // Clear, D, clock, preset, Q and not Q, for each flip-flop
+                for (idx, [clear, d, clock, preset, q, not_q]) in
+                    [[1, 2, 3, 4, 5, 6], [13, 12, 11, 10, 9, 8]]
+                        .into_iter()
+                        .enumerate()
+                {
+                    let bit = 1 << idx;
+                    let rising = level(clock) && self.clocks & bit == 0;
+                    if !level(clear) {
+                        self.state &= !bit;
+                    } else if !level(preset) {
+                        self.state |= bit;
+                    } else if rising {
+                        self.state = (self.state & !bit) | if level(d) { bit } else { 0 };
+                    }
+                    self.clocks = (self.clocks & !bit) | if level(clock) { bit } else { 0 };
+                    let set = self.state & bit != 0;
+                    outputs.push((q, Some(set)));
+                    outputs.push((not_q, Some(!set)));
+                }
+            }
+            Part::Decoder74138 => {
+                let enabled = level(6) && !level(4) && !level(5);
+                let selected = level(1) as u8 | (level(2) as u8) << 1 | (level(3) as u8) << 2;
+                for (idx, y) in [15, 14, 13, 12, 11, 10, 9, 7].into_iter().enumerate() {
+                    outputs.push((y, Some(!(enabled && selected == idx as u8))));
+                }
+            }
+            Part::Counter74161 => {
+                let rising = level(2) && self.clocks == 0;
+                self.clocks = level(2) as u8;
+                let (enable_p, enable_t) = (level(7), level(10));
+                if !level(1) {
+                    // Clearing doesn't wait for the clock
+                    self.state = 0;
+                } else if rising && !level(9) {
+                    self.state = [3, 4, 5, 6]
+                        .into_iter()
+                        .enumerate()
+                        .map(|(bit, pin)| (level(pin) as u8) << bit)
+                        .sum();
+                } else if rising && enable_p && enable_t {
+                    self.state = (self.state + 1) & 0x0f;
+                }
+                for (bit, q) in [14, 13, 12, 11].into_iter().enumerate() {
+                    outputs.push((q, Some(self.state & 1 << bit != 0)));
+                }
+                outputs.push((15, Some(enable_t && self.state == 0x0f)));
+            }
+            Part::Latch74373 => {
+                let ds = [3, 4, 7, 8, 13, 14, 17, 18];
+                let qs = [2, 5, 6, 9, 12, 15, 16, 19];
+                if level(11) {
+                    self.state = ds
+                        .into_iter()
+                        .enumerate()
+                        .map(|(bit, pin)| (level(pin) as u8) << bit)
+                        .sum();
+                }
+                let driving = !level(1);
+                for (bit, q) in qs.into_iter().enumerate() {
+                    outputs.push((q, driving.then_some(self.state & 1 << bit != 0)));
+                }
+            }
+        }
+        outputs
+    }
+}
+
+// Where a computer's port and the breadboard meet: one bit of one register, and which way
+// it goes
+struct PortPin {
+    register: usize,
+    bit: u8,
+    net: Net,
+    // True if the computer drives it, false if the breadboard does
+    from_computer: bool,
+}
+
+// What's on a breadboard, and how it's wired
+pub struct Circuit {
+    // By name, so wiring can be described by what the wires are for
+    nets: Vec<String>,
+    chips: Vec<Chip>,
+    port_pins: Vec<PortPin>,
+    // What's on each net at the moment
+    levels: Vec<bool>,
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self {
+            nets: vec!["GND".to_owned(), "VCC".to_owned()],
+            chips: Vec::new(),
+            port_pins: Vec::new(),
+            levels: vec![false, true],
+        }
+    }
+}
+
+impl Circuit {
+    pub fn from_layout(layout: &CircuitLayout) -> Result<Self, CircuitError> {
+        let mut circuit = Self::default();
+        for chip in layout.chips.iter() {
+            circuit.add_chip(chip.part, chip.delay, &chip.pins)?;
+        }
+        for pin in layout.from_port.iter() {
+            circuit.add_port_pin(pin, true)?;
+        }
+        for pin in layout.to_port.iter() {
+            circuit.add_port_pin(pin, false)?;
+        }
+        Ok(circuit)
+    }
+
+    // The net with this name, adding it if there isn't one yet
+    fn net(&mut self, name: &str) -> Net {
+        if let Some(net) = self.nets.iter().position(|net| net == name) {
+            return net;
+        }
+        self.nets.push(name.to_owned());
+        // Floating until something drives it
+        self.levels.push(true);
+        self.nets.len() - 1
+    }
+
+    fn add_chip(
+        &mut self,
+        part: Part,
+        delay: u32,
+        wiring: &BTreeMap<u8, String>,
+    ) -> Result<(), CircuitError> {
+        let mut pins = vec![None; part.n_pins() as usize + 1];
+        for (&pin, net) in wiring {
+            if pin == 0 || pin > part.n_pins() {
+                return Err(CircuitError::NoSuchPin(part, pin));
+            }
+            if part.is_power(pin) {
+                return Err(CircuitError::PowerPin(part, pin));
+            }
+            pins[pin as usize] = Some(self.net(net));
+        }
+        self.chips.push(Chip {
+            part,
+            pins,
+            delay,
+            state: 0,
+            clocks: 0,
+            pending: VecDeque::new(),
+        });
+        Ok(())
+    }
+
+    fn add_port_pin(
+        &mut self,
+        pin: &PortPinLayout,
+        from_computer: bool,
+    ) -> Result<(), CircuitError> {
+        if pin.register >= PORT_SIZE {
+            return Err(CircuitError::NoSuchRegister(pin.register));
+        }
+        if pin.bit >= 8 {
+            return Err(CircuitError::NoSuchBit(pin.bit));
+        }
+        let net = self.net(&pin.net);
+        self.port_pins.push(PortPin {
+            register: pin.register,
+            bit: pin.bit,
+            net,
+            from_computer,
+        });
+        Ok(())
+    }
+
+    // Moves everything on by a tick
+    fn step(&mut self, registers: &mut [u8; PORT_SIZE]) {
+        let mut drivers: Vec<Option<bool>> = vec![None; self.nets.len()];
+        let mut drive = |net: Net, level: bool| {
+            let driven = drivers[net].get_or_insert(level);
+            *driven &= level;
+        };
+
+        for pin in self.port_pins.iter().filter(|pin| pin.from_computer) {
+            drive(pin.net, registers[pin.register] & 1 << pin.bit != 0);
+        }
+        for chip in self.chips.iter_mut() {
+            let outputs = chip.evaluate(&self.levels);
+            chip.pending.push_back(outputs);
+            if chip.pending.len() <= chip.delay as usize {
+                continue;
+            }
+            for (pin, level) in chip.pending.pop_front().unwrap_or_default() {
+                if let (Some(net), Some(level)) = (chip.pins[pin as usize], level) {
+                    drive(net, level);
+                }
+            }
+        }
+
+        for (net, level) in self.levels.iter_mut().enumerate() {
+            *level = match net {
+                GROUND => false,
+                VCC => true,
+                _ => drivers[net].unwrap_or(true),
+            };
+        }
+        for pin in self.port_pins.iter().filter(|pin| !pin.from_computer) {
+            let register = &mut registers[pin.register];
+            *register = (*register & !(1 << pin.bit)) | (self.levels[pin.net] as u8) << pin.bit;
+        }
+    }
+}
+
+// A breadboard with a circuit built on it, plugged into one of a computer's ports. What each
+// register does is up to the circuit. Empty until its layout's loaded.
+#[derive(Component, Default)]
+pub struct Breadboard {
+    pub circuit: Circuit,
+}
+
+// Spawns a breadboard plugged into one of a computer's ports.
+// Use `Commands::spawn_breadboard` rather than adding this directly.
+pub struct SpawnBreadboard {
+    pub computer: Entity,
+    pub port: usize,
+    pub transform: Transform,
+    // The circuit layout's asset path
+    pub circuit: String,
+}
+
+pub trait SpawnBreadboardExt {
+    fn spawn_breadboard(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        transform: Transform,
+        circuit: &str,
+    );
+}
+
+impl SpawnBreadboardExt for Commands<'_, '_> {
+    fn spawn_breadboard(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        transform: Transform,
+        circuit: &str,
+    ) {
+        self.add(SpawnBreadboard {
+            computer,
+            port,
+            transform,
+            circuit: circuit.to_owned(),
+        });
+    }
+}
+
+// How big a breadboard is, in metres. About the size of a half-size one.
+const BOARD_SIZE: Vec3 = Vec3::new(0.165, 0.01, 0.055);
+
+impl Command for SpawnBreadboard {
+    fn apply(self, world: &mut World) {
+        let board_mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::from_size(BOARD_SIZE));
+        let board_material =
+            world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(StandardMaterial {
+                    base_color: Color::srgb(0.9, 0.9, 0.85),
+                    ..default()
+                });
+
+        let layout: Handle<CircuitLayout> = world.resource::<AssetServer>().load(self.circuit);
+        let board = world
+            .spawn((
+                Name::new("Breadboard"),
+                PbrBundle {
+                    mesh: board_mesh,
+                    material: board_material,
+                    transform: self.transform,
+                    ..default()
+                },
+                Breadboard::default(),
+                Obstacle,
+                layout,
+                BusDevice {
+                    computer: self.computer,
+                    port: self.port,
+                    registers: Port::shared(),
+                },
+            ))
+            .id();
+        // On the end, where the ribbon cable would go
+        SpawnSocket {
+            owner: board,
+            parent: board,
+            name: "BREADBOARD".to_owned(),
+            kind: PortKind::Parallel,
+            port: None,
+            transform: Transform::from_xyz(BOARD_SIZE.x / 2.0 + 0.003, 0.0, 0.0),
+        }
+        .apply(world);
+    }
+}
+
+// A breadboard for the diagnostics computer, on port 12 ($E8C0), with an address decoder and
+// a counter on it
+fn fit_breadboard(mut commands: Commands, computers: Res<ShipComputers>) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_breadboard(
+        computer,
+        12,
+        Transform::from_xyz(0.0, 1.3, -0.4),
+        "circuits/decoder.circuit.ron",
+    );
+}
+
+// What chips look like, which is all the same whatever they are
+#[derive(Resource)]
+struct ChipAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ChipAssets {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            mesh: world
+                .resource_mut::<Assets<Mesh>>()
+                .add(Cuboid::new(0.01, 0.005, 0.02)),
+            material: world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(StandardMaterial {
+                    base_color: Color::srgb(0.05, 0.05, 0.05),
+                    perceptual_roughness: 0.4,
+                    ..default()
+                }),
+        }
+    }
+}
+
+// One of the chips on a breadboard, to look at
+#[derive(Component)]
+struct ChipModel;
+
+// A breadboard, with whatever's already sitting on it
+type BoardParts = (
+    Entity,
+    &'static mut Breadboard,
+    &'static Handle<CircuitLayout>,
+    &'static Name,
+    Option<&'static Children>,
+);
+
+// Builds each breadboard's circuit from its layout, and puts the chips on it, in a row along
+// the middle in the order the layout lists them
+fn apply_circuit_layouts(
+    mut commands: Commands,
+    mut evr_layouts: EventReader<AssetEvent<CircuitLayout>>,
+    layouts: Res<Assets<CircuitLayout>>,
+    assets: Res<ChipAssets>,
+    mut breadboards: Query<BoardParts>,
+    chip_models: Query<(), With<ChipModel>>,
+) {
+    for ev in evr_layouts.read() {
+        // Modified is what we get when the file is hot-reloaded
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = ev else {
+            continue;
+        };
+        let Some(layout) = layouts.get(*id) else {
+            continue;
+        };
+
+        for (board, mut breadboard, handle, name, children) in breadboards.iter_mut() {
+            if handle.id() != *id {
+                continue;
+            }
+            let circuit = match Circuit::from_layout(layout) {
+                Ok(circuit) => circuit,
+                Err(error) => {
+                    warn!("Couldn't build the circuit on {}: {}", name, error);
+                    continue;
+                }
+            };
+
+            for &child in children.into_iter().flatten() {
+                if chip_models.contains(child) {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+            let n_chips = circuit.chips.len();
+            commands.entity(board).with_children(|board| {
+                for idx in 0..n_chips {
+                    let x = (idx as f32 + 0.5) / n_chips as f32 - 0.5;
+                    board.spawn((
+                        PbrBundle {
+                            mesh: assets.mesh.clone(),
+                            material: assets.material.clone(),
+                            transform: Transform::from_xyz(
+                                x * BOARD_SIZE.x,
+                                BOARD_SIZE.y * 0.75,
+                                0.0,
+                            ),
+                            ..default()
+                        },
+                        ChipModel,
+                        Obstacle,
+                    ));
+                }
+            });
+            breadboard.circuit = circuit;
+        }
+    }
+}
+
+fn run_breadboards(mut breadboards: Query<(&mut Breadboard, &BusDevice)>) {
+    for (mut breadboard, device) in breadboards.iter_mut() {
+        let mut port = device.registers.lock().expect("Port lock");
+        breadboard.circuit.step(&mut port.registers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit(layout: &str) -> Circuit {
+        Circuit::from_layout(&ron::from_str(layout).unwrap()).unwrap()
+    }
+
+    // Long enough for anything without a delay to have caught up with its inputs
+    fn settle(circuit: &mut Circuit, registers: &mut [u8; PORT_SIZE]) {
+        for _ in 0..3 {
+            circuit.step(registers);
+        }
+    }
+
+    #[test]
+    fn gates_follow_their_truth_tables() {
+        // The first gate of each chip, all on the same two inputs
+        let mut gates = circuit(
+            r#"(
+                chips: [
+                    (part: Nand7400, pins: {1: "A", 2: "B", 3: "NAND"}),
+                    (part: Nor7402, pins: {2: "A", 3: "B", 1: "NOR"}),
+                    (part: And7408, pins: {1: "A", 2: "B", 3: "AND"}),
+                    (part: Or7432, pins: {1: "A", 2: "B", 3: "OR"}),
+                    (part: Xor7486, pins: {1: "A", 2: "B", 3: "XOR"}),
+                    (part: Not7404, pins: {1: "A", 2: "NOT"}),
+                ],
+                from_port: [
+                    (register: 0, bit: 0, net: "A"),
+                    (register: 0, bit: 1, net: "B"),
+                ],
+                to_port: [
+                    (register: 1, bit: 0, net: "NAND"),
+                    (register: 1, bit: 1, net: "NOR"),
+                    (register: 1, bit: 2, net: "AND"),
+                    (register: 1, bit: 3, net: "OR"),
+                    (register: 1, bit: 4, net: "XOR"),
+                    (register: 1, bit: 5, net: "NOT"),
+                ],
+            )"#,
+        );
+        let mut registers = [0; PORT_SIZE];
+
+        // By input, NOT, XOR, OR, AND, NOR and NAND from the top bit down
+        for (inputs, outputs) in [
+            (0b00, 0b100011),
+            (0b01, 0b011001),
+            (0b10, 0b111001),
+            (0b11, 0b001100),
+        ] {
+            registers[0] = inputs;
+            settle(&mut gates, &mut registers);
+            assert_eq!(registers[1], outputs, "inputs {:02b}", inputs);
+        }
+    }
+
+    #[test]
+    fn the_counter_wraps_round_and_the_decoder_picks_one_line() {
+        let mut board = circuit(include_str!("../assets/circuits/decoder.circuit.ron"));
+        let mut registers = [0; PORT_SIZE];
+        let clock = |board: &mut Circuit, registers: &mut [u8; PORT_SIZE]| {
+            for level in [0b10, 0b11] {
+                registers[2] = level;
+                settle(board, registers);
+            }
+        };
+        // Everything floats high to start with, which counts as a clock, so it's held clear
+        // first
+        settle(&mut board, &mut registers);
+
+        for _ in 0..15 {
+            clock(&mut board, &mut registers);
+        }
+        // 15, with the carry out
+        assert_eq!(registers[3], 0x1f);
+        clock(&mut board, &mut registers);
+        assert_eq!(registers[3], 0x00);
+        clock(&mut board, &mut registers);
+        assert_eq!(registers[3], 0x01);
+        // Clearing doesn't need a clock
+        registers[2] = 0b01;
+        settle(&mut board, &mut registers);
+        assert_eq!(registers[3], 0x00);
+
+        registers[0] = 5;
+        settle(&mut board, &mut registers);
+        assert_eq!(registers[1], 0xff);
+        registers[0] = 0x80 | 5;
+        settle(&mut board, &mut registers);
+        assert_eq!(registers[1], !(1 << 5));
+    }
+
+    #[test]
+    fn slow_chips_take_their_time() {
+        let mut inverter = circuit(
+            r#"(
+                chips: [(part: Not7404, delay: 2, pins: {1: "IN", 2: "OUT"})],
+                from_port: [(register: 0, bit: 0, net: "IN")],
+                to_port: [(register: 1, bit: 0, net: "OUT")],
+            )"#,
+        );
+        let mut registers = [0; PORT_SIZE];
+        settle(&mut inverter, &mut registers);
+        settle(&mut inverter, &mut registers);
+        assert_eq!(registers[1], 1);
+
+        registers[0] = 1;
+        // One step for the input to get there, and two more for the delay
+        let mut outputs = Vec::new();
+        for _ in 0..4 {
+            inverter.step(&mut registers);
+            outputs.push(registers[1]);
+        }
+        assert_eq!(outputs, [1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn power_pins_and_missing_pins_cant_be_wired() {
+        let layout = |pins: &str| -> CircuitLayout {
+            ron::from_str(&format!("(chips: [(part: Nand7400, pins: {})])", pins)).unwrap()
+        };
+        assert!(matches!(
+            Circuit::from_layout(&layout(r#"{7: "A"}"#)),
+            Err(CircuitError::PowerPin(Part::Nand7400, 7))
+        ));
+        assert!(matches!(
+            Circuit::from_layout(&layout(r#"{15: "A"}"#)),
+            Err(CircuitError::NoSuchPin(Part::Nand7400, 15))
+        ));
+    }
+}
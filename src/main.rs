@@ -1,15 +1,42 @@
+mod atmosphere;
+mod cabling;
 mod computer;
+mod console;
 mod core;
+mod devices;
+mod faults;
+mod flight;
+mod headless;
 mod hud;
 mod interaction;
+mod logic;
+mod orbit;
+mod panel;
 mod player;
+mod power;
+mod reactor;
+mod sensors;
+mod thermal;
 
+use atmosphere::AtmospherePlugin;
 use bevy::prelude::*;
+use cabling::CablingPlugin;
 use bevy_mod_outline::OutlinePlugin;
 use computer::ComputerPlugin;
+use console::ConsolePlugin;
+use devices::DevicesPlugin;
+use faults::{FaultSchedule, FaultsPlugin, Scenario};
+use flight::FlightPlugin;
 use hud::HudPlugin;
 use interaction::InteractionPlugin;
+use logic::LogicPlugin;
+use orbit::OrbitPlugin;
+use panel::PanelPlugin;
 use player::PlayerPlugin;
+use power::PowerPlugin;
+use reactor::ReactorPlugin;
+use sensors::SensorsPlugin;
+use thermal::ThermalPlugin;
 
 // Add a checkerboard surface for testing visual stuff
 fn add_checkerboard(
@@ -48,13 +75,44 @@ fn add_checkerboard(
 }
 
 fn main() {
+    // `--scenario FILE` breaks things as the file says, and `--faults SEED` breaks things at
+    // random. Either works with or without a window.
+    let mut args: Vec<String> = std::env::args().collect();
+    let scenario = match Scenario::from_args(&mut args) {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
+    // `ship_6502 --headless PROGRAM [SECONDS]` flies a guidance program with no window, and
+    // says how it got on
+    if args.get(1).is_some_and(|arg| arg == "--headless") {
+        std::process::exit(headless::run(&args[2..], scenario));
+    }
+
     App::new()
         .add_plugins(DefaultPlugins)
+        .add_plugins(AtmospherePlugin)
+        .add_plugins(CablingPlugin)
         .add_plugins(ComputerPlugin)
+        .add_plugins(ConsolePlugin)
+        .add_plugins(DevicesPlugin)
+        .add_plugins(FaultsPlugin)
+        .add_plugins(FlightPlugin)
         .add_plugins(HudPlugin)
         .add_plugins(InteractionPlugin)
+        .add_plugins(LogicPlugin)
+        .add_plugins(OrbitPlugin)
         .add_plugins(OutlinePlugin)
+        .add_plugins(PanelPlugin)
         .add_plugins(PlayerPlugin)
+        .add_plugins(PowerPlugin)
+        .add_plugins(ReactorPlugin)
+        .add_plugins(SensorsPlugin)
+        .add_plugins(ThermalPlugin)
+        .insert_resource(FaultSchedule::new(scenario))
         .add_systems(Startup, add_checkerboard)
         .run();
 }
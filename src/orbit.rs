@@ -0,0 +1,557 @@
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use bevy::ecs::world::Command;
+use bevy::math::{DQuat, DVec3};
+use bevy::prelude::*;
+
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, HELM};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::flight::{move_bodies, setup_flight, FlightBody, ShipFlight};
+
+// Gravity, orbits, and somewhere to go. Anything gravitating pulls on every flight body (so
+// it's n-body, as far as the ships are concerned, but the planets and moons stay put), and
+// the ground stops anything falling through it. The ship's got a list of navigation
+// objectives to get through, and a guidance computer can follow its progress through its
+// telemetry ports.
+pub struct OrbitPlugin;
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<GravityBody>()
+            .register_type::<Orbit>()
+            .register_type::<NavObjective>();
+        // The ship's already been spawned, ready to be put somewhere
+        app.add_systems(
+            Startup,
+            setup_orbits.in_set(SpawningSet).after(setup_flight),
+        );
+        app.add_systems(FixedUpdate, apply_gravity.before(move_bodies));
+        app.add_systems(
+            FixedUpdate,
+            (
+                hold_up_landed_bodies,
+                update_orbits,
+                check_objectives,
+                update_orbit_telemetry,
+                update_target_telemetry,
+            )
+                .chain()
+                .after(move_bodies),
+        );
+        app.add_systems(Startup, fit_helm_telemetry.in_set(FittingSet));
+        app.add_sample_file("ORBIT.BAS", include_str!("../assets/programs/ORBIT.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to get into orbit, for README.TXT
+const README_PARAGRAPH: &str = "\
+The helm's orbit telemetry on port 2 and the station's range
+and bearing on port 3 are for getting somewhere: ORBIT flies
+from the pad into orbit. Rendezvous and docking are up to
+you.
+";
+
+// A full turn in binary angle units, the same as the inertial unit uses
+const BINARY_TURN: f64 = 65536.0;
+
+// The ship's surroundings, for anything that needs to be plugged into them when it's spawned
+#[derive(Resource)]
+pub struct ShipNavigation {
+    // What it's trying to get to
+    pub target: Entity,
+}
+
+// A planet or moon. Doesn't move, and pulls on every flight body.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct GravityBody {
+    // In metres
+    pub position: DVec3,
+    pub radius: f64,
+    // Its mass times the gravitational constant, in m^3/s^2. Known much more accurately
+    // than either of them on its own.
+    pub gravitational_parameter: f64,
+}
+
+// Where a flight body is compared with whatever it's orbiting. Worked out every tick.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Orbit {
+    pub reference: Entity,
+    // Above the surface, in metres
+    pub altitude: f64,
+    // In m/s. Vertical is straight away from the middle, and horizontal is everything else.
+    pub vertical_speed: f64,
+    pub horizontal_speed: f64,
+    // The highest and lowest it's going to get, as altitudes, in metres. If it's going fast
+    // enough to escape, it hasn't got an apoapsis.
+    pub apoapsis: Option<f64>,
+    pub periapsis: f64,
+    // Sitting on the ground
+    pub landed: bool,
+}
+
+impl Orbit {
+    pub fn new(reference: Entity) -> Self {
+        Self {
+            reference,
+            altitude: 0.0,
+            vertical_speed: 0.0,
+            horizontal_speed: 0.0,
+            apoapsis: None,
+            periapsis: 0.0,
+            landed: false,
+        }
+    }
+}
+
+// Something for the ship to do. They're done in order, and each one's only checked once the
+// ones before it are complete.
+#[derive(Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct NavObjective {
+    // Where it comes in the list, from 1
+    pub step: u8,
+    pub goal: NavGoal,
+    pub complete: bool,
+    // How long it took to get done, in seconds from the start
+    pub completed_at: f64,
+}
+
+#[derive(Debug, Clone, Copy, Reflect)]
+pub enum NavGoal {
+    // Get the ship's periapsis above the given altitude, in metres, without escaping
+    ReachOrbit { min_periapsis: f64 },
+    // Get within `range` metres of the target, moving slower than `speed` m/s relative to it
+    Rendezvous { range: f64, speed: f64 },
+    // The same, but close enough to dock. The docking port's on whichever side's closest,
+    // which is near enough for now.
+    Dock { range: f64, speed: f64 },
+}
+
+impl NavObjective {
+    pub fn new(step: u8, goal: NavGoal) -> Self {
+        Self {
+            step,
+            goal,
+            complete: false,
+            completed_at: 0.0,
+        }
+    }
+}
+
+// Lets a computer see the ship's orbit, and which objective it's on.
+// Registers, all 16-bit, low byte first:
+// - 0-1: altitude, in units of 10 metres
+// - 2-3: vertical speed, signed, in m/s
+// - 4-5: horizontal speed, in m/s
+// - 6-7: apoapsis altitude, in units of 10 metres. $FFFF if it's escaping.
+// - 8-9: periapsis altitude, signed, in units of 10 metres. Below 0 means it's going to hit
+//   the ground.
+// - 10-11: how far the nose is above the horizon, signed, as a binary angle
+// - 12-13: how far the way it's moving is above the horizon (the flight path angle),
+//   signed, as a binary angle
+// And two 8-bit ones:
+// - 14: which objective it's on, from 1. 0 once they're all done.
+// - 15: 1 if it's on the ground, 0 if not
+#[derive(Component)]
+pub struct OrbitTelemetry {
+    pub ship: Entity,
+}
+
+// Lets a computer see where the target is, compared with the ship.
+// Registers, all 16-bit and signed, low byte first:
+// - 0-1: range, in metres (not signed, and $FFFF if it's further than that)
+// - 2-3: closing speed, in units of 0.1 m/s. Positive means getting closer.
+// - 4-5, 6-7, 8-9: where the target is, in metres, to the right, above and in front of the
+//   ship, along its own axes
+// - 10-11, 12-13, 14-15: how fast the target's moving, in units of 0.1 m/s, in the same
+//   directions
+// Anything that doesn't fit is as big as it'll go.
+#[derive(Component)]
+pub struct TargetTelemetry {
+    pub ship: Entity,
+    pub target: Entity,
+}
+
+// Plugs orbit telemetry for `ship` into one of a computer's ports.
+// Use `Commands::spawn_orbit_telemetry` rather than adding this directly.
+pub struct SpawnOrbitTelemetry {
+    pub computer: Entity,
+    pub port: usize,
+    pub ship: Entity,
+}
+
+pub trait SpawnOrbitTelemetryExt {
+    fn spawn_orbit_telemetry(&mut self, computer: Entity, port: usize, ship: Entity);
+}
+
+impl SpawnOrbitTelemetryExt for Commands<'_, '_> {
+    fn spawn_orbit_telemetry(&mut self, computer: Entity, port: usize, ship: Entity) {
+        self.add(SpawnOrbitTelemetry {
+            computer,
+            port,
+            ship,
+        });
+    }
+}
+
+impl Command for SpawnOrbitTelemetry {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            OrbitTelemetry { ship: self.ship },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+// Plugs target telemetry for `ship` into one of a computer's ports.
+// Use `Commands::spawn_target_telemetry` rather than adding this directly.
+pub struct SpawnTargetTelemetry {
+    pub computer: Entity,
+    pub port: usize,
+    pub ship: Entity,
+    pub target: Entity,
+}
+
+pub trait SpawnTargetTelemetryExt {
+    fn spawn_target_telemetry(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        ship: Entity,
+        target: Entity,
+    );
+}
+
+impl SpawnTargetTelemetryExt for Commands<'_, '_> {
+    fn spawn_target_telemetry(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        ship: Entity,
+        target: Entity,
+    ) {
+        self.add(SpawnTargetTelemetry {
+            computer,
+            port,
+            ship,
+            target,
+        });
+    }
+}
+
+impl Command for SpawnTargetTelemetry {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            TargetTelemetry {
+                ship: self.ship,
+                target: self.target,
+            },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+pub fn setup_orbits(
+    mut commands: Commands,
+    flight: Res<ShipFlight>,
+    mut bodies: Query<&mut FlightBody>,
+) {
+    // A small moon, with about a thirtieth of Earth's gravity. Low orbit's about 300 m/s,
+    // which the ship's got plenty of propellant for.
+    let radius = 200_000.0;
+    let gravitational_parameter = 2.0e10;
+    let moon = commands
+        .spawn((
+            Name::new("Moon"),
+            GravityBody {
+                position: DVec3::ZERO,
+                radius,
+                gravitational_parameter,
+            },
+        ))
+        .id();
+
+    // The ship starts off landed at the north pole, pointing straight up. Pitching down
+    // points it along the station's orbit.
+    if let Ok(mut ship) = bodies.get_mut(flight.body) {
+        ship.position = DVec3::new(0.0, radius, 0.0);
+        ship.orientation = DQuat::from_rotation_x(FRAC_PI_2);
+    }
+    commands.entity(flight.body).insert(Orbit::new(moon));
+
+    // The station, in a circular orbit 20 km up, over the poles. It's just coming up over
+    // the horizon behind the landing site, so there's a launch window a few minutes in.
+    let station_radius = radius + 20_000.0;
+    let speed = (gravitational_parameter / station_radius).sqrt();
+    let angle: f64 = -0.35;
+    let mut station = FlightBody::new(50_000.0, 0.0, DVec3::splat(1.0e6));
+    station.position = station_radius * DVec3::new(0.0, angle.cos(), -angle.sin());
+    station.velocity = speed * DVec3::new(0.0, -angle.sin(), -angle.cos());
+    let station = commands
+        .spawn((Name::new("Station"), station, Orbit::new(moon)))
+        .id();
+
+    let objectives = [
+        (
+            "Reach orbit",
+            NavGoal::ReachOrbit {
+                min_periapsis: 10_000.0,
+            },
+        ),
+        (
+            "Rendezvous",
+            NavGoal::Rendezvous {
+                range: 1000.0,
+                speed: 5.0,
+            },
+        ),
+        (
+            "Dock",
+            NavGoal::Dock {
+                range: 5.0,
+                speed: 0.3,
+            },
+        ),
+    ];
+    for (step, (name, goal)) in objectives.into_iter().enumerate() {
+        commands.spawn((Name::new(name), NavObjective::new(step as u8 + 1, goal)));
+    }
+
+    commands.insert_resource(ShipNavigation { target: station });
+}
+
+// The helm knows where the ship is going with the orbit telemetry on port 2 ($E820), and
+// where the station is with the target telemetry on port 3 ($E830)
+fn fit_helm_telemetry(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    flight: Res<ShipFlight>,
+    navigation: Res<ShipNavigation>,
+) {
+    let Some(helm) = computers.get(HELM) else {
+        return;
+    };
+    commands.spawn_orbit_telemetry(helm, 2, flight.body);
+    commands.spawn_target_telemetry(helm, 3, flight.body, navigation.target);
+}
+
+fn apply_gravity(gravity_bodies: Query<&GravityBody>, mut bodies: Query<&mut FlightBody>) {
+    for mut body in bodies.iter_mut() {
+        body.gravity = gravity_bodies
+            .iter()
+            .map(|gravity_body| {
+                let offset = gravity_body.position - body.position;
+                let distance = offset.length().max(gravity_body.radius);
+                offset * gravity_body.gravitational_parameter / distance.powi(3)
+            })
+            .sum();
+    }
+}
+
+// Anything that's ended up underground gets put back on the surface, and stops dead. Legs
+// and friction, roughly.
+fn hold_up_landed_bodies(gravity_bodies: Query<&GravityBody>, mut bodies: Query<&mut FlightBody>) {
+    for mut body in bodies.iter_mut() {
+        for gravity_body in gravity_bodies.iter() {
+            let offset = body.position - gravity_body.position;
+            if offset.length() >= gravity_body.radius {
+                continue;
+            }
+            let up = offset.normalize_or(DVec3::Y);
+            body.position = gravity_body.position + up * gravity_body.radius;
+            // Taking off's still allowed
+            body.velocity = up * body.velocity.dot(up).max(0.0);
+            body.angular_velocity = DVec3::ZERO;
+        }
+    }
+}
+
+fn update_orbits(
+    gravity_bodies: Query<&GravityBody>,
+    mut bodies: Query<(&FlightBody, &mut Orbit)>,
+) {
+    for (body, mut orbit) in bodies.iter_mut() {
+        let Ok(reference) = gravity_bodies.get(orbit.reference) else {
+            continue;
+        };
+        let offset = body.position - reference.position;
+        let distance = offset.length();
+        let up = offset / distance;
+        let mu = reference.gravitational_parameter;
+
+        orbit.altitude = distance - reference.radius;
+        orbit.vertical_speed = body.velocity.dot(up);
+        orbit.horizontal_speed = (body.velocity - up * orbit.vertical_speed).length();
+        orbit.landed = orbit.altitude < 0.5 && body.velocity.length() < 0.1;
+
+        // The orbit's shape, from its energy and angular momentum
+        let energy = body.velocity.length_squared() / 2.0 - mu / distance;
+        let angular_momentum = offset.cross(body.velocity).length();
+        let eccentricity = (1.0 + 2.0 * energy * angular_momentum.powi(2) / mu.powi(2))
+            .max(0.0)
+            .sqrt();
+        if energy < 0.0 {
+            let semi_major_axis = -mu / (2.0 * energy);
+            orbit.apoapsis = Some(semi_major_axis * (1.0 + eccentricity) - reference.radius);
+            orbit.periapsis = semi_major_axis * (1.0 - eccentricity) - reference.radius;
+        } else {
+            // Escaping. The periapsis is still where it comes closest.
+            orbit.apoapsis = None;
+            orbit.periapsis =
+                angular_momentum.powi(2) / (mu * (1.0 + eccentricity)) - reference.radius;
+        }
+    }
+}
+
+fn check_objectives(
+    time: Res<Time>,
+    flight: Res<ShipFlight>,
+    navigation: Res<ShipNavigation>,
+    mut objectives: Query<&mut NavObjective>,
+    bodies: Query<(&FlightBody, &Orbit)>,
+) {
+    let Some(mut objective) = objectives
+        .iter_mut()
+        .filter(|objective| !objective.complete)
+        .min_by_key(|objective| objective.step)
+    else {
+        return;
+    };
+    let Ok((ship, orbit)) = bodies.get(flight.body) else {
+        return;
+    };
+    let (range, speed) = match bodies.get(navigation.target) {
+        Ok((target, _)) => (
+            (target.position - ship.position).length(),
+            (target.velocity - ship.velocity).length(),
+        ),
+        Err(_) => (f64::INFINITY, f64::INFINITY),
+    };
+
+    let done = match objective.goal {
+        NavGoal::ReachOrbit { min_periapsis } => {
+            orbit.apoapsis.is_some() && orbit.periapsis >= min_periapsis
+        }
+        NavGoal::Rendezvous {
+            range: max_range,
+            speed: max_speed,
+        }
+        | NavGoal::Dock {
+            range: max_range,
+            speed: max_speed,
+        } => range <= max_range && speed <= max_speed,
+    };
+    if done {
+        objective.complete = true;
+        objective.completed_at = time.elapsed_seconds_f64();
+    }
+}
+
+fn update_orbit_telemetry(
+    telemetry: Query<(&OrbitTelemetry, &BusDevice)>,
+    bodies: Query<(&FlightBody, &Orbit)>,
+    gravity_bodies: Query<&GravityBody>,
+    objectives: Query<&NavObjective>,
+) {
+    let current = objectives
+        .iter()
+        .filter(|objective| !objective.complete)
+        .map(|objective| objective.step)
+        .min()
+        .unwrap_or(0);
+
+    for (telemetry, device) in telemetry.iter() {
+        let Ok((body, orbit)) = bodies.get(telemetry.ship) else {
+            continue;
+        };
+        let Ok(reference) = gravity_bodies.get(orbit.reference) else {
+            continue;
+        };
+        let up = (body.position - reference.position).normalize_or_zero();
+        let nose = body.orientation * DVec3::NEG_Z;
+        let nose_elevation = nose.dot(up).clamp(-1.0, 1.0).asin();
+        let flight_path_angle = if body.velocity.length() > 0.0 {
+            (body.velocity.normalize().dot(up)).clamp(-1.0, 1.0).asin()
+        } else {
+            0.0
+        };
+
+        let words = [
+            (orbit.altitude / 10.0).round().clamp(0.0, u16::MAX as f64) as u16,
+            signed_word(orbit.vertical_speed),
+            orbit.horizontal_speed.round().min(u16::MAX as f64) as u16,
+            orbit.apoapsis.map_or(u16::MAX, |apoapsis| {
+                (apoapsis / 10.0).round().clamp(0.0, u16::MAX as f64 - 1.0) as u16
+            }),
+            signed_word(orbit.periapsis / 10.0),
+            signed_word(nose_elevation / TAU * BINARY_TURN),
+            signed_word(flight_path_angle / TAU * BINARY_TURN),
+        ];
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        for (idx, word) in words.into_iter().enumerate() {
+            let [low, high] = word.to_le_bytes();
+            registers[idx * 2] = low;
+            registers[1 + idx * 2] = high;
+        }
+        registers[14] = current;
+        registers[15] = orbit.landed as u8;
+    }
+}
+
+fn update_target_telemetry(
+    telemetry: Query<(&TargetTelemetry, &BusDevice)>,
+    bodies: Query<&FlightBody>,
+) {
+    for (telemetry, device) in telemetry.iter() {
+        let (Ok(ship), Ok(target)) = (bodies.get(telemetry.ship), bodies.get(telemetry.target))
+        else {
+            continue;
+        };
+        let offset = target.position - ship.position;
+        let velocity = target.velocity - ship.velocity;
+        let range = offset.length();
+        let closing_speed = if range > 0.0 {
+            -velocity.dot(offset / range)
+        } else {
+            0.0
+        };
+        // In the ship's own axes: right is +x, up is +y, and in front is -z
+        let to_ship = ship.orientation.inverse();
+        let (offset, velocity) = (to_ship * offset, to_ship * velocity);
+
+        let words = [
+            range.round().min(u16::MAX as f64) as u16,
+            signed_word(closing_speed * 10.0),
+            signed_word(offset.x),
+            signed_word(offset.y),
+            signed_word(-offset.z),
+            signed_word(velocity.x * 10.0),
+            signed_word(velocity.y * 10.0),
+            signed_word(-velocity.z * 10.0),
+        ];
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        for (idx, word) in words.into_iter().enumerate() {
+            let [low, high] = word.to_le_bytes();
+            registers[idx * 2] = low;
+            registers[1 + idx * 2] = high;
+        }
+    }
+}
+
+// A number for a pair of registers, as a signed 16-bit word, as big as it'll go if it
+// doesn't fit
+fn signed_word(value: f64) -> u16 {
+    value.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16 as u16
+}
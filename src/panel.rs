@@ -0,0 +1,878 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::cabling::{PortKind, SpawnSocket};
+use crate::computer::bus::{BusDevice, Port, PORT_SIZE};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::FittingSet;
+use crate::interaction::{InteractEnded, InteractStarted, Interactable, Obstacle};
+
+// Control panels: switches, buttons and knobs for the player to fiddle with, and gauges,
+// lights and displays for programs to show things on, all screwed to a plate that plugs
+// into a computer port. Each control is wired to some bits of one of the port's registers.
+// The switches, buttons and knobs set their bits, whatever the computer writes there, and
+// everything else shows whatever the computer last wrote to its bits.
+pub struct PanelPlugin;
+impl Plugin for PanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PanelLayout>();
+        app.init_asset_loader::<PanelLayoutLoader>();
+        app.init_resource::<PanelAssets>();
+        app.add_systems(
+            Update,
+            (
+                apply_panel_layouts,
+                operate_controls,
+                update_controls,
+                show_controls,
+            )
+                .chain(),
+        );
+        app.add_systems(Startup, fit_control_panel.in_set(FittingSet));
+        app.add_sample_file("PANEL.BAS", include_str!("../assets/programs/PANEL.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to work the control panel, for README.TXT
+const README_PARAGRAPH: &str = "\
+The diagnostics computer has a control panel on port 13.
+Click switches to flip them, hold buttons in, and click either
+side of a knob to turn it that way. PANEL shows them on the
+lights, display and gauge.
+";
+
+// Describes what's on a panel and what it's wired to, so panels can be laid out in a text
+// file (see assets/panels). These get hot-reloaded like screen layouts.
+#[derive(Asset, TypePath, Clone, Deserialize)]
+pub struct PanelLayout {
+    pub controls: Vec<ControlLayout>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ControlLayout {
+    pub kind: ControlKind,
+    pub register: usize,
+    // The lowest of the bits it's wired to. How many bits it takes up depends on the kind.
+    #[serde(default)]
+    pub bit: u8,
+    // Where it is on the panel, in metres from the middle, with y going up
+    pub at: (f32, f32),
+}
+
+#[derive(Default)]
+pub struct PanelLayoutLoader;
+
+#[derive(Debug, Error)]
+pub enum PanelLayoutLoaderError {
+    #[error("Couldn't read panel: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse panel: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for PanelLayoutLoader {
+    type Asset = PanelLayout;
+    type Settings = ();
+    type Error = PanelLayoutLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<PanelLayout, PanelLayoutLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["panel.ron"]
+    }
+}
+
+// The controls there are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ControlKind {
+    // Click to flip it. Its bit's set while it's up.
+    ToggleSwitch,
+    // Its bit's set for as long as it's held in
+    PushButton,
+    // Click on its right-hand side to turn it one click clockwise, and its left-hand side to
+    // turn it back. Its bits hold which position it's in, counting from 0 all the way
+    // anticlockwise.
+    RotaryKnob { positions: u8 },
+    // Shows a whole register, from 0 all the way left to 255 all the way right
+    NeedleGauge,
+    // Lit while its bit's set
+    Led,
+    // Shows its 4 bits as a hex digit, like a TIL311 with the decoder built in
+    SevenSegment,
+}
+
+impl ControlKind {
+    // How many bits of its register it's wired to
+    fn width(self) -> u8 {
+        match self {
+            ControlKind::ToggleSwitch | ControlKind::PushButton | ControlKind::Led => 1,
+            ControlKind::RotaryKnob { positions } => {
+                (u8::BITS - positions.saturating_sub(1).leading_zeros()) as u8
+            }
+            ControlKind::NeedleGauge => 8,
+            ControlKind::SevenSegment => 4,
+        }
+    }
+
+    // Whether the player sets it, rather than the computer
+    fn is_input(self) -> bool {
+        matches!(
+            self,
+            ControlKind::ToggleSwitch | ControlKind::PushButton | ControlKind::RotaryKnob { .. }
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PanelError {
+    #[error("There's no register {0}; ports have {PORT_SIZE}")]
+    NoSuchRegister(usize),
+    #[error("{width} bits from bit {bit} don't fit in a register")]
+    DoesntFit { bit: u8, width: u8 },
+    #[error("A knob needs between 2 and 16 positions, not {0}")]
+    KnobPositions(u8),
+    #[error("More than one control is setting bit {bit} of register {register}")]
+    Clash { register: usize, bit: u8 },
+}
+
+impl PanelLayout {
+    fn check(&self) -> Result<(), PanelError> {
+        // Which bits of each register the switches, buttons and knobs have already set
+        let mut set = [0u8; PORT_SIZE];
+        for control in self.controls.iter() {
+            if let ControlKind::RotaryKnob { positions } = control.kind {
+                if !(2..=16).contains(&positions) {
+                    return Err(PanelError::KnobPositions(positions));
+                }
+            }
+            let width = control.kind.width();
+            if control.register >= PORT_SIZE {
+                return Err(PanelError::NoSuchRegister(control.register));
+            }
+            if control.bit + width > 8 {
+                return Err(PanelError::DoesntFit {
+                    bit: control.bit,
+                    width,
+                });
+            }
+            if !control.kind.is_input() {
+                continue;
+            }
+            let mask = bits(control.bit, width);
+            let clash = set[control.register] & mask;
+            if clash != 0 {
+                return Err(PanelError::Clash {
+                    register: control.register,
+                    bit: clash.trailing_zeros() as u8,
+                });
+            }
+            set[control.register] |= mask;
+        }
+        Ok(())
+    }
+}
+
+// A mask of `width` bits, starting from `bit`
+fn bits(bit: u8, width: u8) -> u8 {
+    (((1u16 << width) - 1) << bit) as u8
+}
+
+// A plate full of controls, plugged into one of a computer's ports. Empty until its layout's
+// loaded.
+#[derive(Component)]
+pub struct ControlPanel;
+
+// One of the controls on a panel
+#[derive(Component)]
+pub struct Control {
+    pub kind: ControlKind,
+    pub register: usize,
+    pub bit: u8,
+    // What its bits are: where the player's left it, for switches, buttons and knobs, and
+    // what it's showing, for everything else
+    pub state: u8,
+    // Where it sits on the panel, before it's moved to show its state
+    at: Vec3,
+}
+
+// The needle of a gauge, which swings about the middle of the dial
+#[derive(Component)]
+struct Needle;
+
+// One of the segments of a 7-segment display, from 0 (a, along the top) round to 5 (f, top
+// left), then 6 (g, across the middle)
+#[derive(Component)]
+struct Segment(u8);
+
+// Spawns a control panel plugged into one of a computer's ports.
+// Use `Commands::spawn_control_panel` rather than adding this directly.
+pub struct SpawnControlPanel {
+    pub computer: Entity,
+    pub port: usize,
+    pub transform: Transform,
+    // The panel layout's asset path
+    pub layout: String,
+}
+
+pub trait SpawnControlPanelExt {
+    fn spawn_control_panel(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        transform: Transform,
+        layout: &str,
+    );
+}
+
+impl SpawnControlPanelExt for Commands<'_, '_> {
+    fn spawn_control_panel(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        transform: Transform,
+        layout: &str,
+    ) {
+        self.add(SpawnControlPanel {
+            computer,
+            port,
+            transform,
+            layout: layout.to_owned(),
+        });
+    }
+}
+
+// How big a panel is, in metres. The controls go on its +z face.
+const PANEL_SIZE: Vec3 = Vec3::new(0.2, 0.12, 0.01);
+
+// How far a toggle switch's lever leans either side of straight out, in radians
+const LEVER_THROW: f32 = 0.5;
+const LEVER_LENGTH: f32 = 0.03;
+
+// How far a push button goes in when it's pushed, in metres
+const BUTTON_TRAVEL: f32 = 0.003;
+
+// How far a knob turns from its first position to its last, and a gauge's needle from 0 to
+// 255, in radians
+const KNOB_SWEEP: f32 = 1.5 * PI;
+const NEEDLE_SWEEP: f32 = 0.5 * PI;
+
+// Which segments light up for each hex digit, with segment a in bit 0
+const DIGITS: [u8; 16] = [
+    0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f, 0x77, 0x7c, 0x39, 0x5e, 0x79, 0x71,
+];
+
+// Where each segment goes on a display, and whether it's one of the upright ones
+const SEGMENTS: [(f32, f32, bool); 7] = [
+    (0.0, 0.015, false),
+    (0.0065, 0.0075, true),
+    (0.0065, -0.0075, true),
+    (0.0, -0.015, false),
+    (-0.0065, -0.0075, true),
+    (-0.0065, 0.0075, true),
+    (0.0, 0.0, false),
+];
+
+impl Command for SpawnControlPanel {
+    fn apply(self, world: &mut World) {
+        let plate_mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::from_size(PANEL_SIZE));
+        let plate_material =
+            world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(StandardMaterial {
+                    base_color: Color::srgb(0.25, 0.27, 0.3),
+                    perceptual_roughness: 0.6,
+                    ..default()
+                });
+
+        let layout: Handle<PanelLayout> = world.resource::<AssetServer>().load(self.layout);
+        let panel = world
+            .spawn((
+                Name::new("Control panel"),
+                PbrBundle {
+                    mesh: plate_mesh,
+                    material: plate_material,
+                    transform: self.transform,
+                    ..default()
+                },
+                ControlPanel,
+                Obstacle,
+                layout,
+                BusDevice {
+                    computer: self.computer,
+                    port: self.port,
+                    registers: Port::shared(),
+                },
+            ))
+            .id();
+        // Along the bottom edge, so the cable hangs down out of the way
+        SpawnSocket {
+            owner: panel,
+            parent: panel,
+            name: "PANEL".to_owned(),
+            kind: PortKind::Parallel,
+            port: None,
+            transform: Transform::from_xyz(0.0, -PANEL_SIZE.y / 2.0 - 0.003, 0.0),
+        }
+        .apply(world);
+    }
+}
+
+// A control panel for the diagnostics computer, on port 13 ($E8D0), with switches, buttons
+// and a knob for the player, and lights, a display and a gauge for programs
+fn fit_control_panel(mut commands: Commands, computers: Res<ShipComputers>) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_control_panel(
+        computer,
+        13,
+        Transform::from_xyz(0.3, 1.2, -0.45).with_rotation(Quat::from_rotation_x(-PI / 4.0)),
+        "panels/diagnostics.panel.ron",
+    );
+}
+
+// What controls look like. The round ones are turned to face out of the panel.
+#[derive(Resource)]
+struct PanelAssets {
+    lever: Handle<Mesh>,
+    button: Handle<Mesh>,
+    knob: Handle<Mesh>,
+    pointer: Handle<Mesh>,
+    dial: Handle<Mesh>,
+    needle: Handle<Mesh>,
+    led: Handle<Mesh>,
+    display: Handle<Mesh>,
+    across: Handle<Mesh>,
+    upright: Handle<Mesh>,
+    metal: Handle<StandardMaterial>,
+    red: Handle<StandardMaterial>,
+    black: Handle<StandardMaterial>,
+    white: Handle<StandardMaterial>,
+    off: Handle<StandardMaterial>,
+    on: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PanelAssets {
+    fn from_world(world: &mut World) -> Self {
+        let facing_out = Quat::from_rotation_x(FRAC_PI_2);
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let lever = meshes.add(
+            Mesh::from(Cuboid::new(0.006, 0.006, LEVER_LENGTH))
+                .translated_by(Vec3::Z * LEVER_LENGTH / 2.0),
+        );
+        let button = meshes.add(
+            Mesh::from(Cylinder::new(0.01, 0.01))
+                .rotated_by(facing_out)
+                .translated_by(Vec3::Z * 0.005),
+        );
+        let knob = meshes.add(
+            Mesh::from(Cylinder::new(0.016, 0.015))
+                .rotated_by(facing_out)
+                .translated_by(Vec3::Z * 0.0075),
+        );
+        let pointer = meshes.add(Cuboid::new(0.003, 0.01, 0.002));
+        let dial = meshes.add(
+            Mesh::from(Cylinder::new(0.03, 0.004))
+                .rotated_by(facing_out)
+                .translated_by(Vec3::Z * 0.002),
+        );
+        let needle = meshes
+            .add(Mesh::from(Cuboid::new(0.002, 0.025, 0.001)).translated_by(Vec3::Y * 0.0125));
+        let led = meshes.add(Sphere::new(0.005).mesh().uv(12, 6));
+        let display =
+            meshes.add(Mesh::from(Cuboid::new(0.024, 0.042, 0.004)).translated_by(Vec3::Z * 0.002));
+        let across = meshes.add(Cuboid::new(0.01, 0.0025, 0.001));
+        let upright = meshes.add(Cuboid::new(0.0025, 0.0125, 0.001));
+
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let mut colour = |base_color: Color| {
+            materials.add(StandardMaterial {
+                base_color,
+                perceptual_roughness: 0.4,
+                ..default()
+            })
+        };
+        let metal = colour(Color::srgb(0.8, 0.8, 0.8));
+        let red = colour(Color::srgb(0.7, 0.05, 0.05));
+        let black = colour(Color::srgb(0.03, 0.03, 0.03));
+        let white = colour(Color::srgb(0.95, 0.95, 0.9));
+        let off = colour(Color::srgb(0.15, 0.02, 0.02));
+        let on = materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.1, 0.05),
+            emissive: LinearRgba::rgb(6.0, 0.4, 0.2),
+            ..default()
+        });
+
+        Self {
+            lever,
+            button,
+            knob,
+            pointer,
+            dial,
+            needle,
+            led,
+            display,
+            across,
+            upright,
+            metal,
+            red,
+            black,
+            white,
+            off,
+            on,
+        }
+    }
+}
+
+// A panel, with whatever's already on it
+type PanelParts = (
+    Entity,
+    &'static Handle<PanelLayout>,
+    &'static Name,
+    Option<&'static Children>,
+);
+
+// Puts the controls on each panel as its layout says
+fn apply_panel_layouts(
+    mut commands: Commands,
+    mut evr_layouts: EventReader<AssetEvent<PanelLayout>>,
+    layouts: Res<Assets<PanelLayout>>,
+    assets: Res<PanelAssets>,
+    panels: Query<PanelParts, With<ControlPanel>>,
+    controls: Query<(), With<Control>>,
+) {
+    for ev in evr_layouts.read() {
+        // Modified is what we get when the file is hot-reloaded
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = ev else {
+            continue;
+        };
+        let Some(layout) = layouts.get(*id) else {
+            continue;
+        };
+
+        for (panel, handle, name, children) in panels.iter() {
+            if handle.id() != *id {
+                continue;
+            }
+            if let Err(error) = layout.check() {
+                warn!("Couldn't lay out {}: {}", name, error);
+                continue;
+            }
+
+            for &child in children.into_iter().flatten() {
+                if controls.contains(child) {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+            commands.entity(panel).with_children(|panel| {
+                for control in layout.controls.iter() {
+                    spawn_control(panel, &assets, control);
+                }
+            });
+        }
+    }
+}
+
+fn spawn_control(panel: &mut ChildBuilder, assets: &PanelAssets, layout: &ControlLayout) {
+    let control = Control {
+        kind: layout.kind,
+        register: layout.register,
+        bit: layout.bit,
+        state: 0,
+        at: Vec3::new(layout.at.0, layout.at.1, PANEL_SIZE.z / 2.0),
+    };
+    let (mesh, material) = match layout.kind {
+        ControlKind::ToggleSwitch => (&assets.lever, &assets.metal),
+        ControlKind::PushButton => (&assets.button, &assets.red),
+        ControlKind::RotaryKnob { .. } => (&assets.knob, &assets.black),
+        ControlKind::NeedleGauge => (&assets.dial, &assets.white),
+        ControlKind::Led => (&assets.led, &assets.off),
+        ControlKind::SevenSegment => (&assets.display, &assets.black),
+    };
+    let mut entity = panel.spawn((
+        PbrBundle {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            transform: control.pose(),
+            ..default()
+        },
+        control,
+        Interactable,
+    ));
+
+    // The bits that aren't worth looking at on their own
+    entity.with_children(|parts| match layout.kind {
+        ControlKind::RotaryKnob { .. } => {
+            parts.spawn(PbrBundle {
+                mesh: assets.pointer.clone(),
+                material: assets.white.clone(),
+                transform: Transform::from_xyz(0.0, 0.009, 0.0155),
+                ..default()
+            });
+        }
+        ControlKind::NeedleGauge => {
+            parts.spawn((
+                PbrBundle {
+                    mesh: assets.needle.clone(),
+                    material: assets.black.clone(),
+                    transform: needle_pose(0),
+                    ..default()
+                },
+                Needle,
+            ));
+        }
+        ControlKind::SevenSegment => {
+            for (idx, &(x, y, upright)) in SEGMENTS.iter().enumerate() {
+                parts.spawn((
+                    PbrBundle {
+                        mesh: if upright {
+                            assets.upright.clone()
+                        } else {
+                            assets.across.clone()
+                        },
+                        material: assets.off.clone(),
+                        transform: Transform::from_xyz(x, y, 0.0045),
+                        ..default()
+                    },
+                    Segment(idx as u8),
+                ));
+            }
+        }
+        _ => {}
+    });
+}
+
+impl Control {
+    // Where it sits on the panel, moved however its state says
+    fn pose(&self) -> Transform {
+        let at = Transform::from_translation(self.at);
+        match self.kind {
+            // Leaning up when it's on, and down when it's off
+            ControlKind::ToggleSwitch => {
+                let lean = if self.state != 0 { -1.0 } else { 1.0 };
+                at.with_rotation(Quat::from_rotation_x(lean * LEVER_THROW))
+            }
+            ControlKind::PushButton if self.state != 0 => {
+                at.with_translation(self.at - Vec3::Z * BUTTON_TRAVEL)
+            }
+            ControlKind::RotaryKnob { positions } => {
+                let turned = self.state as f32 / (positions - 1) as f32;
+                at.with_rotation(Quat::from_rotation_z((0.5 - turned) * KNOB_SWEEP))
+            }
+            _ => at,
+        }
+    }
+}
+
+fn needle_pose(value: u8) -> Transform {
+    let swung = value as f32 / 255.0;
+    Transform::from_xyz(0.0, -0.01, 0.0045)
+        .with_rotation(Quat::from_rotation_z((0.5 - swung) * NEEDLE_SWEEP))
+}
+
+// Clicking flips switches and turns knobs, and holding the mouse button (or E) down holds
+// buttons in
+fn operate_controls(
+    mut evr_started: EventReader<InteractStarted>,
+    mut evr_ended: EventReader<InteractEnded>,
+    mut controls: Query<(&mut Control, &Parent)>,
+    panels: Query<&GlobalTransform, With<ControlPanel>>,
+) {
+    for ev in evr_started.read() {
+        let Ok((mut control, parent)) = controls.get_mut(ev.entity) else {
+            continue;
+        };
+        match control.kind {
+            ControlKind::ToggleSwitch => control.state ^= 1,
+            ControlKind::PushButton => control.state = 1,
+            // Whichever side of the middle it was clicked on is the way it turns
+            ControlKind::RotaryKnob { positions } => {
+                let Ok(panel) = panels.get(parent.get()) else {
+                    continue;
+                };
+                let on_panel = panel.affine().inverse().transform_point3(ev.point);
+                control.state = if on_panel.x > control.at.x {
+                    (control.state + 1).min(positions - 1)
+                } else {
+                    control.state.saturating_sub(1)
+                };
+            }
+            _ => {}
+        }
+    }
+
+    for ev in evr_ended.read() {
+        if let Ok((mut control, _)) = controls.get_mut(ev.entity) {
+            if control.kind == ControlKind::PushButton {
+                control.state = 0;
+            }
+        }
+    }
+}
+
+// Sets the bits the player's controls are wired to, and picks up what the computer's put in
+// everyone else's
+fn update_controls(
+    panels: Query<&BusDevice, With<ControlPanel>>,
+    mut controls: Query<(&mut Control, &Parent)>,
+) {
+    for (mut control, parent) in controls.iter_mut() {
+        let Ok(device) = panels.get(parent.get()) else {
+            continue;
+        };
+        let mut port = device.registers.lock().expect("Port lock");
+        let register = &mut port.registers[control.register];
+        let mask = bits(control.bit, control.kind.width());
+        if control.kind.is_input() {
+            *register = (*register & !mask) | (control.state << control.bit & mask);
+        } else {
+            let showing = (*register & mask) >> control.bit;
+            // Only when it's changed, so it only gets redrawn when it needs to
+            if control.state != showing {
+                control.state = showing;
+            }
+        }
+    }
+}
+
+fn show_controls(
+    assets: Res<PanelAssets>,
+    mut controls: Query<(Entity, &Control, &mut Transform, Option<&Children>), Changed<Control>>,
+    mut needles: Query<&mut Transform, (With<Needle>, Without<Control>)>,
+    mut lights: Query<(&mut Handle<StandardMaterial>, Option<&Segment>)>,
+) {
+    let lit = |on: bool| {
+        if on {
+            assets.on.clone()
+        } else {
+            assets.off.clone()
+        }
+    };
+
+    for (entity, control, mut transform, children) in controls.iter_mut() {
+        *transform = control.pose();
+        let children = children.into_iter().flatten();
+        match control.kind {
+            ControlKind::NeedleGauge => {
+                for &child in children {
+                    if let Ok(mut needle) = needles.get_mut(child) {
+                        *needle = needle_pose(control.state);
+                    }
+                }
+            }
+            ControlKind::Led => {
+                if let Ok((mut material, _)) = lights.get_mut(entity) {
+                    *material = lit(control.state != 0);
+                }
+            }
+            ControlKind::SevenSegment => {
+                let segments = DIGITS[control.state as usize & 0xf];
+                for &child in children {
+                    if let Ok((mut material, Some(Segment(idx)))) = lights.get_mut(child) {
+                        *material = lit(segments & 1 << idx != 0);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::computer::bus::SharedPort;
+    use crate::interaction::InteractedBy;
+
+    fn control(kind: ControlKind, register: usize, bit: u8) -> ControlLayout {
+        ControlLayout {
+            kind,
+            register,
+            bit,
+            at: (0.0, 0.0),
+        }
+    }
+
+    fn check(controls: Vec<ControlLayout>) -> Result<(), PanelError> {
+        PanelLayout { controls }.check()
+    }
+
+    // A panel plugged into nothing in particular, with the controls on it
+    fn panel(
+        world: &mut World,
+        controls: &[(ControlKind, usize, u8)],
+    ) -> (Vec<Entity>, SharedPort) {
+        let registers = Port::shared();
+        let panel = world
+            .spawn((
+                ControlPanel,
+                BusDevice {
+                    computer: Entity::PLACEHOLDER,
+                    port: 0,
+                    registers: registers.clone(),
+                },
+                GlobalTransform::IDENTITY,
+            ))
+            .id();
+        let controls = controls
+            .iter()
+            .map(|&(kind, register, bit)| {
+                let control = Control {
+                    kind,
+                    register,
+                    bit,
+                    state: 0,
+                    at: Vec3::ZERO,
+                };
+                world.spawn(control).set_parent(panel).id()
+            })
+            .collect();
+        (controls, registers)
+    }
+
+    fn state(world: &World, control: Entity) -> u8 {
+        world.get::<Control>(control).unwrap().state
+    }
+
+    #[test]
+    fn knobs_take_as_many_bits_as_their_positions_need() {
+        let widths: Vec<u8> = [2, 3, 4, 5, 8, 9, 16]
+            .into_iter()
+            .map(|positions| ControlKind::RotaryKnob { positions }.width())
+            .collect();
+        assert_eq!(widths, [1, 2, 2, 3, 3, 4, 4]);
+    }
+
+    #[test]
+    fn panels_that_cant_be_wired_up_are_turned_down() {
+        let layout: PanelLayout =
+            ron::from_str(include_str!("../assets/panels/diagnostics.panel.ron")).unwrap();
+        assert!(layout.check().is_ok());
+
+        let knob = |positions| ControlKind::RotaryKnob { positions };
+        assert!(matches!(
+            check(vec![control(knob(1), 0, 0)]),
+            Err(PanelError::KnobPositions(1))
+        ));
+        assert!(matches!(
+            check(vec![control(ControlKind::Led, PORT_SIZE, 0)]),
+            Err(PanelError::NoSuchRegister(PORT_SIZE))
+        ));
+        assert!(matches!(
+            check(vec![control(ControlKind::SevenSegment, 0, 5)]),
+            Err(PanelError::DoesntFit { bit: 5, width: 4 })
+        ));
+        assert!(matches!(
+            check(vec![
+                control(ControlKind::ToggleSwitch, 0, 2),
+                control(knob(8), 0, 1),
+            ]),
+            Err(PanelError::Clash {
+                register: 0,
+                bit: 2
+            })
+        ));
+        // Anything can show a bit the player's setting
+        assert!(check(vec![
+            control(ControlKind::ToggleSwitch, 0, 2),
+            control(ControlKind::Led, 0, 2),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn controls_set_their_own_bits_and_show_everyone_elses() {
+        let mut world = World::new();
+        let (controls, registers) = panel(
+            &mut world,
+            &[
+                (ControlKind::ToggleSwitch, 0, 0),
+                (ControlKind::RotaryKnob { positions: 4 }, 0, 1),
+                (ControlKind::Led, 1, 3),
+                (ControlKind::SevenSegment, 1, 4),
+            ],
+        );
+        world.get_mut::<Control>(controls[0]).unwrap().state = 1;
+        world.get_mut::<Control>(controls[1]).unwrap().state = 2;
+        registers.lock().unwrap().registers[..2].copy_from_slice(&[0xff, 0xa8]);
+
+        world.run_system_once(update_controls);
+        // The switch and the knob leave the bits that aren't theirs alone
+        assert_eq!(registers.lock().unwrap().registers[0], 0xfd);
+        assert_eq!(
+            (state(&world, controls[2]), state(&world, controls[3])),
+            (1, 0xa)
+        );
+    }
+
+    #[test]
+    fn clicking_flips_switches_holds_buttons_and_turns_knobs() {
+        let mut world = World::new();
+        world.init_resource::<Events<InteractStarted>>();
+        world.init_resource::<Events<InteractEnded>>();
+        let (controls, _) = panel(
+            &mut world,
+            &[
+                (ControlKind::ToggleSwitch, 0, 0),
+                (ControlKind::PushButton, 0, 1),
+                (ControlKind::RotaryKnob { positions: 3 }, 0, 2),
+            ],
+        );
+        let click = |world: &mut World, entity, x| {
+            world.send_event(InteractStarted {
+                entity,
+                point: Vec3::new(x, 0.0, 0.0),
+                by: InteractedBy::Mouse,
+            });
+            world.run_system_once(operate_controls);
+            // Each run's a new system, which would see the same events all over again
+            world.resource_mut::<Events<InteractStarted>>().clear();
+        };
+        let let_go = |world: &mut World, entity| {
+            world.send_event(InteractEnded { entity });
+            world.run_system_once(operate_controls);
+            world.resource_mut::<Events<InteractEnded>>().clear();
+        };
+
+        click(&mut world, controls[0], 0.0);
+        click(&mut world, controls[1], 0.0);
+        assert_eq!(
+            (state(&world, controls[0]), state(&world, controls[1])),
+            (1, 1)
+        );
+        let_go(&mut world, controls[0]);
+        let_go(&mut world, controls[1]);
+        assert_eq!(
+            (state(&world, controls[0]), state(&world, controls[1])),
+            (1, 0)
+        );
+
+        // Clockwise on the right, as far as it goes, and anticlockwise on the left
+        let mut positions = Vec::new();
+        for x in [0.01, 0.01, 0.01, -0.01] {
+            click(&mut world, controls[2], x);
+            positions.push(state(&world, controls[2]));
+        }
+        assert_eq!(positions, [1, 2, 2, 1]);
+    }
+}
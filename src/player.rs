@@ -1,12 +1,17 @@
 use bevy::{input::mouse::MouseMotion, prelude::*, window::CursorGrabMode};
 
-use crate::{core::system_sets::SpawningSet, interaction::Interactor};
+use crate::{console::ControlMode, core::system_sets::SpawningSet, interaction::Interactor};
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_player.in_set(SpawningSet));
-        app.add_systems(Update, (camera_mouse_capturing, camera_looking, player_movement));
+        // While sat at a console, the keyboard and mouse belong to the computer
+        app.add_systems(
+            Update,
+            (camera_mouse_capturing, camera_looking, player_movement)
+                .run_if(in_state(ControlMode::FreeLook)),
+        );
     }
 }
 
@@ -27,6 +32,8 @@ fn setup_player(
             vert_look_sensitivity: 0.7,
             move_speed: 3.0,
         },
+        // So screens can show where the player is
+        Name::new("Player"),
         TransformBundle::default(),
     )).id();
 
@@ -0,0 +1,927 @@
+use std::collections::HashMap;
+
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+
+use crate::cabling::{PortKind, SpawnSocketExt};
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::interaction::Obstacle;
+
+// The ship's electrical system. Generators and batteries feed buses, and everything that
+// needs power (computers included) draws it from one of them. The whole grid gets solved
+// every fixed tick, so it behaves the same whatever the frame rate.
+pub struct PowerPlugin;
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<PowerBus>()
+            .register_type::<Generator>()
+            .register_type::<Battery>()
+            .register_type::<PowerConsumer>();
+        app.add_systems(Startup, setup_power_grid.in_set(SpawningSet));
+        app.add_systems(
+            FixedUpdate,
+            (reset_breakers, solve_power_grid, update_power_monitors).chain(),
+        );
+        app.add_systems(Startup, fit_power_monitor.in_set(FittingSet));
+        app.add_sample_file("POWER.BAS", include_str!("../assets/programs/POWER.BAS"))
+            .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// Where the power monitor is, for README.TXT
+const README_PARAGRAPH: &str = "\
+POWER reads the diagnostics computer's power monitor, on
+port 1.
+";
+
+// How long a shed load stays off before it gets another go, in seconds. Without this, a
+// load on the edge of what the grid can manage flickers on and off every tick.
+const RESTORE_DELAY: f32 = 5.0;
+
+// Once a battery's been run flat, it won't give any more until it's been charged back up to
+// this fraction of its capacity
+const BATTERY_RECONNECT: f32 = 0.2;
+
+// How many sockets there are for plugging things into the main bus
+const OUTLETS: usize = 6;
+
+// The ship's buses, for anything that needs to plug into one when it's spawned
+#[derive(Resource)]
+pub struct ShipGrid {
+    pub main_bus: Entity,
+}
+
+// Somewhere for power to go. Everything on it is connected together, and the readings are
+// worked out again every tick.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct PowerBus {
+    pub nominal_voltage: f32,
+    pub voltage: f32,
+    pub state: BusState,
+    // Everything below is in watts
+    pub generation: f32,
+    // What the consumers want
+    pub demand: f32,
+    // What they're getting
+    pub load: f32,
+    // Going into the batteries if positive, coming out of them if negative
+    pub battery_flow: f32,
+    // How full the batteries are between them, from 0 to 1
+    pub battery_charge: f32,
+    // How many consumers have been shed
+    pub shed: u32,
+    // How many breakers on the bus have tripped
+    pub tripped: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BusState {
+    // The generators are keeping up
+    Normal,
+    // The batteries are making up the difference
+    OnBattery,
+    // Some loads have been switched off so that the rest can run
+    Shedding,
+    // Not enough power even for the essentials, so everything's getting less than it wants
+    BrownOut,
+    // No power at all
+    Dead,
+}
+
+// Sits between something and its bus. Opens by itself if more power goes through it than
+// it's rated for, and stays open until it's reset.
+#[derive(Debug, Clone, Reflect)]
+pub struct Breaker {
+    pub closed: bool,
+    // In watts
+    pub rating: f32,
+    // There's a fuse behind it too, which only goes if something's really wrong. Once it's
+    // blown, resetting the breaker doesn't help; it needs replacing.
+    pub blown: bool,
+}
+
+impl Breaker {
+    pub fn rated(rating: f32) -> Self {
+        Self {
+            closed: true,
+            rating,
+            blown: false,
+        }
+    }
+
+    pub fn blow(&mut self) {
+        self.blown = true;
+        self.closed = false;
+    }
+
+    fn reset(&mut self) {
+        self.closed = !self.blown;
+    }
+
+    // Trips the breaker if `power` is too much for it. Returns whether it's still closed.
+    fn carry(&mut self, power: f32) -> bool {
+        if power > self.rating {
+            self.closed = false;
+        }
+        self.closed
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Generator {
+    pub bus: Entity,
+    pub breaker: Breaker,
+    pub online: bool,
+    // The most it can put out, in watts
+    pub rated_output: f32,
+    // What it's actually putting out. Generators only make as much as is being used.
+    pub output: f32,
+}
+
+impl Generator {
+    pub fn new(bus: Entity, rated_output: f32) -> Self {
+        Self {
+            bus,
+            breaker: Breaker::rated(rated_output * 1.5),
+            online: true,
+            rated_output,
+            output: 0.0,
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Battery {
+    pub bus: Entity,
+    pub breaker: Breaker,
+    // In joules
+    pub capacity: f32,
+    pub charge: f32,
+    // The fastest it'll charge or discharge, in watts
+    pub max_rate: f32,
+    // Into the battery if positive, out of it if negative
+    pub flow: f32,
+    // Whether it's been run flat and cut out
+    pub flat: bool,
+}
+
+impl Battery {
+    // Starts off full
+    pub fn new(bus: Entity, capacity: f32, max_rate: f32) -> Self {
+        Self {
+            bus,
+            breaker: Breaker::rated(max_rate * 1.5),
+            capacity,
+            charge: capacity,
+            max_rate,
+            flow: 0.0,
+            flat: false,
+        }
+    }
+
+    // The most it could give this tick
+    fn available(&self, delta: f32) -> f32 {
+        if !self.breaker.closed || self.flat {
+            return 0.0;
+        }
+        self.max_rate.min(self.charge / delta)
+    }
+
+    // The most it could take this tick
+    fn headroom(&self, delta: f32) -> f32 {
+        if !self.breaker.closed {
+            return 0.0;
+        }
+        self.max_rate.min((self.capacity - self.charge) / delta)
+    }
+}
+
+// When there isn't enough power to go round, the least important loads are switched off
+// first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+pub enum LoadPriority {
+    Low,
+    Normal,
+    // Never shed; these brown out instead
+    Essential,
+}
+
+// Anything that runs on electricity
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct PowerConsumer {
+    pub bus: Entity,
+    pub breaker: Breaker,
+    pub priority: LoadPriority,
+    // What it wants, in watts
+    pub demand: f32,
+    // How much of that it's getting, from 0 to 1
+    pub supply: f32,
+    pub state: PowerState,
+    // Whether it's plugged in to `bus` at all. Only ever false for things with a power
+    // socket, when there's no cable in it.
+    pub connected: bool,
+    // How long until it can come back on, if it's been shed
+    restore_in: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PowerState {
+    Powered,
+    BrownedOut,
+    Shed,
+    // Either the breaker's open, it's unplugged, or there's nothing on the bus
+    Unpowered,
+}
+
+impl PowerConsumer {
+    // Starts off powered, until the grid's had a chance to say otherwise
+    pub fn new(bus: Entity, demand: f32, priority: LoadPriority) -> Self {
+        Self {
+            bus,
+            breaker: Breaker::rated(demand * 1.5),
+            priority,
+            demand,
+            supply: 1.0,
+            state: PowerState::Powered,
+            connected: true,
+            restore_in: 0.0,
+        }
+    }
+}
+
+// Reports on a bus through a computer port, and lets the computer reset its breakers.
+// Registers:
+// - 0: the bus's state: 0 normal, 1 on battery, 2 shedding, 3 brown-out, 4 dead
+// - 1: the voltage, in volts
+// - 2-3, 4-5, 6-7: generation, demand and load, in watts (16-bit, low byte first)
+// - 8: battery charge, as a percentage
+// - 9: how many consumers have been shed
+// - 10: how many breakers have tripped
+// - 15: write anything but 0 to reset every breaker on the bus
+#[derive(Component)]
+pub struct PowerMonitor {
+    pub bus: Entity,
+}
+
+const MONITOR_RESET: usize = 15;
+
+// Plugs a power monitor for `bus` into one of a computer's ports.
+// Use `Commands::spawn_power_monitor` rather than adding this directly.
+pub struct SpawnPowerMonitor {
+    pub computer: Entity,
+    pub port: usize,
+    pub bus: Entity,
+}
+
+pub trait SpawnPowerMonitorExt {
+    fn spawn_power_monitor(&mut self, computer: Entity, port: usize, bus: Entity);
+}
+
+impl SpawnPowerMonitorExt for Commands<'_, '_> {
+    fn spawn_power_monitor(&mut self, computer: Entity, port: usize, bus: Entity) {
+        self.add(SpawnPowerMonitor {
+            computer,
+            port,
+            bus,
+        });
+    }
+}
+
+impl Command for SpawnPowerMonitor {
+    fn apply(self, world: &mut World) {
+        // A card inside the computer, so there's nothing to see
+        world.spawn((
+            PowerMonitor { bus: self.bus },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+pub fn setup_power_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let main_bus = commands
+        .spawn((
+            Name::new("Main bus"),
+            PowerBus {
+                nominal_voltage: 28.0,
+                voltage: 28.0,
+                state: BusState::Normal,
+                generation: 0.0,
+                demand: 0.0,
+                load: 0.0,
+                battery_flow: 0.0,
+                battery_charge: 1.0,
+                shed: 0,
+                tripped: 0,
+            },
+        ))
+        .id();
+
+    // Not quite enough to run everything (life support included), so the battery slowly runs
+    // down, and once it's flat the galley gets shed
+    commands.spawn((Name::new("Fuel cell"), Generator::new(main_bus, 1000.0)));
+    let mut battery = Battery::new(main_bus, 360_000.0, 600.0);
+    battery.charge *= 0.8;
+    commands.spawn((Name::new("Main battery"), battery));
+
+    commands.spawn((
+        Name::new("Cabin lights"),
+        PowerConsumer::new(main_bus, 120.0, LoadPriority::Normal),
+    ));
+    commands.spawn((
+        Name::new("Galley"),
+        PowerConsumer::new(main_bus, 250.0, LoadPriority::Low),
+    ));
+
+    // Somewhere under the consoles to plug them in
+    let outlets = commands
+        .spawn((
+            Name::new("Main bus outlets"),
+            PbrBundle {
+                mesh: meshes.add(Cuboid::new(0.3, 0.08, 0.03)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::srgb(0.3, 0.3, 0.3),
+                    ..default()
+                }),
+                transform: Transform::from_xyz(-0.3, 0.3, -0.6),
+                ..default()
+            },
+            Obstacle,
+        ))
+        .id();
+    for idx in 0..OUTLETS {
+        let x = (idx as f32 + 0.5) * 0.3 / OUTLETS as f32 - 0.15;
+        commands.spawn_socket(
+            main_bus,
+            outlets,
+            &format!("OUTLET {}", idx + 1),
+            PortKind::Power,
+            Transform::from_xyz(x, 0.0, 0.018),
+        );
+    }
+
+    commands.insert_resource(ShipGrid { main_bus });
+}
+
+// A way for the diagnostics computer to keep an eye on the power, on port 1 (address $E810)
+fn fit_power_monitor(mut commands: Commands, computers: Res<ShipComputers>, grid: Res<ShipGrid>) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_power_monitor(computer, 1, grid.main_bus);
+}
+
+// Closes the breakers on any bus whose monitor has been asked to
+fn reset_breakers(
+    monitors: Query<(&PowerMonitor, &BusDevice)>,
+    mut generators: Query<&mut Generator>,
+    mut batteries: Query<&mut Battery>,
+    mut consumers: Query<&mut PowerConsumer>,
+) {
+    for (monitor, device) in monitors.iter() {
+        {
+            let mut port = device.registers.lock().expect("Port lock");
+            if port.registers[MONITOR_RESET] == 0 {
+                continue;
+            }
+            port.registers[MONITOR_RESET] = 0;
+        }
+
+        for mut generator in generators.iter_mut() {
+            if generator.bus == monitor.bus {
+                generator.breaker.reset();
+            }
+        }
+        for mut battery in batteries.iter_mut() {
+            if battery.bus == monitor.bus {
+                battery.breaker.reset();
+            }
+        }
+        for mut consumer in consumers.iter_mut() {
+            if consumer.bus == monitor.bus {
+                consumer.breaker.reset();
+            }
+        }
+    }
+}
+
+// What's on a bus this tick, gathered up before working out who gets what
+#[derive(Default)]
+struct BusTotals {
+    generation: f32,
+    battery_available: f32,
+    battery_headroom: f32,
+    battery_charge: f32,
+    battery_capacity: f32,
+    // Consumers with their breakers closed: (entity, demand, priority, still waiting to be
+    // restored)
+    loads: Vec<(Entity, f32, LoadPriority, bool)>,
+    tripped: u32,
+}
+
+// What's been decided for a bus
+struct BusSolution {
+    // The fraction of their demand that the loads still connected get
+    supply: f32,
+    shed: Vec<Entity>,
+    // The fraction of what they could do that the generators and batteries are doing
+    generator_use: f32,
+    discharge: f32,
+    charge: f32,
+}
+
+fn solve_power_grid(
+    time: Res<Time>,
+    mut buses: Query<(Entity, &mut PowerBus)>,
+    mut generators: Query<&mut Generator>,
+    mut batteries: Query<&mut Battery>,
+    mut consumers: Query<(Entity, &mut PowerConsumer)>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    let mut totals: HashMap<Entity, BusTotals> = HashMap::new();
+
+    for generator in generators.iter() {
+        let bus = totals.entry(generator.bus).or_default();
+        if !generator.breaker.closed {
+            bus.tripped += 1;
+        } else if generator.online {
+            bus.generation += generator.rated_output;
+        }
+    }
+    for battery in batteries.iter() {
+        let bus = totals.entry(battery.bus).or_default();
+        if !battery.breaker.closed {
+            bus.tripped += 1;
+        }
+        bus.battery_available += battery.available(delta);
+        bus.battery_headroom += battery.headroom(delta);
+        bus.battery_charge += battery.charge;
+        bus.battery_capacity += battery.capacity;
+    }
+    for (entity, mut consumer) in consumers.iter_mut() {
+        if !consumer.connected {
+            continue;
+        }
+        let bus = totals.entry(consumer.bus).or_default();
+        let demand = consumer.demand;
+        if !consumer.breaker.carry(demand) {
+            bus.tripped += 1;
+            continue;
+        }
+        consumer.restore_in = (consumer.restore_in - delta).max(0.0);
+        let waiting = consumer.state == PowerState::Shed && consumer.restore_in > 0.0;
+        bus.loads.push((entity, demand, consumer.priority, waiting));
+    }
+
+    let solutions: HashMap<Entity, BusSolution> = totals
+        .iter()
+        .map(|(bus, totals)| (*bus, solve_bus(totals)))
+        .collect();
+
+    // Put the solutions into practice, and let everything know how it's doing
+    for (entity, mut consumer) in consumers.iter_mut() {
+        if !consumer.connected || !consumer.breaker.closed {
+            consumer.supply = 0.0;
+            consumer.state = PowerState::Unpowered;
+            continue;
+        }
+        let Some(solution) = solutions.get(&consumer.bus) else {
+            continue;
+        };
+        if solution.shed.contains(&entity) {
+            // Either it's only just been shed, or it's had its chance to come back and there
+            // still isn't enough to go round
+            if consumer.restore_in <= 0.0 {
+                consumer.restore_in = RESTORE_DELAY;
+            }
+            consumer.supply = 0.0;
+            consumer.state = PowerState::Shed;
+        } else {
+            consumer.supply = solution.supply;
+            consumer.state = if solution.supply <= 0.0 {
+                PowerState::Unpowered
+            } else if solution.supply < 1.0 {
+                PowerState::BrownedOut
+            } else {
+                PowerState::Powered
+            };
+        }
+    }
+    for mut generator in generators.iter_mut() {
+        let use_fraction = solutions
+            .get(&generator.bus)
+            .map_or(0.0, |solution| solution.generator_use);
+        let output = if generator.breaker.closed && generator.online {
+            generator.rated_output * use_fraction
+        } else {
+            0.0
+        };
+        generator.output = if generator.breaker.carry(output) {
+            output
+        } else {
+            0.0
+        };
+    }
+    for mut battery in batteries.iter_mut() {
+        let Some(solution) = solutions.get(&battery.bus) else {
+            continue;
+        };
+        let flow = battery.headroom(delta) * solution.charge
+            - battery.available(delta) * solution.discharge;
+        if !battery.breaker.carry(flow.abs()) {
+            battery.flow = 0.0;
+            continue;
+        }
+        battery.flow = flow;
+        battery.charge = (battery.charge + flow * delta).clamp(0.0, battery.capacity);
+        if battery.charge <= 0.0 {
+            battery.flat = true;
+        } else if battery.charge >= battery.capacity * BATTERY_RECONNECT {
+            battery.flat = false;
+        }
+    }
+
+    for (entity, mut bus) in buses.iter_mut() {
+        let (Some(totals), Some(solution)) = (totals.get(&entity), solutions.get(&entity)) else {
+            continue;
+        };
+        let demand: f32 = totals.loads.iter().map(|(_, demand, ..)| demand).sum();
+        let connected: f32 = totals
+            .loads
+            .iter()
+            .filter(|(load, ..)| !solution.shed.contains(load))
+            .map(|(_, demand, ..)| demand)
+            .sum();
+        let discharging = solution.discharge > 0.0;
+
+        bus.generation = totals.generation * solution.generator_use;
+        bus.demand = demand;
+        bus.load = connected * solution.supply;
+        bus.battery_flow = totals.battery_headroom * solution.charge
+            - totals.battery_available * solution.discharge;
+        bus.battery_charge = if totals.battery_capacity > 0.0 {
+            totals.battery_charge / totals.battery_capacity
+        } else {
+            0.0
+        };
+        bus.shed = solution.shed.len() as u32;
+        bus.tripped = totals.tripped;
+        bus.voltage = bus.nominal_voltage * solution.supply;
+        bus.state = if solution.supply <= 0.0 && demand > 0.0 {
+            BusState::Dead
+        } else if solution.supply < 1.0 {
+            BusState::BrownOut
+        } else if !solution.shed.is_empty() {
+            BusState::Shedding
+        } else if discharging {
+            BusState::OnBattery
+        } else {
+            BusState::Normal
+        };
+    }
+}
+
+// Works out who gets what on one bus. Loads get shed, least important first, until what's
+// left fits in what the generators and batteries can supply. If even the essentials don't
+// fit, everyone gets the same share of what there is.
+fn solve_bus(totals: &BusTotals) -> BusSolution {
+    let available = totals.generation + totals.battery_available;
+
+    // Loads that were shed recently stay off for a while whatever happens
+    let mut shed: Vec<Entity> = totals
+        .loads
+        .iter()
+        .filter(|(.., waiting)| *waiting)
+        .map(|(load, ..)| *load)
+        .collect();
+    let mut demand: f32 = totals
+        .loads
+        .iter()
+        .filter(|(.., waiting)| !waiting)
+        .map(|(_, demand, ..)| demand)
+        .sum();
+
+    let mut candidates: Vec<_> = totals
+        .loads
+        .iter()
+        .filter(|(_, _, priority, waiting)| !waiting && *priority != LoadPriority::Essential)
+        .collect();
+    candidates.sort_by_key(|(_, _, priority, _)| *priority);
+    for (load, load_demand, ..) in candidates {
+        if demand <= available {
+            break;
+        }
+        shed.push(*load);
+        demand -= load_demand;
+    }
+
+    let supply = if demand <= available {
+        1.0
+    } else {
+        available / demand
+    };
+    let delivered = demand * supply;
+
+    // The generators go first, and the batteries make up any difference or soak up any
+    // spare
+    let from_generators = delivered.min(totals.generation);
+    let spare = totals.generation - from_generators;
+    let charging = spare.min(totals.battery_headroom);
+    let discharging = delivered - from_generators;
+
+    BusSolution {
+        supply,
+        shed,
+        generator_use: ratio(from_generators + charging, totals.generation),
+        discharge: ratio(discharging, totals.battery_available),
+        charge: ratio(charging, totals.battery_headroom),
+    }
+}
+
+// `part / whole`, but 0 if there's no whole to speak of
+fn ratio(part: f32, whole: f32) -> f32 {
+    if whole > 0.0 {
+        (part / whole).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+fn update_power_monitors(monitors: Query<(&PowerMonitor, &BusDevice)>, buses: Query<&PowerBus>) {
+    for (monitor, device) in monitors.iter() {
+        let Ok(bus) = buses.get(monitor.bus) else {
+            continue;
+        };
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        registers[0] = match bus.state {
+            BusState::Normal => 0,
+            BusState::OnBattery => 1,
+            BusState::Shedding => 2,
+            BusState::BrownOut => 3,
+            BusState::Dead => 4,
+        };
+        registers[1] = bus.voltage.round() as u8;
+        for (idx, watts) in [bus.generation, bus.demand, bus.load]
+            .into_iter()
+            .enumerate()
+        {
+            let [low, high] = (watts.round() as u16).to_le_bytes();
+            registers[2 + idx * 2] = low;
+            registers[3 + idx * 2] = high;
+        }
+        registers[8] = (bus.battery_charge * 100.0).round() as u8;
+        registers[9] = bus.shed.min(255) as u8;
+        registers[10] = bus.tripped.min(255) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::computer::bus::SharedPort;
+
+    fn totals(generation: f32, loads: &[(f32, LoadPriority)]) -> BusTotals {
+        BusTotals {
+            generation,
+            loads: loads
+                .iter()
+                .enumerate()
+                .map(|(idx, (demand, priority))| {
+                    (Entity::from_raw(idx as u32), *demand, *priority, false)
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn the_least_important_loads_are_shed_first() {
+        let solution = solve_bus(&totals(
+            1000.0,
+            &[
+                (300.0, LoadPriority::Essential),
+                (600.0, LoadPriority::Normal),
+                (600.0, LoadPriority::Low),
+            ],
+        ));
+        assert_eq!(solution.shed, [Entity::from_raw(2)]);
+        assert_eq!(solution.supply, 1.0);
+        assert_eq!(solution.generator_use, 0.9);
+    }
+
+    #[test]
+    fn essentials_brown_out_rather_than_being_shed() {
+        let solution = solve_bus(&totals(
+            100.0,
+            &[
+                (150.0, LoadPriority::Essential),
+                (50.0, LoadPriority::Essential),
+            ],
+        ));
+        assert!(solution.shed.is_empty());
+        assert_eq!(solution.supply, 0.5);
+    }
+
+    #[test]
+    fn spare_generation_charges_the_batteries() {
+        let mut totals = totals(1000.0, &[(300.0, LoadPriority::Normal)]);
+        totals.battery_available = 400.0;
+        totals.battery_headroom = 500.0;
+        let solution = solve_bus(&totals);
+        assert_eq!(solution.generator_use, 0.8);
+        assert_eq!(solution.charge, 1.0);
+        assert_eq!(solution.discharge, 0.0);
+
+        // And once there's nothing spare, they make up the difference instead
+        totals.loads[0].1 = 1200.0;
+        let solution = solve_bus(&totals);
+        assert_eq!(solution.generator_use, 1.0);
+        assert_eq!(solution.charge, 0.0);
+        assert_eq!(solution.discharge, 0.5);
+    }
+
+    fn world() -> (World, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let bus = world
+            .spawn(PowerBus {
+                nominal_voltage: 28.0,
+                voltage: 28.0,
+                state: BusState::Normal,
+                generation: 0.0,
+                demand: 0.0,
+                load: 0.0,
+                battery_flow: 0.0,
+                battery_charge: 0.0,
+                shed: 0,
+                tripped: 0,
+            })
+            .id();
+        (world, bus)
+    }
+
+    fn state(world: &World, consumer: Entity) -> PowerState {
+        world.get::<PowerConsumer>(consumer).unwrap().state
+    }
+
+    fn bus_state(world: &World, bus: Entity) -> BusState {
+        world.get::<PowerBus>(bus).unwrap().state
+    }
+
+    // Runs the grid for a second
+    fn tick(world: &mut World) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(reset_breakers);
+        world.run_system_once(solve_power_grid);
+        world.run_system_once(update_power_monitors);
+    }
+
+    #[test]
+    fn shed_loads_wait_before_coming_back_on() {
+        let (mut world, bus) = world();
+        world.spawn(Generator::new(bus, 500.0));
+        let mut spare = Generator::new(bus, 1000.0);
+        spare.online = false;
+        let spare = world.spawn(spare).id();
+        let galley = world
+            .spawn(PowerConsumer::new(bus, 600.0, LoadPriority::Low))
+            .id();
+
+        tick(&mut world);
+        assert_eq!(state(&world, galley), PowerState::Shed);
+        assert_eq!(bus_state(&world, bus), BusState::Shedding);
+
+        // There's enough power for it now, but it's only just been shed
+        world.get_mut::<Generator>(spare).unwrap().online = true;
+        for _ in 1..RESTORE_DELAY as usize {
+            tick(&mut world);
+            assert_eq!(state(&world, galley), PowerState::Shed);
+        }
+        tick(&mut world);
+        assert_eq!(state(&world, galley), PowerState::Powered);
+        assert_eq!(bus_state(&world, bus), BusState::Normal);
+    }
+
+    #[test]
+    fn flat_batteries_stay_cut_out_until_theyve_had_a_charge() {
+        let (mut world, bus) = world();
+        let generator = world.spawn(Generator::new(bus, 200.0)).id();
+        let mut battery = Battery::new(bus, 1000.0, 1000.0);
+        battery.charge = 0.0;
+        battery.flat = true;
+        let battery = world.spawn(battery).id();
+        world.spawn(PowerConsumer::new(bus, 100.0, LoadPriority::Essential));
+
+        // 100 W spare goes into the battery, but 100 J isn't enough to reconnect it
+        tick(&mut world);
+        let charged = world.get::<Battery>(battery).unwrap();
+        assert_eq!(charged.charge, 100.0);
+        assert!(charged.flat);
+
+        world.get_mut::<Generator>(generator).unwrap().online = false;
+        tick(&mut world);
+        assert_eq!(bus_state(&world, bus), BusState::Dead);
+
+        // Once it's charged past `BATTERY_RECONNECT`, it can take over
+        world.get_mut::<Generator>(generator).unwrap().online = true;
+        tick(&mut world);
+        assert!(!world.get::<Battery>(battery).unwrap().flat);
+        world.get_mut::<Generator>(generator).unwrap().online = false;
+        tick(&mut world);
+        assert_eq!(bus_state(&world, bus), BusState::OnBattery);
+    }
+
+    fn monitor(world: &mut World, bus: Entity) -> SharedPort {
+        let registers = Port::shared();
+        world.spawn((
+            PowerMonitor { bus },
+            BusDevice {
+                computer: Entity::PLACEHOLDER,
+                port: 1,
+                registers: registers.clone(),
+            },
+        ));
+        registers
+    }
+
+    #[test]
+    fn the_monitor_reports_the_bus_in_its_registers() {
+        let (mut world, bus) = world();
+        let registers = monitor(&mut world, bus);
+        world.spawn(Generator::new(bus, 1000.0));
+        let mut battery = Battery::new(bus, 1000.0, 500.0);
+        battery.charge = 500.0;
+        world.spawn(battery);
+        world.spawn(PowerConsumer::new(bus, 1300.0, LoadPriority::Normal));
+        world.spawn(PowerConsumer::new(bus, 400.0, LoadPriority::Low));
+
+        tick(&mut world);
+        let registers = registers.lock().unwrap().registers;
+        // Shedding the 400 W load, with the battery making up the rest
+        assert_eq!(registers[0], 2);
+        assert_eq!(registers[1], 28);
+        assert_eq!(u16::from_le_bytes([registers[2], registers[3]]), 1000);
+        assert_eq!(u16::from_le_bytes([registers[4], registers[5]]), 1700);
+        assert_eq!(u16::from_le_bytes([registers[6], registers[7]]), 1300);
+        assert_eq!(registers[8], 50);
+        assert_eq!(registers[9], 1);
+        assert_eq!(registers[10], 0);
+    }
+
+    #[test]
+    fn tripped_breakers_stay_open_until_the_monitor_resets_them() {
+        let (mut world, bus) = world();
+        let registers = monitor(&mut world, bus);
+        world.spawn(Generator::new(bus, 1000.0));
+        let heater = world
+            .spawn(PowerConsumer::new(bus, 200.0, LoadPriority::Normal))
+            .id();
+        let mut lamp = PowerConsumer::new(bus, 50.0, LoadPriority::Normal);
+        lamp.breaker.blow();
+        let lamp = world.spawn(lamp).id();
+
+        // Drawing more than it's rated for trips it
+        world.get_mut::<PowerConsumer>(heater).unwrap().demand = 400.0;
+        tick(&mut world);
+        assert!(!world.get::<PowerConsumer>(heater).unwrap().breaker.closed);
+        assert_eq!(state(&world, heater), PowerState::Unpowered);
+        assert_eq!(registers.lock().unwrap().registers[10], 2);
+
+        // Fixing the fault isn't enough on its own
+        world.get_mut::<PowerConsumer>(heater).unwrap().demand = 200.0;
+        tick(&mut world);
+        assert!(!world.get::<PowerConsumer>(heater).unwrap().breaker.closed);
+
+        registers.lock().unwrap().registers[MONITOR_RESET] = 1;
+        tick(&mut world);
+        assert_eq!(registers.lock().unwrap().registers[MONITOR_RESET], 0);
+        assert_eq!(state(&world, heater), PowerState::Powered);
+        // A blown fuse needs more than a reset
+        assert_eq!(state(&world, lamp), PowerState::Unpowered);
+        assert_eq!(registers.lock().unwrap().registers[10], 1);
+    }
+}
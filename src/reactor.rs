@@ -0,0 +1,572 @@
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, REACTOR_CONSOLE};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::power::{
+    setup_power_grid, Generator, LoadPriority, PowerConsumer, ShipGrid, SpawnPowerMonitorExt,
+};
+use crate::sensors::{SensorKind, SensorModel, SpawnSensorExt};
+use crate::thermal::{
+    setup_cooling, CoolantLoop, Pump, Radiator, SpawnCoolantPanelExt, ThermalBody, ThermalState,
+};
+
+// A small fission reactor, driving a turbine on the main bus. It doesn't look after itself:
+// the control rods get moved by whatever's plugged into its rod actuator, which is usually a
+// program on the reactor console. The only thing it does by itself is scram if it gets too
+// hot or too powerful.
+pub struct ReactorPlugin;
+impl Plugin for ReactorPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to it
+        app.register_type::<Reactor>();
+        // The turbine goes on the main bus, and the reactor has its own coolant loop
+        app.add_systems(
+            Startup,
+            setup_reactor
+                .in_set(SpawningSet)
+                .after(setup_power_grid)
+                .after(setup_cooling),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                run_rod_actuators,
+                move_rods,
+                run_reactors,
+                update_reactor_sensors,
+            )
+                .chain(),
+        );
+        app.add_systems(Startup, fit_reactor_console.in_set(FittingSet));
+        app.add_sample_file(
+            "REACTOR.BAS",
+            include_str!("../assets/programs/REACTOR.BAS"),
+        )
+        .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// How to keep the reactor going, for README.TXT
+const README_PARAGRAPH: &str = "\
+On the reactor console, REACTOR starts the reactor up and
+keeps it making as much power as the ship needs. Press Esc
+to stop it, and the reactor's on its own.
+";
+
+// How long it takes the chain reaction to respond to a change in reactivity, in seconds.
+// Much longer than it really takes neutrons to get about, because some of them are delayed,
+// and it's the delayed ones that make a reactor controllable at all. A reactivity of 0.001
+// (100 pcm) makes the power go up by a factor of e every 20 seconds.
+const GENERATION_TIME: f32 = 0.02;
+// Fission from stray neutrons, in watts per second, so a shut-down reactor never quite
+// gets to zero and can always be started back up
+const NEUTRON_SOURCE: f32 = 1.0;
+// How much reactivity fresh fuel has with the rods all the way out
+const FUEL_REACTIVITY: f32 = 0.03;
+// How much reactivity the rods take away when they're all the way in. More than the fuel
+// has, so fully inserted rods always shut it down.
+const ROD_WORTH: f32 = 0.05;
+// How much reactivity is lost for every kelvin the core is above room temperature. This is
+// what stops the power running away by itself: it's always trying to settle down.
+const TEMPERATURE_COEFFICIENT: f32 = 1.0e-4;
+const REFERENCE_TEMPERATURE: f32 = 293.15;
+// How much heat a full load of fuel has in it, in joules. About a day at full power.
+const FUEL_ENERGY: f32 = 5.0e8;
+// How fast the rods move, as a fraction of their travel per second. Normally they creep,
+// but in a scram they drop all the way in in a second.
+const ROD_SPEED: f32 = 0.02;
+const SCRAM_SPEED: f32 = 1.0;
+// How far over its rated power it can go before it scrams
+const OVERPOWER: f32 = 1.25;
+// How much of the reactor's heat the turbine can turn into electricity. The rest has to go
+// to the coolant.
+const TURBINE_EFFICIENCY: f32 = 0.4;
+// The most the turbine can put out, in watts
+const TURBINE_RATING: f32 = 2000.0;
+
+// The reactor, for anything that needs to be plugged into it when it's spawned
+#[derive(Resource)]
+pub struct ShipReactor {
+    pub reactor: Entity,
+    pub coolant_loop: Entity,
+}
+
+// The reactor core. Its temperature lives in a `ThermalBody` alongside it.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Reactor {
+    // The generator it drives
+    pub turbine: Entity,
+    // How far in the control rods are, from 0 (all the way out) to 1 (all the way in)
+    pub rod_position: f32,
+    // Where they're heading
+    pub rod_target: f32,
+    // Above 0 the power goes up, below 0 it goes down
+    pub reactivity: f32,
+    // How much heat it's making, in watts
+    pub power: f32,
+    // What it's designed to make, in watts
+    pub rated_power: f32,
+    // How much fuel's left, from 0 to 1
+    pub fuel: f32,
+    pub scram: Scram,
+}
+
+// Whether the reactor's been scrammed, and why. Once scrammed, the rods stay in until
+// someone resets it, and it can only be reset once whatever caused it has gone away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Scram {
+    Clear,
+    Manual,
+    OverTemperature,
+    OverPower,
+}
+
+impl Reactor {
+    // A cold reactor with the rods all the way in
+    pub fn new(turbine: Entity, rated_power: f32) -> Self {
+        Self {
+            turbine,
+            rod_position: 1.0,
+            rod_target: 1.0,
+            reactivity: 0.0,
+            power: 0.0,
+            rated_power,
+            fuel: 1.0,
+            scram: Scram::Clear,
+        }
+    }
+
+    fn overpowered(&self) -> bool {
+        self.power > self.rated_power * OVERPOWER
+    }
+}
+
+// Moves a reactor's control rods.
+// Registers:
+// - 0: where the rods should be, from 0 (all the way out) to 255 (all the way in). Set by
+//   the computer. Starts off all the way in, and goes back there if the reactor scrams.
+// - 1: where the rods actually are, on the same scale
+// - 2: write anything but 0 to scram the reactor
+// - 3: write anything but 0 to reset a scram
+// - 4: whether the reactor's scrammed: 0 if not, 1 by hand, 2 for over-temperature, 3 for
+//   over-power
+#[derive(Component)]
+pub struct RodActuator {
+    pub reactor: Entity,
+}
+
+const ACTUATOR_TARGET: usize = 0;
+const ACTUATOR_SCRAM: usize = 2;
+const ACTUATOR_RESET: usize = 3;
+
+// Plugs a rod actuator for `reactor` into one of a computer's ports.
+// Use `Commands::spawn_rod_actuator` rather than adding this directly.
+pub struct SpawnRodActuator {
+    pub computer: Entity,
+    pub port: usize,
+    pub reactor: Entity,
+}
+
+pub trait SpawnRodActuatorExt {
+    fn spawn_rod_actuator(&mut self, computer: Entity, port: usize, reactor: Entity);
+}
+
+impl SpawnRodActuatorExt for Commands<'_, '_> {
+    fn spawn_rod_actuator(&mut self, computer: Entity, port: usize, reactor: Entity) {
+        self.add(SpawnRodActuator {
+            computer,
+            port,
+            reactor,
+        });
+    }
+}
+
+impl Command for SpawnRodActuator {
+    fn apply(self, world: &mut World) {
+        let registers = Port::shared();
+        registers.lock().expect("Port lock").registers[ACTUATOR_TARGET] = 255;
+        world.spawn((
+            RodActuator {
+                reactor: self.reactor,
+            },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers,
+            },
+        ));
+    }
+}
+
+// Lets a computer see what a reactor's up to.
+// Registers:
+// - 0-1: the heat it's making, in watts (16-bit, low byte first)
+// - 2-3: the core temperature, in degrees Celsius (16-bit signed, low byte first)
+// - 4-5: the reactivity, in pcm, i.e. 100000ths (16-bit signed, low byte first)
+// - 6: how much fuel's left, as a percentage
+// - 7-8: what the turbine's putting out, in watts (16-bit, low byte first)
+#[derive(Component)]
+pub struct ReactorSensor {
+    pub reactor: Entity,
+}
+
+// Plugs a sensor for `reactor` into one of a computer's ports.
+// Use `Commands::spawn_reactor_sensor` rather than adding this directly.
+pub struct SpawnReactorSensor {
+    pub computer: Entity,
+    pub port: usize,
+    pub reactor: Entity,
+}
+
+pub trait SpawnReactorSensorExt {
+    fn spawn_reactor_sensor(&mut self, computer: Entity, port: usize, reactor: Entity);
+}
+
+impl SpawnReactorSensorExt for Commands<'_, '_> {
+    fn spawn_reactor_sensor(&mut self, computer: Entity, port: usize, reactor: Entity) {
+        self.add(SpawnReactorSensor {
+            computer,
+            port,
+            reactor,
+        });
+    }
+}
+
+impl Command for SpawnReactorSensor {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            ReactorSensor {
+                reactor: self.reactor,
+            },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+pub fn setup_reactor(mut commands: Commands, grid: Res<ShipGrid>) {
+    // The reactor gets a coolant loop to itself, since it makes a lot more heat than
+    // everything else put together
+    let coolant_loop = commands
+        .spawn((
+            Name::new("Reactor coolant"),
+            CoolantLoop {
+                temperature: 293.15,
+                heat_capacity: 400_000.0,
+                flow: 1.0,
+                heat_rejected: 0.0,
+            },
+        ))
+        .id();
+    commands.spawn((
+        Name::new("Reactor coolant pump"),
+        Pump {
+            coolant_loop,
+            speed: 1.0,
+            rated_power: 200.0,
+        },
+        PowerConsumer::new(grid.main_bus, 200.0, LoadPriority::Essential),
+    ));
+    // Enough for the waste heat at full power, with the coolant at about 40C
+    commands.spawn((
+        Name::new("Reactor radiator"),
+        Radiator {
+            coolant_loop,
+            area: 6.0,
+            emissivity: 0.9,
+        },
+    ));
+
+    // Spawned after the rest of the cooling's been set up, so it doesn't get mistaken for a
+    // fuel cell. Its heat is the reactor's.
+    let turbine = commands
+        .spawn((
+            Name::new("Reactor turbine"),
+            Generator::new(grid.main_bus, TURBINE_RATING),
+        ))
+        .id();
+
+    // Rated for as much heat as the turbine can use. It gets uncomfortable at 250C, and
+    // scrams at 300C.
+    let reactor = commands
+        .spawn((
+            Name::new("Reactor"),
+            Reactor::new(turbine, TURBINE_RATING / TURBINE_EFFICIENCY),
+            ThermalBody::new(20_000.0, coolant_loop, 60.0, 523.15, 573.15),
+        ))
+        .id();
+
+    commands.insert_resource(ShipReactor {
+        reactor,
+        coolant_loop,
+    });
+}
+
+// The reactor console gets the rods on port 0 ($E800), the core's readings on port 1
+// ($E810), its coolant on port 2 ($E820), and the main bus on port 3 ($E830), so it can
+// tell how much power's needed. And a second opinion on the core temperature, on port 4
+// ($E840).
+fn fit_reactor_console(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    reactor: Res<ShipReactor>,
+    grid: Res<ShipGrid>,
+) {
+    let Some(console) = computers.get(REACTOR_CONSOLE) else {
+        return;
+    };
+    commands.spawn_rod_actuator(console, 0, reactor.reactor);
+    commands.spawn_reactor_sensor(console, 1, reactor.reactor);
+    commands.spawn_coolant_panel(console, 2, reactor.coolant_loop);
+    commands.spawn_power_monitor(console, 3, grid.main_bus);
+    commands.spawn_sensor(
+        console,
+        4,
+        "Core thermometer",
+        SensorKind::Thermometer {
+            entity: reactor.reactor,
+        },
+        SensorModel::THERMOMETER,
+    );
+}
+
+fn run_rod_actuators(
+    actuators: Query<(&RodActuator, &BusDevice)>,
+    mut reactors: Query<(&mut Reactor, &ThermalBody)>,
+) {
+    for (actuator, device) in actuators.iter() {
+        let Ok((mut reactor, core)) = reactors.get_mut(actuator.reactor) else {
+            continue;
+        };
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+
+        if std::mem::take(&mut registers[ACTUATOR_SCRAM]) != 0 && reactor.scram == Scram::Clear {
+            reactor.scram = Scram::Manual;
+        }
+        if std::mem::take(&mut registers[ACTUATOR_RESET]) != 0
+            && core.state != ThermalState::ShutDown
+            && !reactor.overpowered()
+        {
+            reactor.scram = Scram::Clear;
+        }
+
+        if reactor.scram != Scram::Clear {
+            // So the rods don't come straight back out once it's reset
+            registers[ACTUATOR_TARGET] = 255;
+        }
+        reactor.rod_target = registers[ACTUATOR_TARGET] as f32 / 255.0;
+        registers[1] = (reactor.rod_position * 255.0).round() as u8;
+        registers[4] = match reactor.scram {
+            Scram::Clear => 0,
+            Scram::Manual => 1,
+            Scram::OverTemperature => 2,
+            Scram::OverPower => 3,
+        };
+    }
+}
+
+fn move_rods(time: Res<Time>, mut reactors: Query<&mut Reactor>) {
+    let delta = time.delta_seconds();
+    for mut reactor in reactors.iter_mut() {
+        let (target, speed) = if reactor.scram == Scram::Clear {
+            (reactor.rod_target, ROD_SPEED)
+        } else {
+            (1.0, SCRAM_SPEED)
+        };
+        let step = speed * delta;
+        reactor.rod_position += (target - reactor.rod_position).clamp(-step, step);
+    }
+}
+
+// Works out how much heat each reactor's making, how much of it the turbine gets, and
+// whether it needs to scram
+fn run_reactors(
+    time: Res<Time>,
+    mut reactors: Query<(&mut Reactor, &mut ThermalBody)>,
+    mut turbines: Query<&mut Generator>,
+) {
+    let delta = time.delta_seconds();
+    for (mut reactor, mut core) in reactors.iter_mut() {
+        reactor.reactivity = FUEL_REACTIVITY * reactor.fuel
+            - ROD_WORTH * reactor.rod_position
+            - TEMPERATURE_COEFFICIENT * (core.temperature - REFERENCE_TEMPERATURE);
+        reactor.power = reactor.power * (reactor.reactivity / GENERATION_TIME * delta).exp()
+            + NEUTRON_SOURCE * delta;
+        reactor.fuel = (reactor.fuel - reactor.power * delta / FUEL_ENERGY).max(0.0);
+
+        // Whatever the turbine doesn't turn into electricity stays in the core
+        let mut electricity = 0.0;
+        if let Ok(mut turbine) = turbines.get_mut(reactor.turbine) {
+            turbine.rated_output = (reactor.power * TURBINE_EFFICIENCY).min(TURBINE_RATING);
+            electricity = turbine.output;
+        }
+        core.heat_output = (reactor.power - electricity).max(0.0);
+
+        if reactor.scram == Scram::Clear {
+            if core.state == ThermalState::ShutDown {
+                reactor.scram = Scram::OverTemperature;
+            } else if reactor.overpowered() {
+                reactor.scram = Scram::OverPower;
+            }
+        }
+    }
+}
+
+fn update_reactor_sensors(
+    sensors: Query<(&ReactorSensor, &BusDevice)>,
+    reactors: Query<(&Reactor, &ThermalBody)>,
+    turbines: Query<&Generator>,
+) {
+    for (sensor, device) in sensors.iter() {
+        let Ok((reactor, core)) = reactors.get(sensor.reactor) else {
+            continue;
+        };
+        let turbine_output = turbines
+            .get(reactor.turbine)
+            .map_or(0.0, |turbine| turbine.output);
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+
+        let readings = [
+            (reactor.power.round() as u16).to_le_bytes(),
+            ((core.temperature - 273.15).round() as i16).to_le_bytes(),
+            ((reactor.reactivity * 1.0e5).round() as i16).to_le_bytes(),
+        ];
+        for (idx, [low, high]) in readings.into_iter().enumerate() {
+            registers[idx * 2] = low;
+            registers[1 + idx * 2] = high;
+        }
+        registers[6] = (reactor.fuel * 100.0).round() as u8;
+        let [low, high] = (turbine_output.round() as u16).to_le_bytes();
+        registers[7] = low;
+        registers[8] = high;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::computer::bus::SharedPort;
+
+    // A reactor with an actuator plugged into it, and its turbine
+    fn world() -> (World, Entity, SharedPort) {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let turbine = world.spawn(Generator::new(Entity::PLACEHOLDER, 0.0)).id();
+        let reactor = world
+            .spawn((
+                Reactor::new(turbine, 1000.0),
+                ThermalBody::new(1.0e5, Entity::PLACEHOLDER, 10.0, 600.0, 700.0),
+            ))
+            .id();
+        let registers = Port::shared();
+        registers.lock().unwrap().registers[ACTUATOR_TARGET] = 255;
+        world.spawn((
+            RodActuator { reactor },
+            BusDevice {
+                computer: Entity::PLACEHOLDER,
+                port: 0,
+                registers: registers.clone(),
+            },
+        ));
+        (world, reactor, registers)
+    }
+
+    fn tick(world: &mut World, seconds: f32) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        world.run_system_once(run_rod_actuators);
+        world.run_system_once(move_rods);
+        world.run_system_once(run_reactors);
+    }
+
+    fn reactor(world: &World, reactor: Entity) -> &Reactor {
+        world.get::<Reactor>(reactor).unwrap()
+    }
+
+    #[test]
+    fn the_power_follows_the_reactivity() {
+        let (mut world, core, _) = world();
+        world.get_mut::<Reactor>(core).unwrap().power = 1000.0;
+
+        // Rods all the way in: 3% from the fuel, less 5% from the rods
+        tick(&mut world, 1.0);
+        let cold = reactor(&world, core);
+        assert!((cold.reactivity + 0.02).abs() < 1e-6);
+        let expected = 1000.0 * (-0.02 / GENERATION_TIME).exp() + NEUTRON_SOURCE;
+        assert!((cold.power - expected).abs() < 0.01);
+
+        // A hot core has less to give
+        world.get_mut::<ThermalBody>(core).unwrap().temperature = REFERENCE_TEMPERATURE + 100.0;
+        tick(&mut world, 1.0);
+        let reactivity = reactor(&world, core).reactivity;
+        assert!((reactivity + 0.02 + 100.0 * TEMPERATURE_COEFFICIENT).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_rods_creep_unless_theres_a_scram() {
+        let (mut world, core, registers) = world();
+        registers.lock().unwrap().registers[ACTUATOR_TARGET] = 0;
+        tick(&mut world, 10.0);
+        assert!((reactor(&world, core).rod_position - (1.0 - 10.0 * ROD_SPEED)).abs() < 1e-6);
+
+        registers.lock().unwrap().registers[ACTUATOR_SCRAM] = 1;
+        tick(&mut world, 1.0);
+        let registers = registers.lock().unwrap().registers;
+        assert_eq!(reactor(&world, core).scram, Scram::Manual);
+        assert_eq!(reactor(&world, core).rod_position, 1.0);
+        assert_eq!(registers[ACTUATOR_TARGET], 255);
+        assert_eq!(registers[ACTUATOR_SCRAM], 0);
+        assert_eq!(registers[4], 1);
+    }
+
+    #[test]
+    fn a_scram_cant_be_reset_while_whatever_caused_it_is_still_there() {
+        let (mut world, core, registers) = world();
+        world.get_mut::<Reactor>(core).unwrap().power = 2000.0;
+        tick(&mut world, 0.01);
+        assert_eq!(reactor(&world, core).scram, Scram::OverPower);
+
+        world.get_mut::<Reactor>(core).unwrap().power = 2000.0;
+        registers.lock().unwrap().registers[ACTUATOR_RESET] = 1;
+        tick(&mut world, 0.01);
+        assert_eq!(reactor(&world, core).scram, Scram::OverPower);
+        assert_eq!(registers.lock().unwrap().registers[4], 3);
+
+        world.get_mut::<Reactor>(core).unwrap().power = 100.0;
+        registers.lock().unwrap().registers[ACTUATOR_RESET] = 1;
+        tick(&mut world, 0.01);
+        assert_eq!(reactor(&world, core).scram, Scram::Clear);
+
+        // Overheating scrams it too
+        world.get_mut::<ThermalBody>(core).unwrap().state = ThermalState::ShutDown;
+        tick(&mut world, 0.01);
+        assert_eq!(reactor(&world, core).scram, Scram::OverTemperature);
+    }
+
+    #[test]
+    fn the_turbine_gets_its_share_of_the_heat() {
+        let (mut world, core, _) = world();
+        let turbine = reactor(&world, core).turbine;
+        world.get_mut::<Reactor>(core).unwrap().power = 1000.0;
+        world.get_mut::<Generator>(turbine).unwrap().output = 300.0;
+
+        tick(&mut world, 0.01);
+        let power = reactor(&world, core).power;
+        let generator = world.get::<Generator>(turbine).unwrap();
+        assert!((generator.rated_output - power * TURBINE_EFFICIENCY).abs() < 0.01);
+        // What it turns into electricity doesn't heat the core
+        let heat = world.get::<ThermalBody>(core).unwrap().heat_output;
+        assert!((heat - (power - 300.0)).abs() < 0.01);
+    }
+}
@@ -0,0 +1,623 @@
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+
+use bevy::ecs::world::Command;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::atmosphere::Compartment;
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, HELM};
+use crate::core::system_sets::FittingSet;
+use crate::flight::{move_bodies, FlightBody, ShipFlight};
+use crate::thermal::{CoolantLoop, ThermalBody};
+
+// Instruments that read the simulation the way real ones do: a bit noisy, in steps, a little
+// behind, and not always working. Each one plugs into a port on a computer, and they all lay
+// their registers out the same way, so a program can work out what's plugged in where and
+// whether it's getting anything out of it.
+pub struct SensorsPlugin;
+impl Plugin for SensorsPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<Sensor>();
+        app.init_resource::<SensorNoise>();
+        // Once everything's moved, so they're reading where things are now
+        app.add_systems(FixedUpdate, sample_sensors.after(move_bodies));
+        app.add_systems(Startup, fit_flight_sensors.in_set(FittingSet));
+        app.add_sample_file(
+            "SENSORS.BAS",
+            include_str!("../assets/programs/SENSORS.BAS"),
+        )
+        .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// Which sensors the helm has, for README.TXT
+const README_PARAGRAPH: &str = "\
+SENSORS lists the sensors plugged into a computer, and what
+they're reading. The helm's star tracker, radar, accelerometer
+and gyro are on ports 4 to 7.
+";
+
+// A full turn in binary angle units, the same as the inertial unit uses
+const BINARY_TURN: f64 = 65536.0;
+
+// How often an erratic sensor comes out with something random, per reading
+const ERRATIC_CHANCE: f64 = 0.05;
+
+// Where the noise comes from. Seeded, so the same flight gets the same noise every time.
+#[derive(Resource)]
+pub struct SensorNoise {
+    rng: SmallRng,
+}
+
+impl Default for SensorNoise {
+    fn default() -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(6502),
+        }
+    }
+}
+
+impl SensorNoise {
+    // Normally distributed, with a standard deviation of 1 (the Box-Muller transform)
+    fn gaussian(&mut self) -> f64 {
+        let u = 1.0 - self.rng.gen::<f64>();
+        let v = self.rng.gen::<f64>();
+        (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+    }
+}
+
+// What a sensor's measuring, and how it comes out in its readings
+#[derive(Debug, Clone, Copy, Reflect)]
+pub enum SensorKind {
+    // How hard the thrusters are pushing the ship, along its right, up and forward axes, in
+    // cm/s^2. Like any accelerometer, it can't feel gravity.
+    Accelerometer { body: Entity },
+    // How fast the ship's turning, as pitch, yaw and roll rates in binary angle units per
+    // second, the same way round as the inertial unit
+    Gyro { body: Entity },
+    // Which way the ship's pointing, worked out from the stars, as a quaternion (x, y, z
+    // and w) scaled up so that 1 comes out as 32767
+    StarTracker { body: Entity },
+    // Anything else flying within `range` metres. The readings are how many contacts there
+    // are, then the range (in 10 m units), bearing and elevation (binary angles, with right
+    // and up positive) and closing speed (in dm/s) of the one picked by register 14,
+    // nearest first.
+    Radar { body: Entity, range: f64 },
+    // How hot a compartment, a coolant loop, or anything that heats up is, in tenths of a
+    // degree Celsius
+    Thermometer { entity: Entity },
+    // A compartment's air pressure, in tenths of a kPa
+    PressureGauge { compartment: Entity },
+}
+
+impl SensorKind {
+    // What goes in register 15, so programs can tell sensors apart. Starting at $A1 makes
+    // them less likely to be mixed up with whatever other devices have in theirs.
+    fn id(&self) -> u8 {
+        match self {
+            SensorKind::Accelerometer { .. } => 0xA1,
+            SensorKind::Gyro { .. } => 0xA2,
+            SensorKind::StarTracker { .. } => 0xA3,
+            SensorKind::Radar { .. } => 0xA4,
+            SensorKind::Thermometer { .. } => 0xA5,
+            SensorKind::PressureGauge { .. } => 0xA6,
+        }
+    }
+
+    // How many of its readings are counts rather than measurements, which it always gets
+    // right
+    fn exact_channels(&self) -> usize {
+        match self {
+            SensorKind::Radar { .. } => 1,
+            _ => 0,
+        }
+    }
+}
+
+// How good a sensor is. Everything's in register units, i.e. whatever the sensor's readings
+// are in.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct SensorModel {
+    // How far off a reading usually is (the standard deviation)
+    pub noise: f64,
+    // The smallest step between readings
+    pub resolution: f64,
+    // How far behind the readings are, in seconds
+    pub latency: f64,
+}
+
+impl SensorModel {
+    // What the ship's fitted with. Nothing special, but nothing terrible either.
+    pub const ACCELEROMETER: Self = Self {
+        noise: 2.0,
+        resolution: 1.0,
+        latency: 0.0,
+    };
+    pub const GYRO: Self = Self {
+        noise: 4.0,
+        resolution: 2.0,
+        latency: 0.0,
+    };
+    // Star trackers are slow, since they've got to take a picture and match it up
+    pub const STAR_TRACKER: Self = Self {
+        noise: 10.0,
+        resolution: 1.0,
+        latency: 0.25,
+    };
+    pub const RADAR: Self = Self {
+        noise: 1.0,
+        resolution: 1.0,
+        latency: 0.5,
+    };
+    // The probe's got to warm up (or cool down) to whatever it's measuring first
+    pub const THERMOMETER: Self = Self {
+        noise: 2.0,
+        resolution: 5.0,
+        latency: 2.0,
+    };
+    pub const PRESSURE_GAUGE: Self = Self {
+        noise: 1.0,
+        resolution: 1.0,
+        latency: 0.5,
+    };
+}
+
+// The ways a sensor can go wrong
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Deserialize)]
+pub enum SensorFailure {
+    Working,
+    // Keeps giving whatever it was reading when it stuck, as if everything was fine
+    Stuck,
+    // Gives nothing at all, and says so in its status register
+    Dead,
+    // Wanders further off by this much every second, in register units
+    Drifting(f64),
+    // Comes out with something completely random every so often
+    Erratic,
+}
+
+// Registers, the same for every kind of sensor:
+// - 0-11: up to six readings, each signed 16-bit, low byte first. What they are depends on
+//   the kind of sensor (see `SensorKind`).
+// - 12: status. Bit 0 is set while the sensor's giving readings.
+// - 13: goes up by one with every new reading, wrapping round
+// - 14: which thing to read, for sensors that can see more than one. Written by the
+//   computer.
+// - 15: what kind of sensor it is: $A1 accelerometer, $A2 gyro, $A3 star tracker, $A4 radar,
+//   $A5 thermometer, $A6 pressure gauge
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Sensor {
+    pub kind: SensorKind,
+    pub model: SensorModel,
+    pub failure: SensorFailure,
+    // What it's showing, in register units
+    pub readings: [f64; 6],
+    // How far off it's drifted so far, in register units
+    pub drift: f64,
+    // Readings it's taken but isn't showing yet, oldest first, with when they were taken
+    #[reflect(ignore)]
+    pending: VecDeque<(f64, Vec<f64>)>,
+    samples: u8,
+}
+
+impl Sensor {
+    pub fn new(kind: SensorKind, model: SensorModel) -> Self {
+        Self {
+            kind,
+            model,
+            failure: SensorFailure::Working,
+            readings: [0.0; 6],
+            drift: 0.0,
+            pending: VecDeque::new(),
+            samples: 0,
+        }
+    }
+}
+
+// Plugs a sensor into one of a computer's ports. The name's so it can be found again, e.g.
+// to show it on a screen.
+// Use `Commands::spawn_sensor` rather than adding this directly.
+pub struct SpawnSensor {
+    pub computer: Entity,
+    pub port: usize,
+    pub name: String,
+    pub kind: SensorKind,
+    pub model: SensorModel,
+}
+
+pub trait SpawnSensorExt {
+    fn spawn_sensor(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        name: &str,
+        kind: SensorKind,
+        model: SensorModel,
+    );
+}
+
+impl SpawnSensorExt for Commands<'_, '_> {
+    fn spawn_sensor(
+        &mut self,
+        computer: Entity,
+        port: usize,
+        name: &str,
+        kind: SensorKind,
+        model: SensorModel,
+    ) {
+        self.add(SpawnSensor {
+            computer,
+            port,
+            name: name.to_owned(),
+            kind,
+            model,
+        });
+    }
+}
+
+impl Command for SpawnSensor {
+    fn apply(self, world: &mut World) {
+        world.spawn((
+            Name::new(self.name),
+            Sensor::new(self.kind, self.model),
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers: Port::shared(),
+            },
+        ));
+    }
+}
+
+// The sensors a ship flies by, on ports 4 to 7 ($E840 to $E870): the star tracker, the radar
+// (which can see 50 km), the accelerometer and the gyro. Anything flying the ship should
+// have the same ones in the same places, so programs work on all of them.
+fn spawn_flight_sensors(commands: &mut Commands, computer: Entity, body: Entity) {
+    commands.spawn_sensor(
+        computer,
+        4,
+        "Star tracker",
+        SensorKind::StarTracker { body },
+        SensorModel::STAR_TRACKER,
+    );
+    commands.spawn_sensor(
+        computer,
+        5,
+        "Radar",
+        SensorKind::Radar {
+            body,
+            range: 50_000.0,
+        },
+        SensorModel::RADAR,
+    );
+    commands.spawn_sensor(
+        computer,
+        6,
+        "Accelerometer",
+        SensorKind::Accelerometer { body },
+        SensorModel::ACCELEROMETER,
+    );
+    commands.spawn_sensor(
+        computer,
+        7,
+        "Gyro",
+        SensorKind::Gyro { body },
+        SensorModel::GYRO,
+    );
+}
+
+fn fit_flight_sensors(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    flight: Res<ShipFlight>,
+) {
+    let Some(helm) = computers.get(HELM) else {
+        return;
+    };
+    spawn_flight_sensors(&mut commands, helm, flight.body);
+}
+
+// Everything a sensor might be reading from
+type Surroundings<'w, 's> = (
+    Query<'w, 's, (Entity, &'static FlightBody)>,
+    Query<'w, 's, &'static ThermalBody>,
+    Query<'w, 's, &'static CoolantLoop>,
+    Query<'w, 's, &'static Compartment>,
+);
+
+fn sample_sensors(
+    time: Res<Time>,
+    mut noise: ResMut<SensorNoise>,
+    mut sensors: Query<(&mut Sensor, &BusDevice)>,
+    surroundings: Surroundings,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (mut sensor, device) in sensors.iter_mut() {
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+        let kind = sensor.kind;
+        registers[15] = kind.id();
+
+        if sensor.failure == SensorFailure::Dead {
+            sensor.readings = [0.0; 6];
+            sensor.pending.clear();
+            registers[..13].fill(0);
+            continue;
+        }
+
+        // Whatever's true right now, which it'll get round to showing once the latency's up.
+        // If the thing it's reading has gone, there's nothing new to show.
+        if let Some(truth) = measure(&kind, registers[14] as usize, &surroundings) {
+            sensor.pending.push_back((now, truth));
+        }
+        let mut due = None;
+        while let Some((taken, _)) = sensor.pending.front() {
+            if *taken > now - sensor.model.latency {
+                break;
+            }
+            due = sensor.pending.pop_front().map(|(_, truth)| truth);
+        }
+        let Some(truth) = due else {
+            continue;
+        };
+
+        match sensor.failure {
+            // Still ticking over, just not telling the truth any more
+            SensorFailure::Stuck => {}
+            failure => {
+                if let SensorFailure::Drifting(rate) = failure {
+                    sensor.drift += rate * time.delta_seconds_f64();
+                }
+                let model = sensor.model;
+                // Anything it didn't get a reading for this time reads zero, noise and all
+                sensor.readings = [0.0; 6];
+                for (channel, truth) in truth.into_iter().enumerate() {
+                    if channel < kind.exact_channels() {
+                        sensor.readings[channel] = truth;
+                        continue;
+                    }
+                    let mut reading = truth + noise.gaussian() * model.noise + sensor.drift;
+                    if failure == SensorFailure::Erratic && noise.rng.gen_bool(ERRATIC_CHANCE) {
+                        reading = noise.rng.gen_range(i16::MIN as f64..=i16::MAX as f64);
+                    }
+                    if model.resolution > 0.0 {
+                        reading = (reading / model.resolution).round() * model.resolution;
+                    }
+                    sensor.readings[channel] = reading;
+                }
+            }
+        }
+
+        sensor.samples = sensor.samples.wrapping_add(1);
+        for (idx, reading) in sensor.readings.iter().enumerate() {
+            let word = reading.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+            let [low, high] = word.to_le_bytes();
+            registers[idx * 2] = low;
+            registers[idx * 2 + 1] = high;
+        }
+        registers[12] = 1;
+        registers[13] = sensor.samples;
+    }
+}
+
+// What a perfect sensor would read, in register units, as many readings as it's got. `None`
+// if what it's reading from isn't there any more.
+fn measure(kind: &SensorKind, select: usize, surroundings: &Surroundings) -> Option<Vec<f64>> {
+    let (bodies, thermal_bodies, coolant_loops, compartments) = surroundings;
+    let readings = match *kind {
+        SensorKind::Accelerometer { body } => {
+            let (_, body) = bodies.get(body).ok()?;
+            let acceleration = body.force / body.mass() * 100.0;
+            // Forward's -z
+            vec![acceleration.x, acceleration.y, -acceleration.z]
+        }
+        SensorKind::Gyro { body } => {
+            let (_, body) = bodies.get(body).ok()?;
+            let rates = body.angular_velocity / TAU * BINARY_TURN;
+            // Turning around +y is turning left, and around +z is rolling anticlockwise,
+            // which are the wrong way round for a pilot
+            vec![rates.x, -rates.y, -rates.z]
+        }
+        SensorKind::StarTracker { body } => {
+            let (_, body) = bodies.get(body).ok()?;
+            body.orientation
+                .to_array()
+                .map(|part| part * i16::MAX as f64)
+                .to_vec()
+        }
+        SensorKind::Radar { body, range } => {
+            let (ship_entity, ship) = bodies.get(body).ok()?;
+            let mut contacts: Vec<(f64, DVec3, DVec3)> = bodies
+                .iter()
+                .filter(|(entity, _)| *entity != ship_entity)
+                .map(|(_, other)| {
+                    let offset = other.position - ship.position;
+                    (offset.length(), offset, other.velocity - ship.velocity)
+                })
+                .filter(|(distance, _, _)| *distance <= range)
+                .collect();
+            contacts.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut readings = vec![contacts.len() as f64];
+            if let Some((distance, offset, velocity)) = contacts.get(select) {
+                // In the ship's own axes: right is +x, up is +y, and in front is -z
+                let local = ship.orientation.inverse() * *offset;
+                let bearing = local.x.atan2(-local.z);
+                let elevation = (local.y / distance.max(f64::EPSILON))
+                    .clamp(-1.0, 1.0)
+                    .asin();
+                let closing_speed = -velocity.dot(*offset / distance.max(f64::EPSILON));
+                readings.extend([
+                    distance / 10.0,
+                    bearing / TAU * BINARY_TURN,
+                    elevation / TAU * BINARY_TURN,
+                    closing_speed * 10.0,
+                ]);
+            }
+            readings
+        }
+        SensorKind::Thermometer { entity } => {
+            let kelvin = thermal_bodies
+                .get(entity)
+                .map(|body| body.temperature)
+                .or_else(|_| coolant_loops.get(entity).map(|coolant| coolant.temperature))
+                .or_else(|_| {
+                    compartments
+                        .get(entity)
+                        .map(|compartment| compartment.temperature)
+                })
+                .ok()?;
+            vec![(kelvin as f64 - 273.15) * 10.0]
+        }
+        SensorKind::PressureGauge { compartment } => {
+            vec![compartments.get(compartment).ok()?.pressure as f64 * 10.0]
+        }
+    };
+    Some(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::computer::bus::SharedPort;
+
+    // A sensor that gets it exactly right, apart from going in steps of `resolution`
+    fn perfect(resolution: f64, latency: f64) -> SensorModel {
+        SensorModel {
+            noise: 0.0,
+            resolution,
+            latency,
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<SensorNoise>();
+        world
+    }
+
+    fn plug_in(world: &mut World, sensor: Sensor) -> (Entity, SharedPort) {
+        let registers = Port::shared();
+        let sensor = world
+            .spawn((
+                sensor,
+                BusDevice {
+                    computer: Entity::PLACEHOLDER,
+                    port: 4,
+                    registers: registers.clone(),
+                },
+            ))
+            .id();
+        (sensor, registers)
+    }
+
+    fn tick(world: &mut World, seconds: f64) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f64(seconds));
+        world.run_system_once(sample_sensors);
+    }
+
+    fn reading(registers: &SharedPort, idx: usize) -> i16 {
+        let registers = registers.lock().unwrap().registers;
+        i16::from_le_bytes([registers[idx * 2], registers[idx * 2 + 1]])
+    }
+
+    fn probe(world: &mut World, kelvin: f32) -> Entity {
+        let mut body = ThermalBody::new(1.0, Entity::PLACEHOLDER, 1.0, 400.0, 500.0);
+        body.temperature = kelvin;
+        world.spawn(body).id()
+    }
+
+    #[test]
+    fn readings_turn_up_late_and_in_steps() {
+        let mut world = world();
+        let hot = probe(&mut world, 296.15);
+        let (_, registers) = plug_in(
+            &mut world,
+            Sensor::new(SensorKind::Thermometer { entity: hot }, perfect(3.0, 0.5)),
+        );
+
+        // Sampled at 0.25s, so it's due at 0.75s
+        for _ in 0..2 {
+            tick(&mut world, 0.25);
+            assert_eq!(registers.lock().unwrap().registers[12], 0);
+        }
+        tick(&mut world, 0.25);
+        let status = registers.lock().unwrap().registers;
+        assert_eq!((status[12], status[13], status[15]), (1, 1, 0xA5));
+        // 23.0 degrees, to the nearest 3 tenths
+        assert_eq!(reading(&registers, 0), 231);
+    }
+
+    #[test]
+    fn broken_sensors_go_wrong_in_their_own_ways() {
+        let mut world = world();
+        let hot = probe(&mut world, 296.15);
+        let thermometer = |failure| Sensor {
+            failure,
+            ..Sensor::new(SensorKind::Thermometer { entity: hot }, perfect(1.0, 0.0))
+        };
+        let (stuck_sensor, stuck) = plug_in(&mut world, thermometer(SensorFailure::Working));
+        let (_, dead) = plug_in(&mut world, thermometer(SensorFailure::Dead));
+        let (_, drifting) = plug_in(&mut world, thermometer(SensorFailure::Drifting(5.0)));
+
+        tick(&mut world, 1.0);
+        world.get_mut::<Sensor>(stuck_sensor).unwrap().failure = SensorFailure::Stuck;
+        world.get_mut::<ThermalBody>(hot).unwrap().temperature = 303.15;
+        tick(&mut world, 1.0);
+
+        // Still showing the 23.0 it had before it got stuck
+        assert_eq!(reading(&stuck, 0), 230);
+        assert_eq!(stuck.lock().unwrap().registers[12], 1);
+        assert_eq!(dead.lock().unwrap().registers[12], 0);
+        assert_eq!(reading(&dead, 0), 0);
+        // Off by 5 a second, for two seconds
+        assert_eq!(reading(&drifting, 0), 310);
+    }
+
+    #[test]
+    fn radar_finds_the_nearest_contact_first() {
+        let mut world = world();
+        let ship = world.spawn(FlightBody::new(1000.0, 0.0, DVec3::ONE)).id();
+        for position in [DVec3::new(0.0, 0.0, -500.0), DVec3::new(100.0, 0.0, 0.0)] {
+            let mut contact = FlightBody::new(1000.0, 0.0, DVec3::ONE);
+            contact.position = position;
+            world.spawn(contact);
+        }
+        let mut far_away = FlightBody::new(1000.0, 0.0, DVec3::ONE);
+        far_away.position = DVec3::new(0.0, 5000.0, 0.0);
+        world.spawn(far_away);
+        let (_, registers) = plug_in(
+            &mut world,
+            Sensor::new(
+                SensorKind::Radar {
+                    body: ship,
+                    range: 1000.0,
+                },
+                perfect(1.0, 0.0),
+            ),
+        );
+
+        tick(&mut world, 0.1);
+        // Two in range, the nearest 100 m off to the right
+        assert_eq!(reading(&registers, 0), 2);
+        assert_eq!(reading(&registers, 1), 10);
+        assert_eq!(reading(&registers, 2), 16384);
+
+        registers.lock().unwrap().registers[14] = 1;
+        tick(&mut world, 0.1);
+        assert_eq!(reading(&registers, 1), 50);
+        assert_eq!(reading(&registers, 2), 0);
+    }
+}
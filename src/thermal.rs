@@ -0,0 +1,544 @@
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+
+use crate::computer::bus::{BusDevice, Port};
+use crate::computer::{AddSampleFilesExt, ShipComputers, DIAGNOSTICS_COMPUTER};
+use crate::core::system_sets::{FittingSet, SpawningSet};
+use crate::power::{setup_power_grid, Generator, LoadPriority, PowerConsumer, ShipGrid};
+
+// Heat. Anything that gets warm is a thermal body, which gets rid of its heat into a coolant
+// loop. Pumps keep the coolant moving, and radiators get rid of the heat into space. Worked
+// out every fixed tick, like the power and the air.
+pub struct ThermalPlugin;
+impl Plugin for ThermalPlugin {
+    fn build(&self, app: &mut App) {
+        // So screens can bind to them
+        app.register_type::<ThermalBody>()
+            .register_type::<CoolantLoop>()
+            .register_type::<Pump>()
+            .register_type::<Radiator>();
+        // The pumps run off the main bus, and the fuel cell needs cooling
+        app.add_systems(
+            Startup,
+            setup_cooling.in_set(SpawningSet).after(setup_power_grid),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                heat_generators,
+                run_pumps,
+                cool_bodies,
+                run_radiators,
+                update_thermal_states,
+                update_coolant_panels,
+            )
+                .chain(),
+        );
+        app.add_systems(Startup, fit_coolant_panel.in_set(FittingSet));
+        app.add_sample_file(
+            "COOLANT.BAS",
+            include_str!("../assets/programs/COOLANT.BAS"),
+        )
+        .add_readme_paragraph(README_PARAGRAPH);
+    }
+}
+
+// Where the coolant panel is, for README.TXT
+const README_PARAGRAPH: &str = "\
+COOLANT reads the cooling panel on the diagnostics
+computer's port 3, and runs the pumps flat out if it's hot.
+";
+
+// In W/(m^2 K^4)
+const STEFAN_BOLTZMANN: f32 = 5.670e-8;
+// The background temperature of space, in kelvin
+const SPACE_TEMPERATURE: f32 = 3.0;
+
+// How well heat gets moved around with the pumps off, compared with running flat out. The
+// coolant still creeps round a bit by itself.
+const NATURAL_CIRCULATION: f32 = 0.05;
+
+// How much a body slows itself down just before it gets hot enough to shut down. Throttling
+// starts off gentle and gets harsher the hotter it gets.
+const MINIMUM_THROTTLE: f32 = 0.25;
+
+// How much of a generator's output comes out as heat as well. Fuel cells manage about 50%
+// efficiency, so about as much again.
+const GENERATOR_WASTE_HEAT: f32 = 1.0;
+
+// The ship's coolant loops, for anything that needs cooling when it's spawned
+#[derive(Resource)]
+pub struct ShipCooling {
+    pub primary_loop: Entity,
+}
+
+// Something that heats up: a CPU, a fuel cell, a reactor
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct ThermalBody {
+    // In kelvin
+    pub temperature: f32,
+    // In J/K
+    pub heat_capacity: f32,
+    // How much heat it's making, in watts. Set by whatever owns the body.
+    pub heat_output: f32,
+    pub coolant_loop: Entity,
+    // How well heat gets from it into the coolant with the pumps running flat out, in W/K
+    pub conductance: f32,
+    // It slows down above the first, and shuts down above the second. Once shut down, it
+    // stays that way until it's cooled back down below the first.
+    pub throttle_temperature: f32,
+    pub shutdown_temperature: f32,
+    pub state: ThermalState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ThermalState {
+    Normal,
+    Throttling,
+    ShutDown,
+}
+
+impl ThermalBody {
+    // Starts off at the same temperature as the ship
+    pub fn new(
+        heat_capacity: f32,
+        coolant_loop: Entity,
+        conductance: f32,
+        throttle_temperature: f32,
+        shutdown_temperature: f32,
+    ) -> Self {
+        Self {
+            temperature: 293.15,
+            heat_capacity,
+            heat_output: 0.0,
+            coolant_loop,
+            conductance,
+            throttle_temperature,
+            shutdown_temperature,
+            state: ThermalState::Normal,
+        }
+    }
+
+    // How fast it should be running, from 0 (shut down) to 1 (flat out)
+    pub fn throttle(&self) -> f32 {
+        match self.state {
+            ThermalState::Normal => 1.0,
+            ThermalState::Throttling => {
+                let over = (self.temperature - self.throttle_temperature)
+                    / (self.shutdown_temperature - self.throttle_temperature);
+                1.0 - over.clamp(0.0, 1.0) * (1.0 - MINIMUM_THROTTLE)
+            }
+            ThermalState::ShutDown => 0.0,
+        }
+    }
+}
+
+// Coolant going round the ship, picking heat up from bodies and taking it to radiators
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct CoolantLoop {
+    // In kelvin
+    pub temperature: f32,
+    // In J/K
+    pub heat_capacity: f32,
+    // How hard the pumps are pushing it round, from 0 to 1. Worked out every tick.
+    pub flow: f32,
+    // How much heat the radiators are getting rid of, in watts
+    pub heat_rejected: f32,
+}
+
+impl CoolantLoop {
+    // How well heat moves in and out of the coolant, given how fast it's going round
+    fn effectiveness(&self) -> f32 {
+        NATURAL_CIRCULATION + (1.0 - NATURAL_CIRCULATION) * self.flow
+    }
+}
+
+// Keeps a coolant loop moving. Needs a `PowerConsumer` to run, and uses less power the slower
+// it goes.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Pump {
+    pub coolant_loop: Entity,
+    // From 0 to 1
+    pub speed: f32,
+    // What it uses flat out, in watts
+    pub rated_power: f32,
+}
+
+// Gets rid of a coolant loop's heat by glowing (very faintly) into space
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Radiator {
+    pub coolant_loop: Entity,
+    // In square metres
+    pub area: f32,
+    pub emissivity: f32,
+}
+
+// Lets a computer keep an eye on a coolant loop, and set its pumps' speed.
+// Registers:
+// - 0: the coolant temperature, in degrees Celsius (signed)
+// - 1: the pumps' speed, from 0 to 255. Set by the computer, and starts off flat out.
+// - 2: the temperature of the hottest thing on the loop, in degrees Celsius (signed)
+// - 3: how many things on the loop are throttling
+// - 4: how many things on the loop have shut down
+// - 5-6: the heat the radiators are getting rid of, in watts (16-bit, low byte first)
+#[derive(Component)]
+pub struct CoolantPanel {
+    pub coolant_loop: Entity,
+}
+
+const PANEL_PUMP_SPEED: usize = 1;
+
+// Plugs a coolant panel for `coolant_loop` into one of a computer's ports.
+// Use `Commands::spawn_coolant_panel` rather than adding this directly.
+pub struct SpawnCoolantPanel {
+    pub computer: Entity,
+    pub port: usize,
+    pub coolant_loop: Entity,
+}
+
+pub trait SpawnCoolantPanelExt {
+    fn spawn_coolant_panel(&mut self, computer: Entity, port: usize, coolant_loop: Entity);
+}
+
+impl SpawnCoolantPanelExt for Commands<'_, '_> {
+    fn spawn_coolant_panel(&mut self, computer: Entity, port: usize, coolant_loop: Entity) {
+        self.add(SpawnCoolantPanel {
+            computer,
+            port,
+            coolant_loop,
+        });
+    }
+}
+
+impl Command for SpawnCoolantPanel {
+    fn apply(self, world: &mut World) {
+        let registers = Port::shared();
+        registers.lock().expect("Port lock").registers[PANEL_PUMP_SPEED] = 255;
+        world.spawn((
+            CoolantPanel {
+                coolant_loop: self.coolant_loop,
+            },
+            BusDevice {
+                computer: self.computer,
+                port: self.port,
+                registers,
+            },
+        ));
+    }
+}
+
+pub fn setup_cooling(
+    mut commands: Commands,
+    grid: Res<ShipGrid>,
+    generators: Query<Entity, With<Generator>>,
+) {
+    // About 50 litres of water
+    let primary_loop = commands
+        .spawn((
+            Name::new("Primary coolant"),
+            CoolantLoop {
+                temperature: 293.15,
+                heat_capacity: 200_000.0,
+                flow: 1.0,
+                heat_rejected: 0.0,
+            },
+        ))
+        .id();
+
+    commands.spawn((
+        Name::new("Coolant pump"),
+        Pump {
+            coolant_loop: primary_loop,
+            speed: 1.0,
+            rated_power: 100.0,
+        },
+        PowerConsumer::new(grid.main_bus, 100.0, LoadPriority::Essential),
+    ));
+    // Enough to keep the coolant cool with everything running, as long as it keeps moving
+    commands.spawn((
+        Name::new("Radiator"),
+        Radiator {
+            coolant_loop: primary_loop,
+            area: 3.0,
+            emissivity: 0.9,
+        },
+    ));
+
+    // The generators are all fuel cells, which run hot, and stop if they get much hotter
+    for generator in generators.iter() {
+        commands.entity(generator).insert(ThermalBody::new(
+            50_000.0,
+            primary_loop,
+            100.0,
+            353.15,
+            368.15,
+        ));
+    }
+
+    commands.insert_resource(ShipCooling { primary_loop });
+}
+
+// A way for the diagnostics computer to keep an eye on the cooling, on port 3 ($E830)
+fn fit_coolant_panel(
+    mut commands: Commands,
+    computers: Res<ShipComputers>,
+    cooling: Res<ShipCooling>,
+) {
+    let Some(computer) = computers.get(DIAGNOSTICS_COMPUTER) else {
+        return;
+    };
+    commands.spawn_coolant_panel(computer, 3, cooling.primary_loop);
+}
+
+// Generators warm up the more they put out, and stop if they overheat
+fn heat_generators(mut generators: Query<(&mut Generator, &mut ThermalBody)>) {
+    for (mut generator, mut body) in generators.iter_mut() {
+        body.heat_output = generator.output * GENERATOR_WASTE_HEAT;
+        generator.online = body.state != ThermalState::ShutDown;
+    }
+}
+
+fn run_pumps(mut pumps: Query<(&Pump, &mut PowerConsumer)>, mut loops: Query<&mut CoolantLoop>) {
+    for mut coolant_loop in loops.iter_mut() {
+        coolant_loop.flow = 0.0;
+    }
+    for (pump, mut power) in pumps.iter_mut() {
+        power.demand = pump.rated_power * pump.speed;
+        if let Ok(mut coolant_loop) = loops.get_mut(pump.coolant_loop) {
+            coolant_loop.flow = (coolant_loop.flow + pump.speed * power.supply).min(1.0);
+        }
+    }
+}
+
+fn cool_bodies(
+    time: Res<Time>,
+    mut bodies: Query<&mut ThermalBody>,
+    mut loops: Query<&mut CoolantLoop>,
+) {
+    let delta = time.delta_seconds();
+    for mut body in bodies.iter_mut() {
+        let heat = body.heat_output * delta;
+        body.temperature += heat / body.heat_capacity;
+
+        let Ok(mut coolant_loop) = loops.get_mut(body.coolant_loop) else {
+            continue;
+        };
+        let difference = body.temperature - coolant_loop.temperature;
+        let transferred = body.conductance * coolant_loop.effectiveness() * difference * delta;
+        // Any more than this and the two would swap places, rather than meeting in the
+        // middle
+        let to_equalise =
+            difference / (1.0 / body.heat_capacity + 1.0 / coolant_loop.heat_capacity);
+        let transferred = if difference >= 0.0 {
+            transferred.min(to_equalise)
+        } else {
+            transferred.max(to_equalise)
+        };
+
+        body.temperature -= transferred / body.heat_capacity;
+        coolant_loop.temperature += transferred / coolant_loop.heat_capacity;
+    }
+}
+
+fn run_radiators(time: Res<Time>, radiators: Query<&Radiator>, mut loops: Query<&mut CoolantLoop>) {
+    let delta = time.delta_seconds();
+    for mut coolant_loop in loops.iter_mut() {
+        coolant_loop.heat_rejected = 0.0;
+    }
+    for radiator in radiators.iter() {
+        let Ok(mut coolant_loop) = loops.get_mut(radiator.coolant_loop) else {
+            continue;
+        };
+        let power = radiator.emissivity
+            * STEFAN_BOLTZMANN
+            * radiator.area
+            * (coolant_loop.temperature.powi(4) - SPACE_TEMPERATURE.powi(4))
+            * coolant_loop.effectiveness();
+        coolant_loop.temperature -= power * delta / coolant_loop.heat_capacity;
+        coolant_loop.heat_rejected += power;
+    }
+}
+
+fn update_thermal_states(mut bodies: Query<&mut ThermalBody>) {
+    for mut body in bodies.iter_mut() {
+        let state = if body.temperature >= body.shutdown_temperature {
+            ThermalState::ShutDown
+        } else if body.state == ThermalState::ShutDown
+            && body.temperature >= body.throttle_temperature
+        {
+            // Still cooling off
+            ThermalState::ShutDown
+        } else if body.temperature >= body.throttle_temperature {
+            ThermalState::Throttling
+        } else {
+            ThermalState::Normal
+        };
+        if body.state != state {
+            body.state = state;
+        }
+    }
+}
+
+fn update_coolant_panels(
+    panels: Query<(&CoolantPanel, &BusDevice)>,
+    mut pumps: Query<&mut Pump>,
+    loops: Query<&CoolantLoop>,
+    bodies: Query<&ThermalBody>,
+) {
+    for (panel, device) in panels.iter() {
+        let Ok(coolant_loop) = loops.get(panel.coolant_loop) else {
+            continue;
+        };
+        let registers = &mut device.registers.lock().expect("Port lock").registers;
+
+        let speed = registers[PANEL_PUMP_SPEED] as f32 / 255.0;
+        for mut pump in pumps.iter_mut() {
+            if pump.coolant_loop == panel.coolant_loop {
+                pump.speed = speed;
+            }
+        }
+
+        let on_loop = || {
+            bodies
+                .iter()
+                .filter(|body| body.coolant_loop == panel.coolant_loop)
+        };
+        let hottest = on_loop()
+            .map(|body| body.temperature)
+            .fold(coolant_loop.temperature, f32::max);
+        let count = |state| on_loop().filter(|body| body.state == state).count();
+
+        registers[0] = celsius(coolant_loop.temperature);
+        registers[2] = celsius(hottest);
+        registers[3] = count(ThermalState::Throttling).min(255) as u8;
+        registers[4] = count(ThermalState::ShutDown).min(255) as u8;
+        let [low, high] = (coolant_loop.heat_rejected.round() as u16).to_le_bytes();
+        registers[5] = low;
+        registers[6] = high;
+    }
+}
+
+// A temperature for a register: degrees Celsius, as a signed byte
+fn celsius(kelvin: f32) -> u8 {
+    (kelvin - 273.15).round() as i8 as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn body(coolant_loop: Entity) -> ThermalBody {
+        ThermalBody::new(1000.0, coolant_loop, 10.0, 350.0, 400.0)
+    }
+
+    fn coolant_loop(temperature: f32) -> CoolantLoop {
+        CoolantLoop {
+            temperature,
+            heat_capacity: 3000.0,
+            flow: 1.0,
+            heat_rejected: 0.0,
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world
+    }
+
+    #[test]
+    fn bodies_slow_down_more_the_closer_they_get_to_shutting_down() {
+        let mut body = body(Entity::PLACEHOLDER);
+        assert_eq!(body.throttle(), 1.0);
+        body.state = ThermalState::Throttling;
+        body.temperature = 350.0;
+        assert_eq!(body.throttle(), 1.0);
+        body.temperature = 375.0;
+        assert_eq!(body.throttle(), 1.0 - 0.5 * (1.0 - MINIMUM_THROTTLE));
+        body.temperature = 400.0;
+        assert_eq!(body.throttle(), MINIMUM_THROTTLE);
+        body.state = ThermalState::ShutDown;
+        assert_eq!(body.throttle(), 0.0);
+    }
+
+    #[test]
+    fn shut_down_bodies_stay_off_until_theyve_cooled_right_down() {
+        let mut world = world();
+        let body = world.spawn(body(Entity::PLACEHOLDER)).id();
+        let at = |world: &mut World, temperature| {
+            world.get_mut::<ThermalBody>(body).unwrap().temperature = temperature;
+            world.run_system_once(update_thermal_states);
+            world.get::<ThermalBody>(body).unwrap().state
+        };
+
+        assert_eq!(at(&mut world, 360.0), ThermalState::Throttling);
+        assert_eq!(at(&mut world, 400.0), ThermalState::ShutDown);
+        assert_eq!(at(&mut world, 360.0), ThermalState::ShutDown);
+        assert_eq!(at(&mut world, 340.0), ThermalState::Normal);
+        assert_eq!(at(&mut world, 360.0), ThermalState::Throttling);
+    }
+
+    #[test]
+    fn heat_goes_into_the_coolant_without_overshooting() {
+        let mut world = world();
+        let cold = world.spawn(coolant_loop(300.0)).id();
+        let mut hot = body(cold);
+        hot.temperature = 500.0;
+        // Enough to even out in a single tick, if nothing stopped it
+        hot.conductance = 1e6;
+        let hot = world.spawn(hot).id();
+
+        world.run_system_once(cool_bodies);
+        let body = world.get::<ThermalBody>(hot).unwrap();
+        let coolant = world.get::<CoolantLoop>(cold).unwrap();
+        assert!((body.temperature - coolant.temperature).abs() < 0.01);
+        // 1000 J/K at 500 K and 3000 J/K at 300 K meet at 350 K
+        assert!((body.temperature - 350.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn without_the_pumps_the_coolant_hardly_moves_any_heat() {
+        let mut world = world();
+        let coolant = world.spawn(coolant_loop(300.0)).id();
+        let pump = world
+            .spawn((
+                Pump {
+                    coolant_loop: coolant,
+                    speed: 1.0,
+                    rated_power: 200.0,
+                },
+                PowerConsumer::new(Entity::PLACEHOLDER, 200.0, LoadPriority::Normal),
+            ))
+            .id();
+        let mut warm = body(coolant);
+        warm.temperature = 310.0;
+        let warm = world.spawn(warm).id();
+        let heat_moved = |world: &mut World| {
+            world.get_mut::<ThermalBody>(warm).unwrap().temperature = 310.0;
+            world.get_mut::<CoolantLoop>(coolant).unwrap().temperature = 300.0;
+            world.run_system_once(run_pumps);
+            world.run_system_once(cool_bodies);
+            (310.0 - world.get::<ThermalBody>(warm).unwrap().temperature) * 1000.0
+        };
+
+        // 10 W/K across 10 K
+        assert!((heat_moved(&mut world) - 100.0).abs() < 0.1);
+        world.get_mut::<PowerConsumer>(pump).unwrap().supply = 0.0;
+        assert!((heat_moved(&mut world) - 100.0 * NATURAL_CIRCULATION).abs() < 0.1);
+        // And a pump running slower uses less power
+        world.get_mut::<Pump>(pump).unwrap().speed = 0.5;
+        world.run_system_once(run_pumps);
+        assert_eq!(world.get::<PowerConsumer>(pump).unwrap().demand, 100.0);
+    }
+}